@@ -1,5 +1,5 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 
 /// Agent iteration macro
 ///
@@ -109,7 +109,36 @@ fn impl_agents_macro(ast: &syn::DeriveInput) -> TokenStream {
 /// }
 /// ```
 ///
-#[proc_macro_derive(MarketAgentSet)]
+/// Adding the `#[market_agent_set(parallel)]` attribute
+/// also implements `par_update`, updating the agents
+/// concurrently, for example
+///
+/// ```no_rust
+/// #[derive(MarketAgentSet)]
+/// #[market_agent_set(parallel)]
+/// struct SimAgents {
+///     a: AgentTypeA,
+///     b: AgentTypeB,
+/// }
+/// ```
+///
+/// additionally implements
+///
+/// ```no_rust
+/// fn par_update<R: RngCore + SeedableRng + Send, const M: usize, const N: usize>(
+///     &mut self, env: &mut MarketEnv<M, N>, rng: &mut R
+/// ) {
+///     let env = SharedMarketEnv::new(env);
+///     let mut rng_a = R::seed_from_u64(rng.next_u64());
+///     let mut rng_b = R::seed_from_u64(rng.next_u64());
+///     rayon::scope(|s| {
+///         s.spawn(|_| self.a.par_update(&env, &mut rng_a));
+///         s.spawn(|_| self.b.par_update(&env, &mut rng_b));
+///     });
+/// }
+/// ```
+///
+#[proc_macro_derive(MarketAgentSet, attributes(market_agent_set))]
 pub fn market_agents_derive(input: TokenStream) -> TokenStream {
     let ast = syn::parse(input).unwrap();
     impl_market_agents_macro(&ast)
@@ -126,18 +155,48 @@ fn impl_market_agents_macro(ast: &syn::DeriveInput) -> TokenStream {
         _ => panic!("expected a struct with named fields"),
     };
 
+    let parallel = ast.attrs.iter().any(is_parallel_attr);
+
     let mut call_tokens = quote!();
+    let mut seed_tokens = quote!();
+    let mut spawn_tokens = quote!();
 
     for field in fields {
         let field_name = field.ident.clone();
 
-        if field_name.is_some() {
+        if let Some(field_name) = field_name {
+            let rng_name = format_ident!("rng_{}", field_name);
             call_tokens.extend(quote!(
                 self.#field_name.update(env, rng);
             ));
+            seed_tokens.extend(quote!(
+                let mut #rng_name = R::seed_from_u64(rng.next_u64());
+            ));
+            spawn_tokens.extend(quote!(
+                s.spawn(|_| self.#field_name.par_update(&env, &mut #rng_name));
+            ));
         }
     }
 
+    let par_update_tokens = match parallel {
+        true => quote! {
+            fn par_update<
+                R: rand::RngCore + rand::SeedableRng + Send,
+                const M: usize,
+                const N: usize,
+            >(
+                &mut self, env: &mut bourse_de::MarketEnv<M, N>, rng: &mut R
+            ) {
+                let env = bourse_de::SharedMarketEnv::new(env);
+                #seed_tokens
+                bourse_de::rayon::scope(|s| {
+                    #spawn_tokens
+                });
+            }
+        },
+        false => quote!(),
+    };
+
     let output = quote! {
         impl bourse_de::agents::MarketAgentSet for #name {
             fn update<R: rand::RngCore, const M: usize, const N: usize>(
@@ -145,8 +204,24 @@ fn impl_market_agents_macro(ast: &syn::DeriveInput) -> TokenStream {
             ) {
                 #call_tokens
             }
+
+            #par_update_tokens
         }
     };
 
     TokenStream::from(output)
 }
+
+/// Check if an attribute is `#[market_agent_set(parallel)]`
+fn is_parallel_attr(attr: &syn::Attribute) -> bool {
+    if !attr.path.is_ident("market_agent_set") {
+        return false;
+    }
+    match attr.parse_meta() {
+        Ok(syn::Meta::List(list)) => list.nested.iter().any(|x| match x {
+            syn::NestedMeta::Meta(syn::Meta::Path(p)) if p.is_ident("parallel") => true,
+            _ => panic!("expected #[market_agent_set(parallel)]"),
+        }),
+        _ => panic!("expected #[market_agent_set(parallel)]"),
+    }
+}
@@ -0,0 +1,277 @@
+//! Export of order and trade histories as Arrow IPC streams
+//!
+//! Requires the `arrow` feature.
+//!
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, BooleanArray, RecordBatch, UInt32Array, UInt64Array, UInt8Array};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, Field, Schema};
+
+use super::orderbook::OrderBook;
+
+/// Write a single record batch to an IPC stream
+fn write_batch<W: Write>(w: W, batch: &RecordBatch) -> std::io::Result<()> {
+    let to_io = |e: ArrowError| std::io::Error::other(e);
+    let mut writer = StreamWriter::try_new(w, &batch.schema()).map_err(to_io)?;
+    writer.write(batch).map_err(to_io)?;
+    writer.finish().map_err(to_io)
+}
+
+/// Build a record batch from named columns
+fn build_batch(columns: Vec<(&str, ArrayRef)>) -> std::io::Result<RecordBatch> {
+    let fields: Vec<Field> = columns
+        .iter()
+        .map(|(name, col)| Field::new(*name, col.data_type().clone(), false))
+        .collect();
+    let schema = Arc::new(Schema::new(fields));
+    RecordBatch::try_new(schema, columns.into_iter().map(|(_, x)| x).collect())
+        .map_err(std::io::Error::other)
+}
+
+impl<const LEVELS: usize> OrderBook<LEVELS> {
+    /// Write trade records as an Arrow IPC stream
+    ///
+    /// Writes a single record batch with one row per
+    /// trade and columns:
+    ///
+    /// - `t` - Trade time (`u64`)
+    /// - `side` - Side of the passive order, `true`
+    ///   if a bid (`bool`)
+    /// - `price` - Trade price (`u32`)
+    /// - `vol` - Trade volume (`u32`)
+    /// - `active_order_id` - Id of the aggressive order (`u64`)
+    /// - `passive_order_id` - Id of the passive order (`u64`)
+    /// - `agg_is_market` - Flag if the aggressive order
+    ///   was a market order (`bool`)
+    ///
+    /// # Arguments
+    ///
+    /// - `w` - Writer the IPC stream is written to
+    ///
+    pub fn trades_to_arrow_ipc<W: Write>(&self, w: W) -> std::io::Result<()> {
+        let trades = self.get_trades();
+
+        let batch = build_batch(vec![
+            (
+                "t",
+                Arc::new(UInt64Array::from_iter_values(trades.iter().map(|x| x.t))),
+            ),
+            (
+                "side",
+                Arc::new(BooleanArray::from_iter(
+                    trades.iter().map(|x| Some(bool::from(x.side))),
+                )),
+            ),
+            (
+                "price",
+                Arc::new(UInt32Array::from_iter_values(
+                    trades.iter().map(|x| x.price),
+                )),
+            ),
+            (
+                "vol",
+                Arc::new(UInt32Array::from_iter_values(trades.iter().map(|x| x.vol))),
+            ),
+            (
+                "active_order_id",
+                Arc::new(UInt64Array::from_iter_values(
+                    trades.iter().map(|x| x.active_order_id as u64),
+                )),
+            ),
+            (
+                "passive_order_id",
+                Arc::new(UInt64Array::from_iter_values(
+                    trades.iter().map(|x| x.passive_order_id as u64),
+                )),
+            ),
+            (
+                "agg_is_market",
+                Arc::new(BooleanArray::from_iter(
+                    trades.iter().map(|x| Some(x.agg_is_market)),
+                )),
+            ),
+        ])?;
+
+        write_batch(w, &batch)
+    }
+
+    /// Write order records as an Arrow IPC stream
+    ///
+    /// Writes a single record batch with one row per
+    /// created order (in order of creation) and columns:
+    ///
+    /// - `order_id` - Id of the order (`u64`)
+    /// - `trader_id` - Id of the trader (`u32`)
+    /// - `side` - Order side, `true` if a bid (`bool`)
+    /// - `status` - Order status (`u8`), using the
+    ///   same encoding as [Status](crate::types::Status)
+    ///   conversion to `u8`
+    /// - `arr_time` - Arrival time (`u64`)
+    /// - `end_time` - End time (`u64`)
+    /// - `price` - Order price (`u32`)
+    /// - `start_vol` - Original order volume (`u32`)
+    /// - `vol` - Remaining order volume (`u32`)
+    /// - `is_market` - Flag if a market order (`bool`)
+    ///
+    /// # Arguments
+    ///
+    /// - `w` - Writer the IPC stream is written to
+    ///
+    pub fn orders_to_arrow_ipc<W: Write>(&self, w: W) -> std::io::Result<()> {
+        let orders = self.get_orders();
+
+        let batch = build_batch(vec![
+            (
+                "order_id",
+                Arc::new(UInt64Array::from_iter_values(
+                    orders.iter().map(|x| x.order_id as u64),
+                )),
+            ),
+            (
+                "trader_id",
+                Arc::new(UInt32Array::from_iter_values(
+                    orders.iter().map(|x| x.trader_id),
+                )),
+            ),
+            (
+                "side",
+                Arc::new(BooleanArray::from_iter(
+                    orders.iter().map(|x| Some(bool::from(x.side))),
+                )),
+            ),
+            (
+                "status",
+                Arc::new(UInt8Array::from_iter_values(
+                    orders.iter().map(|x| u8::from(x.status)),
+                )),
+            ),
+            (
+                "arr_time",
+                Arc::new(UInt64Array::from_iter_values(
+                    orders.iter().map(|x| x.arr_time),
+                )),
+            ),
+            (
+                "end_time",
+                Arc::new(UInt64Array::from_iter_values(
+                    orders.iter().map(|x| x.end_time),
+                )),
+            ),
+            (
+                "price",
+                Arc::new(UInt32Array::from_iter_values(
+                    orders.iter().map(|x| x.price),
+                )),
+            ),
+            (
+                "start_vol",
+                Arc::new(UInt32Array::from_iter_values(
+                    orders.iter().map(|x| x.start_vol),
+                )),
+            ),
+            (
+                "vol",
+                Arc::new(UInt32Array::from_iter_values(orders.iter().map(|x| x.vol))),
+            ),
+            (
+                "is_market",
+                Arc::new(BooleanArray::from_iter(
+                    orders.iter().map(|x| Some(x.is_market)),
+                )),
+            ),
+        ])?;
+
+        write_batch(w, &batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Side, Status};
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{UInt32Type, UInt64Type, UInt8Type};
+    use arrow_ipc::reader::StreamReader;
+    use arrow_schema::DataType;
+
+    fn read_batch(buffer: Vec<u8>) -> RecordBatch {
+        let mut reader = StreamReader::try_new(std::io::Cursor::new(buffer), None).unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert!(reader.next().is_none());
+        batch
+    }
+
+    fn test_book() -> OrderBook {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+        book.set_time(10);
+        book.create_and_place_order(Side::Ask, 20, 101, Some(50))
+            .unwrap();
+        book.set_time(20);
+        book.create_and_place_order(Side::Bid, 15, 102, Some(50))
+            .unwrap();
+        book
+    }
+
+    #[test]
+    fn test_trades_to_arrow() {
+        let book = test_book();
+
+        let mut buffer = Vec::new();
+        book.trades_to_arrow_ipc(&mut buffer).unwrap();
+        let batch = read_batch(buffer);
+
+        let schema = batch.schema();
+        let names: Vec<&String> = schema.fields().iter().map(|x| x.name()).collect();
+        assert!(
+            names
+                == vec![
+                    "t",
+                    "side",
+                    "price",
+                    "vol",
+                    "active_order_id",
+                    "passive_order_id",
+                    "agg_is_market"
+                ]
+        );
+        assert!(schema.field(0).data_type() == &DataType::UInt64);
+        assert!(schema.field(1).data_type() == &DataType::Boolean);
+        assert!(schema.field(2).data_type() == &DataType::UInt32);
+
+        assert!(batch.num_rows() == 1);
+        assert!(batch.column(0).as_primitive::<UInt64Type>().value(0) == 20);
+        assert!(!batch.column(1).as_boolean().value(0));
+        assert!(batch.column(2).as_primitive::<UInt32Type>().value(0) == 50);
+        assert!(batch.column(3).as_primitive::<UInt32Type>().value(0) == 15);
+        assert!(batch.column(4).as_primitive::<UInt64Type>().value(0) == 1);
+        assert!(batch.column(5).as_primitive::<UInt64Type>().value(0) == 0);
+    }
+
+    #[test]
+    fn test_orders_to_arrow() {
+        let book = test_book();
+
+        let mut buffer = Vec::new();
+        book.orders_to_arrow_ipc(&mut buffer).unwrap();
+        let batch = read_batch(buffer);
+
+        let schema = batch.schema();
+        assert!(schema.fields().len() == 10);
+        assert!(schema.field(0).name() == "order_id");
+        assert!(schema.field(3).name() == "status");
+        assert!(schema.field(3).data_type() == &DataType::UInt8);
+
+        assert!(batch.num_rows() == 2);
+        let trader_ids = batch.column(1).as_primitive::<UInt32Type>();
+        assert!(trader_ids.value(0) == 101);
+        assert!(trader_ids.value(1) == 102);
+        let status = batch.column(3).as_primitive::<UInt8Type>();
+        assert!(status.value(0) == u8::from(Status::Active));
+        assert!(status.value(1) == u8::from(Status::Filled));
+        let vols = batch.column(8).as_primitive::<UInt32Type>();
+        assert!(vols.value(0) == 5);
+        assert!(vols.value(1) == 0);
+    }
+}
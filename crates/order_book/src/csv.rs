@@ -0,0 +1,176 @@
+//! Export of order and trade histories as CSV files
+//!
+//! Rows are written directly, with prices and volumes
+//! as integers, and enums as their lowercase names.
+//!
+use std::io::Write;
+use std::path::Path;
+
+use super::orderbook::OrderBook;
+use super::types::{Order, Side, Status, TimeInForce, Trade};
+
+const TRADE_HEADER: &str = "t,side,price,vol,active_order_id,passive_order_id,agg_is_market";
+
+const ORDER_HEADER: &str = "side,status,arr_time,end_time,vol,start_vol,price,trader_id,\
+    order_id,is_market,expiry,reserve_vol,display_vol,trigger_price,time_in_force,post_only";
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Bid => "bid",
+        Side::Ask => "ask",
+    }
+}
+
+fn status_name(status: Status) -> &'static str {
+    match status {
+        Status::New => "new",
+        Status::Active => "active",
+        Status::Filled => "filled",
+        Status::Cancelled => "cancelled",
+        Status::Rejected => "rejected",
+        Status::Pending => "pending",
+    }
+}
+
+fn time_in_force_name(time_in_force: TimeInForce) -> &'static str {
+    match time_in_force {
+        TimeInForce::GoodTillCancel => "good_till_cancel",
+        TimeInForce::ImmediateOrCancel => "immediate_or_cancel",
+        TimeInForce::FillOrKill => "fill_or_kill",
+        TimeInForce::GoodTillDate => "good_till_date",
+    }
+}
+
+/// Format an optional value, with an empty field if `None`
+fn optional<T: ToString>(x: Option<T>) -> String {
+    x.map_or(String::new(), |x| x.to_string())
+}
+
+/// Write trade records as CSV rows (with a header)
+fn write_trades<W: Write>(mut w: W, trades: &[Trade]) -> std::io::Result<()> {
+    writeln!(w, "{}", TRADE_HEADER)?;
+    for trade in trades.iter() {
+        writeln!(
+            w,
+            "{},{},{},{},{},{},{}",
+            trade.t,
+            side_name(trade.side),
+            trade.price,
+            trade.vol,
+            trade.active_order_id,
+            trade.passive_order_id,
+            trade.agg_is_market,
+        )?;
+    }
+    w.flush()
+}
+
+/// Write order records as CSV rows (with a header)
+fn write_orders<W: Write>(mut w: W, orders: &[&Order]) -> std::io::Result<()> {
+    writeln!(w, "{}", ORDER_HEADER)?;
+    for order in orders.iter() {
+        writeln!(
+            w,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            side_name(order.side),
+            status_name(order.status),
+            order.arr_time,
+            order.end_time,
+            order.vol,
+            order.start_vol,
+            order.price,
+            order.trader_id,
+            order.order_id,
+            order.is_market,
+            optional(order.expiry),
+            order.reserve_vol,
+            order.display_vol,
+            optional(order.trigger_price),
+            time_in_force_name(order.time_in_force),
+            order.post_only,
+        )?;
+    }
+    w.flush()
+}
+
+impl<const LEVELS: usize> OrderBook<LEVELS> {
+    /// Save trade records to a CSV file
+    ///
+    /// Writes one row per trade, with a header row
+    /// of the [Trade] field names. The side is written
+    /// as `bid` or `ask`.
+    ///
+    /// # Arguments
+    ///
+    /// - `path` - Path to write the CSV file to
+    ///
+    pub fn save_trades_csv<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        write_trades(std::io::BufWriter::new(file), self.get_trades())
+    }
+
+    /// Save order records to a CSV file
+    ///
+    /// Writes one row per created order (in order of
+    /// creation), with a header row of the [Order] field
+    /// names. Enums are written as their lowercase names,
+    /// e.g. `bid` and `cancelled`, and optional values
+    /// that are not set as empty fields.
+    ///
+    /// # Arguments
+    ///
+    /// - `path` - Path to write the CSV file to
+    ///
+    pub fn save_orders_csv<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        write_orders(std::io::BufWriter::new(file), &self.get_orders())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Nanos;
+
+    #[test]
+    fn test_save_csv() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+        book.set_time(10);
+        book.create_and_place_order(Side::Ask, 20, 101, Some(50))
+            .unwrap();
+        book.set_time(20);
+        book.create_and_place_order(Side::Bid, 15, 102, None)
+            .unwrap();
+        let expiring = book.create_order(Side::Bid, 10, 103, Some(40)).unwrap();
+        book.set_order_expiry(expiring, 100);
+        book.place_order(expiring);
+
+        let trades_path = std::env::temp_dir().join("bourse_test_trades.csv");
+        book.save_trades_csv(&trades_path).unwrap();
+        let trades = std::fs::read_to_string(&trades_path).unwrap();
+        std::fs::remove_file(&trades_path).unwrap();
+
+        let rows: Vec<&str> = trades.lines().collect();
+        assert!(rows == vec![TRADE_HEADER, "20,ask,50,15,1,0,true"]);
+
+        let orders_path = std::env::temp_dir().join("bourse_test_orders.csv");
+        book.save_orders_csv(&orders_path).unwrap();
+        let orders = std::fs::read_to_string(&orders_path).unwrap();
+        std::fs::remove_file(&orders_path).unwrap();
+
+        let rows: Vec<&str> = orders.lines().collect();
+        assert!(rows.len() == 4);
+        assert!(rows[0] == ORDER_HEADER);
+        assert!(rows[0].split(',').count() == 16);
+        // End time of active orders is the maximum time value
+        assert!(
+            rows[1]
+                == format!(
+                    "ask,active,10,{},5,20,50,101,0,false,,0,0,,good_till_cancel,false",
+                    Nanos::MAX
+                )
+        );
+        assert!(rows[2].starts_with("bid,filled,20,20,0,15,"));
+        assert!(rows[3].ends_with(",100,0,0,,good_till_date,false"));
+    }
+}
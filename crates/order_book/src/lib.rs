@@ -17,7 +17,7 @@
 //! use bourse_book;
 //! use bourse_book::{types, OrderBook};
 //!
-//! let mut book: OrderBook = OrderBook::new(0, 1, true);
+//! let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
 //!
 //! // Create a new order
 //! let order_id = book.create_order(
@@ -50,7 +50,7 @@
 //! ```
 //! # use bourse_book::OrderBook;
 //! # use bourse_book::types::{Order, Trade};
-//! # let book: OrderBook = OrderBook::new(0, 1, true);
+//! # let book: OrderBook = OrderBook::new(0, 1, 1, true);
 //! // Get references to all the orders created
 //! let order_history: Vec<&Order> = book.get_orders();
 //! // Get a reference to trade records
@@ -66,7 +66,7 @@
 //!
 //! ```
 //! # use bourse_book::OrderBook;
-//! # let book: OrderBook = OrderBook::new(0, 1, true);
+//! # let book: OrderBook = OrderBook::new(0, 1, 1, true);
 //! let state = serde_json::to_string(&book).unwrap();
 //! let book = serde_json::from_str::<OrderBook>(state.as_str()).unwrap();
 //! ```
@@ -75,11 +75,14 @@
 //!
 //! ```no_run
 //! # use bourse_book::OrderBook;
-//! # let book: OrderBook = OrderBook::new(0, 1, true);
-//! book.save_json("foo.json", true);
+//! # let book: OrderBook = OrderBook::new(0, 1, 1, true);
+//! book.save_json("foo.json", true, false);
 //! let loaded_book: OrderBook = OrderBook::load_json("foo.json").unwrap();
 //! ```
 //!
+//! Trade and order histories can also be exported as tables
+//! with [OrderBook::save_trades_csv] and [OrderBook::save_orders_csv].
+//!
 //! ## Initialise and Updating a Market
 //!
 //! ```
@@ -105,10 +108,11 @@
 //!
 //! # Notes
 //!
-//! - Orders are sorted by price-time priority. To
-//!   reduce any ambiguity in ordering, the simulated
-//!   time of the market should be updated in
-//!   between placing orders on the market.
+//! - Orders are sorted by price-time priority, with
+//!   ties between orders placed at the same time
+//!   broken by arrival order. Alternatively larger
+//!   orders at a price level can be given priority
+//!   using [OrderBook::set_within_level_priority].
 //! - For accuracy prices are stored as unsigned
 //!   integers (as opposed to a float type), hence
 //!   prices from data should be scaled based on
@@ -120,10 +124,13 @@
 //!   order entry, but does not immediately place
 //!   the order on the market.
 //!
+#[cfg(feature = "arrow")]
+mod arrow;
+mod csv;
 mod market;
 mod orderbook;
 mod side;
 pub mod types;
 
 pub use market::Market;
-pub use orderbook::{OrderBook, OrderError};
+pub use orderbook::{impact_curve, OrderBook, OrderError};
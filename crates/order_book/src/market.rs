@@ -7,7 +7,7 @@ use std::{array, path::Path};
 use super::{OrderBook, OrderError};
 use crate::types::{
     AssetIdx, Event, Level2Data, MarketOrderId, Nanos, Order, OrderCount, Price, Side, TraderId,
-    Vol,
+    TraderSummary, Vol,
 };
 
 /// Multi asset market combining several [OrderBook]
@@ -72,9 +72,29 @@ impl<const ASSETS: usize, const LEVELS: usize> Market<ASSETS, LEVELS> {
     /// - `trading` - If `False` no orders will be matched.
     ///
     pub fn new(start_time: Nanos, tick_size: [Price; ASSETS], trading: bool) -> Self {
+        Self::with_lot_sizes(start_time, tick_size, [1; ASSETS], trading)
+    }
+
+    /// Initialise a market with lot sizes for each asset
+    ///
+    /// # Arguments
+    ///
+    /// - `start_time` - Initial time to assign to the market
+    ///   and contained order-books.
+    /// - `tick_size` - Array of integer tick sizes for each asset.
+    /// - `lot_size` - Array of lot sizes for each asset, order
+    ///   volumes must be a multiple of the lot size.
+    /// - `trading` - If `False` no orders will be matched.
+    ///
+    pub fn with_lot_sizes(
+        start_time: Nanos,
+        tick_size: [Price; ASSETS],
+        lot_size: [Vol; ASSETS],
+        trading: bool,
+    ) -> Self {
         Self {
             order_books: array::from_fn(|i| {
-                OrderBook::<LEVELS>::new(start_time, tick_size[i], trading)
+                OrderBook::<LEVELS>::new(start_time, tick_size[i], lot_size[i], trading)
             }),
         }
     }
@@ -99,6 +119,21 @@ impl<const ASSETS: usize, const LEVELS: usize> Market<ASSETS, LEVELS> {
         &mut self.order_books[asset]
     }
 
+    /// Reset all order-books to an empty state
+    ///
+    /// Clears orders and trades of all assets,
+    /// keeping tick-sizes and trading flags.
+    ///
+    /// # Arguments
+    ///
+    /// - `start_time` - Time to assign to the market
+    ///
+    pub fn reset(&mut self, start_time: Nanos) {
+        for book in self.order_books.iter_mut() {
+            book.reset(start_time)
+        }
+    }
+
     /// Get the market time
     pub fn get_time(&self) -> Nanos {
         self.order_books[0].get_time()
@@ -125,8 +160,10 @@ impl<const ASSETS: usize, const LEVELS: usize> Market<ASSETS, LEVELS> {
 
     /// Disable trade execution for all assets
     ///
-    /// > **_NOTE:_** Currently there is not
-    ///   a un-crossing algorithm implemented
+    /// > **_NOTE:_** Orders placed while trading is
+    /// > disabled may cross, [OrderBook::uncross] can
+    /// > be used to clear a crossed book before trading
+    /// > is re-enabled.
     pub fn disable_trading(&mut self) {
         for book in self.order_books.iter_mut() {
             book.disable_trading()
@@ -331,10 +368,23 @@ impl<const ASSETS: usize, const LEVELS: usize> Market<ASSETS, LEVELS> {
         self.order_books[order_id.0].modify_order(order_id.1, new_price, new_vol)
     }
 
+    /// Reduce the volume of an order by an amount
+    ///
+    /// See [OrderBook::reduce_order].
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Asset index and id of the order to reduce
+    /// - `amount` - Volume to remove from the order
+    ///
+    pub fn reduce_order(&mut self, order_id: MarketOrderId, amount: Vol) {
+        self.order_books[order_id.0].reduce_order(order_id.1, amount)
+    }
+
     /// Process a [Event] order instruction
     ///
-    /// Processes an order instruction to place, cancel
-    /// or modify an order
+    /// Processes an order instruction to place, cancel,
+    /// modify or reduce an order
     ///
     /// # Arguments
     ///
@@ -349,9 +399,37 @@ impl<const ASSETS: usize, const LEVELS: usize> Market<ASSETS, LEVELS> {
                 new_price,
                 new_vol,
             } => self.modify_order(order_id, new_price, new_vol),
+            Event::Reduce { order_id, amount } => self.reduce_order(order_id, amount),
         }
     }
 
+    /// Summarise the trades of a trader across all assets
+    ///
+    /// Trader ids are shared across the order-books
+    /// of all assets, so a trader's activity can be
+    /// combined across the market.
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the trader
+    ///
+    pub fn trader_summary(&self, trader_id: TraderId) -> [TraderSummary; ASSETS] {
+        array::from_fn(|i| self.order_books[i].trader_summary(trader_id))
+    }
+
+    /// Get the volume weighted average trade price of each asset
+    ///
+    /// See [OrderBook::vwap].
+    ///
+    /// # Arguments
+    ///
+    /// - `window` - Optional half-open time window
+    ///   `[start, end)` of trades to include
+    ///
+    pub fn vwap(&self, window: Option<(Nanos, Nanos)>) -> [Option<f64>; ASSETS] {
+        array::from_fn(|i| self.order_books[i].vwap(window))
+    }
+
     /// Reference to list of created orders for an asset
     pub fn get_orders(&self, asset: AssetIdx) -> Vec<&Order> {
         self.order_books[asset].get_orders()
@@ -408,6 +486,21 @@ mod tests {
         assert!(market.bid_asks() == [(0, Price::MAX), (0, Price::MAX)]);
     }
 
+    #[test]
+    fn test_lot_sizes() {
+        let mut market: Market<2> = Market::with_lot_sizes(0, [1, 1], [1, 10], true);
+
+        assert!(market.create_order(0, Side::Bid, 15, 0, Some(100)).is_ok());
+        assert!(matches!(
+            market.create_order(1, Side::Bid, 15, 0, Some(100)),
+            Err(OrderError::LotError {
+                vol: 15,
+                lot_size: 10
+            })
+        ));
+        assert!(market.create_order(1, Side::Bid, 20, 0, Some(100)).is_ok());
+    }
+
     #[test]
     fn test_insert_order() {
         let mut market: Market<2> = Market::new(101, [1, 2], true);
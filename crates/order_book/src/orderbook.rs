@@ -5,7 +5,7 @@
 //! ```
 //! use bourse_book::{types, OrderBook};
 //!
-//! let mut book: OrderBook  = OrderBook::new(0, 1, true);
+//! let mut book: OrderBook  = OrderBook::new(0, 1, 1, true);
 //! let order_id = book.create_order(
 //!     types::Side::Bid, 50, 101, Some(50)
 //! ).unwrap();
@@ -15,16 +15,43 @@
 //! ```
 //!
 use serde::{Deserialize, Serialize};
-use std::cmp::min;
+use std::cmp::{min, Reverse};
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
 use std::fmt;
 use std::path::Path;
 
 use super::side::{get_ask_key, get_bid_key, AskSide, BidSide, SideFunctionality};
 use super::types::{
-    Event, Level1Data, Level2Data, Nanos, Order, OrderCount, OrderId, OrderKey, Price, Side,
-    Status, Trade, TraderId, Vol,
+    BookEvent, Event, Level1Data, Level2Data, Level3Data, Level3Orders, MarketCondition, Nanos,
+    Ohlcv, Order, OrderCount, OrderId, OrderKey, Price, PriceScale, RoundMode, SelfTradePrevention,
+    Side, SimResult, Status, TimeInForce, Trade, TraderId, TraderSummary, Vol, WithinLevelPriority,
 };
 
+/// Order book event callback
+type EventCallback = Box<dyn FnMut(&BookEvent) + Send>;
+
+/// Optional order book event listener
+///
+/// Listeners are not serialised, and are not
+/// copied when an order book is cloned.
+#[derive(Default)]
+struct EventListener(Option<EventCallback>);
+
+impl EventListener {
+    /// Pass an event to the listener (if set)
+    fn emit(&mut self, event: BookEvent) {
+        if let Some(f) = self.0.as_mut() {
+            f(&event);
+        }
+    }
+}
+
+impl Clone for EventListener {
+    fn clone(&self) -> Self {
+        Self(None)
+    }
+}
+
 /// Order data combined with key
 ///
 /// Orders are linked with a key
@@ -36,6 +63,11 @@ pub struct OrderEntry {
     order: Order,
     /// Key associated with order
     key: OrderKey,
+    /// Price of the first order matched by this
+    /// order when aggressive, used to limit the
+    /// trade price move of the order
+    #[serde(skip)]
+    start_price: Option<Price>,
 }
 
 /// Order book with order and trade history
@@ -53,15 +85,18 @@ pub struct OrderEntry {
 ///   the order-book.
 /// - *tick-size*: The price difference between
 ///   price levels.
+/// - *lot-size*: Order volumes must be a multiple
+///   of the lot-size.
 ///
 /// ```
 /// use bourse_book::{types, OrderBook};
 ///
 /// let start_time = 0;
 /// let tick_size = 1;
+/// let lot_size = 1;
 ///
 /// let mut book: OrderBook = OrderBook::new(
-///     start_time, tick_size, true
+///     start_time, tick_size, lot_size, true
 /// );
 ///
 /// // Create a new order and place it
@@ -87,10 +122,10 @@ pub struct OrderEntry {
 /// use bourse_book::OrderBook;
 ///
 /// // Track best 5 price levels
-/// let book = OrderBook::<5>::new(0, 1, true);
+/// let book = OrderBook::<5>::new(0, 1, 1, true);
 /// ```
 ///
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(try_from = "OrderBookState<LEVELS>")]
 pub struct OrderBook<const LEVELS: usize = 10> {
     /// Simulated time, intended to represent
@@ -99,8 +134,13 @@ pub struct OrderBook<const LEVELS: usize = 10> {
     t: Nanos,
     // Market tick size
     tick_size: Price,
+    /// Market lot size
+    lot_size: Vol,
     /// Cumulative trade volume
     trade_vol: Vol,
+    /// Sequence counter used to break ties
+    /// between orders placed at the same time
+    seq: u64,
     /// Ask side of the book data structure
     #[serde(skip_serializing)]
     ask_side: AskSide,
@@ -113,11 +153,59 @@ pub struct OrderBook<const LEVELS: usize = 10> {
     orders: Vec<OrderEntry>,
     /// History of trades
     trades: Vec<Trade>,
+    /// Cumulative traded volume of each trader
+    #[serde(skip_serializing)]
+    trader_vols: HashMap<TraderId, Vol>,
+    /// Net position and cash flow of each trader
+    #[serde(skip_serializing)]
+    trader_positions: HashMap<TraderId, (i64, i64)>,
+    /// Ids of the orders of each trader resting
+    /// on the book
+    #[serde(skip_serializing)]
+    active_orders: HashMap<TraderId, Vec<OrderId>>,
+    /// Cumulative filled (i.e. traded) volume
+    #[serde(skip_serializing)]
+    filled_vol: u64,
+    /// Cumulative remaining volume of cancelled orders
+    #[serde(skip_serializing)]
+    cancelled_vol: u64,
+    /// Queue of order expiry times
+    #[serde(skip_serializing)]
+    expiries: BinaryHeap<Reverse<(Nanos, OrderId)>>,
+    /// Pending buy stop orders by trigger price
+    #[serde(skip_serializing)]
+    buy_stops: BTreeSet<(Price, OrderId)>,
+    /// Pending sell stop orders by trigger price
+    #[serde(skip_serializing)]
+    sell_stops: BTreeSet<(Price, OrderId)>,
     /// Flag if `true` placed orders will be
     /// matched, if `false` no trades will be
     /// executed (but orders can still be
     /// placed and modified)
     trading: bool,
+    /// Priority of orders within a price level
+    priority: WithinLevelPriority,
+    /// Time after placement during which resting
+    /// orders cannot be matched
+    protection_window: Nanos,
+    /// Minimum time resting orders must rest
+    /// before they can be cancelled
+    min_resting_time: Nanos,
+    /// Number of decimal places used when
+    /// formatting prices for display
+    price_decimals: u32,
+    /// Lower and upper limits of the prices
+    /// orders can be placed and matched at
+    price_band: Option<(Price, Price)>,
+    /// Handling of aggressive orders that would
+    /// match resting orders from the same trader
+    self_trade_prevention: SelfTradePrevention,
+    /// Maximum distance a single aggressive order
+    /// can move the trade price
+    max_trade_move: Option<Price>,
+    /// Listener called with order book events
+    #[serde(skip)]
+    on_event: EventListener,
 }
 
 /// Order rejection errors
@@ -127,6 +215,22 @@ pub struct OrderBook<const LEVELS: usize = 10> {
 pub enum OrderError {
     /// Price not a multiple of market tick-size
     PriceError { price: Price, tick_size: Price },
+    /// Volume not a multiple of market lot-size
+    LotError { vol: Vol, lot_size: Vol },
+    /// Iceberg display volume zero or
+    /// greater than the total volume
+    DisplayVolError { display_vol: Vol, vol: Vol },
+    /// Decimal price not on the grid of a
+    /// [PriceScale](crate::types::PriceScale)
+    ///
+    /// Counterpart of [OrderError::PriceError] for
+    /// decimal prices, carrying the offending value as
+    /// given since it may be negative, non-finite, or
+    /// between integer prices.
+    DecimalPriceError { price: f64, tick_size: f64 },
+    /// Cash budget too small to buy or sell a lot,
+    /// or no volume on the opposite side of the book
+    BudgetError { cash: u64 },
 }
 
 impl fmt::Display for OrderError {
@@ -137,6 +241,24 @@ impl fmt::Display for OrderError {
                 "Price {} was not a multiple of tick-size {}",
                 price, tick_size
             ),
+            OrderError::LotError { vol, lot_size } => write!(
+                f,
+                "Volume {} was not a multiple of lot-size {}",
+                vol, lot_size
+            ),
+            OrderError::DisplayVolError { display_vol, vol } => write!(
+                f,
+                "Display volume {} must be non-zero and not exceed volume {}",
+                display_vol, vol
+            ),
+            OrderError::DecimalPriceError { price, tick_size } => write!(
+                f,
+                "Price {} was not a multiple of tick-size {}",
+                price, tick_size
+            ),
+            OrderError::BudgetError { cash } => {
+                write!(f, "Cash budget {} cannot buy or sell any volume", cash)
+            }
         }
     }
 }
@@ -153,20 +275,345 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
     /// - `start_time` - Simulated time to assign to the
     ///   order book
     /// - `tick_size` - Tick size
+    /// - `lot_size` - Lot size, order volumes must
+    ///   be a multiple of this value
     /// - `trading` - Flag to indicate if trades will be
     ///   executed
-    pub fn new(start_time: Nanos, tick_size: Price, trading: bool) -> Self {
+    pub fn new(start_time: Nanos, tick_size: Price, lot_size: Vol, trading: bool) -> Self {
         assert!(tick_size > 0);
+        assert!(lot_size > 0);
 
         Self {
             t: start_time,
             tick_size,
+            lot_size,
             trade_vol: 0,
+            seq: 0,
             ask_side: AskSide::new(),
             bid_side: BidSide::new(),
             orders: Vec::new(),
             trades: Vec::new(),
+            trader_vols: HashMap::new(),
+            trader_positions: HashMap::new(),
+            active_orders: HashMap::new(),
+            filled_vol: 0,
+            cancelled_vol: 0,
+            expiries: BinaryHeap::new(),
+            buy_stops: BTreeSet::new(),
+            sell_stops: BTreeSet::new(),
             trading,
+            priority: WithinLevelPriority::Time,
+            protection_window: 0,
+            min_resting_time: 0,
+            price_decimals: 0,
+            price_band: None,
+            self_trade_prevention: SelfTradePrevention::None,
+            max_trade_move: None,
+            on_event: EventListener::default(),
+        }
+    }
+
+    /// Initialise a new orderbook from a decimal price scale
+    ///
+    /// Creates an empty order book with the integer
+    /// tick-size of the price scale, and formats prices
+    /// (see [OrderBook::format_price]) with the number
+    /// of decimal places of the scale.
+    ///
+    /// # Arguments
+    ///
+    /// - `start_time` - Simulated time to assign to the
+    ///   order book
+    /// - `price_scale` - Decimal price scale
+    /// - `lot_size` - Lot size, order volumes must
+    ///   be a multiple of this value
+    /// - `trading` - Flag to indicate if trades will be
+    ///   executed
+    pub fn with_price_scale(
+        start_time: Nanos,
+        price_scale: PriceScale,
+        lot_size: Vol,
+        trading: bool,
+    ) -> Self {
+        let mut book = Self::new(start_time, price_scale.tick_size(), lot_size, trading);
+        book.set_price_decimals(price_scale.decimals());
+        book
+    }
+
+    /// Reset the order book to an empty state
+    ///
+    /// Clears all orders, trades, volume
+    /// tracking and any price band, keeping the
+    /// tick-size and trading flag of the order book.
+    ///
+    /// # Arguments
+    ///
+    /// - `start_time` - Simulated time to assign to the
+    ///   order book
+    ///
+    pub fn reset(&mut self, start_time: Nanos) {
+        self.t = start_time;
+        self.trade_vol = 0;
+        self.seq = 0;
+        self.ask_side = AskSide::new();
+        self.bid_side = BidSide::new();
+        self.orders.clear();
+        self.trades.clear();
+        self.trader_vols.clear();
+        self.trader_positions.clear();
+        self.active_orders.clear();
+        self.filled_vol = 0;
+        self.cancelled_vol = 0;
+        self.expiries.clear();
+        self.buy_stops.clear();
+        self.sell_stops.clear();
+        self.price_band = None;
+    }
+
+    /// Set the priority of orders within a price level
+    ///
+    /// By default orders at the same price are
+    /// matched in arrival order, with
+    /// [WithinLevelPriority::SizeThenTime] larger
+    /// orders are matched first. Size priority is
+    /// determined by the volume of an order when it
+    /// is placed on the book.
+    ///
+    /// This should be set before any orders are placed,
+    /// panics if there are orders on the book.
+    ///
+    /// # Arguments
+    ///
+    /// - `priority` - Within level priority mode
+    ///
+    pub fn set_within_level_priority(&mut self, priority: WithinLevelPriority) {
+        assert!(
+            (self.bid_side.num_levels() == 0) & (self.ask_side.num_levels() == 0),
+            "Priority cannot be changed with orders on the book"
+        );
+        self.priority = priority;
+    }
+
+    /// Get the priority of orders within a price level
+    pub fn get_within_level_priority(&self) -> WithinLevelPriority {
+        self.priority
+    }
+
+    /// Set the protection window of resting orders
+    ///
+    /// Resting orders cannot be matched until the
+    /// window has passed since they were placed, i.e.
+    /// aggressive orders skip protected orders and
+    /// match against older liquidity (a "speed bump").
+    /// Aggressive orders that are not filled can then
+    /// be placed at a price that crosses protected
+    /// orders. By default the window is `0` and all
+    /// orders can be matched immediately.
+    ///
+    /// # Arguments
+    ///
+    /// - `protection_window` - Time after placement
+    ///   during which orders cannot be matched
+    ///
+    pub fn set_protection_window(&mut self, protection_window: Nanos) {
+        self.protection_window = protection_window;
+    }
+
+    /// Get the protection window of resting orders
+    pub fn get_protection_window(&self) -> Nanos {
+        self.protection_window
+    }
+
+    /// Set the self-trade prevention mode
+    ///
+    /// Determines how an aggressive order is handled
+    /// when the next resting order it would match
+    /// belongs to the same trader:
+    ///
+    /// - [SelfTradePrevention::CancelResting] the resting
+    ///   order is cancelled and matching continues
+    /// - [SelfTradePrevention::CancelAggressor] the
+    ///   aggressive order is cancelled
+    /// - [SelfTradePrevention::DecrementBoth] the smaller
+    ///   order is cancelled, and the volume of the larger
+    ///   order reduced by the volume of the smaller
+    ///
+    /// By default ([SelfTradePrevention::None]) orders
+    /// from the same trader are matched.
+    ///
+    /// # Arguments
+    ///
+    /// - `mode` - Self-trade prevention mode
+    ///
+    pub fn set_self_trade_prevention(&mut self, mode: SelfTradePrevention) {
+        self.self_trade_prevention = mode;
+    }
+
+    /// Get the self-trade prevention mode
+    pub fn get_self_trade_prevention(&self) -> SelfTradePrevention {
+        self.self_trade_prevention
+    }
+
+    /// Set the maximum trade price move of an aggressive order
+    ///
+    /// Matching of an aggressive order halts if the
+    /// price of the next resting order is more than
+    /// `max_trade_move` from the price of the first
+    /// order it matched, with the remaining volume of
+    /// the aggressive order cancelled. Unlike a price
+    /// band (see [OrderBook::set_price_band]) this limits
+    /// the impact of a single order. By default the
+    /// move is not limited.
+    ///
+    /// # Arguments
+    ///
+    /// - `max_trade_move` - Maximum price move, if
+    ///   `None` the move is not limited
+    ///
+    pub fn set_max_trade_move(&mut self, max_trade_move: Option<Price>) {
+        self.max_trade_move = max_trade_move;
+    }
+
+    /// Set a listener called with order book events
+    ///
+    /// The listener is called synchronously with each
+    /// [BookEvent] as it occurs, i.e. orders being placed
+    /// on the market (including triggered stop orders),
+    /// cancelled (including expiries and cancellations of
+    /// unfilled volume) or modified, and trades. Listeners
+    /// are not saved in snapshots, or copied if the order
+    /// book is cloned.
+    ///
+    /// # Arguments
+    ///
+    /// - `listener` - Event callback, if `None` any
+    ///   existing listener is removed
+    ///
+    pub fn set_event_listener(&mut self, listener: Option<EventCallback>) {
+        self.on_event = EventListener(listener);
+    }
+
+    /// Get the maximum trade price move of an aggressive order
+    pub fn get_max_trade_move(&self) -> Option<Price> {
+        self.max_trade_move
+    }
+
+    /// Set the minimum resting time of orders
+    ///
+    /// Active orders cannot be cancelled or modified until
+    /// they have rested on the book for the minimum time,
+    /// cancellations and modifications received before
+    /// then are ignored.
+    /// Orders that have not been placed, and expiring
+    /// orders, are not affected. By default the minimum
+    /// resting time is `0`.
+    ///
+    /// # Arguments
+    ///
+    /// - `min_resting_time` - Time after placement
+    ///   during which orders cannot be cancelled
+    ///   or modified
+    ///
+    pub fn set_min_resting_time(&mut self, min_resting_time: Nanos) {
+        self.min_resting_time = min_resting_time;
+    }
+
+    /// Get the minimum resting time of orders
+    pub fn get_min_resting_time(&self) -> Nanos {
+        self.min_resting_time
+    }
+
+    /// Set the number of decimal places of formatted prices
+    ///
+    /// Prices are integer values, when formatted for
+    /// display (see [OrderBook::format_price]) they are
+    /// scaled down by `10^price_decimals`, e.g. with
+    /// 2 decimal places a price of `12345` is displayed
+    /// as `123.45`. By default prices are displayed
+    /// unscaled.
+    ///
+    /// # Arguments
+    ///
+    /// - `price_decimals` - Number of decimal places
+    ///
+    pub fn set_price_decimals(&mut self, price_decimals: u32) {
+        assert!(
+            price_decimals <= 9,
+            "Price decimals must be less than 10, got {}",
+            price_decimals
+        );
+        self.price_decimals = price_decimals;
+    }
+
+    /// Get the number of decimal places of formatted prices
+    pub fn get_price_decimals(&self) -> u32 {
+        self.price_decimals
+    }
+
+    /// Get the tick size of the market
+    pub fn get_tick_size(&self) -> Price {
+        self.tick_size
+    }
+
+    /// Set the band of prices orders can trade at
+    ///
+    /// Limit orders with a price outside the band
+    /// are rejected, and aggressive orders will not
+    /// match resting orders priced outside the band
+    /// (any unmatched volume is then handled as
+    /// normal, e.g. market orders are cancelled).
+    /// By default prices are not banded.
+    ///
+    /// # Arguments
+    ///
+    /// - `band` - Inclusive lower and upper price
+    ///   limits, if `None` prices are not banded
+    ///
+    pub fn set_price_band(&mut self, band: Option<(Price, Price)>) {
+        if let Some((low, high)) = band {
+            assert!(low <= high, "Lower price band limit above upper limit");
+        }
+        self.price_band = band;
+    }
+
+    /// Get the band of prices orders can trade at
+    pub fn get_price_band(&self) -> Option<(Price, Price)> {
+        self.price_band
+    }
+
+    /// Check if a price is further than the maximum trade move from a start price
+    fn exceeds_trade_move(&self, start_price: Price, price: Price) -> bool {
+        match self.max_trade_move {
+            Some(max_move) => start_price.abs_diff(price) > max_move,
+            None => false,
+        }
+    }
+
+    /// Check if a price is within the price band
+    fn within_band(&self, price: Price) -> bool {
+        match self.price_band {
+            Some((low, high)) => (low..=high).contains(&price),
+            None => true,
+        }
+    }
+
+    /// Format a price for display
+    ///
+    /// Formats an integer price as a decimal string
+    /// with the number of decimal places set by
+    /// [OrderBook::set_price_decimals].
+    ///
+    /// # Arguments
+    ///
+    /// - `p` - Price to format
+    ///
+    pub fn format_price(&self, p: Price) -> String {
+        match self.price_decimals {
+            0 => p.to_string(),
+            d => {
+                let scale = 10u32.pow(d);
+                format!("{}.{:0width$}", p / scale, p % scale, width = d as usize)
+            }
         }
     }
 
@@ -184,6 +631,13 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
         self.t = t;
     }
 
+    /// Get the time elapsed since the most recent trade
+    ///
+    /// Returns `None` if no trades have taken place.
+    pub fn time_since_last_trade(&self) -> Option<Nanos> {
+        self.trades.last().map(|x| self.t.saturating_sub(x.t))
+    }
+
     /// Enable trade execution
     pub fn enable_trading(&mut self) {
         self.trading = true;
@@ -191,12 +645,119 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
 
     /// Disable trade execution
     ///
-    /// > **_NOTE:_** Currently there is not
-    ///   a un-crossing algorithm implemented
+    /// > **_NOTE:_** Orders placed while trading is
+    /// > disabled may cross, [OrderBook::uncross] can
+    /// > be used to clear a crossed book before trading
+    /// > is re-enabled.
     pub fn disable_trading(&mut self) {
         self.trading = false;
     }
 
+    /// Uncross the book with a call auction
+    ///
+    /// Clears a crossed book (e.g. after orders have been
+    /// placed while trading was disabled) by executing
+    /// all crossing volume at a single clearing price.
+    /// The clearing price is the price that maximises the
+    /// executed volume, with ties broken by choosing the
+    /// price that minimises the imbalance between the
+    /// bid volume at or above, and ask volume at or below,
+    /// the price. Any remaining ties are broken by choosing
+    /// the price closest to the mid-price (the lower price
+    /// if equidistant).
+    ///
+    /// Orders are matched in price-time priority, with
+    /// the earlier placed order of each pair treated as the
+    /// passive order of the trade. Returns the generated
+    /// trades, which are also added to the trade records.
+    /// If the book is not crossed no trades take place.
+    /// Hidden reserve volume of iceberg orders is not
+    /// included in the auction, but is replenished as
+    /// usual if their displayed volume is filled.
+    ///
+    pub fn uncross(&mut self) -> Vec<Trade> {
+        let (bid, ask) = self.bid_ask();
+
+        if self.bid_vol() == 0 || self.ask_vol() == 0 || bid < ask {
+            return Vec::new();
+        }
+
+        let bid_levels = self.bid_side.price_levels();
+        let ask_levels = self.ask_side.price_levels();
+        let mid = 0.5 * (f64::from(bid) + f64::from(ask));
+
+        let mut prices: Vec<Price> = bid_levels
+            .iter()
+            .chain(ask_levels.iter())
+            .map(|x| x.0)
+            .filter(|p| (ask..=bid).contains(p))
+            .collect();
+        prices.sort_unstable();
+        prices.dedup();
+
+        let (price, vol) = prices
+            .iter()
+            .map(|p| {
+                let demand: u64 = bid_levels
+                    .iter()
+                    .take_while(|x| x.0 >= *p)
+                    .map(|x| u64::from(x.1))
+                    .sum();
+                let supply: u64 = ask_levels
+                    .iter()
+                    .take_while(|x| x.0 <= *p)
+                    .map(|x| u64::from(x.1))
+                    .sum();
+                (*p, demand.min(supply), demand.abs_diff(supply))
+            })
+            .min_by(|a, b| {
+                b.1.cmp(&a.1).then(a.2.cmp(&b.2)).then(
+                    (f64::from(a.0) - mid)
+                        .abs()
+                        .total_cmp(&(f64::from(b.0) - mid).abs()),
+                )
+            })
+            .map(|x| (x.0, x.1))
+            .unwrap();
+
+        let bid_ids: Vec<OrderId> = self
+            .bid_side
+            .order_idxs()
+            .take_while(|id| self.orders[*id].order.price >= price)
+            .collect();
+        let ask_ids: Vec<OrderId> = self
+            .ask_side
+            .order_idxs()
+            .take_while(|id| self.orders[*id].order.price <= price)
+            .collect();
+
+        let n_trades = self.trades.len();
+        let mut remaining = vol;
+        let (mut i, mut j) = (0, 0);
+
+        while remaining > 0 {
+            let (bid_id, ask_id) = (bid_ids[i], ask_ids[j]);
+            let trade_vol = self.auction_match(bid_id, ask_id, price);
+            remaining -= u64::from(trade_vol);
+            if self.orders[bid_id].order.status == Status::Filled {
+                i += 1;
+            }
+            if self.orders[ask_id].order.status == Status::Filled {
+                j += 1;
+            }
+        }
+
+        // Icebergs are replenished once the auction is complete, so
+        // replenished volume does not trade ahead of other orders
+        for id in bid_ids[..i].iter().chain(ask_ids[..j].iter()) {
+            self.replenish_order(*id);
+        }
+
+        let trades = self.trades[n_trades..].to_vec();
+        self.trigger_stop_orders();
+        trades
+    }
+
     /// Get the current cumulative trade_volume
     pub fn get_trade_vol(&self) -> Vol {
         self.trade_vol
@@ -263,103 +824,541 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
         })
     }
 
+    /// Get the number of populated price levels on a side
+    ///
+    /// Counts the distinct price levels that
+    /// currently have resting orders.
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Side of the book to count levels for
+    ///
+    pub fn num_levels(&self, side: Side) -> usize {
+        match side {
+            Side::Bid => self.bid_side.num_levels(),
+            Side::Ask => self.ask_side.num_levels(),
+        }
+    }
+
     /// Get current bid-ask price
     pub fn bid_ask(&self) -> (Price, Price) {
         (self.bid_side.best_price(), self.ask_side.best_price())
     }
 
-    /// Get current mid-price (as a float)
-    pub fn mid_price(&self) -> f64 {
-        let (bid, ask) = self.bid_ask();
-        let spread = ask - bid;
-        f64::from(bid) + 0.5 * f64::from(spread)
+    /// Set the expiry time of an order
+    ///
+    /// The order will be cancelled by
+    /// [OrderBook::expire_orders] once the order book
+    /// time reaches the expiry time, and good-till-cancel
+    /// orders are marked as [TimeInForce::GoodTillDate].
+    /// No change is made if the order is already filled,
+    /// cancelled or rejected.
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the order
+    /// - `expiry` - Time the order expires
+    ///
+    pub fn set_order_expiry(&mut self, order_id: OrderId, expiry: Nanos) {
+        let order = &mut self.orders[order_id].order;
+
+        if matches!(order.status, Status::New | Status::Active | Status::Pending) {
+            order.expiry = Some(expiry);
+            if order.time_in_force == TimeInForce::GoodTillCancel {
+                order.time_in_force = TimeInForce::GoodTillDate;
+            }
+            self.expiries.push(Reverse((expiry, order_id)));
+        }
     }
 
-    /// Get current level 1 market data
-    ///
-    /// Returns level 1 data which includes
+    /// Cancel orders that have expired
     ///
-    /// - Best bid and ask prices
-    /// - Total bid and ask side volumes
-    /// - Bid and ask volumes at the touch
-    /// - Number of bid and ask orders at the touch
+    /// Cancels any new, active or pending orders with an
+    /// expiry time less than or equal to the current
+    /// order book time.
     ///
-    pub fn level_1_data(&self) -> Level1Data {
-        let (bid_price, ask_price) = self.bid_ask();
-        let (bid_touch_vol, bid_touch_orders) = self.bid_best_vol_and_orders();
-        let (ask_touch_vol, ask_touch_orders) = self.ask_best_vol_and_orders();
-        Level1Data {
-            bid_price,
-            ask_price,
-            bid_vol: self.bid_vol(),
-            ask_vol: self.ask_vol(),
-            bid_touch_vol,
-            ask_touch_vol,
-            bid_touch_orders,
-            ask_touch_orders,
+    pub fn expire_orders(&mut self) {
+        while let Some(Reverse((expiry, order_id))) = self.expiries.peek().copied() {
+            if expiry > self.t {
+                break;
+            }
+            self.expiries.pop();
+            if self.orders[order_id].order.expiry == Some(expiry) {
+                self.force_cancel_order(order_id);
+            }
         }
     }
 
-    /// Get current level 2 market data
+    /// Get the highest priority resting order on each side
     ///
-    /// In this case level 2 data contains
-    /// additional order information at a fixed number
-    /// of ticks from the best price
+    /// Returns references to the orders that would be
+    /// matched first on the bid and ask sides, or `None`
+    /// if a side of the book is empty.
+    pub fn best_orders(&self) -> (Option<&Order>, Option<&Order>) {
+        (
+            self.bid_side
+                .best_order_idx()
+                .map(|x| &self.orders[x].order),
+            self.ask_side
+                .best_order_idx()
+                .map(|x| &self.orders[x].order),
+        )
+    }
+
+    /// Get the number of price levels a limit order would cross
     ///
-    /// - Best bid and ask prices
-    /// - Total bid and ask volumes
-    /// - Volume and number of orders at N levels (ticks)
-    ///   above/below the bid ask (where N is 10 by default)
+    /// Counts the populated price levels on the opposite
+    /// side of the book that a limit order at the given
+    /// price would reach, e.g. for a bid the number of
+    /// ask levels at or below the price.
     ///
-    pub fn level_2_data(&self) -> Level2Data<LEVELS> {
-        let (bid_price, ask_price) = self.bid_ask();
-        Level2Data {
-            bid_price,
-            ask_price,
-            bid_vol: self.bid_vol(),
-            ask_vol: self.ask_vol(),
-            bid_price_levels: self.bid_levels(),
-            ask_price_levels: self.ask_levels(),
+    /// # Arguments
+    ///
+    /// - `side` - Side of the limit order
+    /// - `price` - Price of the limit order
+    ///
+    pub fn levels_crossed(&self, side: Side, price: Price) -> usize {
+        match side {
+            Side::Bid => self
+                .ask_side
+                .price_levels()
+                .iter()
+                .take_while(|(p, _)| *p <= price)
+                .count(),
+            Side::Ask => self
+                .bid_side
+                .price_levels()
+                .iter()
+                .take_while(|(p, _)| *p >= price)
+                .count(),
         }
     }
 
-    /// Get the next order-id in the sequence
-    fn current_order_id(&self) -> OrderId {
-        self.orders.len()
+    /// Get the average price of sweeping the book
+    ///
+    /// Calculates the volume weighted average price
+    /// an aggressive order of the given size would
+    /// execute at, without modifying the book. Returns
+    /// `None` if the volume is zero, or there is
+    /// insufficient volume on the book to fill the order.
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Side of the aggressive order, i.e.
+    ///   a bid order will sweep the ask side of the book
+    /// - `vol` - Volume of the aggressive order
+    ///
+    pub fn sweep_price(&self, side: Side, vol: Vol) -> Option<f64> {
+        let levels = match side {
+            Side::Bid => self.ask_side.price_levels(),
+            Side::Ask => self.bid_side.price_levels(),
+        };
+        sweep_levels(&levels, vol)
     }
 
-    /// Get a reference to the order data stored at the id
+    /// Get the cost of a round trip across the spread
+    ///
+    /// Calculates the cost of buying the given volume
+    /// with a market order, then immediately selling it
+    /// with a market order, i.e. the volume multiplied by
+    /// the difference between the average prices of
+    /// sweeping the ask and bid sides of the book. This
+    /// includes both the spread and the price impact of
+    /// walking the book on each side. The book is not
+    /// modified. Returns `None` if the volume is zero, or
+    /// either side lacks the volume to fill the orders.
     ///
     /// # Arguments
     ///
-    /// - `order_id` - Id of the order
+    /// - `vol` - Volume bought and sold
     ///
-    pub fn order(&self, order_id: OrderId) -> &Order {
-        &self.orders[order_id].order
+    pub fn round_trip_cost(&self, vol: Vol) -> Option<f64> {
+        let buy_price = self.sweep_price(Side::Bid, vol)?;
+        let sell_price = self.sweep_price(Side::Ask, vol)?;
+        Some(f64::from(vol) * (buy_price - sell_price))
     }
 
-    /// Create a new order
+    /// Get the volume weighted average price of resting orders
     ///
-    /// Create a new order in the order list, but
-    /// this order is not automatically placed on
-    /// the market. Returns the id of the newly
-    /// created order.
+    /// Calculates the volume weighted average price of
+    /// the orders in the best `depth` populated price
+    /// levels on a side of the book. Returns `None` if
+    /// the side is empty or `depth` is zero.
     ///
     /// # Arguments
     ///
-    /// - `side` - Order side
-    /// - `vol` - Order volume
-    /// - `trader_id` - Id of the trader placing the order
-    /// - `price` -  Price of the order, if `None` the
-    ///   order is treated as a market order
+    /// - `side` - Side of the book
+    /// - `depth` - Number of price levels to include
     ///
-    pub fn create_order(
+    pub fn resting_vwap(&self, side: Side, depth: usize) -> Option<f64> {
+        let levels = match side {
+            Side::Bid => self.bid_side.price_levels(),
+            Side::Ask => self.ask_side.price_levels(),
+        };
+
+        let (notional, vol) =
+            levels
+                .iter()
+                .take(depth)
+                .fold((0.0, 0u64), |(notional, vol), (p, v)| {
+                    (
+                        notional + f64::from(*p) * f64::from(*v),
+                        vol + u64::from(*v),
+                    )
+                });
+
+        match vol {
+            0 => None,
+            _ => Some(notional / vol as f64),
+        }
+    }
+
+    /// Get the average order size at price levels
+    ///
+    /// Returns the average size of resting orders
+    /// (i.e. volume divided by number of orders) at
+    /// `depth` price levels from the touch, spaced by
+    /// the tick-size as in [OrderBook::level_2_data].
+    /// Levels with no orders have an average size of `0.0`.
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Side of the book
+    /// - `depth` - Number of price levels to include
+    ///
+    pub fn avg_order_size(&self, side: Side, depth: usize) -> Vec<f64> {
+        let (bid, ask) = self.bid_ask();
+        (0..depth)
+            .map(|i| {
+                let offset = Price::try_from(i).unwrap() * self.tick_size;
+                let (vol, n) = match side {
+                    Side::Bid => self
+                        .bid_side
+                        .vol_and_orders_at_price(bid.wrapping_sub(offset)),
+                    Side::Ask => self
+                        .ask_side
+                        .vol_and_orders_at_price(ask.wrapping_add(offset)),
+                };
+                match n {
+                    0 => 0.0,
+                    _ => f64::from(vol) / f64::from(n),
+                }
+            })
+            .collect()
+    }
+
+    /// Get the skew of resting volume around the mid-price
+    ///
+    /// Summarises the shape of the book by weighting the
+    /// volume of the best `depth` populated price levels
+    /// on each side by the inverse of their distance
+    /// (in ticks) from the mid-price, and returning
+    ///
+    /// ```text
+    /// (bid_weight - ask_weight) / (bid_weight + ask_weight)
+    /// ```
+    ///
+    /// The skew is in the range `[-1, 1]`, and is positive
+    /// when the bid side is heavier or closer to the mid.
+    /// Returns `0.0` if either side of the book is empty.
+    ///
+    /// # Arguments
+    ///
+    /// - `depth` - Number of price levels to include
+    ///
+    pub fn book_skew(&self, depth: usize) -> f64 {
+        if self.bid_vol() == 0 || self.ask_vol() == 0 {
+            return 0.0;
+        }
+
+        let mid = self.mid_price();
+        let tick_size = f64::from(self.tick_size);
+
+        let weight = |levels: Vec<(Price, Vol)>| -> f64 {
+            levels
+                .iter()
+                .take(depth)
+                .map(|(p, v)| {
+                    let distance = ((f64::from(*p) - mid).abs() / tick_size).max(0.5);
+                    f64::from(*v) / distance
+                })
+                .sum()
+        };
+
+        let bid_weight = weight(self.bid_side.price_levels());
+        let ask_weight = weight(self.ask_side.price_levels());
+
+        match bid_weight + ask_weight {
+            0.0 => 0.0,
+            total => (bid_weight - ask_weight) / total,
+        }
+    }
+
+    /// Check if the best bid is above the best ask
+    ///
+    /// This can only occur when trading is disabled.
+    pub fn is_crossed(&self) -> bool {
+        self.market_condition() == MarketCondition::Crossed
+    }
+
+    /// Get the current condition of the market
+    ///
+    /// Classifies the market from the current best
+    /// bid and ask prices, see [MarketCondition].
+    /// Markets with an empty side are always
+    /// considered [MarketCondition::Normal].
+    ///
+    pub fn market_condition(&self) -> MarketCondition {
+        let (bid, ask) = self.bid_ask();
+        match bid.cmp(&ask) {
+            std::cmp::Ordering::Less => MarketCondition::Normal,
+            std::cmp::Ordering::Equal => MarketCondition::Locked,
+            std::cmp::Ordering::Greater => MarketCondition::Crossed,
+        }
+    }
+
+    /// Get current mid-price (as a float)
+    pub fn mid_price(&self) -> f64 {
+        let (bid, ask) = self.bid_ask();
+        let spread = ask - bid;
+        f64::from(bid) + 0.5 * f64::from(spread)
+    }
+
+    /// Get the current mid-price rounded to a tick
+    ///
+    /// If the spread is an odd number of ticks the
+    /// mid-price falls half-way between two ticks, and
+    /// is rounded according to the rounding mode. Returns
+    /// `None` if either side of the book is empty.
+    ///
+    /// # Arguments
+    ///
+    /// - `round` - Rounding of mid-prices between ticks
+    ///
+    pub fn mid_price_ticked(&self, round: RoundMode) -> Option<Price> {
+        if self.bid_vol() == 0 || self.ask_vol() == 0 {
+            return None;
+        }
+
+        let (bid, ask) = self.bid_ask();
+        // Mid-price in half-ticks
+        let half_ticks = (u64::from(bid) + u64::from(ask)) / u64::from(self.tick_size);
+        let ticks = match round {
+            RoundMode::Down => half_ticks / 2,
+            RoundMode::Up => half_ticks.div_ceil(2),
+            RoundMode::Nearest => {
+                let down = half_ticks / 2;
+                down + (half_ticks % 2) * (down % 2)
+            }
+        };
+
+        Price::try_from(ticks * u64::from(self.tick_size)).ok()
+    }
+
+    /// Get the volume imbalance of the book
+    ///
+    /// Calculated as `(bid_vol - ask_vol) / (bid_vol + ask_vol)`
+    /// from the volumes at the price levels (ticks) from the
+    /// touch, see [OrderBook::bid_levels] and
+    /// [OrderBook::ask_levels]. Returns `0.0` if there is no
+    /// volume at these levels.
+    ///
+    /// # Arguments
+    ///
+    /// - `levels` - Number of price levels from the touch
+    ///   to include, limited to `LEVELS`
+    ///
+    pub fn book_imbalance(&self, levels: usize) -> f64 {
+        let levels = levels.min(LEVELS);
+        let bid_vol: u64 = self.bid_levels()[..levels]
+            .iter()
+            .map(|x| u64::from(x.0))
+            .sum();
+        let ask_vol: u64 = self.ask_levels()[..levels]
+            .iter()
+            .map(|x| u64::from(x.0))
+            .sum();
+        let total = bid_vol + ask_vol;
+
+        match total {
+            0 => 0.0,
+            _ => (bid_vol as f64 - ask_vol as f64) / total as f64,
+        }
+    }
+
+    /// Get the touch volume weighted mid-price
+    ///
+    /// Returns the touch prices weighted by the volume
+    /// at the opposite touch, i.e.
+    /// `(bid * ask_vol + ask * bid_vol) / (bid_vol + ask_vol)`.
+    /// Returns `0.0` if either side of the book is empty.
+    pub fn weighted_mid_price(&self) -> f64 {
+        let (bid, ask) = self.bid_ask();
+        let bid_vol = f64::from(self.bid_best_vol());
+        let ask_vol = f64::from(self.ask_best_vol());
+
+        match (bid_vol > 0.0) & (ask_vol > 0.0) {
+            true => (f64::from(bid) * ask_vol + f64::from(ask) * bid_vol) / (bid_vol + ask_vol),
+            false => 0.0,
+        }
+    }
+
+    /// Get current level 1 market data
+    ///
+    /// Returns level 1 data which includes
+    ///
+    /// - Best bid and ask prices
+    /// - Total bid and ask side volumes
+    /// - Bid and ask volumes at the touch
+    /// - Number of bid and ask orders at the touch
+    ///
+    pub fn level_1_data(&self) -> Level1Data {
+        let (bid_price, ask_price) = self.bid_ask();
+        let (bid_touch_vol, bid_touch_orders) = self.bid_best_vol_and_orders();
+        let (ask_touch_vol, ask_touch_orders) = self.ask_best_vol_and_orders();
+        Level1Data {
+            bid_price,
+            ask_price,
+            bid_vol: self.bid_vol(),
+            ask_vol: self.ask_vol(),
+            bid_touch_vol,
+            ask_touch_vol,
+            bid_touch_orders,
+            ask_touch_orders,
+        }
+    }
+
+    /// Get current level 2 market data
+    ///
+    /// In this case level 2 data contains
+    /// additional order information at a fixed number
+    /// of ticks from the best price
+    ///
+    /// - Best bid and ask prices
+    /// - Total bid and ask volumes
+    /// - Volume and number of orders at N levels (ticks)
+    ///   above/below the bid ask (where N is 10 by default)
+    ///
+    pub fn level_2_data(&self) -> Level2Data<LEVELS> {
+        let (bid_price, ask_price) = self.bid_ask();
+        Level2Data {
+            bid_price,
+            ask_price,
+            bid_vol: self.bid_vol(),
+            ask_vol: self.ask_vol(),
+            bid_price_levels: self.bid_levels(),
+            ask_price_levels: self.ask_levels(),
+        }
+    }
+
+    /// Get current level 3 market data
+    ///
+    /// Returns, for each of the best `depth` populated
+    /// price levels on each side of the book, the orders
+    /// resting at the price in priority order, along with
+    /// their (displayed) volume, trader id and arrival time.
+    ///
+    /// # Arguments
+    ///
+    /// - `depth` - Number of populated price levels
+    ///   to include on each side
+    ///
+    pub fn level_3_data(&self, depth: usize) -> Level3Data {
+        Level3Data {
+            bid_levels: self.side_level_3_data(self.bid_side.order_idxs(), depth),
+            ask_levels: self.side_level_3_data(self.ask_side.order_idxs(), depth),
+        }
+    }
+
+    /// Group orders (in priority order) by price level
+    fn side_level_3_data(
+        &self,
+        order_idxs: impl Iterator<Item = OrderId>,
+        depth: usize,
+    ) -> Vec<(Price, Level3Orders)> {
+        let mut levels: Vec<(Price, Level3Orders)> = Vec::new();
+
+        for order_id in order_idxs {
+            let order = &self.orders[order_id].order;
+            let entry = (order.order_id, order.vol, order.trader_id, order.arr_time);
+            match levels.last_mut() {
+                Some((price, orders)) if *price == order.price => orders.push(entry),
+                _ => {
+                    if levels.len() == depth {
+                        break;
+                    }
+                    levels.push((order.price, vec![entry]));
+                }
+            }
+        }
+
+        levels
+    }
+
+    /// Get the next order-id in the sequence
+    fn current_order_id(&self) -> OrderId {
+        self.orders.len()
+    }
+
+    /// Get the next order key sequence number
+    fn next_seq(&mut self) -> u64 {
+        let seq = self.seq;
+        self.seq += 1;
+        seq
+    }
+
+    /// Get the within level priority key of an order
+    ///
+    /// # Arguments
+    ///
+    /// - `vol` - Volume of the order
+    ///
+    fn size_priority(&self, vol: Vol) -> Vol {
+        match self.priority {
+            WithinLevelPriority::Time => 0,
+            WithinLevelPriority::SizeThenTime => Vol::MAX - vol,
+        }
+    }
+
+    /// Get a reference to the order data stored at the id
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the order
+    ///
+    pub fn order(&self, order_id: OrderId) -> &Order {
+        &self.orders[order_id].order
+    }
+
+    /// Create a new order
+    ///
+    /// Create a new order in the order list, but
+    /// this order is not automatically placed on
+    /// the market. Returns the id of the newly
+    /// created order.
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Order side
+    /// - `vol` - Order volume
+    /// - `trader_id` - Id of the trader placing the order
+    /// - `price` -  Price of the order, if `None` the
+    ///   order is treated as a market order
+    ///
+    pub fn create_order(
         &mut self,
         side: Side,
         vol: Vol,
         trader_id: TraderId,
         price: Option<Price>,
     ) -> Result<OrderId, OrderError> {
+        if !vol.is_multiple_of(self.lot_size) {
+            return Err(OrderError::LotError {
+                vol,
+                lot_size: self.lot_size,
+            });
+        }
+
         let order_id = self.current_order_id();
 
         let order = match (side, price) {
@@ -386,18 +1385,77 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
         };
 
         let key = match side {
-            Side::Bid => get_bid_key(0, order.price),
-            Side::Ask => get_ask_key(0, order.price),
+            Side::Bid => get_bid_key(0, order.price, 0, 0),
+            Side::Ask => get_ask_key(0, order.price, 0, 0),
         };
 
-        self.orders.push(OrderEntry { order, key });
+        self.orders.push(OrderEntry {
+            order,
+            key,
+            start_price: None,
+        });
 
         Ok(order_id)
     }
 
-    /// Convenience function to create and immediately place an order
+    /// Create a new iceberg (reserve) limit order
     ///
-    /// Create a new order in the order list and place it on the market.
+    /// Only `display_vol` of the order is visible on the
+    /// book at any time, with the remainder held in
+    /// reserve. When the displayed volume is filled it
+    /// is replenished from the reserve, with the new
+    /// slice losing its time priority. Reserve volume is
+    /// not included in side or price level volumes.
+    /// Returns the id of the newly created order.
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Order side
+    /// - `total_vol` - Total volume of the order
+    /// - `display_vol` - Volume displayed on the book
+    /// - `trader_id` - Id of the trader placing the order
+    /// - `price` - Price of the order
+    ///
+    pub fn create_iceberg_order(
+        &mut self,
+        side: Side,
+        total_vol: Vol,
+        display_vol: Vol,
+        trader_id: TraderId,
+        price: Price,
+    ) -> Result<OrderId, OrderError> {
+        if display_vol == 0 || display_vol > total_vol {
+            return Err(OrderError::DisplayVolError {
+                display_vol,
+                vol: total_vol,
+            });
+        }
+        if !display_vol.is_multiple_of(self.lot_size) {
+            return Err(OrderError::LotError {
+                vol: display_vol,
+                lot_size: self.lot_size,
+            });
+        }
+
+        let order_id = self.create_order(side, total_vol, trader_id, Some(price))?;
+
+        let order = &mut self.orders[order_id].order;
+        order.vol = display_vol;
+        order.reserve_vol = total_vol - display_vol;
+        order.display_vol = display_vol;
+
+        Ok(order_id)
+    }
+
+    /// Create a new stop order
+    ///
+    /// When placed, stop orders wait off the book (with
+    /// status [Status::Pending]) until the last trade
+    /// price reaches the trigger price, i.e. a trade at or
+    /// above the trigger price for buy stops, or at or below
+    /// the trigger price for sell stops. Once triggered the
+    /// order is placed on the market as a market order,
+    /// or a limit order if a limit price is given.
     /// Returns the id of the newly created order.
     ///
     /// # Arguments
@@ -405,102 +1463,714 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
     /// - `side` - Order side
     /// - `vol` - Order volume
     /// - `trader_id` - Id of the trader placing the order
+    /// - `trigger` - Trigger price of the order
+    /// - `limit` - Limit price of the triggered order, if
+    ///   `None` the triggered order is a market order
+    ///
+    pub fn create_stop_order(
+        &mut self,
+        side: Side,
+        vol: Vol,
+        trader_id: TraderId,
+        trigger: Price,
+        limit: Option<Price>,
+    ) -> Result<OrderId, OrderError> {
+        if !trigger.is_multiple_of(self.tick_size) {
+            return Err(OrderError::PriceError {
+                price: trigger,
+                tick_size: self.tick_size,
+            });
+        }
+
+        let order_id = self.create_order(side, vol, trader_id, limit)?;
+        self.orders[order_id].order.trigger_price = Some(trigger);
+
+        Ok(order_id)
+    }
+
+    /// Create a new order with a time-in-force
+    ///
+    /// Create a new order in the order list, as
+    /// [OrderBook::create_order], with a time-in-force.
+    /// Immediate-or-cancel limit orders have any volume
+    /// not matched on arrival cancelled, rather than
+    /// resting on the book. Fill-or-kill limit orders
+    /// are rejected, without trading, unless the book has
+    /// enough volume within the limit price to fill the
+    /// whole order (note that orders inside the protection
+    /// window count towards this volume, any volume then
+    /// left unfilled is cancelled). The time-in-force of
+    /// market orders has no effect. Returns the id of the
+    /// newly created order.
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Order side
+    /// - `vol` - Order volume
+    /// - `trader_id` - Id of the trader placing the order
     /// - `price` -  Price of the order, if `None` the
     ///   order is treated as a market order
+    /// - `time_in_force` - Time-in-force of the order
     ///
-    pub fn create_and_place_order(
+    pub fn create_order_with_tif(
         &mut self,
         side: Side,
         vol: Vol,
         trader_id: TraderId,
         price: Option<Price>,
+        time_in_force: TimeInForce,
     ) -> Result<OrderId, OrderError> {
         let order_id = self.create_order(side, vol, trader_id, price)?;
-        self.place_order(order_id);
+        self.orders[order_id].order.time_in_force = time_in_force;
         Ok(order_id)
     }
 
-    /// Match an aggressive buy order
+    /// Create a new post-only limit order
+    ///
+    /// Post-only orders never take liquidity, if the
+    /// order would immediately match against the opposite
+    /// side of the book when placed (i.e. its price crosses
+    /// or locks with the opposite touch) it is rejected.
+    /// Returns the id of the newly created order.
     ///
     /// # Arguments
     ///
-    /// - `order_entry` - Aggressive order details
+    /// - `side` - Order side
+    /// - `vol` - Order volume
+    /// - `trader_id` - Id of the trader placing the order
+    /// - `price` - Price of the order
     ///
-    fn match_bid(&mut self, order_entry: &mut OrderEntry) {
-        while (order_entry.order.vol > 0) & (order_entry.order.price >= self.ask_side.best_price())
-        {
-            let next_order_id = self.ask_side.best_order_idx();
-            match next_order_id {
-                Some(id) => {
-                    let match_order = &mut self.orders.get_mut(id).unwrap();
-                    let trade_vol = match_orders(
-                        self.t,
-                        &mut order_entry.order,
-                        &mut match_order.order,
-                        &mut self.trades,
-                    );
-                    self.trade_vol += trade_vol;
-                    if match_order.order.status == Status::Filled {
-                        self.ask_side.remove_order(match_order.key, trade_vol);
-                    } else {
-                        self.ask_side.remove_vol(match_order.key.1, trade_vol);
-                    }
-                }
-                None => {
-                    break;
-                }
-            }
-        }
+    pub fn create_post_only_order(
+        &mut self,
+        side: Side,
+        vol: Vol,
+        trader_id: TraderId,
+        price: Price,
+    ) -> Result<OrderId, OrderError> {
+        let order_id = self.create_order(side, vol, trader_id, Some(price))?;
+        self.orders[order_id].order.post_only = true;
+        Ok(order_id)
     }
 
-    /// Match an aggressive sell order
+    /// Create a new market order sized by a cash budget
     ///
-    /// # Arguments
+    /// Walks the opposite side of the book from the touch,
+    /// accumulating the cost (price times volume) of resting
+    /// volume, to find the largest volume (a multiple of the
+    /// lot size) that can be bought, or sold, for at most
+    /// `cash`. A market order of this volume is then created.
+    /// Returns the id of the newly created order, and the
+    /// leftover cash that could not be spent. Returns an
+    /// error if the budget cannot buy (or sell) at least
+    /// one lot, including if the opposite side of the book
+    /// is empty.
+    ///
+    /// Note that the volume is calculated from the state of
+    /// the book when the order is created, the order is then
+    /// a regular market order. Hidden iceberg volume is not
+    /// included, and the cost may differ if the book changes
+    /// before the order is placed, or if resting orders are
+    /// inside the protection window.
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Order side
+    /// - `cash` - Cash budget of the order
+    /// - `trader_id` - Id of the trader placing the order
+    ///
+    pub fn create_market_order_by_value(
+        &mut self,
+        side: Side,
+        cash: u64,
+        trader_id: TraderId,
+    ) -> Result<(OrderId, u64), OrderError> {
+        let levels = match side {
+            Side::Bid => self.ask_side.price_levels(),
+            Side::Ask => self.bid_side.price_levels(),
+        };
+        let (vol, cost) = budget_levels(&levels, cash, self.lot_size);
+        if vol == 0 {
+            return Err(OrderError::BudgetError { cash });
+        }
+        let order_id = self.create_order(side, vol, trader_id, None)?;
+        Ok((order_id, cash - cost))
+    }
+
+    /// Convenience function to create and immediately place an order
+    ///
+    /// Create a new order in the order list and place it on the market.
+    /// Returns the id of the newly created order.
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Order side
+    /// - `vol` - Order volume
+    /// - `trader_id` - Id of the trader placing the order
+    /// - `price` -  Price of the order, if `None` the
+    ///   order is treated as a market order
+    ///
+    pub fn create_and_place_order(
+        &mut self,
+        side: Side,
+        vol: Vol,
+        trader_id: TraderId,
+        price: Option<Price>,
+    ) -> Result<OrderId, OrderError> {
+        let order_id = self.create_order(side, vol, trader_id, price)?;
+        self.place_order(order_id);
+        Ok(order_id)
+    }
+
+    /// Match an aggressive buy order
+    ///
+    /// # Arguments
+    ///
+    /// - `order_entry` - Aggressive order details
+    ///
+    fn match_bid(&mut self, order_entry: &mut OrderEntry) {
+        while order_entry.order.vol > 0 {
+            let next_order_id = self.next_match_idx(Side::Ask);
+            match next_order_id {
+                Some(id)
+                    if self.orders[id].order.price <= order_entry.order.price
+                        && self.within_band(self.orders[id].order.price) =>
+                {
+                    let price = self.orders[id].order.price;
+                    if self.exceeds_trade_move(*order_entry.start_price.get_or_insert(price), price)
+                    {
+                        self.cancel_aggressor(order_entry);
+                        break;
+                    }
+                    if self.self_trade_prevention != SelfTradePrevention::None
+                        && self.orders[id].order.trader_id == order_entry.order.trader_id
+                    {
+                        self.prevent_self_trade(order_entry, id);
+                        if order_entry.order.status == Status::Cancelled {
+                            break;
+                        }
+                        continue;
+                    }
+                    let match_order = &mut self.orders.get_mut(id).unwrap();
+                    let trade_vol = match_orders(
+                        self.t,
+                        &mut order_entry.order,
+                        &mut match_order.order,
+                        &mut self.trades,
+                        &mut self.trader_vols,
+                        &mut self.trader_positions,
+                        &mut self.on_event,
+                    );
+                    self.trade_vol += trade_vol;
+                    self.filled_vol += u64::from(trade_vol);
+                    if match_order.order.status == Status::Filled {
+                        self.ask_side.remove_order(match_order.key, trade_vol);
+                        untrack_order(&mut self.active_orders, &match_order.order);
+                        self.replenish_order(id);
+                    } else {
+                        self.ask_side.remove_vol(match_order.key.1, trade_vol);
+                    }
+                }
+                _ => {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Match an aggressive sell order
+    ///
+    /// # Arguments
     ///
     /// - `order_entry` - Aggressive order details
     ///
     fn match_ask(&mut self, order_entry: &mut OrderEntry) {
-        while (order_entry.order.vol > 0) & (order_entry.order.price <= self.bid_side.best_price())
-        {
-            let next_order_id = self.bid_side.best_order_idx();
+        while order_entry.order.vol > 0 {
+            let next_order_id = self.next_match_idx(Side::Bid);
             match next_order_id {
-                Some(id) => {
+                Some(id)
+                    if self.orders[id].order.price >= order_entry.order.price
+                        && self.within_band(self.orders[id].order.price) =>
+                {
+                    let price = self.orders[id].order.price;
+                    if self.exceeds_trade_move(*order_entry.start_price.get_or_insert(price), price)
+                    {
+                        self.cancel_aggressor(order_entry);
+                        break;
+                    }
+                    if self.self_trade_prevention != SelfTradePrevention::None
+                        && self.orders[id].order.trader_id == order_entry.order.trader_id
+                    {
+                        self.prevent_self_trade(order_entry, id);
+                        if order_entry.order.status == Status::Cancelled {
+                            break;
+                        }
+                        continue;
+                    }
                     let match_order = &mut self.orders.get_mut(id).unwrap();
                     let trade_vol = match_orders(
                         self.t,
                         &mut order_entry.order,
                         &mut match_order.order,
                         &mut self.trades,
+                        &mut self.trader_vols,
+                        &mut self.trader_positions,
+                        &mut self.on_event,
                     );
                     self.trade_vol += trade_vol;
+                    self.filled_vol += u64::from(trade_vol);
                     if match_order.order.status == Status::Filled {
                         self.bid_side.remove_order(match_order.key, trade_vol);
+                        untrack_order(&mut self.active_orders, &match_order.order);
+                        self.replenish_order(id);
                     } else {
                         self.bid_side.remove_vol(match_order.key.1, trade_vol);
                     }
                 }
-                None => {
+                _ => {
                     break;
                 }
             }
         }
     }
 
+    /// Simulate placing an order without modifying the order book
+    ///
+    /// Performs a dry run of placing an order, returning
+    /// the trades that would take place and the touch
+    /// prices after the order was placed. Orders are
+    /// matched in price-time priority as with
+    /// [OrderBook::place_order], but the simulation
+    /// iterates over resting orders rather than
+    /// modifying them, so the state of the order book
+    /// is unchanged. Trades are assigned the id the
+    /// order would be given if created next. Note that
+    /// the simulation does not apply:
+    ///
+    /// - Validation of the price and volume against the
+    ///   tick and lot sizes
+    /// - The price band (see [OrderBook::set_price_band])
+    /// - The maximum trade move (see
+    ///   [OrderBook::set_max_trade_move])
+    /// - Self-trade prevention (see
+    ///   [OrderBook::set_self_trade_prevention])
+    /// - Post-only rejection
+    ///
+    /// and hidden reserve volume of iceberg orders is not
+    /// included.
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Side of the order
+    /// - `vol` - Volume of the order
+    /// - `price` - Price of the order, if `None` the
+    ///   order is treated as a market order
+    ///
+    pub fn simulate_order(&self, side: Side, vol: Vol, price: Option<Price>) -> SimResult {
+        let (mut bid_price, mut ask_price) = self.bid_ask();
+
+        let limit = match (side, price) {
+            (_, Some(p)) => p,
+            (Side::Bid, None) => Price::MAX,
+            (Side::Ask, None) => 0,
+        };
+
+        let (trades, remaining) = match (self.trading, side) {
+            (false, _) => (Vec::new(), vol),
+            (true, Side::Bid) => {
+                let (trades, remaining, best) =
+                    self.simulate_matches(&self.ask_side, vol, |p| p <= limit, price.is_none());
+                ask_price = best.unwrap_or(Price::MAX);
+                (trades, remaining)
+            }
+            (true, Side::Ask) => {
+                let (trades, remaining, best) =
+                    self.simulate_matches(&self.bid_side, vol, |p| p >= limit, price.is_none());
+                bid_price = best.unwrap_or(0);
+                (trades, remaining)
+            }
+        };
+
+        if price.is_some() && remaining > 0 {
+            match side {
+                Side::Bid => bid_price = bid_price.max(limit),
+                Side::Ask => ask_price = ask_price.min(limit),
+            }
+        }
+
+        SimResult {
+            trades,
+            filled_vol: vol - remaining,
+            bid_price,
+            ask_price,
+        }
+    }
+
+    /// Simulate matching an aggressive order against a side
+    ///
+    /// Returns the trades that would take place, the
+    /// remaining volume of the aggressive order, and the
+    /// best price of the passive side after matching.
+    ///
+    /// # Arguments
+    ///
+    /// - `passive_side` - Side of the book to match against
+    /// - `vol` - Volume of the aggressive order
+    /// - `crosses` - Check if a passive price can be matched
+    /// - `is_market` - Flag if the aggressive order is a market order
+    ///
+    fn simulate_matches<S: SideFunctionality, F: Fn(Price) -> bool>(
+        &self,
+        passive_side: &S,
+        vol: Vol,
+        crosses: F,
+        is_market: bool,
+    ) -> (Vec<Trade>, Vol, Option<Price>) {
+        let active_order_id = self.current_order_id();
+        let mut trades = Vec::new();
+        let mut filled_ids = Vec::new();
+        let mut remaining = vol;
+
+        for id in passive_side.order_idxs() {
+            if remaining == 0 {
+                break;
+            }
+            let order = &self.orders[id].order;
+            if self.t.saturating_sub(order.arr_time) < self.protection_window {
+                continue;
+            }
+            if !crosses(order.price) {
+                break;
+            }
+            let trade_vol = min(remaining, order.vol);
+            remaining -= trade_vol;
+            if trade_vol == order.vol {
+                filled_ids.push(id);
+            }
+            trades.push(Trade {
+                t: self.t,
+                side: order.side,
+                price: order.price,
+                vol: trade_vol,
+                active_order_id,
+                passive_order_id: id,
+                agg_is_market: is_market,
+            });
+        }
+
+        let best_price = passive_side
+            .order_idxs()
+            .find(|id| !filled_ids.contains(id))
+            .map(|id| self.orders[id].order.price);
+
+        (trades, remaining, best_price)
+    }
+
+    /// Match a pair of resting orders at an auction price
+    ///
+    /// Returns the traded volume. Filled iceberg orders
+    /// are not replenished, see [OrderBook::uncross].
+    ///
+    /// # Arguments
+    ///
+    /// - `bid_id` - Id of the bid order
+    /// - `ask_id` - Id of the ask order
+    /// - `price` - Auction clearing price
+    ///
+    fn auction_match(&mut self, bid_id: OrderId, ask_id: OrderId, price: Price) -> Vol {
+        let bid = self.orders[bid_id];
+        let ask = self.orders[ask_id];
+        let trade_vol = min(bid.order.vol, ask.order.vol);
+
+        let (active, passive) = match (bid.key.3, bid.key.4) <= (ask.key.3, ask.key.4) {
+            true => (ask.order, bid.order),
+            false => (bid.order, ask.order),
+        };
+
+        let trade = Trade {
+            t: self.t,
+            side: passive.side,
+            price,
+            vol: trade_vol,
+            active_order_id: active.order_id,
+            passive_order_id: passive.order_id,
+            agg_is_market: false,
+        };
+        self.trades.push(trade);
+        self.on_event.emit(BookEvent::Trade(trade));
+        self.trade_vol += trade_vol;
+        self.filled_vol += u64::from(trade_vol);
+
+        for id in [bid_id, ask_id] {
+            let entry = &mut self.orders[id];
+            entry.order.vol -= trade_vol;
+            *self.trader_vols.entry(entry.order.trader_id).or_insert(0) += trade_vol;
+            update_position(&mut self.trader_positions, &entry.order, price, trade_vol);
+
+            let filled = entry.order.vol == 0;
+            if filled {
+                entry.order.status = Status::Filled;
+                entry.order.end_time = self.t;
+            }
+
+            if filled {
+                untrack_order(&mut self.active_orders, &entry.order);
+            }
+
+            let key = entry.key;
+            match (key.0, filled) {
+                (Side::Bid, true) => self.bid_side.remove_order(key, trade_vol),
+                (Side::Bid, false) => self.bid_side.remove_vol(key.1, trade_vol),
+                (Side::Ask, true) => self.ask_side.remove_order(key, trade_vol),
+                (Side::Ask, false) => self.ask_side.remove_vol(key.1, trade_vol),
+            }
+        }
+
+        trade_vol
+    }
+
+    /// Replenish the displayed volume of a filled iceberg order
+    ///
+    /// The replenished volume is placed on the
+    /// book with a new time priority.
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the filled order
+    ///
+    fn replenish_order(&mut self, order_id: OrderId) {
+        let mut order = self.orders[order_id].order;
+
+        if order.reserve_vol == 0 {
+            return;
+        }
+
+        replenish_display(&mut order);
+
+        let priority = self.size_priority(order.vol);
+        let seq = self.next_seq();
+        let key = match order.side {
+            Side::Bid => get_bid_key(self.t, order.price, priority, seq),
+            Side::Ask => get_ask_key(self.t, order.price, priority, seq),
+        };
+
+        match order.side {
+            Side::Bid => self.bid_side.insert_order(key, order_id, order.vol),
+            Side::Ask => self.ask_side.insert_order(key, order_id, order.vol),
+        }
+        track_order(&mut self.active_orders, &order);
+
+        self.orders[order_id] = OrderEntry {
+            order,
+            key,
+            start_price: None,
+        };
+    }
+
+    /// Get the id of the next order on a side available to be matched
+    ///
+    /// Skips any orders within the protection window.
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Side of the book to match against
+    ///
+    fn next_match_idx(&self, side: Side) -> Option<OrderId> {
+        if self.protection_window == 0 {
+            return match side {
+                Side::Bid => self.bid_side.best_order_idx(),
+                Side::Ask => self.ask_side.best_order_idx(),
+            };
+        }
+
+        let available = |id: OrderId| {
+            self.t.saturating_sub(self.orders[id].order.arr_time) >= self.protection_window
+        };
+
+        match side {
+            Side::Bid => self.bid_side.first_order_idx(available),
+            Side::Ask => self.ask_side.first_order_idx(available),
+        }
+    }
+
+    /// Apply self-trade prevention to an aggressive order
+    ///
+    /// # Arguments
+    ///
+    /// - `order_entry` - Aggressive order details
+    /// - `resting_id` - Id of the resting order from
+    ///   the same trader
+    ///
+    fn prevent_self_trade(&mut self, order_entry: &mut OrderEntry, resting_id: OrderId) {
+        let agg_vol = order_entry.order.vol;
+        let resting_vol = self.orders[resting_id].order.vol;
+
+        match self.self_trade_prevention {
+            SelfTradePrevention::None => (),
+            SelfTradePrevention::CancelResting => self.force_cancel_order(resting_id),
+            SelfTradePrevention::CancelAggressor => self.cancel_aggressor(order_entry),
+            SelfTradePrevention::DecrementBoth => {
+                if resting_vol <= agg_vol {
+                    self.force_cancel_order(resting_id);
+                }
+                if agg_vol <= resting_vol {
+                    self.cancel_aggressor(order_entry);
+                }
+                // The decremented volume is removed from the
+                // size of the order, so it is counted as
+                // neither filled nor cancelled volume
+                if resting_vol < agg_vol {
+                    order_entry.order.vol -= resting_vol;
+                    order_entry.order.start_vol -= resting_vol;
+                } else if agg_vol < resting_vol {
+                    let mut resting = self.orders[resting_id];
+                    self.reduce_order_vol(&mut resting, agg_vol);
+                    resting.order.start_vol -= agg_vol;
+                    self.orders[resting_id] = resting;
+                }
+            }
+        }
+    }
+
+    /// Get the volume available to fill a fill-or-kill order
+    ///
+    /// Volume on the passive side at prices within the
+    /// limit price of the order. If self-trade prevention
+    /// is enabled then resting volume from the same trader
+    /// cannot be matched against, so is excluded.
+    ///
+    /// # Arguments
+    ///
+    /// - `passive_side` - Side of the book to match against
+    /// - `order` - Fill-or-kill order
+    /// - `crosses` - Check if a passive price can be matched
+    ///
+    fn fill_or_kill_vol<S: SideFunctionality, F: Fn(Price) -> bool>(
+        &self,
+        passive_side: &S,
+        order: &Order,
+        crosses: F,
+    ) -> Vol {
+        let available = passive_side.available_vol_within_price(order.price);
+
+        if self.self_trade_prevention == SelfTradePrevention::None {
+            return available;
+        }
+
+        let own_vol: Vol = passive_side
+            .order_idxs()
+            .map(|id| &self.orders[id].order)
+            .take_while(|x| crosses(x.price))
+            .filter(|x| x.trader_id == order.trader_id)
+            .map(|x| x.vol)
+            .sum();
+
+        available - own_vol
+    }
+
+    /// Cancel an aggressive order during matching
+    ///
+    /// # Arguments
+    ///
+    /// - `order_entry` - Aggressive order details
+    ///
+    fn cancel_aggressor(&mut self, order_entry: &mut OrderEntry) {
+        cancel(
+            &mut order_entry.order,
+            self.t,
+            &mut self.cancelled_vol,
+            &mut self.on_event,
+        );
+    }
+
+    /// Cancel the unfilled volume of an immediate order
+    ///
+    /// Used for immediate-or-cancel and fill-or-kill
+    /// limit orders that are not completely filled on
+    /// arrival, which are cancelled rather than placed
+    /// on the book (or rejected if trading is disabled).
+    ///
+    /// # Arguments
+    ///
+    /// - `order_entry` - Order details
+    ///
+    fn cancel_unfilled(&mut self, order_entry: &mut OrderEntry) {
+        match self.trading {
+            true => {
+                cancel(
+                    &mut order_entry.order,
+                    self.t,
+                    &mut self.cancelled_vol,
+                    &mut self.on_event,
+                );
+            }
+            false => {
+                order_entry.order.status = Status::Rejected;
+                order_entry.order.end_time = self.t;
+            }
+        }
+    }
+
     /// Place a buy limit order on the market
     ///
+    /// Immediate-or-cancel orders have any volume
+    /// not matched on arrival cancelled. Fill-or-kill
+    /// orders are rejected if the opposite side does not
+    /// have enough volume at prices within the limit
+    /// price to fill the order. Post-only orders that
+    /// would cross the book, or orders priced outside
+    /// the price band (if set) are rejected.
+    ///
     /// # Arguments
     ///
     /// - `order_entry` - Order details
     ///
     fn place_bid_limit(&mut self, order_entry: &mut OrderEntry) {
+        if !self.within_band(order_entry.order.price) {
+            order_entry.order.status = Status::Rejected;
+            order_entry.order.end_time = self.t;
+            return;
+        }
         if self.trading {
+            if order_entry.order.post_only && order_entry.order.price >= self.ask_side.best_price()
+            {
+                order_entry.order.status = Status::Rejected;
+                order_entry.order.end_time = self.t;
+                return;
+            }
+            if order_entry.order.time_in_force == TimeInForce::FillOrKill
+                && self.fill_or_kill_vol(&self.ask_side, &order_entry.order, |p| {
+                    p <= order_entry.order.price
+                }) < order_entry.order.vol + order_entry.order.reserve_vol
+            {
+                order_entry.order.status = Status::Rejected;
+                order_entry.order.end_time = self.t;
+                return;
+            }
             self.match_bid(order_entry);
+            while order_entry.order.status == Status::Filled && order_entry.order.reserve_vol > 0 {
+                replenish_display(&mut order_entry.order);
+                self.match_bid(order_entry);
+            }
         }
-        if order_entry.order.status != Status::Filled {
-            let key: OrderKey = (Side::Bid, order_entry.key.1, self.t);
+        if !matches!(order_entry.order.status, Status::Filled | Status::Cancelled) {
+            if matches!(
+                order_entry.order.time_in_force,
+                TimeInForce::ImmediateOrCancel | TimeInForce::FillOrKill
+            ) {
+                self.cancel_unfilled(order_entry);
+                return;
+            }
+            let key: OrderKey = (
+                Side::Bid,
+                order_entry.key.1,
+                self.size_priority(order_entry.order.vol),
+                self.t,
+                self.next_seq(),
+            );
             order_entry.key = key;
             self.bid_side
-                .insert_order(key, order_entry.order.order_id, order_entry.order.vol)
+                .insert_order(key, order_entry.order.order_id, order_entry.order.vol);
+            track_order(&mut self.active_orders, &order_entry.order);
         }
     }
 
@@ -518,9 +2188,15 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
         match self.trading {
             true => {
                 self.match_bid(order_entry);
-                if order_entry.order.status != Status::Filled {
-                    order_entry.order.status = Status::Cancelled;
-                    order_entry.order.end_time = self.t;
+                // Aggressor may already have been cancelled
+                // by self-trade prevention
+                if !matches!(order_entry.order.status, Status::Filled | Status::Cancelled) {
+                    cancel(
+                        &mut order_entry.order,
+                        self.t,
+                        &mut self.cancelled_vol,
+                        &mut self.on_event,
+                    );
                 }
             }
             false => {
@@ -532,18 +2208,64 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
 
     /// Place a sell limit order on the market
     ///
+    /// Immediate-or-cancel orders have any volume
+    /// not matched on arrival cancelled. Fill-or-kill
+    /// orders are rejected if the opposite side does not
+    /// have enough volume at prices within the limit
+    /// price to fill the order. Post-only orders that
+    /// would cross the book, or orders priced outside
+    /// the price band (if set) are rejected.
+    ///
     /// # Arguments
     ///
     /// - `order_entry` - O
     fn place_ask_limit(&mut self, order_entry: &mut OrderEntry) {
+        if !self.within_band(order_entry.order.price) {
+            order_entry.order.status = Status::Rejected;
+            order_entry.order.end_time = self.t;
+            return;
+        }
         if self.trading {
+            if order_entry.order.post_only && order_entry.order.price <= self.bid_side.best_price()
+            {
+                order_entry.order.status = Status::Rejected;
+                order_entry.order.end_time = self.t;
+                return;
+            }
+            if order_entry.order.time_in_force == TimeInForce::FillOrKill
+                && self.fill_or_kill_vol(&self.bid_side, &order_entry.order, |p| {
+                    p >= order_entry.order.price
+                }) < order_entry.order.vol + order_entry.order.reserve_vol
+            {
+                order_entry.order.status = Status::Rejected;
+                order_entry.order.end_time = self.t;
+                return;
+            }
             self.match_ask(order_entry);
+            while order_entry.order.status == Status::Filled && order_entry.order.reserve_vol > 0 {
+                replenish_display(&mut order_entry.order);
+                self.match_ask(order_entry);
+            }
         }
-        if order_entry.order.status != Status::Filled {
-            let key: OrderKey = (Side::Ask, order_entry.key.1, self.t);
+        if !matches!(order_entry.order.status, Status::Filled | Status::Cancelled) {
+            if matches!(
+                order_entry.order.time_in_force,
+                TimeInForce::ImmediateOrCancel | TimeInForce::FillOrKill
+            ) {
+                self.cancel_unfilled(order_entry);
+                return;
+            }
+            let key: OrderKey = (
+                Side::Ask,
+                order_entry.key.1,
+                self.size_priority(order_entry.order.vol),
+                self.t,
+                self.next_seq(),
+            );
             order_entry.key = key;
             self.ask_side
-                .insert_order(key, order_entry.order.order_id, order_entry.order.vol)
+                .insert_order(key, order_entry.order.order_id, order_entry.order.vol);
+            track_order(&mut self.active_orders, &order_entry.order);
         }
     }
 
@@ -561,9 +2283,15 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
         match self.trading {
             true => {
                 self.match_ask(order_entry);
-                if order_entry.order.status != Status::Filled {
-                    order_entry.order.status = Status::Cancelled;
-                    order_entry.order.end_time = self.t;
+                // Aggressor may already have been cancelled
+                // by self-trade prevention
+                if !matches!(order_entry.order.status, Status::Filled | Status::Cancelled) {
+                    cancel(
+                        &mut order_entry.order,
+                        self.t,
+                        &mut self.cancelled_vol,
+                        &mut self.on_event,
+                    );
                 }
             }
             false => {
@@ -575,12 +2303,67 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
 
     /// Place an order on the market
     ///
-    /// Place an order that has been created on the market
+    /// Place an order that has been created on the market.
+    /// Stop orders are held off the book until triggered,
+    /// and any stop orders triggered by trades resulting
+    /// from placing the order are then placed (in order
+    /// of their trigger prices).
     ///
     /// # Arguments
     ///
     /// - `order_id` - Id of the order to place
     pub fn place_order(&mut self, order_id: OrderId) {
+        let order = self.orders[order_id].order;
+
+        match (order.status, order.trigger_price) {
+            (Status::New, Some(trigger)) => {
+                let order = &mut self.orders[order_id].order;
+                order.status = Status::Pending;
+                order.arr_time = self.t;
+                match order.side {
+                    Side::Bid => self.buy_stops.insert((trigger, order_id)),
+                    Side::Ask => self.sell_stops.insert((trigger, order_id)),
+                };
+            }
+            _ => {
+                self.execute_order(order_id);
+                self.trigger_stop_orders();
+            }
+        }
+    }
+
+    /// Place stop orders triggered by the last trade price
+    ///
+    /// Buy stops are triggered in ascending, and sell
+    /// stops in descending, order of trigger price. Stops
+    /// triggered by trades from placing triggered orders
+    /// are also placed.
+    fn trigger_stop_orders(&mut self) {
+        while let Some(last_price) = self.trades.last().map(|x| x.price) {
+            let triggered = match self.buy_stops.first() {
+                Some((trigger, _)) if *trigger <= last_price => self.buy_stops.pop_first(),
+                _ => match self.sell_stops.last() {
+                    Some((trigger, _)) if *trigger >= last_price => self.sell_stops.pop_last(),
+                    _ => None,
+                },
+            };
+
+            match triggered {
+                Some((_, order_id)) => {
+                    self.orders[order_id].order.status = Status::New;
+                    self.execute_order(order_id);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Place a (non-stop) order on the market
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the order to place
+    fn execute_order(&mut self, order_id: OrderId) {
         let mut order_entry = self.orders[order_id];
 
         if order_entry.order.status != Status::New {
@@ -589,6 +2372,7 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
 
         order_entry.order.status = Status::Active;
         order_entry.order.arr_time = self.t;
+        self.on_event.emit(BookEvent::OrderPlaced { order_id });
 
         match order_entry.order.side {
             Side::Bid => {
@@ -613,20 +2397,78 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
     /// Cancel an order
     ///
     /// Attempts to cancel an order, if the order is
-    /// already filled or rejected then no change is made
+    /// already filled or rejected then no change is made.
+    /// Orders that have been created but not placed are
+    /// marked as cancelled and will not be placed. Active
+    /// orders that have rested for less than the minimum
+    /// resting time (see [OrderBook::set_min_resting_time])
+    /// are not cancelled.
     ///
     /// # Arguments
     ///
     /// - `order_id` - Id of the order to cancel
     ///
     pub fn cancel_order(&mut self, order_id: OrderId) {
-        let cancelled_order = self.orders.get_mut(order_id);
-
-        match cancelled_order {
+        if let Some(order_entry) = self.orders.get(order_id) {
+            if self.is_resting(&order_entry.order) {
+                return;
+            }
+        }
+        self.force_cancel_order(order_id);
+    }
+
+    /// Check if an order is within the minimum resting time
+    ///
+    /// Active orders that have rested on the book for less
+    /// than the minimum resting time cannot be cancelled
+    /// or modified.
+    ///
+    /// # Arguments
+    ///
+    /// - `order` - Order to check
+    ///
+    fn is_resting(&self, order: &Order) -> bool {
+        order.status == Status::Active
+            && self.t.saturating_sub(order.arr_time) < self.min_resting_time
+    }
+
+    /// Cancel an order, ignoring the minimum resting time
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the order to cancel
+    ///
+    fn force_cancel_order(&mut self, order_id: OrderId) {
+        let cancelled_order = self.orders.get_mut(order_id);
+
+        match cancelled_order {
             Some(order_entry) => {
-                if order_entry.order.status == Status::Active {
-                    order_entry.order.status = Status::Cancelled;
-                    order_entry.order.end_time = self.t;
+                if order_entry.order.status == Status::New {
+                    cancel(
+                        &mut order_entry.order,
+                        self.t,
+                        &mut self.cancelled_vol,
+                        &mut self.on_event,
+                    );
+                } else if order_entry.order.status == Status::Pending {
+                    cancel(
+                        &mut order_entry.order,
+                        self.t,
+                        &mut self.cancelled_vol,
+                        &mut self.on_event,
+                    );
+                    let key = (order_entry.order.trigger_price.unwrap(), order_id);
+                    match order_entry.order.side {
+                        Side::Bid => self.buy_stops.remove(&key),
+                        Side::Ask => self.sell_stops.remove(&key),
+                    };
+                } else if order_entry.order.status == Status::Active {
+                    cancel(
+                        &mut order_entry.order,
+                        self.t,
+                        &mut self.cancelled_vol,
+                        &mut self.on_event,
+                    );
                     match order_entry.key.0 {
                         Side::Bid => {
                             self.bid_side
@@ -637,6 +2479,7 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
                                 .remove_order(order_entry.key, order_entry.order.vol);
                         }
                     }
+                    untrack_order(&mut self.active_orders, &order_entry.order);
                 }
             }
             None => panic!("No order with id {} exists", order_id),
@@ -688,18 +2531,32 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
 
         order_entry.order.vol = new_vol;
         order_entry.order.price = new_price;
+        order_entry.start_price = None;
 
         if self.trading {
             match order_entry.key.0 {
                 Side::Bid => self.match_bid(order_entry),
                 Side::Ask => self.match_ask(order_entry),
             }
+            // Replenish and continue matching iceberg orders
+            while order_entry.order.status == Status::Filled && order_entry.order.reserve_vol > 0 {
+                replenish_display(&mut order_entry.order);
+                match order_entry.key.0 {
+                    Side::Bid => self.match_bid(order_entry),
+                    Side::Ask => self.match_ask(order_entry),
+                }
+            }
         }
 
-        if order_entry.order.status != Status::Filled {
+        if !matches!(order_entry.order.status, Status::Filled | Status::Cancelled) {
             match order_entry.key.0 {
                 crate::types::Side::Bid => {
-                    let key: OrderKey = get_bid_key(self.t, new_price);
+                    let key: OrderKey = get_bid_key(
+                        self.t,
+                        new_price,
+                        self.size_priority(order_entry.order.vol),
+                        self.next_seq(),
+                    );
                     order_entry.key = key;
 
                     self.bid_side.insert_order(
@@ -709,7 +2566,12 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
                     );
                 }
                 crate::types::Side::Ask => {
-                    let key: OrderKey = get_ask_key(self.t, new_price);
+                    let key: OrderKey = get_ask_key(
+                        self.t,
+                        new_price,
+                        self.size_priority(order_entry.order.vol),
+                        self.next_seq(),
+                    );
                     order_entry.key = key;
 
                     self.ask_side.insert_order(
@@ -719,6 +2581,8 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
                     );
                 }
             }
+        } else {
+            untrack_order(&mut self.active_orders, &order_entry.order);
         }
     }
 
@@ -730,7 +2594,17 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
     /// maintains the same id.
     ///
     /// If the price/vol are None then the original
-    /// price/vol are kept.
+    /// price/vol are kept. Orders that have been
+    /// created but not yet placed are updated in place.
+    /// Modifying the volume of an order to `0` is
+    /// equivalent to cancelling the order (see
+    /// [OrderBook::cancel_order]). Modifications of
+    /// active orders that have rested for less than the
+    /// minimum resting time (see
+    /// [OrderBook::set_min_resting_time]), to a volume
+    /// that is not a multiple of the lot size, or to a
+    /// price outside the price band (see
+    /// [OrderBook::set_price_band]), are ignored.
     ///
     /// # Arguments
     ///
@@ -746,8 +2620,33 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
         new_price: Option<Price>,
         new_vol: Option<Price>,
     ) {
+        if new_vol == Some(0) {
+            self.cancel_order(order_id);
+            return;
+        }
+
+        if new_vol.is_some_and(|v| !v.is_multiple_of(self.lot_size))
+            || new_price.is_some_and(|p| !self.within_band(p))
+        {
+            return;
+        }
+
         let mut order_entry = self.orders[order_id];
 
+        if self.is_resting(&order_entry.order) {
+            return;
+        }
+
+        if matches!(order_entry.order.status, Status::Active | Status::New)
+            && (new_price.is_some() || new_vol.is_some())
+        {
+            self.on_event.emit(BookEvent::OrderModified {
+                order_id,
+                new_price,
+                new_vol,
+            });
+        }
+
         if order_entry.order.status == Status::Active {
             match (new_price, new_vol) {
                 (None, None) => (),
@@ -766,15 +2665,123 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
                 }
                 (Some(p), Some(v)) => self.replace_order(&mut order_entry, p, v),
             }
+        } else if order_entry.order.status == Status::New {
+            if let Some(p) = new_price {
+                order_entry.order.price = p;
+            }
+            if let Some(v) = new_vol {
+                order_entry.order.vol = v;
+                order_entry.order.start_vol = v + order_entry.order.reserve_vol;
+            }
+        }
+
+        self.orders[order_id] = order_entry;
+        self.trigger_stop_orders();
+    }
+
+    /// Modify an order, rejecting any reprice that would cross
+    ///
+    /// Post-only version of [OrderBook::modify_order],
+    /// if the new price would cross (or lock with) the
+    /// opposite touch, or is outside the price band, the
+    /// modification is rejected and the order is left
+    /// unchanged. Returns `true` if the modification was
+    /// applied.
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the order to modify
+    /// - `new_price` - New price of the order, `None`
+    ///   keeps the same price
+    /// - `new_vol` - New volume of the order, `None`
+    ///   keeps the same volume
+    ///
+    pub fn modify_order_post_only(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_vol: Option<Vol>,
+    ) -> bool {
+        let order = self.orders[order_id].order;
+
+        if order.status != Status::Active {
+            return false;
+        }
+
+        let price = new_price.unwrap_or(order.price);
+        let crosses = match order.side {
+            Side::Bid => price >= self.ask_side.best_price(),
+            Side::Ask => price <= self.bid_side.best_price(),
+        };
+
+        if crosses || !self.within_band(price) {
+            return false;
+        }
+
+        self.modify_order(order_id, new_price, new_vol);
+        true
+    }
+
+    /// Reduce the volume of an order by an amount
+    ///
+    /// Reduces the remaining volume of an order in-place,
+    /// so the order keeps its price-time priority. If the
+    /// amount is greater than or equal to the remaining
+    /// volume the order is cancelled. The hidden reserve
+    /// volume of iceberg orders is reduced before the
+    /// displayed volume. Reductions of active orders that
+    /// have rested for less than the minimum resting time
+    /// (see [OrderBook::set_min_resting_time]), or by an
+    /// amount that is not a multiple of the lot size, are
+    /// ignored.
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the order to reduce
+    /// - `amount` - Volume to remove from the order
+    ///
+    pub fn reduce_order(&mut self, order_id: OrderId, amount: Vol) {
+        if amount == 0 || !amount.is_multiple_of(self.lot_size) {
+            return;
+        }
+
+        let mut order_entry = self.orders[order_id];
+
+        if self.is_resting(&order_entry.order)
+            || !matches!(order_entry.order.status, Status::New | Status::Active)
+        {
+            return;
+        }
+
+        if amount >= order_entry.order.vol + order_entry.order.reserve_vol {
+            self.cancel_order(order_id);
+            return;
+        }
+
+        let reserve_reduction = min(amount, order_entry.order.reserve_vol);
+        let vol_reduction = amount - reserve_reduction;
+        order_entry.order.reserve_vol -= reserve_reduction;
+
+        match order_entry.order.status {
+            Status::Active => self.reduce_order_vol(&mut order_entry, vol_reduction),
+            _ => {
+                order_entry.order.vol -= vol_reduction;
+                order_entry.order.start_vol -= amount;
+            }
         }
 
+        self.on_event.emit(BookEvent::OrderModified {
+            order_id,
+            new_price: None,
+            new_vol: Some(order_entry.order.vol),
+        });
         self.orders[order_id] = order_entry;
     }
 
     /// Process an [Event] order instruction
     ///
-    /// Processes an order instruction to place, cancel
-    /// or modify an order
+    /// Processes an order instruction to place, cancel,
+    /// modify or reduce an order
     ///
     /// # Arguments
     ///
@@ -788,6 +2795,7 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
                 new_price,
                 new_vol,
             } => self.modify_order(order_id, new_price, new_vol),
+            Event::Reduce { order_id, amount } => self.reduce_order(order_id, amount),
         }
     }
 
@@ -796,28 +2804,538 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
         self.orders.iter().map(|x| &x.order).collect()
     }
 
+    /// Get the number of orders created on the market
+    pub fn num_orders(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// Recreate a new order from a record
+    ///
+    /// Adds a copy of a previously created (but not
+    /// yet placed) order to the order book, for example
+    /// to replay a recorded simulation. The order is not
+    /// validated against the tick and lot sizes. Panics
+    /// if the order has already been placed, or its id
+    /// is not the next id in the sequence.
+    ///
+    /// # Arguments
+    ///
+    /// - `order` - Order record
+    ///
+    pub fn restore_order(&mut self, order: Order) -> OrderId {
+        let order_id = self.current_order_id();
+
+        assert!(
+            order.status == Status::New,
+            "Only new orders can be restored"
+        );
+        assert!(
+            order.order_id == order_id,
+            "Expected an order with id {}, got {}",
+            order_id,
+            order.order_id
+        );
+
+        let key = match order.side {
+            Side::Bid => get_bid_key(0, order.price, 0, 0),
+            Side::Ask => get_ask_key(0, order.price, 0, 0),
+        };
+
+        self.orders.push(OrderEntry {
+            order,
+            key,
+            start_price: None,
+        });
+
+        if let Some(expiry) = order.expiry {
+            self.expiries.push(Reverse((expiry, order_id)));
+        }
+
+        order_id
+    }
+
+    /// Get orders that have been at least partially filled
+    ///
+    /// Returns orders that have executed some volume,
+    /// regardless of their final status (e.g. including
+    /// orders that were partially filled and then
+    /// cancelled), in order of creation. Fills are
+    /// determined from trade records, so reductions
+    /// in volume from order modifications are not
+    /// counted as fills.
+    ///
+    pub fn partially_filled_orders(&self) -> Vec<&Order> {
+        let mut filled = vec![false; self.orders.len()];
+
+        for trade in self.trades.iter() {
+            filled[trade.active_order_id] = true;
+            filled[trade.passive_order_id] = true;
+        }
+
+        self.orders
+            .iter()
+            .zip(filled)
+            .filter_map(|(x, f)| f.then_some(&x.order))
+            .collect()
+    }
+
+    /// Count orders in each status
+    ///
+    /// Returns the number of created orders with
+    /// each [Status], indexed in the order
+    /// `[New, Active, Filled, Cancelled, Rejected, Pending]`.
+    ///
+    pub fn status_histogram(&self) -> [usize; 6] {
+        let mut counts = [0; 6];
+        for order_entry in self.orders.iter() {
+            counts[usize::from(u8::from(order_entry.order.status))] += 1;
+        }
+        counts
+    }
+
     /// Reference to trade records
     pub fn get_trades(&self) -> &Vec<Trade> {
         &self.trades
     }
 
+    /// Get the ids of all traders that have created orders
+    ///
+    /// Returns the distinct trader ids in ascending order.
+    pub fn trader_ids(&self) -> Vec<TraderId> {
+        let mut ids: Vec<TraderId> = self.orders.iter().map(|x| x.order.trader_id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Get the number of traders with resting orders
+    ///
+    /// Counts the distinct trader ids with at least one
+    /// order currently on the book, unlike
+    /// [OrderBook::trader_ids] which includes all traders
+    /// that have ever created an order.
+    pub fn num_active_traders(&self) -> usize {
+        self.active_orders.len()
+    }
+
+    /// Get the orders of a trader resting on the book
+    ///
+    /// Resting orders are tracked per trader as they
+    /// are placed on, and removed from, the book, so
+    /// this does not require a scan of the full order
+    /// history. Orders that have been created but
+    /// not placed, or pending stop orders, are not
+    /// included.
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the trader
+    ///
+    pub fn active_orders_for(&self, trader_id: TraderId) -> Vec<&Order> {
+        self.active_orders
+            .get(&trader_id)
+            .map_or(Vec::new(), |ids| {
+                ids.iter().map(|x| &self.orders[*x].order).collect()
+            })
+    }
+
+    /// Get the total notional value of all trades
+    pub fn turnover(&self) -> u128 {
+        self.trades.iter().map(|x| u128::from(x.notional())).sum()
+    }
+
+    /// Get the cumulative traded volume of a trader
+    ///
+    /// Total volume executed by the trader as
+    /// either the aggressive or passive side of
+    /// trades.
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the trader
+    ///
+    pub fn trader_volume(&self, trader_id: TraderId) -> Vol {
+        self.trader_vols.get(&trader_id).copied().unwrap_or(0)
+    }
+
+    /// Get the net position and cash flow of a trader
+    ///
+    /// Returns the cumulative net volume (buys increase
+    /// and sells decrease the position) and signed cash
+    /// flow (buys decrease and sells increase the cash
+    /// by the trade notional) of the trader, as either
+    /// the aggressive or passive side of trades.
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the trader
+    ///
+    pub fn trader_position(&self, trader_id: TraderId) -> (i64, i64) {
+        self.trader_positions
+            .get(&trader_id)
+            .copied()
+            .unwrap_or((0, 0))
+    }
+
+    /// Get the cumulative filled volume
+    ///
+    /// Total volume executed in trades, so partially
+    /// filled orders contribute their filled volume.
+    pub fn filled_volume(&self) -> u64 {
+        self.filled_vol
+    }
+
+    /// Get the cumulative cancelled volume
+    ///
+    /// Total remaining (i.e. unfilled) volume of
+    /// orders at the time they were cancelled,
+    /// including any hidden reserve volume.
+    pub fn cancelled_volume(&self) -> u64 {
+        self.cancelled_vol
+    }
+
+    /// Summarise the trades of a trader
+    ///
+    /// Combines the net position and cash flow (see
+    /// [OrderBook::trader_position]) and traded volume
+    /// (see [OrderBook::trader_volume]) of a trader,
+    /// with the profit and loss of the trader marked
+    /// at the last trade price, see [TraderSummary].
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the trader
+    ///
+    pub fn trader_summary(&self, trader_id: TraderId) -> TraderSummary {
+        let (position, cash) = self.trader_position(trader_id);
+        let mark = self.trades.last().map_or(0.0, |x| f64::from(x.price));
+
+        TraderSummary {
+            position,
+            cash,
+            traded_vol: u64::from(self.trader_volume(trader_id)),
+            pnl: cash as f64 + position as f64 * mark,
+        }
+    }
+
+    /// Get the realised profit and loss of a trader
+    ///
+    /// Folds over the trade records, tracking the cost
+    /// of the open position of the trader (using the
+    /// average cost of the position), and realising
+    /// profit and loss when the position is reduced.
+    /// Returns a tuple containing the realised profit and
+    /// loss, net position and net cash flow of the trader
+    /// (see [OrderBook::trader_position]). Realised values
+    /// are truncated to integer price units when a
+    /// position is partially closed.
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the trader
+    ///
+    pub fn trader_pnl(&self, trader_id: TraderId) -> (i64, i64, i64) {
+        let mut realised: i64 = 0;
+        let mut position: i64 = 0;
+        // Signed cost of the open position
+        let mut cost: i64 = 0;
+
+        for trade in self.trades.iter() {
+            for order_id in [trade.active_order_id, trade.passive_order_id] {
+                let order = &self.orders[order_id].order;
+                if order.trader_id != trader_id {
+                    continue;
+                }
+
+                let price = i64::from(trade.price);
+                let fill = match order.side {
+                    Side::Bid => i64::from(trade.vol),
+                    Side::Ask => -i64::from(trade.vol),
+                };
+
+                if position == 0 || position.signum() == fill.signum() {
+                    position += fill;
+                    cost += price * fill;
+                    continue;
+                }
+
+                let closed = fill.abs().min(position.abs());
+                let closed_cost = cost * closed / position.abs();
+                realised += position.signum() * (price * closed - closed_cost.abs());
+                cost -= closed_cost;
+                position -= position.signum() * closed;
+
+                let opened = fill - fill.signum() * closed;
+                if opened != 0 {
+                    position = opened;
+                    cost = price * opened;
+                }
+            }
+        }
+
+        let (position, cash) = self.trader_position(trader_id);
+        (realised, position, cash)
+    }
+
+    /// Get the volume weighted average price of trades
+    ///
+    /// Calculates the volume weighted average price of
+    /// all recorded trades, or only trades that took
+    /// place within a time window. Returns `None` if no
+    /// trades took place (in the window).
+    ///
+    /// # Arguments
+    ///
+    /// - `window` - Optional half-open time window
+    ///   `[start, end)` of trades to include
+    ///
+    pub fn vwap(&self, window: Option<(Nanos, Nanos)>) -> Option<f64> {
+        let (notional, vol) = self
+            .trades
+            .iter()
+            .filter(|x| window.is_none_or(|(start, end)| (start..end).contains(&x.t)))
+            .fold((0u64, 0u64), |(notional, vol), x| {
+                (notional + x.notional(), vol + u64::from(x.vol))
+            });
+
+        match vol {
+            0 => None,
+            _ => Some(notional as f64 / vol as f64),
+        }
+    }
+
+    /// Aggregate trades into open, high, low, close and volume bars
+    ///
+    /// Trades are grouped into bars covering the time
+    /// intervals `[k * bar_size, (k + 1) * bar_size)`.
+    /// Bars are returned in time order, and intervals
+    /// without any trades are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// - `bar_size` - Time-length of each bar
+    ///
+    pub fn ohlcv_bars(&self, bar_size: Nanos) -> Vec<Ohlcv> {
+        assert!(bar_size > 0, "Bar size must be non-zero");
+
+        let mut bars: Vec<Ohlcv> = Vec::new();
+
+        for trade in self.trades.iter() {
+            let t = trade.t - trade.t % bar_size;
+            match bars.last_mut() {
+                Some(bar) if bar.t == t => {
+                    bar.high = bar.high.max(trade.price);
+                    bar.low = bar.low.min(trade.price);
+                    bar.close = trade.price;
+                    bar.vol += trade.vol;
+                }
+                _ => bars.push(Ohlcv {
+                    t,
+                    open: trade.price,
+                    high: trade.price,
+                    low: trade.price,
+                    close: trade.price,
+                    vol: trade.vol,
+                }),
+            }
+        }
+
+        bars
+    }
+
+    /// Get the cumulative order flow imbalance of all trades
+    ///
+    /// Signed sum of traded volume, where volume from
+    /// buyer-initiated trades is counted as positive
+    /// and seller-initiated trades as negative.
+    ///
+    pub fn cumulative_ofi(&self) -> i64 {
+        self.trades
+            .iter()
+            .map(|x| match x.aggressor_side() {
+                Side::Bid => i64::from(x.vol),
+                Side::Ask => -i64::from(x.vol),
+            })
+            .sum()
+    }
+
+    /// Get the fraction of traded volume driven by market orders
+    ///
+    /// Ratio of the volume executed with a market
+    /// order as the aggressor, to the total executed
+    /// volume (i.e. the remainder is volume executed
+    /// by marketable limit orders). Returns `0.0` if
+    /// no trades have taken place.
+    ///
+    pub fn aggression_ratio(&self) -> f64 {
+        let (market_vol, total_vol) =
+            self.trades
+                .iter()
+                .fold((0u64, 0u64), |(market_vol, total_vol), trade| {
+                    let vol = u64::from(trade.vol);
+                    match trade.agg_is_market {
+                        true => (market_vol + vol, total_vol + vol),
+                        false => (market_vol, total_vol + vol),
+                    }
+                });
+
+        match total_vol {
+            0 => 0.0,
+            _ => market_vol as f64 / total_vol as f64,
+        }
+    }
+
+    /// Estimate the probability a resting order is filled
+    ///
+    /// Heuristic estimate of the probability that an
+    /// order is filled within a time horizon. The volume
+    /// consumed at or through the order's price level
+    /// over the previous `horizon` time units (taken
+    /// from the trade records) is used as the expected
+    /// consumption over the next `horizon`. The estimate
+    /// is the ratio of this consumption to the volume
+    /// ahead of the order in the queue (including at
+    /// better prices) plus the order's own volume,
+    /// capped at `1.0`.
+    ///
+    /// Filled orders return `1.0`, and orders that are
+    /// not active (i.e. new, cancelled or rejected) `0.0`.
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the order
+    /// - `horizon` - Time horizon of the estimate
+    ///
+    pub fn estimate_fill_probability(&self, order_id: OrderId, horizon: Nanos) -> f64 {
+        let order_entry = &self.orders[order_id];
+        let order = &order_entry.order;
+
+        match order.status {
+            Status::Filled => return 1.0,
+            Status::Active => (),
+            _ => return 0.0,
+        }
+
+        // Keys on a side are ordered by priority, so
+        // orders ahead have a lower key
+        let priority = |k: &OrderKey| (k.1, k.2, k.3, k.4);
+
+        let queue_ahead: u64 = self
+            .orders
+            .iter()
+            .filter(|x| {
+                x.order.status == Status::Active
+                    && x.key.0 == order_entry.key.0
+                    && priority(&x.key) < priority(&order_entry.key)
+            })
+            .map(|x| u64::from(x.order.vol))
+            .sum();
+
+        let window_start = self.t.saturating_sub(horizon);
+
+        let consumed: u64 = self
+            .trades
+            .iter()
+            .rev()
+            .take_while(|x| x.t >= window_start)
+            .filter(|x| {
+                x.side == order.side
+                    && match order.side {
+                        Side::Bid => x.price >= order.price,
+                        Side::Ask => x.price <= order.price,
+                    }
+            })
+            .map(|x| u64::from(x.vol))
+            .sum();
+
+        let required = queue_ahead + u64::from(order.vol);
+
+        (consumed as f64 / required as f64).min(1.0)
+    }
+
     /// Save a snapshot of the order-book to JSON
     ///
+    /// By default the snapshot contains the full history
+    /// of orders and trades. If `prune` is `true` only
+    /// live (i.e. new, active and pending) orders are
+    /// saved, along with the market parameters, producing
+    /// a smaller snapshot that can be loaded to continue
+    /// trading from the same resting state. Note that in
+    /// a pruned snapshot
+    ///
+    /// - The history of filled, cancelled and rejected
+    ///   orders, and of trades, is lost
+    /// - Live orders are assigned new ids, in the order
+    ///   they were created
+    /// - Per trader volumes and positions, and the
+    ///   cumulative filled and cancelled volumes, of the
+    ///   loaded book start from zero
+    ///
     /// # Argument
     ///
     /// - `path` - Path to write snapshot JSON to
     /// - `pretty` - If `True` JSON will be pretty printed
+    /// - `prune` - If `True` terminal orders and trades
+    ///   are removed from the snapshot
     ///
-    pub fn save_json<P: AsRef<Path>>(&self, path: P, pretty: bool) -> std::io::Result<()> {
+    pub fn save_json<P: AsRef<Path>>(
+        &self,
+        path: P,
+        pretty: bool,
+        prune: bool,
+    ) -> std::io::Result<()> {
         let file = std::fs::File::create(path)?;
         let file = std::io::BufWriter::new(file);
-        match pretty {
-            true => serde_json::to_writer_pretty(file, self)?,
-            false => serde_json::to_writer(file, self)?,
+        match (pretty, prune) {
+            (true, false) => serde_json::to_writer_pretty(file, self)?,
+            (false, false) => serde_json::to_writer(file, self)?,
+            (true, true) => serde_json::to_writer_pretty(file, &self.pruned_state())?,
+            (false, true) => serde_json::to_writer(file, &self.pruned_state())?,
         }
         Ok(())
     }
 
+    /// Get the order book state with only live orders
+    ///
+    /// Live orders are re-numbered to keep order ids
+    /// contiguous, and the trade history is removed.
+    fn pruned_state(&self) -> OrderBookState<LEVELS> {
+        let orders = self
+            .orders
+            .iter()
+            .filter(|x| {
+                matches!(
+                    x.order.status,
+                    Status::New | Status::Active | Status::Pending
+                )
+            })
+            .enumerate()
+            .map(|(i, x)| {
+                let mut entry = *x;
+                entry.order.order_id = i;
+                entry
+            })
+            .collect();
+
+        OrderBookState {
+            t: self.t,
+            tick_size: self.tick_size,
+            lot_size: self.lot_size,
+            trade_vol: self.trade_vol,
+            seq: self.seq,
+            orders,
+            trades: Vec::new(),
+            trading: self.trading,
+            priority: self.priority,
+            protection_window: self.protection_window,
+            min_resting_time: self.min_resting_time,
+            price_decimals: self.price_decimals,
+            price_band: self.price_band,
+            self_trade_prevention: self.self_trade_prevention,
+            max_trade_move: self.max_trade_move,
+        }
+    }
+
     /// Load an order-book from a JSON snapshot
     ///
     /// # Argument
@@ -839,24 +3357,38 @@ impl<const LEVELS: usize> OrderBook<LEVELS> {
 /// - `agg_order` - Aggressive order data
 /// - `pass_order` - Passive order data
 /// - `trades` - Trade records
+/// - `trader_vols` - Per trader cumulative trade volumes
+/// - `trader_positions` - Per trader net positions
+///   and cash flows
+/// - `on_event` - Order book event listener
 ///
 fn match_orders(
     t: Nanos,
     agg_order: &mut Order,
     pass_order: &mut Order,
     trades: &mut Vec<Trade>,
+    trader_vols: &mut HashMap<TraderId, Vol>,
+    trader_positions: &mut HashMap<TraderId, (i64, i64)>,
+    on_event: &mut EventListener,
 ) -> Vol {
     let trade_vol = min(agg_order.vol, pass_order.vol);
     agg_order.vol -= trade_vol;
     pass_order.vol -= trade_vol;
-    trades.push(Trade {
+    *trader_vols.entry(agg_order.trader_id).or_insert(0) += trade_vol;
+    *trader_vols.entry(pass_order.trader_id).or_insert(0) += trade_vol;
+    update_position(trader_positions, agg_order, pass_order.price, trade_vol);
+    update_position(trader_positions, pass_order, pass_order.price, trade_vol);
+    let trade = Trade {
         t,
         side: pass_order.side,
         price: pass_order.price,
         vol: trade_vol,
         active_order_id: agg_order.order_id,
         passive_order_id: pass_order.order_id,
-    });
+        agg_is_market: agg_order.is_market,
+    };
+    trades.push(trade);
+    on_event.emit(BookEvent::Trade(trade));
     if pass_order.vol == 0 {
         pass_order.end_time = t;
         pass_order.status = Status::Filled;
@@ -869,50 +3401,343 @@ fn match_orders(
     trade_vol
 }
 
-/// Dummy order book to enable deserialization
-#[derive(Deserialize)]
-struct OrderBookState<const LEVELS: usize = 10> {
-    t: Nanos,
-    tick_size: Price,
-    trade_vol: Vol,
-    orders: Vec<OrderEntry>,
-    trades: Vec<Trade>,
-    trading: bool,
+/// Add an order to the index of resting orders of its trader
+///
+/// # Arguments
+///
+/// - `active_orders` - Per trader resting order ids
+/// - `order` - Order placed on the book
+///
+fn track_order(active_orders: &mut HashMap<TraderId, Vec<OrderId>>, order: &Order) {
+    active_orders
+        .entry(order.trader_id)
+        .or_default()
+        .push(order.order_id);
 }
 
-struct OrderBookConversionErrror;
-
-impl fmt::Display for OrderBookConversionErrror {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Failed to convert OrderBookState to an OrderBook")
-    }
+/// Remove an order from the index of resting orders of its trader
+///
+/// # Arguments
+///
+/// - `active_orders` - Per trader resting order ids
+/// - `order` - Order removed from the book
+///
+fn untrack_order(active_orders: &mut HashMap<TraderId, Vec<OrderId>>, order: &Order) {
+    if let Some(ids) = active_orders.get_mut(&order.trader_id) {
+        ids.retain(|x| *x != order.order_id);
+        if ids.is_empty() {
+            active_orders.remove(&order.trader_id);
+        }
+    }
+}
+
+/// Unfilled volume of an order, including hidden reserve volume
+fn cancelled_order_vol(order: &Order) -> u64 {
+    u64::from(order.vol) + u64::from(order.reserve_vol)
+}
+
+/// Cancel an order, adding its unfilled volume to a running total
+///
+/// All cancellations should go through this function so
+/// that the cancelled volume is counted consistently (and
+/// matches the total recomputed when loading a book).
+///
+/// # Arguments
+///
+/// - `order` - Order to cancel
+/// - `t` - Time of the cancellation
+/// - `cancelled_vol` - Cumulative cancelled volume
+/// - `on_event` - Order book event listener
+///
+fn cancel(order: &mut Order, t: Nanos, cancelled_vol: &mut u64, on_event: &mut EventListener) {
+    order.status = Status::Cancelled;
+    order.end_time = t;
+    *cancelled_vol += cancelled_order_vol(order);
+    on_event.emit(BookEvent::OrderCancelled {
+        order_id: order.order_id,
+    });
+}
+
+/// Update the net position and cash flow of the trader of an order
+///
+/// # Arguments
+///
+/// - `trader_positions` - Per trader net positions
+///   and cash flows
+/// - `order` - Order that traded
+/// - `price` - Trade price
+/// - `vol` - Trade volume
+///
+fn update_position(
+    trader_positions: &mut HashMap<TraderId, (i64, i64)>,
+    order: &Order,
+    price: Price,
+    vol: Vol,
+) {
+    let vol = i64::from(vol);
+    let notional = i64::from(price) * vol;
+    let (position, cash) = trader_positions.entry(order.trader_id).or_insert((0, 0));
+    match order.side {
+        Side::Bid => {
+            *position += vol;
+            *cash -= notional;
+        }
+        Side::Ask => {
+            *position -= vol;
+            *cash += notional;
+        }
+    }
+}
+
+/// Refill the displayed volume of an iceberg order from its reserve
+fn replenish_display(order: &mut Order) {
+    let vol = min(order.display_vol, order.reserve_vol);
+    order.vol = vol;
+    order.reserve_vol -= vol;
+    order.status = Status::Active;
+    order.end_time = Nanos::MAX;
+}
+
+/// Average execution price sweeping price levels
+fn sweep_levels(levels: &[(Price, Vol)], vol: Vol) -> Option<f64> {
+    if vol == 0 {
+        return None;
+    }
+
+    let mut remaining = vol;
+    let mut notional = 0.0;
+
+    for (price, level_vol) in levels.iter() {
+        let filled = min(remaining, *level_vol);
+        notional += f64::from(*price) * f64::from(filled);
+        remaining -= filled;
+        if remaining == 0 {
+            return Some(notional / f64::from(vol));
+        }
+    }
+
+    None
+}
+
+/// Largest volume (and its cost) that can be executed sweeping
+/// price levels within a cash budget
+fn budget_levels(levels: &[(Price, Vol)], cash: u64, lot_size: Vol) -> (Vol, u64) {
+    let mut remaining = cash;
+    let mut vol: Vol = 0;
+
+    for (price, level_vol) in levels.iter() {
+        let price = u64::from(*price);
+        // Volume at a zero price costs nothing
+        let filled = match price {
+            0 => u64::from(*level_vol),
+            _ => min(u64::from(*level_vol), remaining / price),
+        };
+        vol += Vol::try_from(filled).unwrap();
+        remaining -= filled * price;
+        if filled < u64::from(*level_vol) {
+            break;
+        }
+    }
+
+    // Round down to the lot size, removing the most expensive volume
+    let vol = vol - vol % lot_size;
+    let mut unpriced = vol;
+    let mut cost = 0;
+
+    for (price, level_vol) in levels.iter() {
+        let filled = min(unpriced, *level_vol);
+        cost += u64::from(*price) * u64::from(filled);
+        unpriced -= filled;
+        if unpriced == 0 {
+            break;
+        }
+    }
+
+    (vol, cost)
+}
+
+/// Calculate the market impact curve of an order book
+///
+/// Calculates the average execution price of
+/// aggressive orders of increasing size (from
+/// `max_vol / steps` up to `max_vol` in `steps`
+/// increments), without modifying the book. Sizes
+/// that exceed the available volume on the book
+/// are omitted from the curve.
+///
+/// # Arguments
+///
+/// - `book` - Order book
+/// - `side` - Side of the aggressive orders
+/// - `max_vol` - Largest order size
+/// - `steps` - Number of order sizes
+///
+pub fn impact_curve<const LEVELS: usize>(
+    book: &OrderBook<LEVELS>,
+    side: Side,
+    max_vol: Vol,
+    steps: u32,
+) -> Vec<(Vol, f64)> {
+    let levels = match side {
+        Side::Bid => book.ask_side.price_levels(),
+        Side::Ask => book.bid_side.price_levels(),
+    };
+
+    (1..=steps)
+        .map(|i| Vol::try_from(u64::from(max_vol) * u64::from(i) / u64::from(steps)).unwrap())
+        .map_while(|v| sweep_levels(&levels, v).map(|p| (v, p)))
+        .collect()
+}
+
+/// Dummy order book to enable deserialization
+/// (and serialization of pruned snapshots)
+#[derive(Serialize, Deserialize)]
+struct OrderBookState<const LEVELS: usize = 10> {
+    t: Nanos,
+    tick_size: Price,
+    #[serde(default = "default_lot_size")]
+    lot_size: Vol,
+    trade_vol: Vol,
+    #[serde(default)]
+    seq: u64,
+    orders: Vec<OrderEntry>,
+    trades: Vec<Trade>,
+    trading: bool,
+    #[serde(default)]
+    priority: WithinLevelPriority,
+    #[serde(default)]
+    protection_window: Nanos,
+    #[serde(default)]
+    min_resting_time: Nanos,
+    #[serde(default)]
+    price_decimals: u32,
+    #[serde(default)]
+    price_band: Option<(Price, Price)>,
+    #[serde(default)]
+    self_trade_prevention: SelfTradePrevention,
+    #[serde(default)]
+    max_trade_move: Option<Price>,
+}
+
+fn default_lot_size() -> Vol {
+    1
+}
+
+struct OrderBookConversionErrror;
+
+impl fmt::Display for OrderBookConversionErrror {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Failed to convert OrderBookState to an OrderBook")
+    }
 }
 
 impl<const LEVELS: usize> std::convert::TryFrom<OrderBookState<LEVELS>> for OrderBook<{ LEVELS }> {
     type Error = OrderBookConversionErrror;
 
     fn try_from(state: OrderBookState<LEVELS>) -> Result<Self, Self::Error> {
+        // Order ids index into the order list, so should
+        // be unique and contiguous from 0, and trades should
+        // only reference orders in the list
+        if state
+            .orders
+            .iter()
+            .enumerate()
+            .any(|(i, x)| x.order.order_id != i)
+            || state.trades.iter().any(|x| {
+                x.active_order_id >= state.orders.len() || x.passive_order_id >= state.orders.len()
+            })
+        {
+            return Err(OrderBookConversionErrror);
+        }
+
         let mut bid_side = BidSide::default();
         let mut ask_side = AskSide::default();
 
-        for OrderEntry { order, key } in state.orders.iter() {
+        let mut active_orders = HashMap::new();
+
+        for OrderEntry { order, key, .. } in state.orders.iter() {
             if order.status == Status::Active {
                 match order.side {
                     Side::Bid => bid_side.insert_order(*key, order.order_id, order.vol),
                     Side::Ask => ask_side.insert_order(*key, order.order_id, order.vol),
                 }
+                track_order(&mut active_orders, order);
+            }
+        }
+
+        let mut trader_vols = HashMap::new();
+        let mut trader_positions = HashMap::new();
+        let mut expiries = BinaryHeap::new();
+        let mut buy_stops = BTreeSet::new();
+        let mut sell_stops = BTreeSet::new();
+
+        for OrderEntry { order, .. } in state.orders.iter() {
+            if let Some(expiry) = order.expiry {
+                if matches!(order.status, Status::New | Status::Active | Status::Pending) {
+                    expiries.push(Reverse((expiry, order.order_id)));
+                }
+            }
+            if let (Status::Pending, Some(trigger)) = (order.status, order.trigger_price) {
+                match order.side {
+                    Side::Bid => buy_stops.insert((trigger, order.order_id)),
+                    Side::Ask => sell_stops.insert((trigger, order.order_id)),
+                };
+            }
+        }
+
+        for trade in state.trades.iter() {
+            for order_id in [trade.active_order_id, trade.passive_order_id] {
+                let order = &state.orders[order_id].order;
+                *trader_vols.entry(order.trader_id).or_insert(0) += trade.vol;
+                update_position(&mut trader_positions, order, trade.price, trade.vol);
             }
         }
 
+        // Snapshots without a sequence counter continue
+        // from the highest sequence number of the order keys
+        let seq = state
+            .orders
+            .iter()
+            .map(|x| x.key.4 + 1)
+            .max()
+            .unwrap_or(0)
+            .max(state.seq);
+
+        let filled_vol = state.trades.iter().map(|x| u64::from(x.vol)).sum();
+        let cancelled_vol = state
+            .orders
+            .iter()
+            .filter(|x| x.order.status == Status::Cancelled)
+            .map(|x| cancelled_order_vol(&x.order))
+            .sum();
+
         Ok(Self {
             t: state.t,
             tick_size: state.tick_size,
+            lot_size: state.lot_size,
             trade_vol: state.trade_vol,
+            seq,
             ask_side,
             bid_side,
             orders: state.orders,
             trades: state.trades,
+            trader_vols,
+            trader_positions,
+            active_orders,
+            filled_vol,
+            cancelled_vol,
+            expiries,
+            buy_stops,
+            sell_stops,
             trading: state.trading,
+            priority: state.priority,
+            protection_window: state.protection_window,
+            min_resting_time: state.min_resting_time,
+            price_decimals: state.price_decimals,
+            price_band: state.price_band,
+            self_trade_prevention: state.self_trade_prevention,
+            max_trade_move: state.max_trade_move,
+            on_event: EventListener::default(),
         })
     }
 }
@@ -924,7 +3749,7 @@ mod tests {
 
     #[test]
     fn test_init() {
-        let book: OrderBook = OrderBook::new(0, 1, true);
+        let book: OrderBook = OrderBook::new(0, 1, 1, true);
 
         assert!(book.bid_vol() == 0);
         assert!(book.ask_vol() == 0);
@@ -937,7 +3762,7 @@ mod tests {
 
     #[test]
     fn test_insert_order() {
-        let mut book: OrderBook = OrderBook::new(0, 1, true);
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
 
         book.create_and_place_order(Side::Ask, 10, 0, Some(100))
             .unwrap();
@@ -981,7 +3806,7 @@ mod tests {
 
     #[test]
     fn test_level_data() {
-        let mut book = OrderBook::<4>::new(0, 2, true);
+        let mut book = OrderBook::<4>::new(0, 2, 1, true);
 
         let bid_levels = book.bid_levels();
 
@@ -1049,266 +3874,2692 @@ mod tests {
     }
 
     #[test]
-    fn test_cancel_order() {
-        let mut book: OrderBook = OrderBook::new(0, 1, true);
+    fn test_num_levels() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.num_levels(Side::Bid) == 0);
+        assert!(book.num_levels(Side::Ask) == 0);
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(95))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(110))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(120))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(150))
+            .unwrap();
+
+        assert!(book.num_levels(Side::Bid) == 2);
+        assert!(book.num_levels(Side::Ask) == 3);
+
+        book.cancel_order(2);
+        book.cancel_order(0);
+
+        assert!(book.num_levels(Side::Bid) == 1);
+        assert!(book.num_levels(Side::Ask) == 3);
+    }
+
+    #[test]
+    fn test_cancel_order() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(50))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(90))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(60))
+            .unwrap();
+
+        assert!(book.bid_ask() == (60, 90));
+        assert!(book.ask_vol() == 20);
+        assert!(book.bid_vol() == 20);
+        assert!(book.bid_best_vol() == 10);
+        assert!(book.ask_best_vol() == 10);
+        assert!(book.bid_best_vol_and_orders() == (10, 1));
+        assert!(book.ask_best_vol_and_orders() == (10, 1));
+
+        book.cancel_order(0);
+        book.cancel_order(3);
+
+        assert!(book.bid_ask() == (50, 90));
+        assert!(book.ask_vol() == 10);
+        assert!(book.bid_vol() == 10);
+        assert!(book.bid_best_vol() == 10);
+        assert!(book.ask_best_vol() == 10);
+        assert!(book.bid_best_vol_and_orders() == (10, 1));
+        assert!(book.ask_best_vol_and_orders() == (10, 1));
+
+        book.cancel_order(1);
+        book.cancel_order(2);
+
+        assert!(book.bid_ask() == (0, Price::MAX));
+        assert!(book.ask_vol() == 0);
+        assert!(book.bid_vol() == 0);
+        assert!(book.bid_best_vol() == 0);
+        assert!(book.ask_best_vol() == 0);
+        assert!(book.bid_best_vol_and_orders() == (0, 0));
+        assert!(book.ask_best_vol_and_orders() == (0, 0));
+
+        assert!(matches!(book.orders[0].order.status, Status::Cancelled));
+        assert!(matches!(book.orders[1].order.status, Status::Cancelled));
+        assert!(matches!(book.orders[2].order.status, Status::Cancelled));
+        assert!(matches!(book.orders[3].order.status, Status::Cancelled));
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut book: OrderBook = OrderBook::new(0, 2, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(50))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 5, 0, None).unwrap();
+
+        book.reset(10);
+
+        assert!(book.get_time() == 10);
+        assert!(book.bid_ask() == (0, Price::MAX));
+        assert!(book.bid_vol() == 0);
+        assert!(book.ask_vol() == 0);
+        assert!(book.get_orders().is_empty());
+        assert!(book.get_trades().is_empty());
+        assert!(book.get_trade_vol() == 0);
+
+        let id = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+
+        assert!(id == 0);
+        assert!(book.bid_ask() == (0, 100));
+        assert!(book.create_order(Side::Ask, 10, 0, Some(101)).is_err());
+    }
+
+    #[test]
+    fn test_partially_filled_orders() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let partial = book
+            .create_and_place_order(Side::Ask, 20, 0, Some(100))
+            .unwrap();
+        let untouched = book
+            .create_and_place_order(Side::Ask, 20, 0, Some(102))
+            .unwrap();
+        let modified = book
+            .create_and_place_order(Side::Bid, 20, 0, Some(98))
+            .unwrap();
+        let aggressor = book.create_and_place_order(Side::Bid, 5, 1, None).unwrap();
+
+        book.modify_order(modified, None, Some(10));
+        book.cancel_order(partial);
+        book.cancel_order(untouched);
+
+        assert!(book.order(partial).status == Status::Cancelled);
+        assert!(book.order(untouched).status == Status::Cancelled);
+        assert!(book.order(partial).is_partially_filled_then_cancelled());
+        assert!(!book.order(untouched).is_partially_filled_then_cancelled());
+
+        let filled: Vec<OrderId> = book
+            .partially_filled_orders()
+            .iter()
+            .map(|x| x.order_id)
+            .collect();
+
+        assert!(filled == vec![partial, aggressor]);
+    }
+
+    #[test]
+    fn test_lot_size() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 100, true);
+
+        let res = book.create_order(Side::Bid, 150, 0, Some(50));
+        assert!(matches!(
+            res,
+            Err(OrderError::LotError {
+                vol: 150,
+                lot_size: 100
+            })
+        ));
+        let res = book.create_order(Side::Ask, 50, 0, None);
+        assert!(matches!(res, Err(OrderError::LotError { .. })));
+        assert!(book.orders.is_empty());
+
+        let id = book
+            .create_and_place_order(Side::Bid, 200, 0, Some(50))
+            .unwrap();
+        assert!(book.order(id).vol == 200);
+        assert!(book.bid_vol() == 200);
+
+        // Modifications to invalid volumes are ignored
+        book.modify_order(id, None, Some(150));
+        assert!(book.order(id).vol == 200);
+        book.modify_order(id, Some(51), Some(250));
+        assert!(book.order(id).vol == 200);
+        assert!(book.order(id).price == 50);
+
+        book.modify_order(id, None, Some(100));
+        assert!(book.order(id).vol == 100);
+        assert!(book.bid_vol() == 100);
+    }
+
+    #[test]
+    fn test_trader_ids() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.trader_ids().is_empty());
+
+        book.create_and_place_order(Side::Ask, 10, 12, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 3, Some(98))
+            .unwrap();
+        book.create_order(Side::Bid, 10, 7, Some(97)).unwrap();
+        book.create_and_place_order(Side::Bid, 10, 12, None)
+            .unwrap();
+
+        assert!(book.trader_ids() == vec![3, 7, 12]);
+    }
+
+    #[test]
+    fn test_num_active_traders() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.num_active_traders() == 0);
+
+        book.create_and_place_order(Side::Ask, 10, 1, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 1, Some(101))
+            .unwrap();
+        let cancelled = book
+            .create_and_place_order(Side::Bid, 10, 2, Some(98))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 3, Some(97))
+            .unwrap();
+        book.create_order(Side::Bid, 10, 4, Some(97)).unwrap();
+
+        assert!(book.num_active_traders() == 3);
+
+        book.cancel_order(cancelled);
+        assert!(book.num_active_traders() == 2);
+
+        // Fill trader 1's orders, trader 5's order is filled on arrival
+        book.create_and_place_order(Side::Bid, 20, 5, None).unwrap();
+        assert!(book.num_active_traders() == 1);
+        assert!(book.trader_ids() == vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_active_orders_for() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let active_ids = |book: &OrderBook, trader_id| {
+            book.active_orders_for(trader_id)
+                .iter()
+                .map(|x| x.order_id)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(book.active_orders_for(1).is_empty());
+
+        let a = book
+            .create_and_place_order(Side::Ask, 10, 1, Some(100))
+            .unwrap();
+        let b = book
+            .create_and_place_order(Side::Ask, 10, 1, Some(101))
+            .unwrap();
+        let c = book
+            .create_and_place_order(Side::Bid, 10, 2, Some(98))
+            .unwrap();
+        // Unplaced orders are not included
+        let d = book.create_order(Side::Bid, 10, 2, Some(97)).unwrap();
+        let e = book.create_iceberg_order(Side::Bid, 20, 5, 3, 96).unwrap();
+        book.place_order(e);
+
+        assert!(active_ids(&book, 1) == vec![a, b]);
+        assert!(active_ids(&book, 2) == vec![c]);
+        assert!(active_ids(&book, 3) == vec![e]);
+
+        book.cancel_order(c);
+        book.place_order(d);
+        assert!(active_ids(&book, 2) == vec![d]);
+
+        // Partial fill keeps the order, fills remove it
+        book.create_and_place_order(Side::Bid, 15, 4, None).unwrap();
+        assert!(active_ids(&book, 1) == vec![b]);
+        assert!(book.active_orders_for(1)[0].vol == 5);
+        assert!(book.active_orders_for(4).is_empty());
+
+        // Modified orders are kept until filled
+        book.modify_order(b, Some(102), None);
+        assert!(active_ids(&book, 1) == vec![b]);
+        book.modify_order(d, Some(102), None);
+        assert!(book.active_orders_for(1).is_empty());
+        assert!(active_ids(&book, 2) == vec![d]);
+
+        // Iceberg orders are kept while replenished
+        book.create_and_place_order(Side::Ask, 17, 5, None).unwrap();
+        assert!(book.active_orders_for(2).is_empty());
+        assert!(active_ids(&book, 3) == vec![e]);
+        book.create_and_place_order(Side::Ask, 8, 5, None).unwrap();
+        assert!(book.active_orders_for(3).is_empty());
+
+        // Index is rebuilt when loading a snapshot
+        let f = book
+            .create_and_place_order(Side::Bid, 10, 3, Some(90))
+            .unwrap();
+        let book_snapshot = serde_json::to_string(&book).unwrap();
+        let loaded_book = serde_json::from_str::<OrderBook>(book_snapshot.as_str()).unwrap();
+        assert!(active_ids(&loaded_book, 3) == vec![f]);
+    }
+
+    #[test]
+    fn test_turnover() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.turnover() == 0);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 15, 1, None).unwrap();
+
+        assert!(book.trades.len() == 2);
+        assert!(book.trades[0].notional() == 1000);
+        assert!(book.trades[1].notional() == 510);
+        assert!(book.turnover() == 1510);
+    }
+
+    #[test]
+    fn test_round_trip_cost() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.round_trip_cost(10).is_none());
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(110))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(112))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+
+        // Insufficient bid volume
+        assert!(book.round_trip_cost(20).is_none());
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(97))
+            .unwrap();
+
+        assert!(book.round_trip_cost(0).is_none());
+        // Just the spread within the touch volume
+        assert!(book.round_trip_cost(10) == Some(100.0));
+        // Spread plus impact of walking both sides of the book
+        assert!(book.round_trip_cost(20) == Some(20.0 * 10.0 + 10.0 * 2.0 + 10.0 * 3.0));
+        assert!(book.round_trip_cost(21).is_none());
+    }
+
+    #[test]
+    fn test_impact_curve() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(101))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 20, 0, Some(104))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(98))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(95))
+            .unwrap();
+
+        assert!(book.sweep_price(Side::Bid, 0).is_none());
+        assert!(book.sweep_price(Side::Bid, 10) == Some(100.0));
+        assert!(book.sweep_price(Side::Bid, 20) == Some(100.5));
+        assert!(book.sweep_price(Side::Bid, 41).is_none());
+        assert!(book.sweep_price(Side::Ask, 20) == Some(96.5));
+
+        let curve = impact_curve(&book, Side::Bid, 40, 8);
+
+        assert!(curve.len() == 8);
+        assert!(curve[0] == (5, 100.0));
+        assert!(curve[7] == (40, 102.25));
+        assert!(curve.windows(2).all(|x| x[1].1 >= x[0].1));
+        assert!(curve.windows(2).any(|x| x[1].1 > x[0].1));
+
+        let curve = impact_curve(&book, Side::Ask, 30, 3);
+
+        assert!(curve == vec![(10, 98.0), (20, 96.5)]);
+        assert!(book.ask_vol() == 40);
+        assert!(book.bid_vol() == 20);
+    }
+
+    #[test]
+    fn test_expire_orders() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let a = book
+            .create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+        let b = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+        let c = book.create_order(Side::Ask, 10, 0, Some(103)).unwrap();
+
+        book.set_order_expiry(a, 10);
+        book.set_order_expiry(b, 20);
+        book.set_order_expiry(c, 10);
+
+        book.set_time(9);
+        book.expire_orders();
+
+        assert!(book.order(a).status == Status::Active);
+        assert!(book.order(c).status == Status::New);
+
+        book.set_time(10);
+        book.expire_orders();
+
+        assert!(book.order(a).status == Status::Cancelled);
+        assert!(book.order(a).end_time == 10);
+        assert!(book.order(b).status == Status::Active);
+        assert!(book.order(c).status == Status::Cancelled);
+        assert!(book.bid_vol() == 0);
+
+        book.set_time(25);
+        book.expire_orders();
+
+        assert!(book.order(b).status == Status::Cancelled);
+        assert!(book.order(b).end_time == 25);
+        assert!(book.ask_vol() == 0);
+    }
+
+    #[test]
+    fn test_market_condition() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, false);
+
+        assert!(book.market_condition() == MarketCondition::Normal);
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+
+        assert!(book.market_condition() == MarketCondition::Normal);
+        assert!(!book.is_crossed());
+
+        let locking_id = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+
+        assert!(book.bid_ask() == (100, 100));
+        assert!(book.market_condition() == MarketCondition::Locked);
+        assert!(!book.is_crossed());
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(101))
+            .unwrap();
+
+        assert!(book.bid_ask() == (101, 100));
+        assert!(book.market_condition() == MarketCondition::Crossed);
+        assert!(book.is_crossed());
+
+        book.cancel_order(locking_id);
+
+        assert!(book.bid_ask() == (101, 102));
+        assert!(book.market_condition() == MarketCondition::Normal);
+        assert!(book.trades.is_empty());
+    }
+
+    #[test]
+    fn test_aggression_ratio() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.aggression_ratio() == 0.0);
+
+        book.create_and_place_order(Side::Ask, 20, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 20, 0, Some(90))
+            .unwrap();
+
+        // Market order aggressor
+        book.create_and_place_order(Side::Bid, 5, 1, None).unwrap();
+        // Marketable limit order aggressor
+        book.create_and_place_order(Side::Bid, 10, 1, Some(100))
+            .unwrap();
+        // Market order aggressor
+        book.create_and_place_order(Side::Ask, 5, 1, None).unwrap();
+
+        assert!(book.trades.len() == 3);
+        assert!(book.trades[0].agg_is_market);
+        assert!(!book.trades[1].agg_is_market);
+        assert!(book.trades[2].agg_is_market);
+        assert!(book.aggression_ratio() == 0.5);
+    }
+
+    #[test]
+    fn test_cancel_unplaced_order() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let id = book.create_order(Side::Ask, 10, 0, Some(100)).unwrap();
+        book.set_time(5);
+        book.cancel_order(id);
+
+        assert!(book.order(id).status == Status::Cancelled);
+        assert!(book.order(id).end_time == 5);
+
+        book.place_order(id);
+
+        assert!(book.order(id).status == Status::Cancelled);
+        assert!(book.bid_ask() == (0, Price::MAX));
+        assert!(book.ask_vol() == 0);
+    }
+
+    #[test]
+    fn test_mod_order_vol() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(50))
+            .unwrap();
+
+        book.modify_order(0, None, Some(8));
+        book.modify_order(1, None, Some(5));
+
+        assert!(book.ask_vol() == 8);
+        assert!(book.ask_best_vol() == 8);
+        assert!(book.ask_best_vol_and_orders() == (8, 1));
+        assert!(book.bid_vol() == 5);
+        assert!(book.bid_best_vol() == 5);
+        assert!(book.bid_best_vol_and_orders() == (5, 1));
+
+        assert!(book.orders[0].order.vol == 8);
+        assert!(book.orders[1].order.vol == 5);
+    }
+
+    #[test]
+    fn test_modify_order() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(50))
+            .unwrap();
+
+        assert!(book.bid_ask() == (50, 100));
+
+        book.modify_order(0, Some(110), Some(15));
+        book.modify_order(1, Some(60), Some(20));
+
+        assert!(book.ask_vol() == 15);
+        assert!(book.ask_best_vol() == 15);
+        assert!(book.bid_vol() == 20);
+        assert!(book.bid_best_vol() == 20);
+        assert!(book.bid_ask() == (60, 110));
+    }
+
+    #[test]
+    fn test_modify_crossing_iceberg() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 50, 0, Some(60))
+            .unwrap();
+        let a = book
+            .create_iceberg_order(Side::Bid, 100, 10, 1, 50)
+            .unwrap();
+        book.place_order(a);
+
+        book.modify_order(a, Some(60), None);
+
+        // Reserve volume continues matching after
+        // the displayed volume is filled
+        assert!(book.get_trades().len() == 5);
+        assert!(book.ask_vol() == 0);
+        assert!(book.order(a).status == Status::Active);
+        assert!(book.order(a).vol == 10);
+        assert!(book.order(a).reserve_vol == 40);
+        assert!(book.bid_ask() == (60, Price::MAX));
+        assert!(book.bid_vol() == 10);
+        assert!(book.active_orders_for(1).len() == 1);
+    }
+
+    #[test]
+    fn test_reduce_order() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let a = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        let b = book
+            .create_and_place_order(Side::Ask, 10, 1, Some(100))
+            .unwrap();
+
+        // Reduced order keeps its priority
+        book.reduce_order(a, 4);
+        assert!(book.order(a).vol == 6);
+        assert!(book.ask_vol() == 16);
+        assert!(book.ask_best_vol_and_orders() == (16, 2));
+
+        book.create_and_place_order(Side::Bid, 6, 2, None).unwrap();
+        assert!(book.order(a).status == Status::Filled);
+        assert!(book.order(b).vol == 10);
+
+        // Reducing by the remaining volume cancels the order
+        book.reduce_order(b, 15);
+        assert!(book.order(b).status == Status::Cancelled);
+        assert!(book.ask_vol() == 0);
+
+        // Unplaced orders are reduced in place
+        let c = book.create_order(Side::Bid, 10, 0, Some(90)).unwrap();
+        book.reduce_order(c, 3);
+        assert!(book.order(c).vol == 7);
+        assert!(book.order(c).start_vol == 7);
+        book.place_order(c);
+        assert!(book.bid_vol() == 7);
+
+        // Iceberg reserve volume is reduced first
+        let d = book.create_iceberg_order(Side::Bid, 20, 5, 0, 95).unwrap();
+        book.place_order(d);
+        book.reduce_order(d, 12);
+        assert!(book.order(d).vol == 5);
+        assert!(book.order(d).reserve_vol == 3);
+        book.reduce_order(d, 6);
+        assert!(book.order(d).vol == 2);
+        assert!(book.order(d).reserve_vol == 0);
+        assert!(book.bid_best_vol() == 2);
+
+        book.process_event(Event::Reduce {
+            order_id: d,
+            amount: 2,
+        });
+        assert!(book.order(d).status == Status::Cancelled);
+        assert!(book.bid_ask() == (90, Price::MAX));
+    }
+
+    #[test]
+    fn test_modify_order_crossing() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(50))
+            .unwrap();
+
+        assert!(book.bid_ask() == (50, 100));
+
+        book.modify_order(1, Some(100), Some(20));
+
+        assert!(book.ask_vol() == 0);
+        assert!(book.ask_best_vol() == 0);
+        assert!(book.ask_best_vol_and_orders() == (0, 0));
+        assert!(book.bid_vol() == 10);
+        assert!(book.bid_best_vol() == 10);
+        assert!(book.bid_best_vol_and_orders() == (10, 1));
+        assert!(book.bid_ask() == (100, Price::MAX));
+
+        assert!(book.trades.len() == 1);
+        assert!(book.trades[0].price == 100);
+        assert!(book.trades[0].vol == 10);
+    }
+
+    #[test]
+    fn test_modify_order_post_only() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(50))
+            .unwrap();
+
+        assert!(!book.modify_order_post_only(1, Some(100), Some(20)));
+        assert!(!book.modify_order_post_only(0, Some(50), None));
+
+        assert!(book.bid_ask() == (50, 100));
+        assert!(book.bid_best_vol_and_orders() == (10, 1));
+        assert!(book.ask_best_vol_and_orders() == (10, 1));
+        assert!(book.orders[1].order.price == 50);
+        assert!(book.orders[1].order.vol == 10);
+        assert!(book.trades.is_empty());
+
+        assert!(book.modify_order_post_only(1, Some(99), Some(20)));
+
+        assert!(book.bid_ask() == (99, 100));
+        assert!(book.bid_best_vol_and_orders() == (20, 1));
+        assert!(book.trades.is_empty());
+    }
+
+    #[test]
+    fn test_trades() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_order(Side::Ask, 101, 101, Some(20)).unwrap();
+        book.create_order(Side::Ask, 101, 101, Some(18)).unwrap();
+        book.create_order(Side::Bid, 202, 101, Some(12)).unwrap();
+        book.create_order(Side::Bid, 202, 101, Some(14)).unwrap();
+
+        book.place_order(0);
+        book.set_time(1);
+        book.place_order(1);
+        book.set_time(2);
+        book.place_order(2);
+        book.set_time(3);
+        book.place_order(3);
+        book.set_time(4);
+
+        book.create_order(Side::Bid, 102, 101, None).unwrap();
+        book.place_order(4);
+
+        assert!(book.ask_vol() == 100);
+        assert!(book.bid_ask() == (14, 20));
+
+        assert!(book.trades.len() == 2);
+        assert!(book.trades[0].price == 18);
+        assert!(book.trades[0].vol == 101);
+        assert!(book.trades[1].price == 20);
+        assert!(book.trades[1].vol == 1);
+        assert!(book.get_trade_vol() == 102);
+
+        book.create_order(Side::Ask, 204, 101, Some(14)).unwrap();
+        book.place_order(5);
+
+        assert!(book.bid_vol() == 202);
+        assert!(book.ask_vol() == 102);
+        assert!(book.bid_best_vol_and_orders() == (202, 1));
+        assert!(book.ask_best_vol_and_orders() == (2, 1));
+        assert!(book.bid_ask() == (12, 14));
+
+        assert!(book.trades.len() == 3);
+        assert!(book.trades[2].price == 14);
+        assert!(book.trades[2].vol == 202);
+        assert!(book.get_trade_vol() == 304);
+    }
+
+    #[test]
+    fn test_same_time_priority() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        for _ in 0..10 {
+            book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+                .unwrap();
+        }
+
+        assert!(book.ask_best_vol_and_orders() == (100, 10));
+
+        book.create_and_place_order(Side::Bid, 100, 0, None)
+            .unwrap();
+
+        assert!(book.ask_vol() == 0);
+        assert!(book.trades.len() == 10);
+
+        for (i, trade) in book.trades.iter().enumerate() {
+            assert!(trade.passive_order_id == i);
+        }
+    }
+
+    #[test]
+    fn test_load_without_seq() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        for _ in 0..3 {
+            book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+                .unwrap();
+        }
+
+        let mut state = serde_json::to_value(&book).unwrap();
+        state.as_object_mut().unwrap().remove("seq");
+        let mut loaded_book = serde_json::from_value::<OrderBook>(state).unwrap();
+
+        assert!(loaded_book.seq == book.seq);
+
+        // Orders placed after loading keep time priority
+        loaded_book
+            .create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        loaded_book
+            .create_and_place_order(Side::Bid, 40, 0, None)
+            .unwrap();
+
+        assert!(loaded_book.trades.len() == 4);
+        for (i, trade) in loaded_book.trades.iter().enumerate() {
+            assert!(trade.passive_order_id == i);
+        }
+    }
+
+    #[test]
+    fn test_load_pruned_snapshot() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 1, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 2, Some(101))
+            .unwrap();
+        let cancelled = book
+            .create_and_place_order(Side::Bid, 10, 3, Some(80))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 15, 3, None).unwrap();
+        book.create_and_place_order(Side::Bid, 20, 3, Some(90))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 5, 4, Some(90))
+            .unwrap();
+        book.cancel_order(cancelled);
+
+        let path = std::env::temp_dir().join("bourse_test_pruned.json");
+        book.save_json(&path, false, true).unwrap();
+        let mut loaded_book: OrderBook = OrderBook::load_json(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Same resting state
+        assert!(loaded_book.level_2_data() == book.level_2_data());
+        assert!(loaded_book.get_time() == book.get_time());
+        assert!(loaded_book.get_trade_vol() == book.get_trade_vol());
+
+        // Only live orders are kept, with new ids
+        assert!(loaded_book.get_trades().is_empty());
+        assert!(loaded_book.orders.len() == 3);
+        assert!(loaded_book
+            .get_orders()
+            .iter()
+            .enumerate()
+            .all(|(i, x)| x.order_id == i && x.status == Status::Active));
+        assert!(loaded_book.order(0).vol == 5 && loaded_book.order(0).price == 101);
+
+        // Loaded book continues trading with the same priority
+        loaded_book
+            .create_and_place_order(Side::Ask, 22, 5, None)
+            .unwrap();
+        let trades = loaded_book.get_trades();
+        assert!(trades.len() == 2);
+        assert!(trades[0].passive_order_id == 1 && trades[0].vol == 20);
+        assert!(loaded_book.order(3).status == Status::Filled);
+        assert!(loaded_book.bid_ask() == (90, 101));
+    }
+
+    #[test]
+    fn test_trader_volume() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 1, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 2, Some(101))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 15, 1, None).unwrap();
+        book.create_and_place_order(Side::Bid, 20, 3, Some(90))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 8, 1, None).unwrap();
+
+        assert!(book.trader_volume(1) == 33);
+        assert!(book.trader_volume(2) == 5);
+        assert!(book.trader_volume(3) == 8);
+        assert!(book.trader_volume(4) == 0);
+
+        let book_snapshot = serde_json::to_string(&book).unwrap();
+        let loaded_book = serde_json::from_str::<OrderBook>(book_snapshot.as_str()).unwrap();
+
+        assert!(loaded_book.trader_volume(1) == 33);
+        assert!(loaded_book.trader_volume(2) == 5);
+        assert!(loaded_book.trader_volume(3) == 8);
+    }
+
+    #[test]
+    fn test_trader_position() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 1, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 2, Some(101))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 15, 3, None).unwrap();
+        book.create_and_place_order(Side::Bid, 20, 3, Some(90))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 8, 1, None).unwrap();
+
+        assert!(book.trader_position(1) == (-18, 1720));
+        assert!(book.trader_position(2) == (-5, 505));
+        assert!(book.trader_position(3) == (23, -2225));
+        assert!(book.trader_position(4) == (0, 0));
+
+        let book_snapshot = serde_json::to_string(&book).unwrap();
+        let loaded_book = serde_json::from_str::<OrderBook>(book_snapshot.as_str()).unwrap();
+
+        for trader_id in 1..4 {
+            assert!(loaded_book.trader_position(trader_id) == book.trader_position(trader_id));
+        }
+
+        book.reset(0);
+        assert!(book.trader_position(1) == (0, 0));
+    }
+
+    #[test]
+    fn test_size_then_time_priority() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+        book.set_within_level_priority(WithinLevelPriority::SizeThenTime);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.set_time(1);
+        book.create_and_place_order(Side::Ask, 20, 0, Some(100))
+            .unwrap();
+        book.set_time(2);
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.set_time(3);
+        book.create_and_place_order(Side::Bid, 25, 0, None).unwrap();
+
+        assert!(book.trades.len() == 2);
+        assert!(book.trades[0].passive_order_id == 1);
+        assert!(book.trades[0].vol == 20);
+        assert!(book.trades[1].passive_order_id == 0);
+        assert!(book.trades[1].vol == 5);
+        assert!(book.ask_best_vol_and_orders() == (15, 2));
+
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+        book.set_time(1);
+        book.create_and_place_order(Side::Bid, 20, 0, Some(100))
+            .unwrap();
+        book.set_time(2);
+        book.create_and_place_order(Side::Ask, 15, 0, None).unwrap();
+
+        assert!(book.trades.len() == 2);
+        assert!(book.trades[0].passive_order_id == 0);
+        assert!(book.trades[1].passive_order_id == 1);
+    }
+
+    #[test]
+    fn test_market_order_no_trading() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, false);
+
+        book.create_and_place_order(Side::Bid, 101, 101, None)
+            .unwrap();
+
+        assert!(book.bid_ask() == (0, Price::MAX));
+        assert!(book.bid_vol() == 0);
+        assert!(book.ask_vol() == 0);
+        assert!(book.orders[0].order.status == Status::Rejected);
+    }
+
+    #[test]
+    fn test_unfilled_market_order() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 101, Some(50))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 20, 101, None)
+            .unwrap();
+
+        assert!(book.bid_ask() == (0, Price::MAX));
+        assert!(book.bid_vol() == 0);
+        assert!(book.ask_vol() == 0);
+        assert!(book.orders[1].order.status == Status::Cancelled);
+    }
+
+    #[test]
+    fn test_incorrect_price_err() {
+        let mut book: OrderBook = OrderBook::new(0, 2, 1, true);
+
+        let res = book.create_order(Side::Ask, 100, 101, Some(51));
+
+        assert!(res.is_err_and(|e| matches!(
+            e,
+            OrderError::PriceError {
+                price: 51,
+                tick_size: 2
+            }
+        )));
+    }
+
+    #[test]
+    fn test_no_double_place() {
+        let mut book: OrderBook = OrderBook::new(0, 2, 1, true);
+
+        let id = book.create_order(Side::Ask, 100, 101, Some(50)).unwrap();
+
+        book.place_order(id);
+
+        assert!(book.bid_ask() == (0, 50));
+        assert!(book.ask_best_vol_and_orders() == (100, 1));
+
+        book.place_order(id);
+
+        assert!(book.bid_ask() == (0, 50));
+        assert!(book.ask_best_vol_and_orders() == (100, 1));
+    }
+
+    #[test]
+    fn test_serialisation() {
+        use rand::{seq::SliceRandom, Rng};
+        use rand_xoshiro::rand_core::SeedableRng;
+        use rand_xoshiro::Xoroshiro128Plus;
+
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let mut rng = Xoroshiro128Plus::seed_from_u64(101);
+
+        for i in (0..200).into_iter() {
+            let side = [Side::Bid, Side::Ask].choose(&mut rng).unwrap();
+            let price = rng.gen_range(20..40);
+            let vol = rng.gen_range(5..20);
+            book.create_and_place_order(*side, vol, 0, Some(price))
+                .unwrap();
+            book.set_time(i);
+        }
+
+        let book_snapshot = serde_json::to_string(&book).unwrap();
+        let loaded_book = serde_json::from_str::<OrderBook>(book_snapshot.as_str()).unwrap();
+
+        assert!(book.trading == loaded_book.trading);
+        assert!(book.trade_vol == loaded_book.trade_vol);
+
+        assert!(book.bid_ask() == loaded_book.bid_ask());
+
+        assert!(book.bid_best_vol_and_orders() == loaded_book.bid_best_vol_and_orders());
+        assert!(book.bid_vol() == loaded_book.bid_vol());
+
+        assert!(book.ask_best_vol_and_orders() == loaded_book.ask_best_vol_and_orders());
+        assert!(book.ask_vol() == loaded_book.ask_vol());
+
+        assert!(book.current_order_id() == loaded_book.current_order_id());
+        assert!(book.seq == loaded_book.seq);
+        assert!(book.lot_size == loaded_book.lot_size);
+        assert!(book.priority == loaded_book.priority);
+
+        assert!(book.bid_side.best_order_idx() == loaded_book.bid_side.best_order_idx());
+        assert!(book.ask_side.best_order_idx() == loaded_book.ask_side.best_order_idx());
+    }
+
+    #[test]
+    fn test_time_in_force_serialisation() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_order(Side::Bid, 10, 0, Some(20)).unwrap();
+        book.create_order_with_tif(Side::Bid, 10, 0, Some(20), TimeInForce::ImmediateOrCancel)
+            .unwrap();
+
+        assert!(book.order(0).time_in_force == TimeInForce::GoodTillCancel);
+        assert!(book.order(1).time_in_force == TimeInForce::ImmediateOrCancel);
+
+        let book_snapshot = serde_json::to_string(&book).unwrap();
+        let loaded_book = serde_json::from_str::<OrderBook>(book_snapshot.as_str()).unwrap();
+
+        assert!(loaded_book.order(0).time_in_force == TimeInForce::GoodTillCancel);
+        assert!(loaded_book.order(1).time_in_force == TimeInForce::ImmediateOrCancel);
+        assert!(loaded_book.get_orders()[1].time_in_force == TimeInForce::ImmediateOrCancel);
+    }
+
+    #[test]
+    fn test_good_till_date() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let order_id = book.create_order(Side::Bid, 10, 0, Some(20)).unwrap();
+        book.set_order_expiry(order_id, 100);
+        assert!(book.order(order_id).time_in_force == TimeInForce::GoodTillDate);
+
+        // Order rests on the book until it expires
+        book.place_order(order_id);
+        assert!(book.order(order_id).status == Status::Active);
+        assert!(book.bid_vol() == 10);
+
+        let book_snapshot = serde_json::to_string(&book).unwrap();
+        let loaded_book = serde_json::from_str::<OrderBook>(book_snapshot.as_str()).unwrap();
+        assert!(loaded_book.order(order_id).time_in_force == TimeInForce::GoodTillDate);
+
+        book.set_time(100);
+        book.expire_orders();
+        assert!(book.order(order_id).status == Status::Cancelled);
+
+        // Immediate-or-cancel orders keep their time-in-force
+        let order_id = book
+            .create_order_with_tif(Side::Bid, 10, 0, Some(20), TimeInForce::ImmediateOrCancel)
+            .unwrap();
+        book.set_order_expiry(order_id, 200);
+        assert!(book.order(order_id).time_in_force == TimeInForce::ImmediateOrCancel);
+    }
+
+    #[test]
+    fn test_immediate_or_cancel() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+
+        let id = book
+            .create_order_with_tif(Side::Bid, 15, 1, Some(101), TimeInForce::ImmediateOrCancel)
+            .unwrap();
+        book.place_order(id);
+
+        assert!(book.order(id).status == Status::Cancelled);
+        assert!(book.order(id).vol == 5);
+        assert!(book.get_trades().len() == 1);
+        assert!(book.bid_vol() == 0);
+        assert!(book.bid_ask() == (0, 102));
+        assert!(book.cancelled_volume() == 5);
+
+        let id = book
+            .create_order_with_tif(Side::Bid, 5, 1, Some(102), TimeInForce::ImmediateOrCancel)
+            .unwrap();
+        book.place_order(id);
+
+        assert!(book.order(id).status == Status::Filled);
+        assert!(book.ask_vol() == 5);
+    }
+
+    #[test]
+    fn test_fill_or_kill() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(98))
+            .unwrap();
+
+        let id = book
+            .create_order_with_tif(Side::Ask, 15, 1, Some(99), TimeInForce::FillOrKill)
+            .unwrap();
+        book.place_order(id);
+
+        assert!(book.order(id).status == Status::Rejected);
+        assert!(book.order(id).vol == 15);
+        assert!(book.get_trades().is_empty());
+        assert!(book.bid_vol() == 20);
+        assert!(book.ask_vol() == 0);
+
+        let id = book
+            .create_order_with_tif(Side::Ask, 15, 1, Some(98), TimeInForce::FillOrKill)
+            .unwrap();
+        book.place_order(id);
+
+        assert!(book.order(id).status == Status::Filled);
+        assert!(book.get_trades().len() == 2);
+        assert!(book.bid_vol() == 5);
+        assert!(book.bid_ask() == (98, Price::MAX));
+    }
+
+    #[test]
+    fn test_post_only_order() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+
+        let crossing_bid = book.create_post_only_order(Side::Bid, 5, 1, 102).unwrap();
+        book.place_order(crossing_bid);
+        let crossing_ask = book.create_post_only_order(Side::Ask, 5, 1, 99).unwrap();
+        book.place_order(crossing_ask);
+
+        assert!(book.order(crossing_bid).status == Status::Rejected);
+        assert!(book.order(crossing_ask).status == Status::Rejected);
+        assert!(book.get_trades().is_empty());
+        assert!(book.bid_vol() == 10);
+        assert!(book.ask_vol() == 10);
+
+        let bid = book.create_post_only_order(Side::Bid, 5, 1, 101).unwrap();
+        book.place_order(bid);
+        let ask = book.create_post_only_order(Side::Ask, 5, 1, 102).unwrap();
+        book.place_order(ask);
+
+        assert!(book.order(bid).status == Status::Active);
+        assert!(book.order(ask).status == Status::Active);
+        assert!(book.bid_ask() == (101, 102));
+        assert!(book.ask_vol() == 15);
+    }
+
+    #[test]
+    fn test_deserialise_invalid_order_ids() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        for i in 0..3 {
+            book.create_and_place_order(Side::Bid, 10, 0, Some(20 + i))
+                .unwrap();
+        }
+        book.create_and_place_order(Side::Ask, 5, 1, None).unwrap();
+
+        let state = serde_json::to_value(&book).unwrap();
+        assert!(serde_json::from_value::<OrderBook>(state.clone()).is_ok());
+
+        // Duplicate order id
+        let mut duplicate = state.clone();
+        duplicate["orders"][2]["order"]["order_id"] = serde_json::json!(1);
+        assert!(serde_json::from_value::<OrderBook>(duplicate).is_err());
+
+        // Non-contiguous order ids
+        let mut gap = state.clone();
+        gap["orders"][2]["order"]["order_id"] = serde_json::json!(5);
+        assert!(serde_json::from_value::<OrderBook>(gap).is_err());
+
+        // Trades referencing unknown orders
+        let mut active = state.clone();
+        active["trades"][0]["active_order_id"] = serde_json::json!(4);
+        assert!(serde_json::from_value::<OrderBook>(active).is_err());
+
+        let mut passive = state.clone();
+        passive["trades"][0]["passive_order_id"] = serde_json::json!(10);
+        assert!(serde_json::from_value::<OrderBook>(passive).is_err());
+    }
+
+    #[test]
+    fn test_market_order_by_value_zero_price() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(0))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+
+        // 10 at 100 and 10 at 0 leaving 200
+        let (order_id, leftover) = book
+            .create_market_order_by_value(Side::Ask, 1200, 1)
+            .unwrap();
+
+        assert!(leftover == 200);
+        assert!(book.order(order_id).vol == 20);
+    }
+
+    #[test]
+    fn test_market_order_by_value() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+
+        // 10 at 100 and 4 at 102 leaving 92
+        let (order_id, leftover) = book
+            .create_market_order_by_value(Side::Bid, 1500, 1)
+            .unwrap();
+
+        assert!(leftover == 92);
+        assert!(book.order(order_id).vol == 14);
+        assert!(book.order(order_id).is_market);
+
+        book.place_order(order_id);
+
+        assert!(book.order(order_id).status == Status::Filled);
+        let spent: u64 = book.get_trades().iter().map(|x| x.notional()).sum();
+        assert!(spent == 1500 - leftover);
+        assert!(book.ask_vol() == 6);
+
+        // Budget exceeding the available volume
+        let (order_id, leftover) = book
+            .create_market_order_by_value(Side::Bid, 1000, 1)
+            .unwrap();
+        assert!(book.order(order_id).vol == 6);
+        assert!(leftover == 388);
+
+        // Budget smaller than the price of a lot
+        let n_orders = book.num_orders();
+        let res = book.create_market_order_by_value(Side::Bid, 101, 1);
+        assert!(matches!(res, Err(OrderError::BudgetError { cash: 101 })));
+
+        // Empty side of the book
+        let res = book.create_market_order_by_value(Side::Ask, 1000, 1);
+        assert!(matches!(res, Err(OrderError::BudgetError { cash: 1000 })));
+        assert!(book.num_orders() == n_orders);
+    }
+
+    #[test]
+    fn test_market_order_by_value_lot_size() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 5, true);
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(99))
+            .unwrap();
+
+        // Could afford 10 at 100 and 3 at 99, rounded down to 10
+        let (order_id, leftover) = book
+            .create_market_order_by_value(Side::Ask, 1300, 1)
+            .unwrap();
+
+        assert!(book.order(order_id).vol == 10);
+        assert!(leftover == 300);
+    }
+
+    #[test]
+    fn test_level_3_data() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Bid, 10, 1, Some(100))
+            .unwrap();
+        book.set_time(1);
+        book.create_and_place_order(Side::Bid, 20, 2, Some(100))
+            .unwrap();
+        book.set_time(2);
+        book.create_and_place_order(Side::Bid, 30, 3, Some(98))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 40, 4, Some(97))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 15, 5, Some(102))
+            .unwrap();
+
+        let data = book.level_3_data(2);
+
+        assert!(data.bid_levels.len() == 2);
+        assert!(data.bid_levels[0] == (100, vec![(0, 10, 1, 0), (1, 20, 2, 1)]));
+        assert!(data.bid_levels[1] == (98, vec![(2, 30, 3, 2)]));
+        assert!(data.ask_levels == vec![(102, vec![(4, 15, 5, 2)])]);
+
+        book.create_and_place_order(Side::Ask, 5, 6, None).unwrap();
+
+        let data = book.level_3_data(10);
+
+        assert!(data.bid_levels.len() == 3);
+        assert!(data.bid_levels[0] == (100, vec![(0, 5, 1, 0), (1, 20, 2, 1)]));
+        assert!(book.level_3_data(0).bid_levels.is_empty());
+    }
+
+    #[test]
+    fn test_modify_to_zero_vol() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let a = book
+            .create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+        let b = book
+            .create_and_place_order(Side::Bid, 20, 0, Some(100))
+            .unwrap();
+        let c = book.create_order(Side::Ask, 10, 0, Some(105)).unwrap();
+
+        book.modify_order(a, None, Some(0));
+
+        assert!(book.order(a).status == Status::Cancelled);
+        assert!(book.bid_vol() == 20);
+        assert!(book.bid_best_vol_and_orders() == (20, 1));
+        assert!(book.bid_levels()[0] == (20, 1));
+        assert!(book.bid_side.best_order_idx() == Some(b));
+
+        book.modify_order(b, Some(101), Some(0));
+
+        assert!(book.order(b).status == Status::Cancelled);
+        assert!(book.bid_vol() == 0);
+        assert!(book.bid_levels()[0] == (0, 0));
+
+        book.modify_order(c, None, Some(0));
+        assert!(book.order(c).status == Status::Cancelled);
+    }
+
+    #[test]
+    fn test_modify_new_order() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let a = book.create_order(Side::Bid, 10, 0, Some(100)).unwrap();
+        book.modify_order(a, Some(101), Some(6));
+
+        assert!(book.order(a).status == Status::New);
+        assert!(book.order(a).price == 101);
+        assert!(book.order(a).vol == 6);
+        assert!(book.order(a).start_vol == 6);
+
+        let b = book.create_iceberg_order(Side::Bid, 20, 5, 0, 100).unwrap();
+        book.modify_order(b, None, Some(4));
+
+        assert!(book.order(b).vol == 4);
+        assert!(book.order(b).start_vol == 19);
+    }
+
+    #[test]
+    fn test_uncross() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, false);
+
+        assert!(book.uncross().is_empty());
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(99))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 1, Some(100))
+            .unwrap();
+
+        // Book is not crossed
+        assert!(book.uncross().is_empty());
+
+        book.set_time(1);
+        let b0 = book
+            .create_and_place_order(Side::Bid, 10, 0, Some(102))
+            .unwrap();
+        let b1 = book
+            .create_and_place_order(Side::Bid, 10, 0, Some(101))
+            .unwrap();
+        let b2 = book
+            .create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+        let a0 = book
+            .create_and_place_order(Side::Ask, 10, 1, Some(99))
+            .unwrap();
+        let a1 = book
+            .create_and_place_order(Side::Ask, 5, 1, Some(101))
+            .unwrap();
+
+        assert!(book.bid_ask() == (102, 99));
+
+        let trades = book.uncross();
+
+        // 20 lots can execute at 100 or 101, with the
+        // smallest imbalance at 101
+        assert!(trades.iter().all(|x| x.price == 101));
+        assert!(trades.iter().map(|x| x.vol).sum::<Vol>() == 20);
+        assert!(book.get_trades().len() == trades.len());
+        assert!(book.get_trade_vol() == 20);
+
+        assert!(book.order(b0).status == Status::Filled);
+        assert!(book.order(b1).status == Status::Filled);
+        assert!(book.order(b2).status == Status::Active);
+        assert!(book.order(a0).status == Status::Filled);
+        assert!(book.order(1).status == Status::Filled);
+        assert!(book.order(a1).status == Status::Active);
+
+        // Earlier placed orders are passive
+        assert!(trades[0].active_order_id == a0);
+        assert!(trades[0].passive_order_id == b0);
+        assert!(trades[1].active_order_id == b1);
+        assert!(trades[1].passive_order_id == 1);
+
+        assert!(book.bid_ask() == (100, 101));
+        assert!(book.bid_vol() == 20);
+        assert!(book.ask_vol() == 5);
+        assert!(book.uncross().is_empty());
+    }
+
+    #[test]
+    fn test_uncross_iceberg_orders() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, false);
+
+        let bid_iceberg = book
+            .create_iceberg_order(Side::Bid, 100, 5, 0, 102)
+            .unwrap();
+        book.place_order(bid_iceberg);
+        let b1 = book
+            .create_and_place_order(Side::Bid, 3, 0, Some(101))
+            .unwrap();
+        let ask_iceberg = book
+            .create_iceberg_order(Side::Ask, 100, 5, 1, 100)
+            .unwrap();
+        book.place_order(ask_iceberg);
+        let a1 = book
+            .create_and_place_order(Side::Ask, 3, 1, Some(101))
+            .unwrap();
+
+        let trades = book.uncross();
+
+        // Only displayed volume is included in the auction
+        assert!(trades.len() == 2);
+        assert!(trades.iter().all(|x| x.price == 101));
+        assert!(trades.iter().map(|x| x.vol).sum::<Vol>() == 8);
+        assert!(trades[0].passive_order_id == bid_iceberg);
+        assert!(trades[0].active_order_id == ask_iceberg);
+        assert!(trades[1].passive_order_id == b1);
+        assert!(trades[1].active_order_id == a1);
+
+        assert!(book.order(b1).status == Status::Filled);
+        assert!(book.order(a1).status == Status::Filled);
+
+        // Icebergs are replenished after the auction
+        for id in [bid_iceberg, ask_iceberg] {
+            let order = book.order(id);
+            assert!(order.status == Status::Active);
+            assert!(order.vol == 5);
+            assert!(order.reserve_vol == 90);
+        }
+        assert!(book.bid_ask() == (102, 100));
+        assert!(book.bid_vol() == 5);
+        assert!(book.ask_vol() == 5);
+    }
+
+    #[test]
+    fn test_uncross_imbalance_tie_break() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, false);
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(102))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 5, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 1, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 2, 1, Some(101))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 1, 1, Some(102))
+            .unwrap();
+
+        // 10 lots can execute at 100, 101 or 102,
+        // with the smallest imbalance at 101
+        let trades = book.uncross();
+
+        assert!(trades.iter().all(|x| x.price == 101));
+        assert!(trades.iter().map(|x| x.vol).sum::<Vol>() == 10);
+        assert!(book.bid_ask() == (100, 101));
+    }
+
+    #[test]
+    fn test_iceberg_order() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.create_iceberg_order(Side::Ask, 30, 0, 0, 100).is_err());
+        assert!(book
+            .create_iceberg_order(Side::Ask, 30, 40, 0, 100)
+            .is_err());
+
+        let iceberg = book
+            .create_iceberg_order(Side::Ask, 30, 10, 0, 100)
+            .unwrap();
+        book.place_order(iceberg);
+        book.set_time(1);
+        let other = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+
+        // Only the displayed volume is visible
+        assert!(book.ask_vol() == 20);
+        assert!(book.ask_best_vol_and_orders() == (20, 2));
+        assert!(book.order(iceberg).is_iceberg());
+        assert!(book.order(iceberg).reserve_vol == 20);
+
+        // Filling the displayed volume replenishes the order
+        // behind the other order at the level
+        book.set_time(2);
+        book.create_and_place_order(Side::Bid, 10, 1, None).unwrap();
+
+        assert!(book.order(iceberg).status == Status::Active);
+        assert!(book.order(iceberg).vol == 10);
+        assert!(book.order(iceberg).reserve_vol == 10);
+        assert!(book.ask_vol() == 20);
+        assert!(book.ask_side.best_order_idx() == Some(other));
+
+        // Trades against replenished volume are recorded
+        book.set_time(3);
+        book.create_and_place_order(Side::Bid, 25, 1, Some(100))
+            .unwrap();
+
+        assert!(book.get_trades().len() == 4);
+        assert!(book.get_trades().iter().map(|x| x.vol).sum::<Vol>() == 35);
+        assert!(book.order(other).status == Status::Filled);
+        assert!(book.order(iceberg).status == Status::Active);
+        assert!(book.order(iceberg).vol == 5);
+        assert!(book.order(iceberg).reserve_vol == 0);
+        assert!(book.ask_vol() == 5);
+
+        // Serialisation round-trips reserve volume
+        let iceberg_2 = book.create_iceberg_order(Side::Bid, 50, 20, 2, 90).unwrap();
+        book.place_order(iceberg_2);
+
+        let book_snapshot = serde_json::to_string(&book).unwrap();
+        let loaded_book = serde_json::from_str::<OrderBook>(book_snapshot.as_str()).unwrap();
+
+        assert!(loaded_book.order(iceberg_2).reserve_vol == 30);
+        assert!(loaded_book.order(iceberg_2).display_vol == 20);
+        assert!(loaded_book.bid_vol() == 20);
+
+        // Cancelling cancels the reserve volume
+        book.cancel_order(iceberg_2);
+        assert!(book.cancelled_volume() == 50);
+        assert!(book.bid_vol() == 0);
+    }
+
+    #[test]
+    fn test_aggressive_iceberg_order() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 25, 0, Some(100))
+            .unwrap();
+
+        let iceberg = book
+            .create_iceberg_order(Side::Bid, 40, 10, 1, 100)
+            .unwrap();
+        book.place_order(iceberg);
+
+        assert!(book.get_trades().iter().map(|x| x.vol).sum::<Vol>() == 25);
+        assert!(book.order(iceberg).status == Status::Active);
+        assert!(book.order(iceberg).vol == 5);
+        assert!(book.order(iceberg).reserve_vol == 10);
+        assert!(book.bid_ask() == (100, Price::MAX));
+        assert!(book.bid_vol() == 5);
+    }
+
+    #[test]
+    fn test_estimate_fill_probability() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let ids: Vec<OrderId> = (0..5)
+            .map(|_| {
+                book.create_and_place_order(Side::Ask, 10, 0, Some(50))
+                    .unwrap()
+            })
+            .collect();
+        let behind = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(52))
+            .unwrap();
+
+        book.set_time(10);
+        book.create_and_place_order(Side::Bid, 10, 1, None).unwrap();
+        book.set_time(20);
+        book.create_and_place_order(Side::Bid, 10, 1, None).unwrap();
+
+        let front = ids[2];
+        let deep = ids[4];
+
+        assert!(book.estimate_fill_probability(ids[0], 20) == 1.0);
+        assert!(book.estimate_fill_probability(front, 20) == 1.0);
+        assert!((book.estimate_fill_probability(deep, 20) - 20.0 / 30.0).abs() < 1e-9);
+        assert!(
+            book.estimate_fill_probability(front, 20) > book.estimate_fill_probability(deep, 20)
+        );
+        assert!(
+            book.estimate_fill_probability(behind, 20) < book.estimate_fill_probability(deep, 20)
+        );
+
+        // Only the most recent trade falls within the horizon
+        assert!((book.estimate_fill_probability(deep, 5) - 10.0 / 30.0).abs() < 1e-9);
+
+        book.set_time(100);
+        assert!(book.estimate_fill_probability(front, 20) == 0.0);
+
+        book.cancel_order(deep);
+        assert!(book.estimate_fill_probability(deep, 100) == 0.0);
+    }
+
+    #[test]
+    fn test_trader_summary() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.trader_summary(0) == TraderSummary::default());
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 6, 1, None).unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(98))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 4, 1, None).unwrap();
+
+        let summary = book.trader_summary(0);
+        assert!(summary.position == -2);
+        assert!(summary.cash == 600 - 392);
+        assert!(summary.traded_vol == 10);
+        assert!(summary.pnl == 208.0 - 2.0 * 98.0);
+
+        let summary = book.trader_summary(1);
+        assert!(summary.position == 2);
+        assert!(summary.cash == 392 - 600);
+        assert!(summary.traded_vol == 10);
+        assert!(summary.pnl == -208.0 + 2.0 * 98.0);
+
+        // Consistent with position tracking and realised PnL
+        for trader_id in [0, 1] {
+            let summary = book.trader_summary(trader_id);
+            let (_, position, cash) = book.trader_pnl(trader_id);
+            assert!((summary.position, summary.cash) == book.trader_position(trader_id));
+            assert!((summary.position, summary.cash) == (position, cash));
+        }
+
+        // Summaries of a reloaded book match
+        let book_snapshot = serde_json::to_string(&book).unwrap();
+        let loaded_book = serde_json::from_str::<OrderBook>(book_snapshot.as_str()).unwrap();
+        assert!(loaded_book.trader_summary(0) == book.trader_summary(0));
+        assert!(loaded_book.trader_summary(1) == book.trader_summary(1));
+    }
+
+    #[test]
+    fn test_vwap() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.vwap(None).is_none());
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+        book.set_time(10);
+        book.create_and_place_order(Side::Bid, 10, 1, None).unwrap();
+        book.set_time(20);
+        book.create_and_place_order(Side::Bid, 5, 1, None).unwrap();
+
+        assert!(book.vwap(None) == Some((1000.0 + 510.0) / 15.0));
+        assert!(book.vwap(Some((0, 20))) == Some(100.0));
+        assert!(book.vwap(Some((10, 21))) == Some((1000.0 + 510.0) / 15.0));
+        assert!(book.vwap(Some((20, 30))) == Some(102.0));
+        assert!(book.vwap(Some((21, 30))).is_none());
+    }
+
+    #[test]
+    fn test_book_imbalance() {
+        let mut book: OrderBook<4> = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.book_imbalance(4) == 0.0);
+        assert!(book.weighted_mid_price() == 0.0);
+
+        book.create_and_place_order(Side::Bid, 30, 0, Some(100))
+            .unwrap();
+
+        assert!(book.book_imbalance(4) == 1.0);
+        assert!(book.weighted_mid_price() == 0.0);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(98))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 20, 0, Some(105))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 50, 0, Some(110))
+            .unwrap();
+
+        assert!(book.book_imbalance(0) == 0.0);
+        assert!(book.book_imbalance(1) == 0.5);
+        // Levels are ticks from the touch
+        assert!(book.book_imbalance(3) == 0.6);
+        assert!(book.book_imbalance(4) == 10.0 / 70.0);
+        // Levels are limited to the number tracked
+        assert!(book.book_imbalance(10) == 10.0 / 70.0);
+
+        assert!(book.weighted_mid_price() == (100.0 * 10.0 + 102.0 * 30.0) / 40.0);
+    }
+
+    #[test]
+    fn test_mid_price_ticked() {
+        let mut book: OrderBook = OrderBook::new(0, 2, 1, true);
+
+        assert!(book.mid_price_ticked(RoundMode::Down).is_none());
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+
+        assert!(book.mid_price_ticked(RoundMode::Nearest).is_none());
+
+        // Mid-price on a tick
+        book.create_and_place_order(Side::Ask, 10, 0, Some(104))
+            .unwrap();
+
+        for round in [RoundMode::Down, RoundMode::Up, RoundMode::Nearest] {
+            assert!(book.mid_price_ticked(round) == Some(102));
+        }
+
+        // One tick spread, mid-price between ticks
+        book.create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+
+        assert!(book.mid_price() == 101.0);
+        assert!(book.mid_price_ticked(RoundMode::Down) == Some(100));
+        assert!(book.mid_price_ticked(RoundMode::Up) == Some(102));
+        assert!(book.mid_price_ticked(RoundMode::Nearest) == Some(100));
+
+        // Mid-prices half-way between ticks are rounded
+        // to the nearest even tick
+        let mut book: OrderBook = OrderBook::new(0, 2, 1, true);
+        book.create_and_place_order(Side::Bid, 10, 0, Some(102))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(104))
+            .unwrap();
+
+        assert!(book.mid_price() == 103.0);
+        assert!(book.mid_price_ticked(RoundMode::Down) == Some(102));
+        assert!(book.mid_price_ticked(RoundMode::Up) == Some(104));
+        assert!(book.mid_price_ticked(RoundMode::Nearest) == Some(104));
+    }
+
+    #[test]
+    fn test_trader_pnl() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.trader_pnl(0) == (0, 0, 0));
+
+        // Buy 10 at 100 in two fills
+        book.create_and_place_order(Side::Ask, 4, 1, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 6, 1, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, None).unwrap();
+
+        assert!(book.trader_pnl(0) == (0, 10, -1000));
+
+        // Sell 4 at 105
+        book.create_and_place_order(Side::Bid, 4, 1, Some(105))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 4, 0, None).unwrap();
+
+        assert!(book.trader_pnl(0) == (20, 6, -580));
+
+        // Sell 10 at 97, closing the position and going short
+        book.create_and_place_order(Side::Bid, 10, 1, Some(97))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, None).unwrap();
+
+        assert!(book.trader_pnl(0) == (20 - 18, -4, 390));
+
+        // Buy back 4 at 95, round-trip complete
+        book.create_and_place_order(Side::Ask, 4, 1, Some(95))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 4, 0, None).unwrap();
+
+        let (realised, position, cash) = book.trader_pnl(0);
+        assert!(position == 0);
+        assert!(realised == 2 + 8);
+        assert!(realised == cash);
+
+        let (realised, position, cash) = book.trader_pnl(1);
+        assert!(position == 0);
+        assert!(realised == -10);
+        assert!(realised == cash);
+    }
+
+    #[test]
+    fn test_min_resting_time() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+        book.set_min_resting_time(50);
+
+        let a = book.create_order(Side::Bid, 10, 0, Some(100)).unwrap();
+        let b = book
+            .create_and_place_order(Side::Bid, 10, 0, Some(99))
+            .unwrap();
+        let c = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(105))
+            .unwrap();
+        book.set_order_expiry(c, 20);
+
+        // Unplaced orders can be cancelled
+        book.cancel_order(a);
+        assert!(book.order(a).status == Status::Cancelled);
+
+        // Cancellation in the resting window is ignored
+        book.set_time(20);
+        book.cancel_order(b);
+        assert!(book.order(b).status == Status::Active);
+        assert!(book.bid_vol() == 10);
+
+        // As are modifications
+        book.modify_order(b, Some(98), None);
+        book.modify_order(b, None, Some(5));
+        assert!(book.order(b).price == 99);
+        assert!(book.order(b).vol == 10);
+        assert!(book.bid_ask() == (99, 105));
+
+        // Expiry is not affected
+        book.expire_orders();
+        assert!(book.order(c).status == Status::Cancelled);
+
+        book.set_time(50);
+        book.modify_order(b, None, Some(5));
+        assert!(book.order(b).vol == 5);
+        book.cancel_order(b);
+        assert!(book.order(b).status == Status::Cancelled);
+        assert!(book.bid_vol() == 0);
+    }
+
+    #[test]
+    fn test_protection_window() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+        book.set_protection_window(50);
+
+        let old = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(101))
+            .unwrap();
+
+        book.set_time(100);
+        let fresh = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+
+        book.set_time(110);
+        let agg = book.create_and_place_order(Side::Bid, 5, 1, None).unwrap();
+
+        assert!(book.get_trades().len() == 1);
+        assert!(book.get_trades()[0].passive_order_id == old);
+        assert!(book.get_trades()[0].price == 101);
+        assert!(book.order(agg).status == Status::Filled);
+        assert!(book.order(fresh).vol == 10);
+
+        // Limit orders do not match protected orders
+        // and can rest at a crossing price
+        let limit = book
+            .create_and_place_order(Side::Bid, 5, 1, Some(100))
+            .unwrap();
+        assert!(book.get_trades().len() == 1);
+        assert!(book.order(limit).status == Status::Active);
+        assert!(book.bid_ask() == (100, 100));
+
+        // Once the window has passed the order can be hit
+        book.set_time(150);
+        book.create_and_place_order(Side::Bid, 5, 1, None).unwrap();
+
+        assert!(book.get_trades().len() == 2);
+        assert!(book.get_trades()[1].passive_order_id == fresh);
+        assert!(book.get_trades()[1].price == 100);
+    }
+
+    #[test]
+    fn test_status_histogram() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.status_histogram() == [0, 0, 0, 0, 0, 0]);
+
+        book.create_order(Side::Bid, 10, 0, Some(50)).unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(51))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(52))
+            .unwrap();
+        let cancelled = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(60))
+            .unwrap();
+        book.cancel_order(cancelled);
+        book.create_and_place_order(Side::Ask, 10, 1, None).unwrap();
+
+        book.disable_trading();
+        book.create_and_place_order(Side::Ask, 10, 1, None).unwrap();
+
+        book.enable_trading();
+        let stop = book.create_stop_order(Side::Bid, 10, 1, 60, None).unwrap();
+        book.place_order(stop);
+
+        assert!(book.status_histogram() == [1, 1, 2, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_stop_orders() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(104))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(98))
+            .unwrap();
+
+        let stop_a = book.create_stop_order(Side::Bid, 5, 1, 102, None).unwrap();
+        let stop_b = book
+            .create_stop_order(Side::Bid, 10, 1, 100, Some(102))
+            .unwrap();
+        let stop_c = book.create_stop_order(Side::Ask, 5, 1, 99, None).unwrap();
+        let stop_d = book.create_stop_order(Side::Bid, 5, 1, 110, None).unwrap();
+
+        for id in [stop_a, stop_b, stop_c, stop_d] {
+            book.place_order(id);
+            assert!(book.order(id).status == Status::Pending);
+        }
+
+        // Pending orders are not on the book
+        assert!(book.bid_vol() == 10);
+        assert!(book.bid_ask() == (98, 100));
+
+        // Trade at 100 triggers stop b, which then trades
+        // at 102 triggering stop a
+        book.set_time(1);
+        book.create_and_place_order(Side::Bid, 5, 2, None).unwrap();
+
+        assert!(book.order(stop_b).status == Status::Filled);
+        assert!(book.order(stop_b).arr_time == 1);
+        assert!(book.order(stop_a).status == Status::Filled);
+        assert!(book.order(stop_c).status == Status::Pending);
+        assert!(book.order(stop_d).status == Status::Pending);
+
+        let trades = book.get_trades();
+        assert!(trades.len() == 4);
+        assert!(trades[1].active_order_id == stop_b);
+        assert!(trades[1].price == 100);
+        assert!(trades[2].active_order_id == stop_b);
+        assert!(trades[2].price == 102);
+        assert!(trades[3].active_order_id == stop_a);
+        assert!(trades[3].price == 102);
+
+        // Trade at 98 triggers sell stop
+        book.create_and_place_order(Side::Ask, 5, 2, None).unwrap();
+        assert!(book.order(stop_c).status == Status::Filled);
+        assert!(book.get_trades().last().unwrap().active_order_id == stop_c);
+
+        // Pending orders can be cancelled and are serialised
+        let book_snapshot = serde_json::to_string(&book).unwrap();
+        let loaded_book = serde_json::from_str::<OrderBook>(book_snapshot.as_str()).unwrap();
+        assert!(loaded_book.buy_stops.len() == 1);
+        assert!(loaded_book.order(stop_d).trigger_price == Some(110));
+
+        book.cancel_order(stop_d);
+        assert!(book.order(stop_d).status == Status::Cancelled);
+        assert!(book.buy_stops.is_empty());
+    }
+
+    #[test]
+    fn test_stop_order_trigger_order() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        book.create_and_place_order(Side::Bid, 100, 0, Some(90))
+            .unwrap();
+
+        let stops: Vec<OrderId> = [95, 97, 96]
+            .into_iter()
+            .map(|p| {
+                let id = book.create_stop_order(Side::Ask, 5, 1, p, None).unwrap();
+                book.place_order(id);
+                id
+            })
+            .collect();
+
+        book.create_and_place_order(Side::Ask, 5, 2, None).unwrap();
+
+        // Triggered in descending trigger order
+        let trades = book.get_trades();
+        assert!(trades.len() == 4);
+        assert!(trades[1].active_order_id == stops[1]);
+        assert!(trades[2].active_order_id == stops[2]);
+        assert!(trades[3].active_order_id == stops[0]);
+    }
+
+    #[test]
+    fn test_cumulative_ofi() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.cumulative_ofi() == 0);
+
+        book.create_and_place_order(Side::Ask, 20, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 20, 0, Some(98))
+            .unwrap();
+
+        book.create_and_place_order(Side::Bid, 8, 1, None).unwrap();
+        assert!(book.get_trades()[0].aggressor_side() == Side::Bid);
+        assert!(book.cumulative_ofi() == 8);
+
+        book.create_and_place_order(Side::Ask, 5, 1, None).unwrap();
+        assert!(book.get_trades()[1].aggressor_side() == Side::Ask);
+        assert!(book.cumulative_ofi() == 3);
+
+        book.create_and_place_order(Side::Ask, 10, 1, Some(98))
+            .unwrap();
+        assert!(book.cumulative_ofi() == -7);
+    }
+
+    #[test]
+    fn test_price_scale() {
+        let scale = PriceScale::new(0.05, 2);
+
+        assert!(scale.tick_size() == 5);
+        assert!(scale.decimals() == 2);
+        assert!(scale.to_ticks(1.25).unwrap() == 125);
+        assert!(scale.to_ticks(0.1).unwrap() == 10);
+        assert!(scale.to_ticks(0.0).unwrap() == 0);
+        assert!(scale.to_decimal(125) == 1.25);
+
+        assert!(matches!(
+            scale.to_ticks(1.27),
+            Err(OrderError::DecimalPriceError {
+                price: 1.27,
+                tick_size: 0.05
+            })
+        ));
+        assert!(scale.to_ticks(1.251).is_err());
+        assert!(scale.to_ticks(-0.05).is_err());
+        assert!(scale.to_ticks(f64::NAN).is_err());
+
+        let mut book: OrderBook = OrderBook::with_price_scale(0, scale, 1, true);
+
+        assert!(book.get_tick_size() == 5);
+        assert!(book.get_price_decimals() == 2);
+
+        let price = scale.to_ticks(12.35).unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(price))
+            .unwrap();
+
+        assert!(book.format_price(book.bid_ask().0) == "12.35");
+        assert!(book
+            .create_and_place_order(Side::Bid, 10, 0, Some(1234))
+            .is_err());
+    }
+
+    #[test]
+    fn test_format_price() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        assert!(book.format_price(12345) == "12345");
+
+        book.set_price_decimals(2);
+        assert!(book.format_price(12345) == "123.45");
+        assert!(book.format_price(12305) == "123.05");
+        assert!(book.format_price(5) == "0.05");
+
+        book.set_price_decimals(3);
+        assert!(book.format_price(12345) == "12.345");
+    }
+
+    #[test]
+    fn test_filled_and_cancelled_volume() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let partial = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        let unfilled = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 4, 1, None).unwrap();
+
+        assert!(book.filled_volume() == 4);
+        assert!(book.cancelled_volume() == 0);
+
+        book.cancel_order(partial);
+        assert!(book.filled_volume() == 4);
+        assert!(book.cancelled_volume() == 6);
+
+        book.cancel_order(unfilled);
+        assert!(book.cancelled_volume() == 16);
+
+        // Cancelling an already cancelled order has no effect
+        book.cancel_order(partial);
+        assert!(book.cancelled_volume() == 16);
+
+        let json = serde_json::to_string(&book).unwrap();
+        let loaded: OrderBook = serde_json::from_str(&json).unwrap();
+        assert!(loaded.filled_volume() == 4);
+        assert!(loaded.cancelled_volume() == 16);
+
+        // Unfilled remainder of a market order is cancelled
+        book.create_and_place_order(Side::Ask, 5, 0, Some(101))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 8, 1, None).unwrap();
+        assert!(book.filled_volume() == 9);
+        assert!(book.cancelled_volume() == 19);
+
+        // Hidden volume of iceberg orders is included
+        let iceberg = book.create_iceberg_order(Side::Bid, 20, 5, 1, 90).unwrap();
+        book.place_order(iceberg);
+        book.cancel_order(iceberg);
+        assert!(book.cancelled_volume() == 39);
+
+        let json = serde_json::to_string(&book).unwrap();
+        let loaded: OrderBook = serde_json::from_str(&json).unwrap();
+        assert!(loaded.filled_volume() == book.filled_volume());
+        assert!(loaded.cancelled_volume() == book.cancelled_volume());
+
+        book.reset(0);
+        assert!(book.filled_volume() == 0);
+        assert!(book.cancelled_volume() == 0);
+    }
+
+    #[test]
+    fn test_resting_vwap() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
 
-        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+        assert!(book.resting_vwap(Side::Bid, 3).is_none());
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
             .unwrap();
-        book.create_and_place_order(Side::Bid, 10, 0, Some(50))
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
             .unwrap();
-        book.create_and_place_order(Side::Ask, 10, 0, Some(90))
+        book.create_and_place_order(Side::Bid, 30, 0, Some(98))
             .unwrap();
-        book.create_and_place_order(Side::Bid, 10, 0, Some(60))
+        book.create_and_place_order(Side::Bid, 50, 0, Some(90))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(105))
             .unwrap();
 
-        assert!(book.bid_ask() == (60, 90));
-        assert!(book.ask_vol() == 20);
-        assert!(book.bid_vol() == 20);
-        assert!(book.bid_best_vol() == 10);
-        assert!(book.ask_best_vol() == 10);
-        assert!(book.bid_best_vol_and_orders() == (10, 1));
-        assert!(book.ask_best_vol_and_orders() == (10, 1));
-
-        book.cancel_order(0);
-        book.cancel_order(3);
+        assert!(book.resting_vwap(Side::Bid, 0).is_none());
+        assert!(book.resting_vwap(Side::Bid, 1) == Some(100.0));
+        assert!(book.resting_vwap(Side::Bid, 2) == Some((2000.0 + 2940.0) / 50.0));
+        assert!(book.resting_vwap(Side::Bid, 10) == Some((2000.0 + 2940.0 + 4500.0) / 100.0));
+        assert!(book.resting_vwap(Side::Ask, 3) == Some(105.0));
+    }
 
-        assert!(book.bid_ask() == (50, 90));
-        assert!(book.ask_vol() == 10);
-        assert!(book.bid_vol() == 10);
-        assert!(book.bid_best_vol() == 10);
-        assert!(book.ask_best_vol() == 10);
-        assert!(book.bid_best_vol_and_orders() == (10, 1));
-        assert!(book.ask_best_vol_and_orders() == (10, 1));
+    #[test]
+    fn test_simulate_order() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
 
-        book.cancel_order(1);
-        book.cancel_order(2);
+        book.create_and_place_order(Side::Ask, 10, 0, Some(101))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 20, 0, Some(102))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(98))
+            .unwrap();
 
-        assert!(book.bid_ask() == (0, Price::MAX));
-        assert!(book.ask_vol() == 0);
-        assert!(book.bid_vol() == 0);
-        assert!(book.bid_best_vol() == 0);
-        assert!(book.ask_best_vol() == 0);
-        assert!(book.bid_best_vol_and_orders() == (0, 0));
-        assert!(book.ask_best_vol_and_orders() == (0, 0));
+        // Limit order that partially fills and then rests
+        let result = book.simulate_order(Side::Bid, 50, Some(101));
+        assert!(result.filled_vol == 10);
+        assert!(result.trades.len() == 1);
+        assert!(result.bid_price == 101);
+        assert!(result.ask_price == 102);
+
+        // Limit order that does not cross
+        let result = book.simulate_order(Side::Ask, 5, Some(100));
+        assert!(result.trades.is_empty());
+        assert!(result.bid_price == 98);
+        assert!(result.ask_price == 100);
+
+        let result = book.simulate_order(Side::Bid, 25, None);
+
+        // Book is unchanged
+        assert!(book.bid_ask() == (98, 101));
+        assert!(book.ask_vol() == 40);
+        assert!(book.get_trades().is_empty());
+        assert!(book.get_orders().len() == 4);
+
+        assert!(result.filled_vol == 25);
+        assert!(result.bid_price == 98);
+        assert!(result.ask_price == 102);
+
+        book.create_and_place_order(Side::Bid, 25, 0, None).unwrap();
+
+        assert!(book.bid_ask() == (result.bid_price, result.ask_price));
+        assert!(book.get_trades().len() == result.trades.len());
+
+        for (a, b) in book.get_trades().iter().zip(result.trades.iter()) {
+            assert!(a.t == b.t);
+            assert!(a.side == b.side);
+            assert!(a.price == b.price);
+            assert!(a.vol == b.vol);
+            assert!(a.active_order_id == b.active_order_id);
+            assert!(a.passive_order_id == b.passive_order_id);
+            assert!(a.agg_is_market == b.agg_is_market);
+        }
 
-        assert!(matches!(book.orders[0].order.status, Status::Cancelled));
-        assert!(matches!(book.orders[1].order.status, Status::Cancelled));
-        assert!(matches!(book.orders[2].order.status, Status::Cancelled));
-        assert!(matches!(book.orders[3].order.status, Status::Cancelled));
+        // Orders sweeping a side
+        let result = book.simulate_order(Side::Bid, 100, None);
+        assert!(result.filled_vol == 15);
+        assert!(result.ask_price == Price::MAX);
+        let result = book.simulate_order(Side::Ask, 100, Some(90));
+        assert!(result.filled_vol == 10);
+        assert!(result.bid_price == 0);
+        assert!(result.ask_price == 90);
     }
 
     #[test]
-    fn test_mod_order_vol() {
-        let mut book: OrderBook = OrderBook::new(0, 1, true);
+    fn test_time_since_last_trade() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
 
-        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
-            .unwrap();
-        book.create_and_place_order(Side::Bid, 10, 0, Some(50))
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
             .unwrap();
+        assert!(book.time_since_last_trade().is_none());
 
-        book.modify_order(0, None, Some(8));
-        book.modify_order(1, None, Some(5));
+        book.set_time(50);
+        book.create_and_place_order(Side::Ask, 5, 1, Some(100))
+            .unwrap();
+        assert!(book.time_since_last_trade() == Some(0));
 
-        assert!(book.ask_vol() == 8);
-        assert!(book.ask_best_vol() == 8);
-        assert!(book.ask_best_vol_and_orders() == (8, 1));
-        assert!(book.bid_vol() == 5);
-        assert!(book.bid_best_vol() == 5);
-        assert!(book.bid_best_vol_and_orders() == (5, 1));
+        book.set_time(120);
+        assert!(book.time_since_last_trade() == Some(70));
 
-        assert!(book.orders[0].order.vol == 8);
-        assert!(book.orders[1].order.vol == 5);
+        book.create_and_place_order(Side::Ask, 5, 1, Some(100))
+            .unwrap();
+        assert!(book.time_since_last_trade() == Some(0));
     }
 
     #[test]
-    fn test_modify_order() {
-        let mut book: OrderBook = OrderBook::new(0, 1, true);
+    fn test_book_skew() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
 
-        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+        assert!(book.book_skew(5) == 0.0);
+
+        book.create_and_place_order(Side::Bid, 20, 0, Some(99))
             .unwrap();
-        book.create_and_place_order(Side::Bid, 10, 0, Some(50))
+        book.create_and_place_order(Side::Bid, 10, 0, Some(97))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 20, 0, Some(101))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(103))
             .unwrap();
 
-        assert!(book.bid_ask() == (50, 100));
+        assert!(book.book_skew(5).abs() < 1e-9);
+        assert!(book.book_skew(0) == 0.0);
 
-        book.modify_order(0, Some(110), Some(15));
-        book.modify_order(1, Some(60), Some(20));
+        book.create_and_place_order(Side::Bid, 30, 0, Some(98))
+            .unwrap();
 
-        assert!(book.ask_vol() == 15);
-        assert!(book.ask_best_vol() == 15);
-        assert!(book.bid_vol() == 20);
-        assert!(book.bid_best_vol() == 20);
-        assert!(book.bid_ask() == (60, 110));
+        assert!(book.book_skew(5) > 0.0);
+        // Level outside the depth is ignored
+        assert!(book.book_skew(1).abs() < 1e-9);
+
+        book.create_and_place_order(Side::Ask, 100, 0, Some(102))
+            .unwrap();
+
+        assert!(book.book_skew(5) < 0.0);
     }
 
     #[test]
-    fn test_modify_order_crossing() {
-        let mut book: OrderBook = OrderBook::new(0, 1, true);
+    fn test_avg_order_size() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
 
-        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+        assert!(book.avg_order_size(Side::Bid, 2) == vec![0.0, 0.0]);
+
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
             .unwrap();
-        book.create_and_place_order(Side::Bid, 10, 0, Some(50))
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 30, 0, Some(99))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 30, 0, Some(102))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(104))
             .unwrap();
 
-        assert!(book.bid_ask() == (50, 100));
-
-        book.modify_order(1, Some(100), Some(20));
-
-        assert!(book.ask_vol() == 0);
-        assert!(book.ask_best_vol() == 0);
-        assert!(book.ask_best_vol_and_orders() == (0, 0));
-        assert!(book.bid_vol() == 10);
-        assert!(book.bid_best_vol() == 10);
-        assert!(book.bid_best_vol_and_orders() == (10, 1));
-        assert!(book.bid_ask() == (100, Price::MAX));
-
-        assert!(book.trades.len() == 1);
-        assert!(book.trades[0].price == 100);
-        assert!(book.trades[0].vol == 10);
+        assert!(book.avg_order_size(Side::Bid, 3) == vec![10.0, 30.0, 0.0]);
+        assert!(book.avg_order_size(Side::Ask, 3) == vec![30.0, 0.0, 10.0]);
+        assert!(book.avg_order_size(Side::Ask, 0).is_empty());
     }
 
     #[test]
-    fn test_trades() {
-        let mut book: OrderBook = OrderBook::new(0, 1, true);
+    fn test_best_orders() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
 
-        book.create_order(Side::Ask, 101, 101, Some(20)).unwrap();
-        book.create_order(Side::Ask, 101, 101, Some(18)).unwrap();
-        book.create_order(Side::Bid, 202, 101, Some(12)).unwrap();
-        book.create_order(Side::Bid, 202, 101, Some(14)).unwrap();
+        let (bid, ask) = book.best_orders();
+        assert!(bid.is_none());
+        assert!(ask.is_none());
 
-        book.place_order(0);
+        book.create_and_place_order(Side::Bid, 10, 0, Some(99))
+            .unwrap();
         book.set_time(1);
-        book.place_order(1);
+        let best_bid = book
+            .create_and_place_order(Side::Bid, 20, 0, Some(100))
+            .unwrap();
         book.set_time(2);
-        book.place_order(2);
-        book.set_time(3);
-        book.place_order(3);
-        book.set_time(4);
+        book.create_and_place_order(Side::Bid, 30, 0, Some(100))
+            .unwrap();
+        let best_ask = book
+            .create_and_place_order(Side::Ask, 15, 0, Some(102))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 25, 0, Some(102))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 5, 0, Some(103))
+            .unwrap();
 
-        book.create_order(Side::Bid, 102, 101, None).unwrap();
-        book.place_order(4);
+        let (bid, ask) = book.best_orders();
+        let (bid, ask) = (bid.unwrap(), ask.unwrap());
 
-        assert!(book.ask_vol() == 100);
-        assert!(book.bid_ask() == (14, 20));
+        assert!(bid.order_id == best_bid);
+        assert!(bid.vol == 20);
+        assert!(bid.arr_time == 1);
+        assert!(ask.order_id == best_ask);
+        assert!(ask.vol == 15);
+        assert!(ask.arr_time == 2);
+    }
 
-        assert!(book.trades.len() == 2);
-        assert!(book.trades[0].price == 18);
-        assert!(book.trades[0].vol == 101);
-        assert!(book.trades[1].price == 20);
-        assert!(book.trades[1].vol == 1);
-        assert!(book.get_trade_vol() == 102);
+    #[test]
+    fn test_levels_crossed() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
 
-        book.create_order(Side::Ask, 204, 101, Some(14)).unwrap();
-        book.place_order(5);
+        assert!(book.levels_crossed(Side::Bid, 100) == 0);
 
-        assert!(book.bid_vol() == 202);
-        assert!(book.ask_vol() == 102);
-        assert!(book.bid_best_vol_and_orders() == (202, 1));
-        assert!(book.ask_best_vol_and_orders() == (2, 1));
-        assert!(book.bid_ask() == (12, 14));
+        for p in [101, 105, 110] {
+            book.create_and_place_order(Side::Ask, 10, 0, Some(p))
+                .unwrap();
+        }
+        for p in [98, 90] {
+            book.create_and_place_order(Side::Bid, 10, 0, Some(p))
+                .unwrap();
+        }
 
-        assert!(book.trades.len() == 3);
-        assert!(book.trades[2].price == 14);
-        assert!(book.trades[2].vol == 202);
-        assert!(book.get_trade_vol() == 304);
+        assert!(book.levels_crossed(Side::Bid, 100) == 0);
+        assert!(book.levels_crossed(Side::Bid, 101) == 1);
+        assert!(book.levels_crossed(Side::Bid, 108) == 2);
+        assert!(book.levels_crossed(Side::Bid, Price::MAX) == 3);
+
+        assert!(book.levels_crossed(Side::Ask, 99) == 0);
+        assert!(book.levels_crossed(Side::Ask, 90) == 2);
     }
 
     #[test]
-    fn test_market_order_no_trading() {
-        let mut book: OrderBook = OrderBook::new(0, 1, false);
-
-        book.create_and_place_order(Side::Bid, 101, 101, None)
-            .unwrap();
-
-        assert!(book.bid_ask() == (0, Price::MAX));
-        assert!(book.bid_vol() == 0);
-        assert!(book.ask_vol() == 0);
-        assert!(book.orders[0].order.status == Status::Rejected);
+    fn test_price_band() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let a = book.create_order(Side::Ask, 10, 0, Some(100)).unwrap();
+        let b = book.create_order(Side::Ask, 10, 0, Some(110)).unwrap();
+        book.place_order(a);
+        book.place_order(b);
+
+        book.set_price_band(Some((90, 105)));
+        assert!(book.get_price_band() == Some((90, 105)));
+
+        let c = book.create_order(Side::Bid, 10, 1, Some(80)).unwrap();
+        book.place_order(c);
+        assert!(book.order(c).status == Status::Rejected);
+
+        let d = book.create_order(Side::Bid, 15, 1, None).unwrap();
+        book.place_order(d);
+        assert!(book.order(d).status == Status::Cancelled);
+        assert!(book.order(d).vol == 5);
+        assert!(book.get_trades().len() == 1);
+        assert!(book.bid_ask() == (0, 110));
+
+        book.set_price_band(None);
+        let e = book.create_order(Side::Bid, 5, 1, None).unwrap();
+        book.place_order(e);
+        assert!(book.order(e).status == Status::Filled);
+
+        let state = serde_json::to_string(&book).unwrap();
+        book.set_price_band(Some((100, 120)));
+        let state_band = serde_json::to_string(&book).unwrap();
+        let restored: OrderBook = serde_json::from_str(&state).unwrap();
+        let restored_band: OrderBook = serde_json::from_str(&state_band).unwrap();
+        assert!(restored.get_price_band().is_none());
+        assert!(restored_band.get_price_band() == Some((100, 120)));
+
+        book.reset(0);
+        assert!(book.get_price_band().is_none());
     }
 
     #[test]
-    fn test_unfilled_market_order() {
-        let mut book: OrderBook = OrderBook::new(0, 1, true);
+    fn test_price_band_modify() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
 
-        book.create_and_place_order(Side::Ask, 10, 101, Some(50))
+        let a = book
+            .create_and_place_order(Side::Bid, 10, 0, Some(50))
             .unwrap();
-        book.create_and_place_order(Side::Bid, 20, 101, None)
+        let b = book
+            .create_and_place_order(Side::Ask, 10, 1, Some(55))
             .unwrap();
-
-        assert!(book.bid_ask() == (0, Price::MAX));
-        assert!(book.bid_vol() == 0);
-        assert!(book.ask_vol() == 0);
-        assert!(book.orders[1].order.status == Status::Cancelled);
+        book.set_price_band(Some((40, 60)));
+
+        book.modify_order(a, Some(100), None);
+        assert!(book.order(a).status == Status::Active);
+        assert!(book.order(a).price == 50);
+        assert!(book.get_trades().is_empty());
+        assert!(book.bid_ask() == (50, 55));
+
+        assert!(!book.modify_order_post_only(b, Some(70), None));
+        assert!(book.order(b).price == 55);
+
+        book.modify_order(a, Some(45), Some(20));
+        assert!(book.order(a).price == 45);
+        assert!(book.order(a).vol == 20);
+        assert!(book.modify_order_post_only(b, Some(60), None));
+        assert!(book.bid_ask() == (45, 60));
     }
 
     #[test]
-    fn test_incorrect_price_err() {
-        let mut book: OrderBook = OrderBook::new(0, 2, true);
+    fn test_self_trade_prevention() {
+        let setup = |mode: SelfTradePrevention| {
+            let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+            book.set_self_trade_prevention(mode);
+            book.create_and_place_order(Side::Ask, 10, 1, Some(100))
+                .unwrap();
+            book.create_and_place_order(Side::Ask, 10, 2, Some(101))
+                .unwrap();
+            let id = book
+                .create_and_place_order(Side::Bid, 15, 1, Some(101))
+                .unwrap();
+            (book, id)
+        };
 
-        let res = book.create_order(Side::Ask, 100, 101, Some(51));
+        let (book, id) = setup(SelfTradePrevention::None);
+        assert!(book.get_trades().len() == 2);
+        assert!(book.get_trades()[0].passive_order_id == 0);
+        assert!(book.order(id).status == Status::Filled);
+
+        let (book, id) = setup(SelfTradePrevention::CancelResting);
+        assert!(book.order(0).status == Status::Cancelled);
+        assert!(book.get_trades().len() == 1);
+        assert!(book.get_trades()[0].passive_order_id == 1);
+        assert!(book.order(id).status == Status::Active);
+        assert!(book.order(id).vol == 5);
+        assert!(book.bid_ask() == (101, Price::MAX));
+
+        let (book, id) = setup(SelfTradePrevention::CancelAggressor);
+        assert!(book.get_trades().is_empty());
+        assert!(book.order(id).status == Status::Cancelled);
+        assert!(book.order(0).status == Status::Active);
+        assert!(book.bid_ask() == (0, 100));
+        assert!(book.cancelled_volume() == 15);
+
+        let (mut book, id) = setup(SelfTradePrevention::DecrementBoth);
+        assert!(book.order(0).status == Status::Cancelled);
+        assert!(book.get_trades().len() == 1);
+        assert!(book.get_trades()[0].vol == 5);
+        assert!(book.order(id).status == Status::Filled);
+        assert!(book.ask_vol() == 5);
+        // Decremented volume is not counted as filled
+        assert!(book.order(id).start_vol == 5);
+        assert!(book.cancelled_volume() == 10);
+
+        let id = book.create_and_place_order(Side::Bid, 3, 2, None).unwrap();
+        assert!(book.order(id).status == Status::Cancelled);
+        assert!(book.order(1).status == Status::Active);
+        assert!(book.order(1).vol == 2);
+        assert!(book.order(1).start_vol == 7);
+        assert!(book.ask_vol() == 2);
+        assert!(book.get_trades().len() == 1);
+        assert!(book.cancelled_volume() == 13);
+
+        // Filled volume of each order matches the trades
+        for order in book.get_orders() {
+            let traded: Vol = book
+                .get_trades()
+                .iter()
+                .filter(|x| {
+                    x.active_order_id == order.order_id || x.passive_order_id == order.order_id
+                })
+                .map(|x| x.vol)
+                .sum();
+            let unfilled = match order.status {
+                Status::Cancelled => 0,
+                _ => order.vol,
+            };
+            let cancelled = match order.status {
+                Status::Cancelled => order.vol,
+                _ => 0,
+            };
+            assert!(order.start_vol == traded + unfilled + cancelled);
+        }
 
-        assert!(res.is_err_and(|e| matches!(
-            e,
-            OrderError::PriceError {
-                price: 51,
-                tick_size: 2
-            }
-        )));
+        let json = serde_json::to_string(&book).unwrap();
+        let loaded: OrderBook = serde_json::from_str(&json).unwrap();
+        assert!(loaded.cancelled_volume() == book.cancelled_volume());
     }
 
     #[test]
-    fn test_no_double_place() {
-        let mut book: OrderBook = OrderBook::new(0, 2, true);
+    fn test_fill_or_kill_self_trade_prevention() {
+        let setup = |mode: SelfTradePrevention| {
+            let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+            book.set_self_trade_prevention(mode);
+            book.create_and_place_order(Side::Ask, 10, 1, Some(100))
+                .unwrap();
+            book.create_and_place_order(Side::Ask, 10, 2, Some(101))
+                .unwrap();
+            let id = book
+                .create_order_with_tif(Side::Bid, 15, 1, Some(101), TimeInForce::FillOrKill)
+                .unwrap();
+            book.place_order(id);
+            (book, id)
+        };
 
-        let id = book.create_order(Side::Ask, 100, 101, Some(50)).unwrap();
+        let (book, id) = setup(SelfTradePrevention::None);
+        assert!(book.order(id).status == Status::Filled);
+        assert!(book.get_trades().len() == 2);
+
+        // Own resting volume cannot fill the order
+        for mode in [
+            SelfTradePrevention::CancelResting,
+            SelfTradePrevention::CancelAggressor,
+            SelfTradePrevention::DecrementBoth,
+        ] {
+            let (book, id) = setup(mode);
+            assert!(book.order(id).status == Status::Rejected);
+            assert!(book.order(0).status == Status::Active);
+            assert!(book.order(0).vol == 10);
+            assert!(book.get_trades().is_empty());
+        }
 
+        // Order can be filled by other traders' volume
+        let (mut book, _) = setup(SelfTradePrevention::CancelResting);
+        let id = book
+            .create_order_with_tif(Side::Bid, 10, 1, Some(101), TimeInForce::FillOrKill)
+            .unwrap();
         book.place_order(id);
+        assert!(book.order(id).status == Status::Filled);
+        assert!(book.order(0).status == Status::Cancelled);
+        assert!(book.get_trades().len() == 1);
+    }
 
-        assert!(book.bid_ask() == (0, 50));
-        assert!(book.ask_best_vol_and_orders() == (100, 1));
+    #[test]
+    fn test_ohlcv_bars() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
 
-        book.place_order(id);
+        assert!(book.ohlcv_bars(100).is_empty());
 
-        assert!(book.bid_ask() == (0, 50));
-        assert!(book.ask_best_vol_and_orders() == (100, 1));
+        book.create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        book.create_and_place_order(Side::Ask, 10, 0, Some(102))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(95))
+            .unwrap();
+
+        book.set_time(10);
+        book.create_and_place_order(Side::Bid, 5, 1, None).unwrap();
+        book.set_time(50);
+        book.create_and_place_order(Side::Bid, 8, 1, None).unwrap();
+        book.set_time(99);
+        book.create_and_place_order(Side::Ask, 2, 1, None).unwrap();
+
+        book.set_time(150);
+        book.create_and_place_order(Side::Bid, 4, 1, None).unwrap();
+        book.set_time(180);
+        book.create_and_place_order(Side::Ask, 6, 1, None).unwrap();
+
+        let bars = book.ohlcv_bars(100);
+
+        assert!(bars.len() == 2);
+        assert!(
+            bars[0]
+                == Ohlcv {
+                    t: 0,
+                    open: 100,
+                    high: 102,
+                    low: 95,
+                    close: 95,
+                    vol: 15
+                }
+        );
+        assert!(
+            bars[1]
+                == Ohlcv {
+                    t: 100,
+                    open: 102,
+                    high: 102,
+                    low: 95,
+                    close: 95,
+                    vol: 10
+                }
+        );
     }
 
     #[test]
-    fn test_serialisation() {
-        use rand::{seq::SliceRandom, Rng};
-        use rand_xoshiro::rand_core::SeedableRng;
-        use rand_xoshiro::Xoroshiro128Plus;
-
-        let mut book: OrderBook = OrderBook::new(0, 1, true);
+    fn test_max_trade_move() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+        book.set_max_trade_move(Some(10));
 
-        let mut rng = Xoroshiro128Plus::seed_from_u64(101);
-
-        for i in (0..200).into_iter() {
-            let side = [Side::Bid, Side::Ask].choose(&mut rng).unwrap();
-            let price = rng.gen_range(20..40);
-            let vol = rng.gen_range(5..20);
-            book.create_and_place_order(*side, vol, 0, Some(price))
+        for (i, price) in [100, 104, 110, 111, 130].into_iter().enumerate() {
+            book.create_and_place_order(Side::Ask, 5, 0, Some(price))
+                .unwrap();
+            book.create_and_place_order(Side::Bid, 5, 0, Some(90 - 5 * i as u32))
                 .unwrap();
-            book.set_time(i);
         }
 
-        let book_snapshot = serde_json::to_string(&book).unwrap();
-        let loaded_book = serde_json::from_str::<OrderBook>(book_snapshot.as_str()).unwrap();
+        let a = book
+            .create_and_place_order(Side::Bid, 100, 1, None)
+            .unwrap();
 
-        assert!(book.trading == loaded_book.trading);
-        assert!(book.trade_vol == loaded_book.trade_vol);
+        assert!(book.order(a).status == Status::Cancelled);
+        assert!(book.order(a).vol == 85);
+        assert!(book.get_trades().len() == 3);
+        assert!(book.get_trades()[2].price == 110);
+        assert!(book.bid_ask() == (90, 111));
 
-        assert!(book.bid_ask() == loaded_book.bid_ask());
+        let b = book
+            .create_and_place_order(Side::Ask, 20, 1, Some(75))
+            .unwrap();
 
-        assert!(book.bid_best_vol_and_orders() == loaded_book.bid_best_vol_and_orders());
-        assert!(book.bid_vol() == loaded_book.bid_vol());
+        assert!(book.order(b).status == Status::Cancelled);
+        assert!(book.order(b).vol == 5);
+        assert!(book.get_trades().len() == 6);
+        assert!(book.get_trades()[5].price == 80);
+        assert!(book.bid_ask() == (75, 111));
 
-        assert!(book.ask_best_vol_and_orders() == loaded_book.ask_best_vol_and_orders());
-        assert!(book.ask_vol() == loaded_book.ask_vol());
+        book.set_max_trade_move(None);
+        let c = book.create_and_place_order(Side::Bid, 10, 1, None).unwrap();
+        assert!(book.order(c).status == Status::Filled);
+        assert!(book.get_trades()[7].price == 130);
 
-        assert!(book.current_order_id() == loaded_book.current_order_id());
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+        book.set_max_trade_move(Some(10));
 
-        assert!(book.bid_side.best_order_idx() == loaded_book.bid_side.best_order_idx());
-        assert!(book.ask_side.best_order_idx() == loaded_book.ask_side.best_order_idx());
+        for price in [100, 105, 110, 111] {
+            book.create_and_place_order(Side::Ask, 5, 0, Some(price))
+                .unwrap();
+        }
+
+        // Move is measured from the first trade of the
+        // order, not from each replenishment of the
+        // displayed volume
+        let a = book.create_iceberg_order(Side::Bid, 20, 5, 1, 120).unwrap();
+        book.place_order(a);
+
+        assert!(book.order(a).status == Status::Cancelled);
+        assert!(book.get_trades().len() == 3);
+        assert!(
+            book.get_trades()
+                .iter()
+                .map(|x| x.price)
+                .collect::<Vec<_>>()
+                == vec![100, 105, 110]
+        );
+        assert!(book.bid_ask() == (0, 111));
+        assert!(book.filled_volume() == 15);
+        assert!(book.cancelled_volume() == 5);
+    }
+
+    #[test]
+    fn test_event_listener() {
+        use std::sync::{Arc, Mutex};
+
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        book.set_event_listener(Some(Box::new(move |e: &BookEvent| {
+            recorded.lock().unwrap().push(*e)
+        })));
+
+        let a = book
+            .create_and_place_order(Side::Ask, 10, 0, Some(100))
+            .unwrap();
+        let b = book
+            .create_and_place_order(Side::Bid, 5, 1, Some(90))
+            .unwrap();
+        book.modify_order(b, Some(95), None);
+        let c = book
+            .create_and_place_order(Side::Bid, 4, 1, Some(100))
+            .unwrap();
+        book.cancel_order(a);
+
+        let recorded = events.lock().unwrap().clone();
+        assert!(recorded.len() == 6);
+        assert!(matches!(recorded[0], BookEvent::OrderPlaced { order_id } if order_id == a));
+        assert!(matches!(recorded[1], BookEvent::OrderPlaced { order_id } if order_id == b));
+        assert!(matches!(
+            recorded[2],
+            BookEvent::OrderModified {
+                order_id,
+                new_price: Some(95),
+                new_vol: None
+            } if order_id == b
+        ));
+        assert!(matches!(recorded[3], BookEvent::OrderPlaced { order_id } if order_id == c));
+        assert!(matches!(
+            recorded[4],
+            BookEvent::Trade(Trade {
+                price: 100,
+                vol: 4,
+                active_order_id,
+                passive_order_id,
+                ..
+            }) if active_order_id == c && passive_order_id == a
+        ));
+        assert!(matches!(recorded[5], BookEvent::OrderCancelled { order_id } if order_id == a));
+
+        // Listener is not copied to clones of the book
+        let mut cloned = book.clone();
+        cloned
+            .create_and_place_order(Side::Ask, 10, 0, Some(120))
+            .unwrap();
+        assert!(events.lock().unwrap().len() == 6);
     }
 }
@@ -27,19 +27,29 @@ pub trait SideFunctionality {
     fn vol(&self) -> Vol;
     /// Get the id of the highest priority order
     fn best_order_idx(&self) -> Option<OrderId>;
+    /// Get the id of the highest priority order satisfying a condition
+    fn first_order_idx<F: FnMut(OrderId) -> bool>(&self, f: F) -> Option<OrderId>;
+    /// Iterate over the ids of orders in priority order
+    fn order_idxs(&self) -> impl Iterator<Item = OrderId> + '_;
     /// Get the volume and orders at a price level
     fn vol_and_orders_at_price(&self, price: Price) -> (Vol, OrderCount);
+    /// Get the number of populated price levels
+    fn num_levels(&self) -> usize;
+    /// Get prices and volumes of populated levels from the touch
+    fn price_levels(&self) -> Vec<(Price, Vol)>;
+    /// Get the volume at prices at least as good as a price
+    fn available_vol_within_price(&self, price: Price) -> Vol;
 }
 
 /// Order book side data structure
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct OrderBookSide {
     /// Total volume
     vol: Vol,
     /// Volume at price levels
     volumes: BTreeMap<Price, (Vol, OrderCount)>,
     /// Order map and price-time priority queue
-    orders: BTreeMap<(Price, Nanos), OrderId>,
+    orders: BTreeMap<(Price, Vol, Nanos, u64), OrderId>,
 }
 
 impl OrderBookSide {
@@ -52,7 +62,7 @@ impl OrderBookSide {
     /// - `vol` - Volume of the order
     ///
     fn insert_order(&mut self, key: OrderKey, idx: OrderId, vol: Vol) {
-        self.orders.insert((key.1, key.2), idx);
+        self.orders.insert((key.1, key.2, key.3, key.4), idx);
         match self.volumes.get_mut(&key.1) {
             Some(v) => {
                 v.0 += vol;
@@ -73,7 +83,7 @@ impl OrderBookSide {
     /// - `vol` - Remaining volume of the order to remove
     ///
     fn remove_order(&mut self, key: OrderKey, vol: Vol) {
-        self.orders.remove(&(key.1, key.2));
+        self.orders.remove(&(key.1, key.2, key.3, key.4));
         let vol_at_price = self.volumes.get_mut(&key.1).unwrap();
         vol_at_price.0 -= vol;
         vol_at_price.1 -= 1;
@@ -129,6 +139,21 @@ impl OrderBookSide {
         self.orders.first_key_value().map(|(_, v)| *v)
     }
 
+    /// Get the id of the highest priority order satisfying a condition
+    ///
+    /// # Arguments
+    ///
+    /// - `f` - Condition on the id of the order
+    ///
+    fn first_order_idx<F: FnMut(OrderId) -> bool>(&self, mut f: F) -> Option<OrderId> {
+        self.orders.values().copied().find(|x| f(*x))
+    }
+
+    /// Iterate over the ids of orders in priority order
+    fn order_idxs(&self) -> impl Iterator<Item = OrderId> + '_ {
+        self.orders.values().copied()
+    }
+
     /// Get volume and numbers of orders at a price level
     ///
     /// # Arguments
@@ -141,14 +166,34 @@ impl OrderBookSide {
             None => (0, 0),
         }
     }
+
+    /// Get the number of distinct populated price levels
+    fn num_levels(&self) -> usize {
+        self.volumes.len()
+    }
+
+    /// Get the (internal) prices and volumes of price levels
+    fn price_levels(&self) -> impl Iterator<Item = (Price, Vol)> + '_ {
+        self.volumes.iter().map(|(p, v)| (*p, v.0))
+    }
+
+    /// Get the total volume at (internal) prices up to and including a price
+    ///
+    /// # Arguments
+    ///
+    /// - `price` - Internal price of the furthest level
+    ///
+    fn vol_up_to_price(&self, price: Price) -> Vol {
+        self.volumes.range(..=price).map(|(_, v)| v.0).sum()
+    }
 }
 
 /// Bid-side specific functionality
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct BidSide(OrderBookSide);
 
 /// Ask-side specific functionality
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct AskSide(OrderBookSide);
 
 impl SideFunctionality for BidSide {
@@ -215,10 +260,38 @@ impl SideFunctionality for BidSide {
         self.0.best_order_idx()
     }
 
+    /// Get the id of the best bid order satisfying a condition
+    fn first_order_idx<F: FnMut(OrderId) -> bool>(&self, f: F) -> Option<OrderId> {
+        self.0.first_order_idx(f)
+    }
+
+    /// Iterate over the ids of bid orders in priority order
+    fn order_idxs(&self) -> impl Iterator<Item = OrderId> + '_ {
+        self.0.order_idxs()
+    }
+
     fn vol_and_orders_at_price(&self, price: Price) -> (Vol, OrderCount) {
         let price = Price::MAX - price;
         self.0.vol_and_orders_at_price(price)
     }
+
+    /// Get the number of populated bid price levels
+    fn num_levels(&self) -> usize {
+        self.0.num_levels()
+    }
+
+    /// Get bid prices and volumes in descending price order
+    fn price_levels(&self) -> Vec<(Price, Vol)> {
+        self.0
+            .price_levels()
+            .map(|(p, v)| (Price::MAX - p, v))
+            .collect()
+    }
+
+    /// Get the bid volume at prices greater than or equal to a price
+    fn available_vol_within_price(&self, price: Price) -> Vol {
+        self.0.vol_up_to_price(Price::MAX - price)
+    }
 }
 
 impl SideFunctionality for AskSide {
@@ -285,9 +358,34 @@ impl SideFunctionality for AskSide {
         self.0.best_order_idx()
     }
 
+    /// Get the index of the best ask order satisfying a condition
+    fn first_order_idx<F: FnMut(OrderId) -> bool>(&self, f: F) -> Option<OrderId> {
+        self.0.first_order_idx(f)
+    }
+
+    /// Iterate over the ids of ask orders in priority order
+    fn order_idxs(&self) -> impl Iterator<Item = OrderId> + '_ {
+        self.0.order_idxs()
+    }
+
     fn vol_and_orders_at_price(&self, price: Price) -> (Vol, OrderCount) {
         self.0.vol_and_orders_at_price(price)
     }
+
+    /// Get the number of populated ask price levels
+    fn num_levels(&self) -> usize {
+        self.0.num_levels()
+    }
+
+    /// Get ask prices and volumes in ascending price order
+    fn price_levels(&self) -> Vec<(Price, Vol)> {
+        self.0.price_levels().collect()
+    }
+
+    /// Get the ask volume at prices less than or equal to a price
+    fn available_vol_within_price(&self, price: Price) -> Vol {
+        self.0.vol_up_to_price(price)
+    }
 }
 
 /// Generate a lookup key for a bid-order
@@ -296,9 +394,13 @@ impl SideFunctionality for AskSide {
 ///
 /// - `t` - Order arrival time
 /// - `price` - Price of the order
+/// - `priority` - Size priority of the order within
+///   the price level (0 for pure time priority)
+/// - `seq` - Sequence number of the order, used to
+///   order orders arriving at the same time
 ///
-pub fn get_bid_key(t: Nanos, price: Price) -> OrderKey {
-    (Side::Bid, Price::MAX - price, t)
+pub fn get_bid_key(t: Nanos, price: Price, priority: Vol, seq: u64) -> OrderKey {
+    (Side::Bid, Price::MAX - price, priority, t, seq)
 }
 
 /// Generate a lookup key for a ask-order
@@ -307,9 +409,13 @@ pub fn get_bid_key(t: Nanos, price: Price) -> OrderKey {
 ///
 /// - `t` - Order arrival time
 /// - `price` - Price of the order
+/// - `priority` - Size priority of the order within
+///   the price level (0 for pure time priority)
+/// - `seq` - Sequence number of the order, used to
+///   order orders arriving at the same time
 ///
-pub fn get_ask_key(t: Nanos, price: Price) -> OrderKey {
-    (Side::Ask, price, t)
+pub fn get_ask_key(t: Nanos, price: Price, priority: Vol, seq: u64) -> OrderKey {
+    (Side::Ask, price, priority, t, seq)
 }
 
 #[cfg(test)]
@@ -342,7 +448,7 @@ mod tests {
     fn test_insert_order() {
         let mut side = OrderBookSide::default();
 
-        side.insert_order((Side::Ask, 100, 10), 1, 10);
+        side.insert_order((Side::Ask, 100, 0, 10, 0), 1, 10);
 
         assert!(side.vol() == 10);
         assert!(side.best_vol() == 10);
@@ -351,7 +457,7 @@ mod tests {
         assert!(side.best_order_idx() == Some(1));
 
         // Insert order at the same level
-        side.insert_order((Side::Ask, 100, 11), 2, 11);
+        side.insert_order((Side::Ask, 100, 0, 11, 0), 2, 11);
 
         assert!(side.vol() == 21);
         assert!(side.best_vol() == 21);
@@ -360,7 +466,7 @@ mod tests {
         assert!(side.best_order_idx() == Some(1));
 
         // Insert higher price
-        side.insert_order((Side::Ask, 101, 12), 3, 12);
+        side.insert_order((Side::Ask, 101, 0, 12, 0), 3, 12);
 
         assert!(side.vol() == 33);
         assert!(side.best_vol() == 21);
@@ -369,7 +475,7 @@ mod tests {
         assert!(side.best_order_idx() == Some(1));
 
         // Insert lower price
-        side.insert_order((Side::Ask, 99, 13), 4, 2);
+        side.insert_order((Side::Ask, 99, 0, 13, 0), 4, 2);
 
         assert!(side.vol() == 35);
         assert!(side.best_vol() == 2);
@@ -387,7 +493,7 @@ mod tests {
     fn test_best_bid_price() {
         let mut side = BidSide::new();
 
-        side.insert_order(get_bid_key(0, 100), 1, 10);
+        side.insert_order(get_bid_key(0, 100, 0, 0), 1, 10);
 
         assert!(side.best_price() == 100);
     }
@@ -396,7 +502,7 @@ mod tests {
     fn test_best_ask_price() {
         let mut side = AskSide::new();
 
-        side.insert_order(get_ask_key(0, 100), 1, 10);
+        side.insert_order(get_ask_key(0, 100, 0, 0), 1, 10);
 
         assert!(side.best_price() == 100);
     }
@@ -405,36 +511,36 @@ mod tests {
     fn test_remove_order() {
         let mut side = AskSide::new();
 
-        side.insert_order(get_ask_key(0, 100), 1, 10);
-        side.insert_order(get_ask_key(1, 99), 2, 10);
+        side.insert_order(get_ask_key(0, 100, 0, 0), 1, 10);
+        side.insert_order(get_ask_key(1, 99, 0, 0), 2, 10);
 
         assert!(side.best_price() == 99);
         assert!(side.vol() == 20);
         assert!(side.best_vol_and_orders() == (10, 1));
         assert!(side.best_order_idx() == Some(2));
 
-        side.remove_order(get_ask_key(1, 99), 10);
+        side.remove_order(get_ask_key(1, 99, 0, 0), 10);
 
         assert!(side.best_price() == 100);
         assert!(side.vol() == 10);
         assert!(side.best_vol_and_orders() == (10, 1));
         assert!(side.best_order_idx() == Some(1));
 
-        side.insert_order(get_ask_key(3, 100), 3, 15);
+        side.insert_order(get_ask_key(3, 100, 0, 0), 3, 15);
 
         assert!(side.best_price() == 100);
         assert!(side.vol() == 25);
         assert!(side.best_vol_and_orders() == (25, 2));
         assert!(side.best_order_idx() == Some(1));
 
-        side.remove_order(get_ask_key(3, 100), 15);
+        side.remove_order(get_ask_key(3, 100, 0, 0), 15);
 
         assert!(side.best_price() == 100);
         assert!(side.vol() == 10);
         assert!(side.best_vol_and_orders() == (10, 1));
         assert!(side.best_order_idx() == Some(1));
 
-        side.remove_order(get_ask_key(0, 100), 10);
+        side.remove_order(get_ask_key(0, 100, 0, 0), 10);
 
         assert!(side.best_price() == Price::MAX);
         assert!(side.vol() == 0);
@@ -446,7 +552,7 @@ mod tests {
     fn test_remove_vol() {
         let mut side = AskSide::new();
 
-        side.insert_order(get_ask_key(0, 100), 1, 10);
+        side.insert_order(get_ask_key(0, 100, 0, 0), 1, 10);
 
         side.remove_vol(100, 5);
 
@@ -455,16 +561,61 @@ mod tests {
         assert!(side.vol() == 5);
     }
 
+    #[test]
+    fn test_same_time_orders() {
+        let mut side = AskSide::new();
+
+        for i in 0..20 {
+            side.insert_order(get_ask_key(0, 100, 0, i), usize::try_from(i).unwrap(), 10);
+        }
+
+        assert!(side.vol() == 200);
+        assert!(side.best_vol_and_orders() == (200, 20));
+
+        for i in 0..20 {
+            assert!(side.best_order_idx() == Some(usize::try_from(i).unwrap()));
+            side.remove_order(get_ask_key(0, 100, 0, i), 10);
+        }
+
+        assert!(side.vol() == 0);
+        assert!(side.best_order_idx().is_none());
+    }
+
     #[test]
     fn test_vol_and_orders_at_price() {
         let mut side = AskSide::new();
 
-        side.insert_order(get_ask_key(0, 100), 1, 10);
-        side.insert_order(get_ask_key(1, 100), 2, 20);
-        side.insert_order(get_ask_key(1, 101), 3, 40);
+        side.insert_order(get_ask_key(0, 100, 0, 0), 1, 10);
+        side.insert_order(get_ask_key(1, 100, 0, 0), 2, 20);
+        side.insert_order(get_ask_key(1, 101, 0, 0), 3, 40);
 
         assert!(side.vol_and_orders_at_price(100) == (30, 2));
         assert!(side.vol_and_orders_at_price(101) == (40, 1));
         assert!(side.vol_and_orders_at_price(102) == (0, 0));
     }
+
+    #[test]
+    fn test_available_vol_within_price() {
+        let mut ask_side = AskSide::new();
+
+        ask_side.insert_order(get_ask_key(0, 100, 0, 0), 1, 10);
+        ask_side.insert_order(get_ask_key(1, 101, 0, 0), 2, 20);
+        ask_side.insert_order(get_ask_key(2, 103, 0, 0), 3, 40);
+
+        assert!(ask_side.available_vol_within_price(99) == 0);
+        assert!(ask_side.available_vol_within_price(100) == 10);
+        assert!(ask_side.available_vol_within_price(102) == 30);
+        assert!(ask_side.available_vol_within_price(Price::MAX) == 70);
+
+        let mut bid_side = BidSide::new();
+
+        bid_side.insert_order(get_bid_key(0, 100, 0, 0), 1, 10);
+        bid_side.insert_order(get_bid_key(1, 99, 0, 0), 2, 20);
+        bid_side.insert_order(get_bid_key(2, 97, 0, 0), 3, 40);
+
+        assert!(bid_side.available_vol_within_price(101) == 0);
+        assert!(bid_side.available_vol_within_price(100) == 10);
+        assert!(bid_side.available_vol_within_price(98) == 30);
+        assert!(bid_side.available_vol_within_price(0) == 70);
+    }
 }
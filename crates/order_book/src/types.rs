@@ -1,11 +1,17 @@
 //! Type aliases and order data-structures
 
+use crate::OrderError;
 use serde::{Deserialize, Serialize};
 
 /// Order-id
 pub type OrderId = usize;
 /// Order lookup key
-pub type OrderKey = (Side, u32, u64);
+///
+/// Tuple of side, (internal) price, (internal)
+/// size priority, arrival time and a sequence
+/// number used to break ties between orders
+/// arriving at the same time
+pub type OrderKey = (Side, u32, u32, u64, u64);
 /// Simulated time
 pub type Nanos = u64;
 /// Prices
@@ -22,7 +28,7 @@ pub type AssetIdx = usize;
 pub type MarketOrderId = (AssetIdx, OrderId);
 
 /// Market side
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Side {
     Bid,
     Ask,
@@ -46,6 +52,88 @@ impl From<Side> for bool {
     }
 }
 
+/// Priority of orders within a price level
+///
+/// Determines which order at a price level is
+/// matched first.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum WithinLevelPriority {
+    /// Orders are matched in arrival order
+    #[default]
+    Time,
+    /// Larger orders are matched first, orders
+    /// of equal size are matched in arrival order
+    SizeThenTime,
+}
+
+/// Rounding of prices that fall between ticks
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoundMode {
+    /// Round down to the tick below
+    Down,
+    /// Round up to the tick above
+    Up,
+    /// Round to the nearest tick, with prices exactly
+    /// between two ticks rounded to the even tick
+    Nearest,
+}
+
+/// Relative state of the best bid and ask prices
+///
+/// Locked and crossed markets can only occur
+/// when trading is disabled, as otherwise
+/// orders would be matched.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MarketCondition {
+    /// Best bid is below the best ask
+    Normal,
+    /// Best bid is equal to the best ask
+    Locked,
+    /// Best bid is above the best ask
+    Crossed,
+}
+
+/// Self-trade prevention mode
+///
+/// Determines how orders from the same trader
+/// that would match each other are handled.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum SelfTradePrevention {
+    /// Orders from the same trader are matched
+    #[default]
+    None,
+    /// The resting order is cancelled
+    CancelResting,
+    /// The incoming (aggressive) order is cancelled
+    CancelAggressor,
+    /// The smaller order is cancelled, and the
+    /// volume of the larger order reduced by the
+    /// volume of the smaller order
+    DecrementBoth,
+}
+
+/// Order time-in-force
+///
+/// Determines how long an order remains
+/// on the market.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum TimeInForce {
+    /// Order rests on the book until it is
+    /// filled or cancelled
+    #[default]
+    GoodTillCancel,
+    /// Any volume not immediately matched
+    /// is cancelled
+    ImmediateOrCancel,
+    /// Order is only executed if its full
+    /// volume can be immediately matched
+    FillOrKill,
+    /// Order rests on the book until it is
+    /// filled, cancelled or reaches its
+    /// expiry time
+    GoodTillDate,
+}
+
 /// Order status
 #[derive(Clone, PartialEq, Eq, Copy, Debug, Serialize, Deserialize)]
 pub enum Status {
@@ -60,6 +148,9 @@ pub enum Status {
     /// Rejected, e.g. a market order
     /// placed in a no-trading period
     Rejected,
+    /// Stop order waiting off the book
+    /// for its trigger price
+    Pending,
 }
 
 impl From<Status> for u8 {
@@ -70,6 +161,7 @@ impl From<Status> for u8 {
             Status::Filled => 2,
             Status::Cancelled => 3,
             Status::Rejected => 4,
+            Status::Pending => 5,
         }
     }
 }
@@ -98,14 +190,40 @@ pub struct Order {
     pub trader_id: TraderId,
     /// Id of the order
     pub order_id: OrderId,
+    /// Flag indicating if the order
+    /// is a market order
+    #[serde(default)]
+    pub is_market: bool,
+    /// Time after which the order is
+    /// cancelled, if set
+    #[serde(default)]
+    pub expiry: Option<Nanos>,
+    /// Hidden reserve volume of an
+    /// iceberg order
+    #[serde(default)]
+    pub reserve_vol: Vol,
+    /// Volume displayed by an iceberg
+    /// order, `0` for regular orders
+    #[serde(default)]
+    pub display_vol: Vol,
+    /// Trigger price of a stop order
+    #[serde(default)]
+    pub trigger_price: Option<Price>,
+    /// Time-in-force of the order
+    #[serde(default)]
+    pub time_in_force: TimeInForce,
+    /// Flag indicating if the order is
+    /// rejected if it would cross the book
+    #[serde(default)]
+    pub post_only: bool,
 }
 
 /// Trade record
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Trade {
     /// Trade time
     pub t: Nanos,
-    /// Trade side
+    /// Trade side (i.e. the side of the passive order)
     pub side: Side,
     /// trade price
     pub price: Price,
@@ -115,9 +233,98 @@ pub struct Trade {
     pub active_order_id: OrderId,
     /// Id of the passive order
     pub passive_order_id: OrderId,
+    /// Flag indicating if the aggressive
+    /// order was a market order
+    #[serde(default)]
+    pub agg_is_market: bool,
+}
+
+impl Trade {
+    /// Notional value of the trade (price times volume)
+    pub fn notional(&self) -> u64 {
+        u64::from(self.price) * u64::from(self.vol)
+    }
+
+    /// Side of the aggressive order of the trade
+    ///
+    /// The opposite side to the passive (resting)
+    /// order, i.e. [Side::Bid] for a buyer-initiated
+    /// trade.
+    pub fn aggressor_side(&self) -> Side {
+        match self.side {
+            Side::Bid => Side::Ask,
+            Side::Ask => Side::Bid,
+        }
+    }
+}
+
+/// Result of simulating an order against an order book
+///
+/// See [OrderBook::simulate_order](crate::OrderBook::simulate_order).
+pub struct SimResult {
+    /// Trades that would take place
+    pub trades: Vec<Trade>,
+    /// Total volume that would be filled
+    pub filled_vol: Vol,
+    /// Bid price after the order is placed
+    pub bid_price: Price,
+    /// Ask price after the order is placed
+    pub ask_price: Price,
+}
+
+/// Summary of the trades of a trader on an order book
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TraderSummary {
+    /// Net position (bought minus sold volume)
+    pub position: i64,
+    /// Net cash flow (sale proceeds minus
+    /// purchase costs)
+    pub cash: i64,
+    /// Total traded volume
+    pub traded_vol: u64,
+    /// Profit and loss, with the position marked
+    /// at the last trade price
+    pub pnl: f64,
+}
+
+/// Trades aggregated over a fixed time interval
+///
+/// See [OrderBook::ohlcv_bars](crate::OrderBook::ohlcv_bars).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Ohlcv {
+    /// Start time of the bar
+    pub t: Nanos,
+    /// Price of the first trade
+    pub open: Price,
+    /// Highest trade price
+    pub high: Price,
+    /// Lowest trade price
+    pub low: Price,
+    /// Price of the last trade
+    pub close: Price,
+    /// Total trade volume
+    pub vol: Vol,
 }
 
 impl Order {
+    /// Check if the order is an iceberg order
+    pub fn is_iceberg(&self) -> bool {
+        self.display_vol > 0
+    }
+
+    /// Check if the order was partially filled and then cancelled
+    ///
+    /// An order is partially filled then cancelled if it
+    /// was cancelled with less than its starting volume
+    /// remaining (including any hidden iceberg volume).
+    /// Note that volume reductions from modifying an
+    /// active order are not distinguished from fills,
+    /// see [crate::OrderBook::partially_filled_orders]
+    /// to identify fills from trade records.
+    pub fn is_partially_filled_then_cancelled(&self) -> bool {
+        self.status == Status::Cancelled && self.start_vol > self.vol + self.reserve_vol
+    }
+
     /// Initialise a buy limit-order
     ///
     /// # Arguments
@@ -145,6 +352,13 @@ impl Order {
             price,
             trader_id,
             order_id,
+            is_market: false,
+            expiry: None,
+            reserve_vol: 0,
+            display_vol: 0,
+            trigger_price: None,
+            time_in_force: TimeInForce::GoodTillCancel,
+            post_only: false,
         }
     }
 
@@ -168,6 +382,13 @@ impl Order {
             price: Price::MAX,
             trader_id,
             order_id,
+            is_market: true,
+            expiry: None,
+            reserve_vol: 0,
+            display_vol: 0,
+            trigger_price: None,
+            time_in_force: TimeInForce::GoodTillCancel,
+            post_only: false,
         }
     }
 
@@ -198,6 +419,13 @@ impl Order {
             price,
             trader_id,
             order_id,
+            is_market: false,
+            expiry: None,
+            reserve_vol: 0,
+            display_vol: 0,
+            trigger_price: None,
+            time_in_force: TimeInForce::GoodTillCancel,
+            post_only: false,
         }
     }
 
@@ -221,11 +449,19 @@ impl Order {
             price: 0,
             trader_id,
             order_id,
+            is_market: true,
+            expiry: None,
+            reserve_vol: 0,
+            display_vol: 0,
+            trigger_price: None,
+            time_in_force: TimeInForce::GoodTillCancel,
+            post_only: false,
         }
     }
 }
 
 /// Order transaction instruction
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Event<ID> {
     /// Place an order on the market
     New {
@@ -246,6 +482,43 @@ pub enum Event<ID> {
         /// New volume of the order
         new_vol: Option<Vol>,
     },
+    /// Reduce the volume of an order
+    Reduce {
+        /// Id of the order to reduce
+        order_id: ID,
+        /// Volume to remove from the order
+        amount: Vol,
+    },
+}
+
+/// Order book state change
+///
+/// Events passed to an order book event listener
+/// (see [crate::OrderBook::set_event_listener])
+/// as they occur.
+#[derive(Clone, Copy)]
+pub enum BookEvent {
+    /// An order was placed on the market
+    OrderPlaced {
+        /// Id of the placed order
+        order_id: OrderId,
+    },
+    /// An order was cancelled
+    OrderCancelled {
+        /// Id of the cancelled order
+        order_id: OrderId,
+    },
+    /// An order was modified
+    OrderModified {
+        /// Id of the modified order
+        order_id: OrderId,
+        /// New price of the order
+        new_price: Option<Price>,
+        /// New volume of the order
+        new_vol: Option<Vol>,
+    },
+    /// A trade occurred
+    Trade(Trade),
 }
 
 /// Level 1 market data
@@ -269,6 +542,7 @@ pub struct Level1Data {
 }
 
 /// Level 2 market data
+#[derive(Clone, PartialEq)]
 pub struct Level2Data<const N: usize> {
     /// Bid touch price
     pub bid_price: Price,
@@ -283,3 +557,249 @@ pub struct Level2Data<const N: usize> {
     /// Volume and number of ask orders at price-levels
     pub ask_price_levels: [(Vol, OrderCount); N],
 }
+
+impl<const N: usize> Level2Data<N> {
+    /// Convert to a normalized feature vector
+    ///
+    /// Produces a vector of length `4 + 2 * N` containing
+    ///
+    /// - The bid and ask touch prices as signed tick
+    ///   offsets from the reference price
+    /// - The bid and ask total volumes
+    /// - The bid and ask volume at each price level,
+    ///   interleaved as `[bid_0, ask_0, bid_1, ...]`
+    ///
+    /// with volumes divided by `max_vol`. If a side of
+    /// the book is empty its touch price offset is `NaN`.
+    ///
+    /// # Arguments
+    ///
+    /// - `reference_price` - Price that offsets are
+    ///   measured from
+    /// - `tick_size` - Market tick size
+    /// - `max_vol` - Scale volumes are divided by
+    ///
+    pub fn to_normalized_vec(
+        &self,
+        reference_price: Price,
+        tick_size: Price,
+        max_vol: Vol,
+    ) -> Vec<f64> {
+        assert!(tick_size > 0, "Tick size must be positive");
+        assert!(max_vol > 0, "Max volume must be positive");
+
+        let reference_price = f64::from(reference_price);
+        let tick_size = f64::from(tick_size);
+        let max_vol = f64::from(max_vol);
+
+        let mut features = Vec::with_capacity(4 + 2 * N);
+
+        let offset = |price: Price, vol: Vol| match vol > 0 {
+            true => (f64::from(price) - reference_price) / tick_size,
+            false => f64::NAN,
+        };
+
+        features.push(offset(self.bid_price, self.bid_vol));
+        features.push(offset(self.ask_price, self.ask_vol));
+        features.push(f64::from(self.bid_vol) / max_vol);
+        features.push(f64::from(self.ask_vol) / max_vol);
+
+        for (bid, ask) in self
+            .bid_price_levels
+            .iter()
+            .zip(self.ask_price_levels.iter())
+        {
+            features.push(f64::from(bid.0) / max_vol);
+            features.push(f64::from(ask.0) / max_vol);
+        }
+
+        features
+    }
+}
+
+/// Conversion between decimal and integer prices
+///
+/// Prices on the order book are integers, representing
+/// decimal prices scaled up by `10^decimals`. For example
+/// with 2 decimal places and a tick size of `0.05` a
+/// price of `1.25` is represented as `125`, with an
+/// integer tick-size of `5`.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_book::types::PriceScale;
+///
+/// let scale = PriceScale::new(0.05, 2);
+///
+/// assert!(scale.tick_size() == 5);
+/// assert!(scale.to_ticks(1.25).unwrap() == 125);
+/// assert!(scale.to_decimal(125) == 1.25);
+/// assert!(scale.to_ticks(1.27).is_err());
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PriceScale {
+    tick_size: Price,
+    decimals: u32,
+}
+
+impl PriceScale {
+    /// Initialise a price scale
+    ///
+    /// # Arguments
+    ///
+    /// - `tick_size` - Decimal tick size, must be
+    ///   representable with the given number of
+    ///   decimal places
+    /// - `decimals` - Number of decimal places
+    ///
+    pub fn new(tick_size: f64, decimals: u32) -> Self {
+        assert!(
+            decimals <= 9,
+            "Price decimals must be less than 10, got {}",
+            decimals
+        );
+        let scaled = tick_size * f64::from(10u32.pow(decimals));
+        let ticks = scaled.round();
+        assert!(
+            ticks >= 1.0 && ticks <= f64::from(Price::MAX) && (scaled - ticks).abs() < 1e-6,
+            "Tick size {} cannot be represented with {} decimal places",
+            tick_size,
+            decimals
+        );
+
+        Self {
+            tick_size: ticks as Price,
+            decimals,
+        }
+    }
+
+    /// Get the integer tick size
+    pub fn tick_size(&self) -> Price {
+        self.tick_size
+    }
+
+    /// Get the number of decimal places
+    pub fn decimals(&self) -> u32 {
+        self.decimals
+    }
+
+    /// Convert a decimal price to an integer price
+    ///
+    /// Returns an [OrderError::DecimalPriceError] with
+    /// the offending decimal price if the price is not a
+    /// (non-negative) multiple of the tick size.
+    ///
+    /// # Arguments
+    ///
+    /// - `price` - Decimal price
+    ///
+    pub fn to_ticks(&self, price: f64) -> Result<Price, OrderError> {
+        let scaled = price * f64::from(10u32.pow(self.decimals));
+        let p = scaled.round();
+
+        if !p.is_finite()
+            || !(0.0..=f64::from(Price::MAX)).contains(&p)
+            || (scaled - p).abs() > 1e-6
+            || !(p as Price).is_multiple_of(self.tick_size)
+        {
+            return Err(OrderError::DecimalPriceError {
+                price,
+                tick_size: self.to_decimal(self.tick_size),
+            });
+        }
+
+        Ok(p as Price)
+    }
+
+    /// Convert an integer price to a decimal price
+    ///
+    /// # Arguments
+    ///
+    /// - `price` - Integer price
+    ///
+    pub fn to_decimal(&self, price: Price) -> f64 {
+        f64::from(price) / f64::from(10u32.pow(self.decimals))
+    }
+}
+
+/// Orders resting at a price level, in priority order, as
+/// (order id, volume, trader id, arrival time)
+pub type Level3Orders = Vec<(OrderId, Vol, TraderId, Nanos)>;
+
+/// Level 3 (order-by-order) market data
+///
+/// Populated price levels from the touch, each with
+/// the orders resting at the price in priority order.
+pub struct Level3Data {
+    /// Bid price levels in descending price order
+    pub bid_levels: Vec<(Price, Level3Orders)>,
+    /// Ask price levels in ascending price order
+    pub ask_levels: Vec<(Price, Level3Orders)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partially_filled_then_cancelled() {
+        let mut order = Order::sell_limit(0, 20, 100, 0, 0);
+        assert!(!order.is_partially_filled_then_cancelled());
+
+        // Untouched cancel
+        order.status = Status::Cancelled;
+        assert!(!order.is_partially_filled_then_cancelled());
+
+        // Partial fill, still active
+        order.status = Status::Active;
+        order.vol = 15;
+        assert!(!order.is_partially_filled_then_cancelled());
+
+        order.status = Status::Cancelled;
+        assert!(order.is_partially_filled_then_cancelled());
+
+        // Unfilled iceberg with hidden volume
+        let mut order = Order::buy_limit(0, 20, 100, 0, 1);
+        order.vol = 5;
+        order.display_vol = 5;
+        order.reserve_vol = 15;
+        order.status = Status::Cancelled;
+        assert!(!order.is_partially_filled_then_cancelled());
+
+        // Filled orders are not included
+        order.vol = 0;
+        order.reserve_vol = 0;
+        order.status = Status::Filled;
+        assert!(!order.is_partially_filled_then_cancelled());
+    }
+
+    #[test]
+    fn test_normalized_level_2_data() {
+        let mut data = Level2Data::<2> {
+            bid_price: 96,
+            ask_price: 104,
+            bid_vol: 30,
+            ask_vol: 30,
+            bid_price_levels: [(10, 1), (20, 1)],
+            ask_price_levels: [(30, 1), (0, 0)],
+        };
+
+        let features = data.to_normalized_vec(100, 2, 10);
+
+        assert!(features.len() == 8);
+        assert!(features == vec![-2.0, 2.0, 3.0, 3.0, 1.0, 3.0, 2.0, 0.0]);
+
+        // Touch offset of an empty side is undefined
+        data.ask_price = Price::MAX;
+        data.ask_vol = 0;
+        data.ask_price_levels = [(0, 0), (0, 0)];
+
+        let features = data.to_normalized_vec(100, 2, 10);
+
+        assert!(features.len() == 8);
+        assert!(features[0] == -2.0);
+        assert!(features[1].is_nan());
+        assert!(features[2..] == [3.0, 0.0, 1.0, 0.0, 2.0, 0.0]);
+    }
+}
@@ -7,6 +7,24 @@ use rand_distr::Distribution;
 
 use crate::types::{OrderId, Price, Side, Status, TraderId, Vol};
 use crate::{Env, MarketEnv, OrderError};
+use bourse_book::OrderBook;
+
+/// Reference price that agents place orders around
+///
+/// Determines the price used when the mid-price is
+/// undefined, i.e. when one (or both) sides of the
+/// order book are empty.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PriceReference {
+    /// Always use the mid-price
+    #[default]
+    Mid,
+    /// Fall back to the price of the last trade
+    LastTrade,
+    /// Fall back to the touch price of the
+    /// populated side of the book
+    Touch,
+}
 
 /// Round a price up to the nearest tick and cast to a [Price]
 ///
@@ -40,6 +58,65 @@ pub fn round_price_down(p: f64, tick_size: f64) -> Price {
     p as Price
 }
 
+/// Round a price to the nearest tick, without clamping
+///
+/// Unlike [round_price_up] and [round_price_down], prices
+/// outside of the range representable by [Price] (or
+/// non-finite values) are not clamped, instead `None`
+/// is returned, allowing invalid prices to be detected.
+///
+/// # Arguments
+///
+/// - `p` - Float price
+/// - `tick_size` - Tick size as a float
+///
+pub fn try_round_price(p: f64, tick_size: f64) -> Option<Price> {
+    let p = (p / tick_size).round() * tick_size;
+    match p.is_finite() && (0.0..=Price::MAX.into()).contains(&p) {
+        true => Some(p as Price),
+        false => None,
+    }
+}
+
+/// Get the reference price of an order book
+///
+/// Returns the mid-price if both sides of the book
+/// have orders, otherwise the price given by the
+/// [PriceReference] fallback. If the fallback price
+/// is also undefined (e.g. no trades have taken place)
+/// then the mid-price is returned.
+///
+/// # Arguments
+///
+/// - `order_book` - Order book
+/// - `reference` - Fallback reference price
+///
+pub fn reference_price<const N: usize>(
+    order_book: &OrderBook<N>,
+    reference: PriceReference,
+) -> f64 {
+    let mid_price = order_book.mid_price();
+    let (bid, ask) = order_book.bid_ask();
+    let (bid_empty, ask_empty) = (order_book.bid_vol() == 0, order_book.ask_vol() == 0);
+
+    if !(bid_empty || ask_empty) {
+        return mid_price;
+    }
+
+    match reference {
+        PriceReference::Mid => mid_price,
+        PriceReference::LastTrade => order_book
+            .get_trades()
+            .last()
+            .map_or(mid_price, |x| f64::from(x.price)),
+        PriceReference::Touch => match (bid_empty, ask_empty) {
+            (false, true) => f64::from(bid),
+            (true, false) => f64::from(ask),
+            _ => mid_price,
+        },
+    }
+}
+
 /// Filter active orders and randomly cancel them
 ///
 /// Filter a vec of [OrderId] for those that are active and
@@ -78,7 +155,8 @@ pub fn cancel_live_orders<R: RngCore>(
 ///
 /// <div class="warning">
 ///
-/// Prices are rounded *down* to the nearest tick
+/// Prices are rounded *down* to the nearest tick,
+/// with a minimum price of one tick
 ///
 /// </div>
 ///
@@ -101,9 +179,7 @@ pub fn place_buy_limit_order<R: RngCore, D: Distribution<f64>>(
     trade_vol: Vol,
     trader_id: TraderId,
 ) -> Result<OrderId, OrderError> {
-    let dist = price_dist.sample(rng).abs();
-    let price = mid_price - dist;
-    let price = round_price_down(price, tick_size);
+    let price = sample_buy_price(rng, price_dist, mid_price, tick_size);
     env.place_order(Side::Bid, trade_vol, trader_id, Some(price))
 }
 
@@ -134,9 +210,7 @@ pub fn place_sell_limit_order<R: RngCore, D: Distribution<f64>>(
     trade_vol: Vol,
     trader_id: TraderId,
 ) -> Result<OrderId, OrderError> {
-    let dist = price_dist.sample(rng).abs();
-    let price = mid_price + dist;
-    let price = round_price_up(price, tick_size);
+    let price = sample_sell_price(rng, price_dist, mid_price, tick_size);
     env.place_order(Side::Ask, trade_vol, trader_id, Some(price))
 }
 
@@ -159,13 +233,8 @@ pub fn cancel_live_orders_market<R: RngCore, const M: usize, const N: usize>(
     orders: &[MarketOrderId],
     p_cancel: f32,
 ) -> Vec<MarketOrderId> {
-    let live_orders = orders
-        .iter()
-        .filter(|x| env.order_status(**x) == Status::Active);
-
-    let (live_orders, to_cancel): (Vec<MarketOrderId>, Vec<MarketOrderId>) = live_orders
-        .into_iter()
-        .partition(|_| rng.gen::<f32>() > p_cancel);
+    let statuses: Vec<Status> = orders.iter().map(|x| env.order_status(*x)).collect();
+    let (live_orders, to_cancel) = select_cancellations(rng, orders, &statuses, p_cancel);
 
     for order_id in to_cancel.into_iter() {
         env.cancel_order(order_id);
@@ -174,11 +243,189 @@ pub fn cancel_live_orders_market<R: RngCore, const M: usize, const N: usize>(
     live_orders
 }
 
+/// Filter active orders and randomly select orders for cancellation
+///
+/// Filters orders for those that are active, then
+/// randomly selects orders for cancellation. Returns
+/// the orders that will remain active and the orders
+/// to cancel. Unlike [cancel_live_orders_market] this
+/// does not access the environment, the status of each
+/// order should be read beforehand.
+///
+/// # Arguments
+///
+/// - `rng` - Random generator
+/// - `orders` - Current order ids
+/// - `statuses` - Status of each order
+/// - `p_cancel` - Probability orders are cancelled
+///
+pub fn select_cancellations<R: RngCore, T: Copy>(
+    rng: &mut R,
+    orders: &[T],
+    statuses: &[Status],
+    p_cancel: f32,
+) -> (Vec<T>, Vec<T>) {
+    orders
+        .iter()
+        .zip(statuses.iter())
+        .filter(|(_, status)| **status == Status::Active)
+        .map(|(order_id, _)| *order_id)
+        .partition(|_| rng.gen::<f32>() > p_cancel)
+}
+
+/// Sample a buy order price a random distance below the mid-price
+///
+/// Prices are rounded *down* to the nearest tick,
+/// with a minimum price of one tick
+///
+/// # Arguments
+///
+/// - `rng` -  Random generator
+/// - `price_dist` - Price sampling distribution
+/// - `mid_price` - Current mid-price
+/// - `tick_size` - Tick size (as a float)
+///
+pub fn sample_buy_price<R: RngCore, D: Distribution<f64>>(
+    rng: &mut R,
+    price_dist: D,
+    mid_price: f64,
+    tick_size: f64,
+) -> Price {
+    let dist = price_dist.sample(rng).abs();
+    let price = mid_price - dist;
+    round_price_down(price, tick_size).max(tick_size as Price)
+}
+
+/// Sample a sell order price a random distance above the mid-price
+///
+/// Prices are rounded *up* to the nearest tick
+///
+/// # Arguments
+///
+/// - `rng` -  Random generator
+/// - `price_dist` - Price sampling distribution
+/// - `mid_price` - Current mid-price
+/// - `tick_size` - Tick size (as a float)
+///
+pub fn sample_sell_price<R: RngCore, D: Distribution<f64>>(
+    rng: &mut R,
+    price_dist: D,
+    mid_price: f64,
+    tick_size: f64,
+) -> Price {
+    let dist = price_dist.sample(rng).abs();
+    let price = mid_price + dist;
+    round_price_up(price, tick_size)
+}
+
+/// Instructions decided on by an agent trading one asset
+///
+/// Separates deciding on instructions from submitting them
+/// to a [MarketEnv], so that agents updated in parallel (see
+/// [MarketAgent::par_update](super::MarketAgent::par_update))
+/// only need exclusive access to the environment to read
+/// market data and then submit their instructions.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::MarketEnv;
+/// use bourse_de::agents::common::MarketInstructions;
+/// use bourse_de::types::Side;
+///
+/// let mut env = MarketEnv::<2>::new(0, [1, 1], 1000, true);
+///
+/// let mut instructions = MarketInstructions::new(1);
+/// instructions.place(Side::Bid, 10, 101, Some(50));
+/// instructions.place(Side::Ask, 10, 101, None);
+///
+/// // Returns the ids of the new limit orders
+/// let order_ids = instructions.submit(&mut env).unwrap();
+/// assert!(order_ids == vec![(1, 0)]);
+/// ```
+pub struct MarketInstructions {
+    asset: AssetIdx,
+    cancellations: Vec<MarketOrderId>,
+    orders: Vec<(Side, Vol, TraderId, Option<Price>)>,
+}
+
+impl MarketInstructions {
+    /// Initialise an empty set of instructions
+    ///
+    /// # Arguments
+    ///
+    /// - `asset` - Index of the asset to trade
+    ///
+    pub fn new(asset: AssetIdx) -> Self {
+        Self {
+            asset,
+            cancellations: Vec::new(),
+            orders: Vec::new(),
+        }
+    }
+
+    /// Add cancellations of orders
+    ///
+    /// # Arguments
+    ///
+    /// - `order_ids` - Ids of orders to cancel
+    ///
+    pub fn cancel(&mut self, order_ids: &[MarketOrderId]) {
+        self.cancellations.extend_from_slice(order_ids);
+    }
+
+    /// Add a new order
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Side of the order
+    /// - `vol` - Volume of the order
+    /// - `trader_id` - Id of the trader/agent
+    /// - `price` - Price of the order, if `None` the
+    ///   order is a market order
+    ///
+    pub fn place(&mut self, side: Side, vol: Vol, trader_id: TraderId, price: Option<Price>) {
+        self.orders.push((side, vol, trader_id, price));
+    }
+
+    /// Submit instructions to the environment
+    ///
+    /// Cancellations are submitted before new orders,
+    /// with orders submitted in the order they were added.
+    /// Returns the ids of new limit orders, in the order
+    /// they were added.
+    ///
+    /// # Arguments
+    ///
+    /// - `env` - Multi-asset simulation environment
+    ///
+    pub fn submit<const M: usize, const N: usize>(
+        self,
+        env: &mut MarketEnv<M, N>,
+    ) -> Result<Vec<MarketOrderId>, OrderError> {
+        for order_id in self.cancellations.into_iter() {
+            env.cancel_order(order_id);
+        }
+
+        let mut limit_orders = Vec::new();
+
+        for (side, vol, trader_id, price) in self.orders.into_iter() {
+            let order_id = env.place_order(self.asset, side, vol, trader_id, price)?;
+            if price.is_some() {
+                limit_orders.push(order_id);
+            }
+        }
+
+        Ok(limit_orders)
+    }
+}
+
 /// Place a buy order a random distance below the mid-price
 ///
 /// <div class="warning">
 ///
-/// Prices are rounded *down* to the nearest tick
+/// Prices are rounded *down* to the nearest tick,
+/// with a minimum price of one tick
 ///
 /// </div>
 ///
@@ -209,9 +456,7 @@ pub fn place_buy_limit_order_market<
     asset: AssetIdx,
     trader_id: TraderId,
 ) -> Result<MarketOrderId, OrderError> {
-    let dist = price_dist.sample(rng).abs();
-    let price = mid_price - dist;
-    let price = round_price_down(price, tick_size);
+    let price = sample_buy_price(rng, price_dist, mid_price, tick_size);
     env.place_order(asset, Side::Bid, trade_vol, trader_id, Some(price))
 }
 
@@ -250,9 +495,7 @@ pub fn place_sell_limit_order_market<
     asset: AssetIdx,
     trader_id: TraderId,
 ) -> Result<MarketOrderId, OrderError> {
-    let dist = price_dist.sample(rng).abs();
-    let price = mid_price + dist;
-    let price = round_price_up(price, tick_size);
+    let price = sample_sell_price(rng, price_dist, mid_price, tick_size);
     env.place_order(asset, Side::Ask, trade_vol, trader_id, Some(price))
 }
 
@@ -304,6 +547,18 @@ mod test {
         assert!(p == Price::MAX);
     }
 
+    #[test]
+    fn test_try_rounding() {
+        assert!(try_round_price(5.2, 2.0) == Some(6));
+        assert!(try_round_price(4.9, 2.0) == Some(4));
+        assert!(try_round_price(0.0, 2.0) == Some(0));
+
+        assert!(try_round_price(-2.2, 4.0).is_none());
+        assert!(try_round_price(1.0f64 + 2.0f64.powi(32), 4.0).is_none());
+        assert!(try_round_price(f64::NAN, 1.0).is_none());
+        assert!(try_round_price(f64::INFINITY, 1.0).is_none());
+    }
+
     #[test]
     fn test_cancel_orders() {
         let mut env = Env::new(0, 1, 1_000_000, true);
@@ -353,4 +608,43 @@ mod test {
         assert!(sell_order.price % 5 == 0);
         assert!(sell_order.price >= 200);
     }
+
+    #[test]
+    fn test_minimum_buy_price() {
+        let mut env = Env::new(0, 1, 1_000_000, true);
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+        let price_dist = Uniform::<f64>::new(50.0, 100.0);
+
+        // Sampled prices are below zero
+        let buy_id =
+            place_buy_limit_order(&mut env, &mut rng, price_dist, 10.0, 5.0, 100, 101).unwrap();
+
+        assert!(env.order(buy_id).price == 5);
+    }
+
+    #[test]
+    fn test_reference_price() {
+        let mut book: OrderBook = OrderBook::new(0, 1, 1, true);
+
+        let mid = (f64::from(Price::MAX)) / 2.0;
+
+        assert!(reference_price(&book, PriceReference::Mid) == mid);
+        assert!(reference_price(&book, PriceReference::LastTrade) == mid);
+        assert!(reference_price(&book, PriceReference::Touch) == mid);
+
+        book.create_and_place_order(Side::Ask, 10, 0, Some(110))
+            .unwrap();
+        book.create_and_place_order(Side::Bid, 10, 0, Some(100))
+            .unwrap();
+
+        assert!(reference_price(&book, PriceReference::Mid) == 105.0);
+        assert!(reference_price(&book, PriceReference::LastTrade) == 105.0);
+        assert!(reference_price(&book, PriceReference::Touch) == 105.0);
+
+        book.create_and_place_order(Side::Ask, 10, 0, None).unwrap();
+
+        assert!(reference_price(&book, PriceReference::Mid) == 55.0);
+        assert!(reference_price(&book, PriceReference::LastTrade) == 100.0);
+        assert!(reference_price(&book, PriceReference::Touch) == 110.0);
+    }
 }
@@ -0,0 +1,422 @@
+//! Agent that replays recorded order-flow
+use super::common;
+use super::common::PriceReference;
+use super::Agent;
+use crate::types::{OrderId, Price, Side, Status, TraderId, Vol};
+use crate::Env;
+use rand::RngCore;
+#[cfg(feature = "csv")]
+use std::path::Path;
+
+/// Order-flow instruction type
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FlowAction {
+    /// Place a limit order
+    Limit,
+    /// Place a market order
+    Market,
+    /// Cancel live orders on a side
+    Cancel,
+}
+
+/// Single order-flow instruction
+#[derive(Clone, Copy, Debug)]
+pub struct FlowRecord {
+    /// Simulation step the instruction is submitted
+    pub step: usize,
+    /// Side of the order
+    pub side: Side,
+    /// Volume of the order
+    pub vol: Vol,
+    /// Limit price offset from the reference price in ticks
+    pub price_offset: i32,
+    /// Instruction type
+    pub action: FlowAction,
+}
+
+/// Agent that replays a fixed sequence of order-flow
+///
+/// Replays recorded (or synthesised) instructions at
+/// their given step index. Each step the agent submits
+/// any instructions recorded for that step:
+///
+/// - *Limit* instructions place a limit order at a price
+///   offset (in ticks) from the current reference price
+///   (by default the mid-price). Bid prices are rounded
+///   down, and ask prices up, to the nearest tick.
+/// - *Market* instructions place a market order.
+/// - *Cancel* instructions cancel any live orders the
+///   agent has on the given side.
+///
+/// Instructions that cannot be placed on the market,
+/// e.g. limit orders with a price below one tick, are
+/// skipped.
+///
+/// With the `csv` feature enabled flow can be loaded
+/// from a CSV file with [HistoricalFlowAgent::from_csv].
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::agents::{
+///     Agent, AgentSet, FlowAction, FlowRecord, HistoricalFlowAgent, PriceReference
+/// };
+/// use bourse_de::types::Side;
+/// use bourse_de::{sim_runner, Env};
+///
+/// #[derive(AgentSet)]
+/// struct Agents {
+///     pub a: HistoricalFlowAgent,
+/// }
+///
+/// let mut env = Env::new(0, 1, 1_000_000, true);
+///
+/// let records = vec![
+///     FlowRecord{
+///         step: 1,
+///         side: Side::Bid,
+///         vol: 10,
+///         price_offset: -2,
+///         action: FlowAction::Limit
+///     },
+/// ];
+///
+/// let mut agents = Agents {
+///     a: HistoricalFlowAgent::new(0, 1, records, PriceReference::Mid),
+/// };
+///
+/// sim_runner(&mut env, &mut agents, 101, 10, false);
+/// ```
+pub struct HistoricalFlowAgent {
+    trader_id: TraderId,
+    tick_size: f64,
+    records: Vec<FlowRecord>,
+    next_record: usize,
+    step: usize,
+    orders: Vec<OrderId>,
+    price_reference: PriceReference,
+}
+
+impl HistoricalFlowAgent {
+    /// Initialise a flow agent from a list of instructions
+    ///
+    /// Instructions are sorted by step, preserving
+    /// the order of instructions within a step.
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the agent
+    /// - `tick_size` - Market tick size
+    /// - `records` - Order-flow instructions
+    /// - `price_reference` - Reference price used if
+    ///   the mid-price is undefined
+    ///
+    pub fn new(
+        trader_id: TraderId,
+        tick_size: Price,
+        records: Vec<FlowRecord>,
+        price_reference: PriceReference,
+    ) -> Self {
+        let mut records = records;
+        records.sort_by_key(|x| x.step);
+
+        Self {
+            trader_id,
+            tick_size: tick_size.into(),
+            records,
+            next_record: 0,
+            step: 0,
+            orders: Vec::new(),
+            price_reference,
+        }
+    }
+
+    /// Initialise a flow agent from a CSV file
+    ///
+    /// The CSV should have a header and the columns
+    /// `step, side, vol, price_offset, action` where
+    /// `side` is one of `bid`/`ask` and `action` is
+    /// one of `limit`/`market`/`cancel`.
+    ///
+    /// # Arguments
+    ///
+    /// - `path` - Path of the CSV file
+    /// - `trader_id` - Id of the agent
+    /// - `tick_size` - Market tick size
+    /// - `price_reference` - Reference price used if
+    ///   the mid-price is undefined
+    ///
+    #[cfg(feature = "csv")]
+    pub fn from_csv<P: AsRef<Path>>(
+        path: P,
+        trader_id: TraderId,
+        tick_size: Price,
+        price_reference: PriceReference,
+    ) -> std::io::Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_path(path)?;
+
+        let mut records = Vec::new();
+
+        for row in reader.records() {
+            let row = row?;
+            records.push(parse_record(&row)?);
+        }
+
+        Ok(Self::new(trader_id, tick_size, records, price_reference))
+    }
+
+    fn limit_price(&self, reference: f64, record: &FlowRecord) -> Price {
+        let price = reference + f64::from(record.price_offset) * self.tick_size;
+        match record.side {
+            Side::Bid => common::round_price_down(price, self.tick_size),
+            Side::Ask => common::round_price_up(price, self.tick_size),
+        }
+    }
+}
+
+impl Agent for HistoricalFlowAgent {
+    fn update<R: RngCore>(&mut self, env: &mut Env, _rng: &mut R) {
+        let reference = common::reference_price(env.get_orderbook(), self.price_reference);
+
+        let mut live_orders: Vec<OrderId> = self
+            .orders
+            .iter()
+            .filter(|x| env.order_status(**x) == Status::Active)
+            .cloned()
+            .collect();
+
+        while self.next_record < self.records.len()
+            && self.records[self.next_record].step <= self.step
+        {
+            let record = self.records[self.next_record];
+            self.next_record += 1;
+
+            if record.step < self.step {
+                continue;
+            }
+
+            match record.action {
+                FlowAction::Limit => {
+                    let price = self.limit_price(reference, &record);
+                    if price == 0 {
+                        continue;
+                    }
+                    if let Ok(order_id) =
+                        env.place_order(record.side, record.vol, self.trader_id, Some(price))
+                    {
+                        live_orders.push(order_id);
+                    }
+                }
+                FlowAction::Market => {
+                    let _ = env.place_order(record.side, record.vol, self.trader_id, None);
+                }
+                FlowAction::Cancel => {
+                    let (to_cancel, remaining): (Vec<OrderId>, Vec<OrderId>) = live_orders
+                        .into_iter()
+                        .partition(|x| env.order(*x).side == record.side);
+                    for order_id in to_cancel.into_iter() {
+                        env.cancel_order(order_id);
+                    }
+                    live_orders = remaining;
+                }
+            }
+        }
+
+        self.orders = live_orders;
+        self.step += 1;
+    }
+}
+
+/// Parse a CSV row into a [FlowRecord]
+#[cfg(feature = "csv")]
+fn parse_record(row: &csv::StringRecord) -> std::io::Result<FlowRecord> {
+    let invalid = |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+
+    if row.len() != 5 {
+        return Err(invalid(format!("Expected 5 columns, found {}", row.len())));
+    }
+
+    let step = row[0]
+        .parse::<usize>()
+        .map_err(|e| invalid(format!("Invalid step {}: {}", &row[0], e)))?;
+    let side = match row[1].to_lowercase().as_str() {
+        "bid" => Side::Bid,
+        "ask" => Side::Ask,
+        x => return Err(invalid(format!("Invalid side {}", x))),
+    };
+    let vol = row[2]
+        .parse::<Vol>()
+        .map_err(|e| invalid(format!("Invalid volume {}: {}", &row[2], e)))?;
+    let price_offset = row[3]
+        .parse::<i32>()
+        .map_err(|e| invalid(format!("Invalid price offset {}: {}", &row[3], e)))?;
+    let action = match row[4].to_lowercase().as_str() {
+        "limit" => FlowAction::Limit,
+        "market" => FlowAction::Market,
+        "cancel" => FlowAction::Cancel,
+        x => return Err(invalid(format!("Invalid action {}", x))),
+    };
+
+    Ok(FlowRecord {
+        step,
+        side,
+        vol,
+        price_offset,
+        action,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bourse_book::types::Event;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoroshiro128StarStar;
+
+    fn check_flow(agent: &mut HistoricalFlowAgent) {
+        let mut env = Env::new(0, 1, 1000, true);
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+
+        env.place_order(Side::Bid, 10, 0, Some(98)).unwrap();
+        env.place_order(Side::Ask, 10, 0, Some(102)).unwrap();
+        env.step(&mut rng);
+
+        // Step 0
+        agent.update(&mut env, &mut rng);
+        assert!(env.get_transactions().len() == 1);
+        env.step(&mut rng);
+        assert!(env.order(2).side == Side::Bid);
+        assert!(env.order(2).price == 99);
+        assert!(env.order(2).status == Status::Active);
+
+        // Step 1
+        agent.update(&mut env, &mut rng);
+        assert!(env.get_transactions().is_empty());
+        env.step(&mut rng);
+
+        // Step 2
+        agent.update(&mut env, &mut rng);
+        assert!(env.get_transactions().len() == 2);
+        env.step(&mut rng);
+        assert!(env.order(3).side == Side::Ask);
+        assert!(env.order(3).price == 102);
+        assert!(env.order(4).side == Side::Bid);
+        assert!(env.order(4).price == Price::MAX);
+
+        // Step 3
+        agent.update(&mut env, &mut rng);
+        assert!(env.get_transactions().len() == 1);
+        assert!(matches!(
+            env.get_transactions()[0],
+            Event::Cancellation { order_id: 2 }
+        ));
+        env.step(&mut rng);
+        assert!(env.order_status(2) == Status::Cancelled);
+    }
+
+    #[test]
+    fn test_flow_replay() {
+        let records = vec![
+            FlowRecord {
+                step: 2,
+                side: Side::Ask,
+                vol: 5,
+                price_offset: 1,
+                action: FlowAction::Limit,
+            },
+            FlowRecord {
+                step: 0,
+                side: Side::Bid,
+                vol: 5,
+                price_offset: -1,
+                action: FlowAction::Limit,
+            },
+            FlowRecord {
+                step: 2,
+                side: Side::Bid,
+                vol: 5,
+                price_offset: 0,
+                action: FlowAction::Market,
+            },
+            FlowRecord {
+                step: 3,
+                side: Side::Bid,
+                vol: 0,
+                price_offset: 0,
+                action: FlowAction::Cancel,
+            },
+        ];
+
+        let mut agent = HistoricalFlowAgent::new(1, 1, records, PriceReference::Mid);
+        check_flow(&mut agent);
+    }
+
+    #[test]
+    fn test_flow_one_sided_book() {
+        let mut env = Env::new(0, 1, 1000, true);
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+
+        env.place_order(Side::Bid, 10, 0, Some(98)).unwrap();
+        env.step(&mut rng);
+
+        let records = vec![
+            FlowRecord {
+                step: 0,
+                side: Side::Ask,
+                vol: 5,
+                price_offset: 2,
+                action: FlowAction::Limit,
+            },
+            // Price below one tick is skipped
+            FlowRecord {
+                step: 0,
+                side: Side::Bid,
+                vol: 5,
+                price_offset: -200,
+                action: FlowAction::Limit,
+            },
+            FlowRecord {
+                step: 0,
+                side: Side::Bid,
+                vol: 5,
+                price_offset: -1,
+                action: FlowAction::Limit,
+            },
+        ];
+
+        let mut agent = HistoricalFlowAgent::new(1, 1, records, PriceReference::Touch);
+        agent.update(&mut env, &mut rng);
+
+        // Prices offset from the touch of the populated side
+        assert!(env.get_transactions().len() == 2);
+        assert!(env.order(1).side == Side::Ask);
+        assert!(env.order(1).price == 100);
+        assert!(env.order(2).side == Side::Bid);
+        assert!(env.order(2).price == 97);
+
+        env.step(&mut rng);
+        assert!(agent.orders == vec![1, 2]);
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_flow_from_csv() {
+        let path = std::env::temp_dir().join("bourse_test_flow.csv");
+        std::fs::write(
+            &path,
+            "step,side,vol,price_offset,action\n\
+             0,bid,5,-1,limit\n\
+             2,ask,5,1,limit\n\
+             2,bid,5,0,market\n\
+             3,bid,0,0,cancel\n",
+        )
+        .unwrap();
+
+        let mut agent = HistoricalFlowAgent::from_csv(&path, 1, 1, PriceReference::Mid).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        check_flow(&mut agent);
+    }
+}
@@ -0,0 +1,100 @@
+//! Agent wrapping a closure
+use super::Agent;
+use crate::Env;
+use rand::RngCore;
+
+/// Agent that calls a closure each step
+///
+/// Adapter allowing simple (e.g. one-off) agent
+/// behaviours to be defined with a closure, rather
+/// than implementing [Agent] for a new type. Each
+/// step the closure is called with a reference to
+/// the environment and the random generator.
+///
+/// # Examples
+///
+/// Boxing the closure gives a concrete type that can
+/// be used as a field of a derived [AgentSet](super::AgentSet)
+///
+/// ```
+/// use bourse_de::agents::{Agent, AgentSet, FnAgent};
+/// use bourse_de::types::Side;
+/// use bourse_de::{sim_runner, Env};
+/// use rand::RngCore;
+///
+/// #[derive(AgentSet)]
+/// struct Agents {
+///     pub a: FnAgent<Box<dyn FnMut(&mut Env, &mut dyn RngCore)>>,
+/// }
+///
+/// let mut env = Env::new(0, 1, 1_000, true);
+///
+/// let mut agents = Agents {
+///     a: FnAgent::new(Box::new(|env: &mut Env, _rng: &mut dyn RngCore| {
+///         env.place_order(Side::Bid, 10, 0, Some(50)).unwrap();
+///     })),
+/// };
+///
+/// sim_runner(&mut env, &mut agents, 101, 10, false);
+/// ```
+pub struct FnAgent<F: FnMut(&mut Env, &mut dyn RngCore)> {
+    f: F,
+}
+
+impl<F: FnMut(&mut Env, &mut dyn RngCore)> FnAgent<F> {
+    /// Initialise an agent from a closure
+    ///
+    /// # Arguments
+    ///
+    /// - `f` - Function called each step with the
+    ///   environment and random generator
+    ///
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<F: FnMut(&mut Env, &mut dyn RngCore)> Agent for FnAgent<F> {
+    fn update<R: RngCore>(&mut self, env: &mut Env, rng: &mut R) {
+        (self.f)(env, rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::AgentSet;
+    use crate::sim_runner;
+    use crate::types::{Side, Status};
+
+    struct Agents<F: FnMut(&mut Env, &mut dyn RngCore)> {
+        a: FnAgent<F>,
+    }
+
+    impl<F: FnMut(&mut Env, &mut dyn RngCore)> AgentSet for Agents<F> {
+        fn update<R: RngCore>(&mut self, env: &mut Env, rng: &mut R) {
+            self.a.update(env, rng);
+        }
+    }
+
+    #[test]
+    fn test_fn_agent() {
+        let mut env = Env::new(0, 1, 1_000, true);
+        let mut n_calls = 0;
+
+        let mut agents = Agents {
+            a: FnAgent::new(|env: &mut Env, _rng: &mut dyn RngCore| {
+                env.place_order(Side::Bid, 10, 0, Some(50)).unwrap();
+                n_calls += 1;
+            }),
+        };
+
+        sim_runner(&mut env, &mut agents, 101, 5, false);
+
+        assert!(n_calls == 5);
+        assert!(env.get_orders().len() == 5);
+        assert!(env.get_orders().iter().all(|x| x.status == Status::Active));
+        assert!(env.level_2_data().bid_vol == 50);
+        assert!(env.level_2_data().bid_price == 50);
+    }
+}
@@ -0,0 +1,280 @@
+//! Value-investor agent trading towards a fundamental price
+use super::common;
+use super::common::PriceReference;
+use super::Agent;
+use crate::types::{OrderId, Price, TraderId, Vol};
+use crate::Env;
+use rand::RngCore;
+use rand_distr::LogNormal;
+
+/// Fundamental agent parameters
+pub struct FundamentalAgentParams {
+    /// Tick-size of the market
+    pub tick_size: Price,
+    /// Probability of cancelling a live order
+    pub p_cancel: f32,
+    /// Maximum size of orders placed by the agents
+    pub trade_vol: Vol,
+    /// Scale of the order size relative to the gap
+    /// between the fundamental and market price
+    pub aggressiveness: f64,
+    /// Log-normal price distribution mean
+    pub price_dist_mu: f64,
+    /// Log-normal price distribution width
+    pub price_dist_sigma: f64,
+    /// Reference price used if the mid-price
+    /// is undefined
+    pub price_reference: PriceReference,
+}
+
+/// Agent(s) that trade towards a fundamental value
+///
+/// Represents a group of value-investors that compare
+/// the market price to a fundamental value, buying when
+/// the market is cheap and selling when it is rich. The
+/// fundamental value is given by a function called with
+/// the index of the current update (starting from `0`).
+///
+/// Each step:
+///
+/// - Any currently live orders are randomly selected for cancellation
+/// - The gap `G` between the fundamental value and the reference
+///   price (by default the mid-price) is calculated
+/// - If the market price is below/above the fundamental value each
+///   agent places a buy/sell limit order a distance below/above the
+///   fundamental value sampled from a log-normal distribution
+///
+/// Orders are sized in proportion to the gap, i.e.
+///
+/// ```notrust
+/// vol = trade_vol * min(aggressiveness * |G|, 1)
+/// ```
+/// rounded up, so no orders are placed when the market
+/// price matches the fundamental value.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::agents::{
+///     Agent, AgentSet, FundamentalAgent, FundamentalAgentParams, PriceReference
+/// };
+/// use bourse_de::{sim_runner, Env};
+///
+/// #[derive(AgentSet)]
+/// struct Agents {
+///     pub a: FundamentalAgent<fn(usize) -> f64>,
+/// }
+///
+/// let mut env = Env::new(0, 1, 1_000_000, true);
+///
+/// let params = FundamentalAgentParams {
+///     tick_size: 2,
+///     p_cancel: 0.1,
+///     trade_vol: 100,
+///     aggressiveness: 0.1,
+///     price_dist_mu: 0.0,
+///     price_dist_sigma: 1.0,
+///     price_reference: PriceReference::LastTrade,
+/// };
+/// let mut agents = Agents {
+///     a: FundamentalAgent::new(0, 5, |_| 1_000.0, params),
+/// };
+///
+/// sim_runner(&mut env, &mut agents, 101, 10, false);
+/// ```
+pub struct FundamentalAgent<F: FnMut(usize) -> f64> {
+    fundamental: F,
+    step: usize,
+    tick_size: f64,
+    price_dist: LogNormal<f64>,
+    orders: Vec<OrderId>,
+    trader_ids: Vec<TraderId>,
+    params: FundamentalAgentParams,
+}
+
+impl<F: FnMut(usize) -> f64> FundamentalAgent<F> {
+    /// Initialise a group of fundamental agents
+    ///
+    /// # Arguments
+    ///
+    /// - `agent_id_start` - Starting id for
+    ///   agents in this set
+    /// - `n_agents` - Number of agents
+    /// - `fundamental` - Function returning the
+    ///   fundamental value, called with the index
+    ///   of the update
+    /// - `params` - Agent parameters, see
+    ///   [FundamentalAgentParams]
+    ///
+    pub fn new(
+        agent_id_start: TraderId,
+        n_agents: u16,
+        fundamental: F,
+        params: FundamentalAgentParams,
+    ) -> Self {
+        assert!(
+            params.aggressiveness >= 0.0,
+            "Aggressiveness must be non-negative"
+        );
+
+        let trader_ids = (agent_id_start..agent_id_start + TraderId::from(n_agents)).collect();
+
+        Self {
+            fundamental,
+            step: 0,
+            tick_size: params.tick_size.into(),
+            price_dist: LogNormal::<f64>::new(params.price_dist_mu, params.price_dist_sigma)
+                .unwrap(),
+            orders: Vec::new(),
+            trader_ids,
+            params,
+        }
+    }
+}
+
+impl FundamentalAgent<Box<dyn FnMut(usize) -> f64>> {
+    /// Initialise a group of fundamental agents from a price path
+    ///
+    /// The fundamental value at each update is taken
+    /// from the path, with the last value of the path
+    /// used once it has been exhausted.
+    ///
+    /// # Arguments
+    ///
+    /// - `agent_id_start` - Starting id for
+    ///   agents in this set
+    /// - `n_agents` - Number of agents
+    /// - `path` - Fundamental value at each update
+    /// - `params` - Agent parameters, see
+    ///   [FundamentalAgentParams]
+    ///
+    pub fn from_path(
+        agent_id_start: TraderId,
+        n_agents: u16,
+        path: Vec<f64>,
+        params: FundamentalAgentParams,
+    ) -> Self {
+        assert!(!path.is_empty(), "Fundamental price path cannot be empty");
+
+        Self::new(
+            agent_id_start,
+            n_agents,
+            Box::new(move |i| path[i.min(path.len() - 1)]),
+            params,
+        )
+    }
+}
+
+impl<F: FnMut(usize) -> f64> Agent for FundamentalAgent<F> {
+    fn update<R: RngCore>(&mut self, env: &mut Env, rng: &mut R) {
+        let mut live_orders =
+            common::cancel_live_orders(env, rng, &self.orders, self.params.p_cancel);
+
+        let fundamental = (self.fundamental)(self.step);
+        self.step += 1;
+
+        let market_price =
+            common::reference_price(env.get_orderbook(), self.params.price_reference);
+        let gap = fundamental - market_price;
+
+        let scale = (self.params.aggressiveness * gap.abs()).min(1.0);
+        let vol = (scale * f64::from(self.params.trade_vol)).ceil() as Vol;
+
+        if vol > 0 {
+            for trader_id in self.trader_ids.iter() {
+                let order_id = match gap > 0.0 {
+                    true => common::place_buy_limit_order(
+                        env,
+                        rng,
+                        self.price_dist,
+                        fundamental,
+                        self.tick_size,
+                        vol,
+                        *trader_id,
+                    )
+                    .unwrap(),
+                    false => common::place_sell_limit_order(
+                        env,
+                        rng,
+                        self.price_dist,
+                        fundamental,
+                        self.tick_size,
+                        vol,
+                        *trader_id,
+                    )
+                    .unwrap(),
+                };
+                live_orders.push(order_id);
+            }
+        }
+
+        self.orders = live_orders;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Side;
+    use bourse_book::types::Event;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoroshiro128StarStar;
+
+    fn params(aggressiveness: f64) -> FundamentalAgentParams {
+        FundamentalAgentParams {
+            tick_size: 1,
+            p_cancel: 0.0,
+            trade_vol: 10,
+            aggressiveness,
+            price_dist_mu: 0.0,
+            price_dist_sigma: 0.1,
+            price_reference: PriceReference::Mid,
+        }
+    }
+
+    fn placed_orders(env: &Env) -> Vec<(Side, Price, Vol)> {
+        env.get_transactions()
+            .iter()
+            .map(|x| match x {
+                Event::New { order_id } => {
+                    let order = env.order(*order_id);
+                    (order.side, order.price, order.vol)
+                }
+                _ => panic!("Only new orders should have been placed"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fundamental_orders() {
+        let mut env = Env::new(0, 1, 1000, true);
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+        let mut agent = FundamentalAgent::from_path(1, 2, vec![150.0, 80.0, 100.0], params(0.01));
+
+        env.place_order(Side::Bid, 100, 0, Some(90)).unwrap();
+        env.place_order(Side::Ask, 100, 0, Some(110)).unwrap();
+        env.step(&mut rng);
+
+        // Market cheap relative to fundamental
+        agent.update(&mut env, &mut rng);
+        let orders = placed_orders(&env);
+        assert!(orders.len() == 2);
+        assert!(orders
+            .iter()
+            .all(|(side, price, vol)| *side == Side::Bid && *price <= 150 && *vol == 5));
+        env.step(&mut rng);
+
+        // Market rich relative to fundamental
+        agent.update(&mut env, &mut rng);
+        let orders = placed_orders(&env);
+        assert!(orders.len() == 2);
+        assert!(orders
+            .iter()
+            .all(|(side, price, vol)| *side == Side::Ask && *price >= 80 && *vol == 2));
+        env.step(&mut rng);
+
+        // Market at fundamental value
+        agent.update(&mut env, &mut rng);
+        assert!(env.get_transactions().is_empty());
+    }
+}
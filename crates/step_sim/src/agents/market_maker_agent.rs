@@ -0,0 +1,249 @@
+//! Market-maker agent that skews its quotes by inventory
+use super::common;
+use super::common::PriceReference;
+use super::Agent;
+use crate::types::{OrderId, Price, Side, Status, TraderId, Vol};
+use crate::Env;
+use rand::RngCore;
+
+/// Market-maker agent parameters
+pub struct MarketMakerParams {
+    /// Tick-size of the market
+    pub tick_size: Price,
+    /// Distance of quotes from the reference
+    /// price, in ticks
+    pub base_spread_ticks: Price,
+    /// Size of quotes
+    pub quote_vol: Vol,
+    /// Number of ticks quotes are shifted by
+    /// per unit of inventory
+    pub inventory_skew: f64,
+    /// Absolute inventory at which the agent only
+    /// quotes on the side reducing its position
+    pub max_inventory: i64,
+    /// Reference price used if the mid-price
+    /// is undefined
+    pub price_reference: PriceReference,
+}
+
+/// Inventory-aware market-maker agent
+///
+/// Agent that quotes two-sided limit orders around the
+/// reference price (by default the mid-price), leaning
+/// against its accumulated inventory. Each step the agent
+///
+/// - Updates its inventory from fills of its quotes
+/// - Cancels its previous quotes
+/// - Places a bid and an ask quote
+///
+/// The quotes are placed at
+///
+/// ```notrust
+/// bid = P - tick_size * (base_spread_ticks + inventory_skew * I)
+/// ask = P + tick_size * (base_spread_ticks - inventory_skew * I)
+/// ```
+///
+/// where `P` is the reference price and `I` the inventory
+/// (net position) of the agent, with bid prices rounded down
+/// (to a minimum of one tick) and ask prices rounded up to
+/// the nearest tick. If the
+/// absolute inventory reaches the maximum inventory the agent
+/// only quotes on the side that reduces its position.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::agents::{
+///     Agent, AgentSet, MarketMakerAgent, MarketMakerParams, PriceReference
+/// };
+/// use bourse_de::{sim_runner, Env};
+///
+/// #[derive(AgentSet)]
+/// struct Agents {
+///     pub a: MarketMakerAgent,
+/// }
+///
+/// let mut env = Env::new(0, 1, 1_000_000, true);
+///
+/// let params = MarketMakerParams {
+///     tick_size: 1,
+///     base_spread_ticks: 2,
+///     quote_vol: 10,
+///     inventory_skew: 0.1,
+///     max_inventory: 100,
+///     price_reference: PriceReference::Mid,
+/// };
+/// let mut agents = Agents {
+///     a: MarketMakerAgent::new(0, params),
+/// };
+///
+/// sim_runner(&mut env, &mut agents, 101, 10, false);
+/// ```
+pub struct MarketMakerAgent {
+    trader_id: TraderId,
+    tick_size: f64,
+    inventory: i64,
+    quotes: Vec<OrderId>,
+    filled: Vec<(OrderId, Vol)>,
+    params: MarketMakerParams,
+}
+
+impl MarketMakerAgent {
+    /// Initialise a market-maker agent
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the agent
+    /// - `params` - Agent parameters, see
+    ///   [MarketMakerParams]
+    ///
+    pub fn new(trader_id: TraderId, params: MarketMakerParams) -> Self {
+        assert!(
+            params.base_spread_ticks > 0,
+            "Base spread must be at least one tick"
+        );
+        assert!(params.max_inventory > 0, "Max inventory must be positive");
+
+        Self {
+            trader_id,
+            tick_size: params.tick_size.into(),
+            inventory: 0,
+            quotes: Vec::new(),
+            filled: Vec::new(),
+            params,
+        }
+    }
+
+    /// Get the current inventory (net position) of the agent
+    pub fn inventory(&self) -> i64 {
+        self.inventory
+    }
+
+    /// Update the inventory from fills of previous quotes
+    ///
+    /// Quotes continue to be tracked until they are
+    /// no longer active, as they may receive fills
+    /// before their cancellation is processed.
+    fn update_inventory(&mut self, env: &Env) {
+        let mut filled = Vec::with_capacity(self.filled.len());
+
+        for (order_id, prev_filled) in self.filled.iter() {
+            let order = env.order(*order_id);
+            let order_filled = order.start_vol - order.vol;
+            let fill = i64::from(order_filled - prev_filled);
+
+            self.inventory += match order.side {
+                Side::Bid => fill,
+                Side::Ask => -fill,
+            };
+
+            if matches!(order.status, Status::New | Status::Active) {
+                filled.push((*order_id, order_filled));
+            }
+        }
+
+        self.filled = filled;
+    }
+
+    fn place_quote(&mut self, env: &mut Env, side: Side, price: Price) {
+        let order_id = env
+            .place_order(side, self.params.quote_vol, self.trader_id, Some(price))
+            .unwrap();
+        self.quotes.push(order_id);
+        self.filled.push((order_id, 0));
+    }
+}
+
+impl Agent for MarketMakerAgent {
+    fn update<R: RngCore>(&mut self, env: &mut Env, rng: &mut R) {
+        self.update_inventory(env);
+        self.quotes = common::cancel_live_orders(env, rng, &self.quotes, 1.0);
+
+        let reference = common::reference_price(env.get_orderbook(), self.params.price_reference);
+        let half_spread = f64::from(self.params.base_spread_ticks) * self.tick_size;
+        let skew = self.params.inventory_skew * self.inventory as f64 * self.tick_size;
+
+        if self.inventory < self.params.max_inventory {
+            let bid = common::round_price_down(reference - half_spread - skew, self.tick_size)
+                .max(self.params.tick_size);
+            self.place_quote(env, Side::Bid, bid);
+        }
+
+        if self.inventory > -self.params.max_inventory {
+            let ask = common::round_price_up(reference + half_spread - skew, self.tick_size);
+            self.place_quote(env, Side::Ask, ask);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bourse_book::types::Event;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoroshiro128StarStar;
+
+    fn placed_quotes(env: &Env) -> Vec<(Side, Price)> {
+        env.get_transactions()
+            .iter()
+            .filter_map(|x| match x {
+                Event::New { order_id } => {
+                    let order = env.order(*order_id);
+                    Some((order.side, order.price))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_market_maker_quotes() {
+        let mut env = Env::new(0, 1, 1000, true);
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+        let mut agent = MarketMakerAgent::new(
+            1,
+            MarketMakerParams {
+                tick_size: 1,
+                base_spread_ticks: 5,
+                quote_vol: 10,
+                inventory_skew: 0.5,
+                max_inventory: 20,
+                price_reference: PriceReference::Mid,
+            },
+        );
+
+        env.place_order(Side::Bid, 100, 0, Some(80)).unwrap();
+        env.place_order(Side::Ask, 100, 0, Some(120)).unwrap();
+        env.step(&mut rng);
+
+        // Symmetric quotes with no inventory
+        agent.update(&mut env, &mut rng);
+        assert!(placed_quotes(&env) == vec![(Side::Bid, 95), (Side::Ask, 105)]);
+        env.step(&mut rng);
+
+        // Bid quote is filled
+        env.place_order(Side::Ask, 10, 0, None).unwrap();
+        env.step(&mut rng);
+
+        agent.update(&mut env, &mut rng);
+        assert!(agent.inventory() == 10);
+        // Quotes skewed down to lean against the long position
+        assert!(placed_quotes(&env) == vec![(Side::Bid, 82), (Side::Ask, 93)]);
+        assert!(env
+            .get_transactions()
+            .contains(&Event::Cancellation { order_id: 3 }));
+        env.step(&mut rng);
+
+        // Bid quote is filled again
+        env.place_order(Side::Ask, 10, 0, None).unwrap();
+        env.step(&mut rng);
+
+        // Only the reducing side is quoted
+        agent.update(&mut env, &mut rng);
+        assert!(agent.inventory() == 20);
+        assert!(placed_quotes(&env) == vec![(Side::Ask, 82)]);
+        assert!(env
+            .get_transactions()
+            .contains(&Event::Cancellation { order_id: 6 }));
+    }
+}
@@ -3,18 +3,31 @@
 //! The simulation agents must implement an `update` function
 //! that is called each step of the simulation.
 //!
-use crate::{Env, MarketEnv};
+use crate::{Env, MarketEnv, SharedMarketEnv};
 
-use rand::RngCore;
+use rand::{RngCore, SeedableRng};
 pub mod common;
+mod flow_agent;
+mod fn_agent;
+mod fundamental_agent;
+mod market_maker_agent;
 mod momentum_agent;
 mod noise_agent;
+mod pov_agent;
 mod random_agent;
+mod zi_agent;
 
 pub use bourse_macros::{AgentSet, MarketAgentSet};
+pub use common::PriceReference;
+pub use flow_agent::{FlowAction, FlowRecord, HistoricalFlowAgent};
+pub use fn_agent::FnAgent;
+pub use fundamental_agent::{FundamentalAgent, FundamentalAgentParams};
+pub use market_maker_agent::{MarketMakerAgent, MarketMakerParams};
 pub use momentum_agent::{MomentumAgent, MomentumMarketAgent, MomentumParams};
 pub use noise_agent::{NoiseAgent, NoiseAgentParams, NoiseMarketAgent};
+pub use pov_agent::PovAgent;
 pub use random_agent::{RandomAgents, RandomMarketAgents};
+pub use zi_agent::{ZeroIntelligenceAgent, ZeroIntelligenceParams};
 
 /// Homogeneous agent set functionality
 ///
@@ -172,6 +185,32 @@ pub trait MarketAgent {
         env: &mut MarketEnv<M, N>,
         rng: &mut R,
     );
+
+    /// Update the state of the agent(s) concurrently with other agents
+    ///
+    /// Called when agents are updated in parallel (see
+    /// [MarketAgentSet::par_update]). By default the
+    /// agent holds exclusive access to the environment
+    /// for the whole of its update, so agents are
+    /// effectively updated one at a time. Agents can
+    /// override this to only access the environment
+    /// to read data and submit instructions, so that
+    /// their other work runs concurrently (see
+    /// [common::MarketInstructions]). The built-in
+    /// market agents are updated this way.
+    ///
+    /// # Argument
+    ///
+    /// - `env` - Shared handle to a [MarketEnv] simulation environment
+    /// - `rng` - Random generator
+    ///
+    fn par_update<R: RngCore, const M: usize, const N: usize>(
+        &mut self,
+        env: &SharedMarketEnv<M, N>,
+        rng: &mut R,
+    ) {
+        env.with(|env| self.update(env, rng));
+    }
 }
 
 /// Functionality required for simulation agents
@@ -256,4 +295,32 @@ pub trait MarketAgentSet {
         env: &mut MarketEnv<M, N>,
         rng: &mut R,
     );
+
+    /// Update function with agents updated in parallel
+    ///
+    /// By default this is the same as [MarketAgentSet::update].
+    /// Sets derived with `#[market_agent_set(parallel)]`
+    /// update each field concurrently (see
+    /// [MarketAgent::par_update]), with each field
+    /// passed its own random generator seeded from `rng`.
+    ///
+    /// Instructions are submitted in a non-deterministic
+    /// order, so this should only be used where agents
+    /// act independently, i.e. agents trade disjoint
+    /// assets, and do not depend on the order ids
+    /// assigned to other agents' orders. Agents of a set
+    /// must implement [Send].
+    ///
+    /// # Arguments
+    ///
+    /// - `env` - Simulation environment
+    /// - `rng` - Random generator
+    ///
+    fn par_update<R: RngCore + SeedableRng + Send, const M: usize, const N: usize>(
+        &mut self,
+        env: &mut MarketEnv<M, N>,
+        rng: &mut R,
+    ) {
+        self.update(env, rng);
+    }
 }
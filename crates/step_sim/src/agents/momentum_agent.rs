@@ -1,9 +1,10 @@
 use super::common;
+use super::common::MarketInstructions;
 use super::Agent;
 use super::MarketAgent;
-use crate::types::{OrderId, Price, Side, TraderId, Vol};
+use crate::types::{OrderId, Price, Side, Status, TraderId, Vol};
 use crate::Env;
-use crate::MarketEnv;
+use crate::{MarketEnv, SharedMarketEnv};
 use bourse_book::types::AssetIdx;
 use bourse_book::types::MarketOrderId;
 use rand::{Rng, RngCore};
@@ -324,16 +325,26 @@ impl MomentumMarketAgent {
     }
 }
 
-impl MarketAgent for MomentumMarketAgent {
-    fn update<R: RngCore, const M: usize, const N: usize>(
+impl MomentumMarketAgent {
+    /// Read the status of live orders and the mid-price
+    fn observe<const M: usize, const N: usize>(&self, env: &MarketEnv<M, N>) -> (Vec<Status>, f64) {
+        let statuses = self.orders.iter().map(|x| env.order_status(*x)).collect();
+        let mid_price = env.get_market().get_order_book(self.asset).mid_price();
+        (statuses, mid_price)
+    }
+
+    /// Update the momentum and decide on cancellations and new orders
+    fn decide<R: RngCore>(
         &mut self,
-        env: &mut MarketEnv<M, N>,
         rng: &mut R,
-    ) {
-        let mut live_orders =
-            common::cancel_live_orders_market(env, rng, &self.orders, self.params.p_cancel);
+        statuses: &[Status],
+        mid_price: f64,
+    ) -> MarketInstructions {
+        let (live_orders, to_cancel) =
+            common::select_cancellations(rng, &self.orders, statuses, self.params.p_cancel);
 
-        let mid_price = env.get_market().get_order_book(self.asset).mid_price();
+        let mut instructions = MarketInstructions::new(self.asset);
+        instructions.cancel(&to_cancel);
 
         let (m, p_market) = match self.last_price {
             Some(p) => {
@@ -350,53 +361,21 @@ impl MarketAgent for MomentumMarketAgent {
         for trader_id in self.trader_ids.iter() {
             if rng.gen::<f64>() < p_limit {
                 if m > 0.0 {
-                    let order_id = common::place_buy_limit_order_market(
-                        env,
-                        rng,
-                        self.price_dist,
-                        mid_price,
-                        self.tick_size,
-                        self.params.trade_vol,
-                        self.asset,
-                        *trader_id,
-                    )
-                    .unwrap();
-                    live_orders.push(order_id);
+                    let price =
+                        common::sample_buy_price(rng, self.price_dist, mid_price, self.tick_size);
+                    instructions.place(Side::Bid, self.params.trade_vol, *trader_id, Some(price));
                 } else if m < 0.0 {
-                    let order_id = common::place_sell_limit_order_market(
-                        env,
-                        rng,
-                        self.price_dist,
-                        mid_price,
-                        self.tick_size,
-                        self.params.trade_vol,
-                        self.asset,
-                        *trader_id,
-                    )
-                    .unwrap();
-                    live_orders.push(order_id);
+                    let price =
+                        common::sample_sell_price(rng, self.price_dist, mid_price, self.tick_size);
+                    instructions.place(Side::Ask, self.params.trade_vol, *trader_id, Some(price));
                 }
             }
 
             if rng.gen::<f64>() < p_market {
                 if m > 0.0 {
-                    env.place_order(
-                        self.asset,
-                        Side::Bid,
-                        self.params.trade_vol,
-                        *trader_id,
-                        None,
-                    )
-                    .unwrap();
+                    instructions.place(Side::Bid, self.params.trade_vol, *trader_id, None);
                 } else if m < 0.0 {
-                    env.place_order(
-                        self.asset,
-                        Side::Ask,
-                        self.params.trade_vol,
-                        *trader_id,
-                        None,
-                    )
-                    .unwrap();
+                    instructions.place(Side::Ask, self.params.trade_vol, *trader_id, None);
                 }
             }
         }
@@ -405,6 +384,31 @@ impl MarketAgent for MomentumMarketAgent {
         self.last_price = Some(mid_price);
 
         self.orders = live_orders;
+        instructions
+    }
+}
+
+impl MarketAgent for MomentumMarketAgent {
+    fn update<R: RngCore, const M: usize, const N: usize>(
+        &mut self,
+        env: &mut MarketEnv<M, N>,
+        rng: &mut R,
+    ) {
+        let (statuses, mid_price) = self.observe(env);
+        let instructions = self.decide(rng, &statuses, mid_price);
+        let order_ids = instructions.submit(env).unwrap();
+        self.orders.extend(order_ids);
+    }
+
+    fn par_update<R: RngCore, const M: usize, const N: usize>(
+        &mut self,
+        env: &SharedMarketEnv<M, N>,
+        rng: &mut R,
+    ) {
+        let (statuses, mid_price) = env.with(|env| self.observe(env));
+        let instructions = self.decide(rng, &statuses, mid_price);
+        let order_ids = env.with(|env| instructions.submit(env)).unwrap();
+        self.orders.extend(order_ids);
     }
 }
 
@@ -1,10 +1,11 @@
 //! Agent that randomly places and cancels limit and market orders
 use super::common;
+use super::common::{MarketInstructions, PriceReference};
 use super::Agent;
 use super::MarketAgent;
-use crate::types::{AssetIdx, OrderId, Price, Side, TraderId, Vol};
+use crate::types::{AssetIdx, OrderId, Price, Side, Status, TraderId, Vol};
 use crate::Env;
-use crate::MarketEnv;
+use crate::{MarketEnv, SharedMarketEnv};
 use bourse_book::types::MarketOrderId;
 use rand::Rng;
 use rand::RngCore;
@@ -26,6 +27,9 @@ pub struct NoiseAgentParams {
     pub price_dist_mu: f64,
     /// Log-normal price distribution width
     pub price_dist_sigma: f64,
+    /// Reference price used if the mid-price
+    /// is undefined
+    pub price_reference: PriceReference,
 }
 
 /// Agent(s) that randomly place and cancel limit and market orders
@@ -36,7 +40,8 @@ pub struct NoiseAgentParams {
 /// - Any currently live orders are randomly selected for cancellation
 /// - Each agent randomly chooses to place a limit order, if so they
 ///   place an order on a random side with a price above/below the
-///   mid-price by a distance sampled from a log-normal distribution
+///   reference price (by default the mid-price) by a distance
+///   sampled from a log-normal distribution
 /// - Each agent randomly chooses to place a market order, if so they
 ///   place an order on a random side
 ///
@@ -45,7 +50,7 @@ pub struct NoiseAgentParams {
 /// # Examples
 ///
 /// ```
-/// use bourse_de::agents::{Agent, AgentSet, NoiseAgent, NoiseAgentParams};
+/// use bourse_de::agents::{Agent, AgentSet, NoiseAgent, NoiseAgentParams, PriceReference};
 /// use bourse_de::{sim_runner, Env};
 ///
 /// #[derive(AgentSet)]
@@ -63,6 +68,7 @@ pub struct NoiseAgentParams {
 ///     trade_vol: 100,
 ///     price_dist_mu: 0.0,
 ///     price_dist_sigma: 1.0,
+///     price_reference: PriceReference::Mid,
 /// };
 /// let mut agents = Agents {
 ///     a: NoiseAgent::new(0, 5, params),
@@ -107,6 +113,9 @@ impl NoiseAgent {
     /// - `price_dist_sigma` - Width parameter of the
     ///   log-normal distribution that limit-order
     ///   prices are sampled from
+    /// - `price_reference` - Reference price limit
+    ///   orders are placed around if the mid-price is
+    ///   undefined (i.e. one side of the book is empty)
     ///
     pub fn new(agent_id_start: TraderId, n_agents: u16, params: NoiseAgentParams) -> Self {
         let trader_ids = (agent_id_start..agent_id_start + TraderId::from(n_agents)).collect();
@@ -128,7 +137,7 @@ impl Agent for NoiseAgent {
         let mut live_orders =
             common::cancel_live_orders(env, rng, &self.orders, self.params.p_cancel);
 
-        let mid_price = env.get_orderbook().mid_price();
+        let mid_price = common::reference_price(env.get_orderbook(), self.params.price_reference);
 
         for trader_id in self.trader_ids.iter() {
             if rng.gen::<f32>() < self.params.p_limit {
@@ -184,7 +193,8 @@ impl Agent for NoiseAgent {
 /// - Any currently live orders are randomly selected for cancellation
 /// - Each agent randomly chooses to place a limit order, if so they
 ///   place an order on a random side with a price above/below the
-///   mid-price by a distance sampled from a log-normal distribution
+///   reference price (by default the mid-price) by a distance
+///   sampled from a log-normal distribution
 /// - Each agent randomly chooses to place a market order, if so they
 ///   place an order on a random side
 ///
@@ -193,7 +203,9 @@ impl Agent for NoiseAgent {
 /// # Examples
 ///
 /// ```
-/// use bourse_de::agents::{MarketAgent, MarketAgentSet, NoiseMarketAgent, NoiseAgentParams};
+/// use bourse_de::agents::{
+///     MarketAgent, MarketAgentSet, NoiseMarketAgent, NoiseAgentParams, PriceReference
+/// };
 /// use bourse_de::{market_sim_runner, MarketEnv};
 ///
 /// #[derive(MarketAgentSet)]
@@ -211,6 +223,7 @@ impl Agent for NoiseAgent {
 ///     trade_vol: 100,
 ///     price_dist_mu: 0.0,
 ///     price_dist_sigma: 1.0,
+///     price_reference: PriceReference::Mid,
 /// };
 /// let mut agents = Agents {
 ///     a: NoiseMarketAgent::new(0, 5, 0, params),
@@ -256,6 +269,9 @@ impl NoiseMarketAgent {
     /// - `price_dist_sigma` - Width parameter of the
     ///   log-normal distribution that limit-order
     ///   prices are sampled from
+    /// - `price_reference` - Reference price limit
+    ///   orders are placed around if the mid-price is
+    ///   undefined (i.e. one side of the book is empty)
     ///
     pub fn new(
         asset: AssetIdx,
@@ -277,74 +293,80 @@ impl NoiseMarketAgent {
     }
 }
 
-impl MarketAgent for NoiseMarketAgent {
-    fn update<R: RngCore, const M: usize, const N: usize>(
+impl NoiseMarketAgent {
+    /// Read the status of live orders and the reference price
+    fn observe<const M: usize, const N: usize>(&self, env: &MarketEnv<M, N>) -> (Vec<Status>, f64) {
+        let statuses = self.orders.iter().map(|x| env.order_status(*x)).collect();
+        let mid_price = common::reference_price(
+            env.get_market().get_order_book(self.asset),
+            self.params.price_reference,
+        );
+        (statuses, mid_price)
+    }
+
+    /// Decide on cancellations and new orders
+    fn decide<R: RngCore>(
         &mut self,
-        env: &mut MarketEnv<M, N>,
         rng: &mut R,
-    ) {
-        let mut live_orders =
-            common::cancel_live_orders_market(env, rng, &self.orders, self.params.p_cancel);
+        statuses: &[Status],
+        mid_price: f64,
+    ) -> MarketInstructions {
+        let (live_orders, to_cancel) =
+            common::select_cancellations(rng, &self.orders, statuses, self.params.p_cancel);
 
-        let mid_price = env.get_market().get_order_book(self.asset).mid_price();
+        let mut instructions = MarketInstructions::new(self.asset);
+        instructions.cancel(&to_cancel);
 
         for trader_id in self.trader_ids.iter() {
             if rng.gen::<f32>() < self.params.p_limit {
-                let side = rng.gen_bool(0.5);
-
-                let order_id = match side {
-                    true => common::place_buy_limit_order_market(
-                        env,
-                        rng,
-                        self.price_dist,
-                        mid_price,
-                        self.tick_size,
-                        self.params.trade_vol,
-                        self.asset,
-                        *trader_id,
-                    )
-                    .unwrap(),
-                    false => common::place_sell_limit_order_market(
-                        env,
-                        rng,
-                        self.price_dist,
-                        mid_price,
-                        self.tick_size,
-                        self.params.trade_vol,
-                        self.asset,
-                        *trader_id,
-                    )
-                    .unwrap(),
+                let (side, price) = match rng.gen_bool(0.5) {
+                    true => (
+                        Side::Bid,
+                        common::sample_buy_price(rng, self.price_dist, mid_price, self.tick_size),
+                    ),
+                    false => (
+                        Side::Ask,
+                        common::sample_sell_price(rng, self.price_dist, mid_price, self.tick_size),
+                    ),
                 };
-                live_orders.push(order_id);
+                instructions.place(side, self.params.trade_vol, *trader_id, Some(price));
             }
 
             if rng.gen::<f32>() < self.params.p_market {
-                let side = rng.gen_bool(0.5);
-                match side {
-                    true => env
-                        .place_order(
-                            self.asset,
-                            Side::Bid,
-                            self.params.trade_vol,
-                            *trader_id,
-                            None,
-                        )
-                        .unwrap(),
-                    false => env
-                        .place_order(
-                            self.asset,
-                            Side::Ask,
-                            self.params.trade_vol,
-                            *trader_id,
-                            None,
-                        )
-                        .unwrap(),
+                let side = match rng.gen_bool(0.5) {
+                    true => Side::Bid,
+                    false => Side::Ask,
                 };
+                instructions.place(side, self.params.trade_vol, *trader_id, None);
             }
         }
 
         self.orders = live_orders;
+        instructions
+    }
+}
+
+impl MarketAgent for NoiseMarketAgent {
+    fn update<R: RngCore, const M: usize, const N: usize>(
+        &mut self,
+        env: &mut MarketEnv<M, N>,
+        rng: &mut R,
+    ) {
+        let (statuses, mid_price) = self.observe(env);
+        let instructions = self.decide(rng, &statuses, mid_price);
+        let order_ids = instructions.submit(env).unwrap();
+        self.orders.extend(order_ids);
+    }
+
+    fn par_update<R: RngCore, const M: usize, const N: usize>(
+        &mut self,
+        env: &SharedMarketEnv<M, N>,
+        rng: &mut R,
+    ) {
+        let (statuses, mid_price) = env.with(|env| self.observe(env));
+        let instructions = self.decide(rng, &statuses, mid_price);
+        let order_ids = env.with(|env| instructions.submit(env)).unwrap();
+        self.orders.extend(order_ids);
     }
 }
 
@@ -367,6 +389,7 @@ mod tests {
             trade_vol: 100,
             price_dist_mu: 0.0,
             price_dist_sigma: 1.0,
+            price_reference: PriceReference::Mid,
         };
         let agents = NoiseAgent::new(10, 4, params);
 
@@ -386,6 +409,7 @@ mod tests {
             trade_vol: 100,
             price_dist_mu: 0.0,
             price_dist_sigma: 10.0,
+            price_reference: PriceReference::Mid,
         };
         let mut agents = NoiseAgent::new(10, 10, params);
 
@@ -423,4 +447,43 @@ mod tests {
             assert!(env.order(i).status == Status::Cancelled);
         }
     }
+
+    #[test]
+    fn test_price_reference() {
+        let mut env = Env::new(0, 1, 1_000_000, true);
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+
+        env.place_order(Side::Bid, 10, 0, Some(1000)).unwrap();
+        env.step(&mut rng);
+
+        let params = NoiseAgentParams {
+            tick_size: 1,
+            p_limit: 1.0,
+            p_market: 0.0,
+            p_cancel: 0.0,
+            trade_vol: 100,
+            price_dist_mu: 0.0,
+            price_dist_sigma: 1.0,
+            price_reference: PriceReference::Touch,
+        };
+        let mut agents = NoiseAgent::new(10, 20, params);
+
+        agents.update(&mut env, &mut rng);
+
+        // Orders are placed around the bid touch,
+        // rather than the (very large) mid-price
+        for event in env.get_transactions().iter() {
+            match event {
+                Event::New { order_id } => {
+                    let order = env.order(*order_id);
+                    match order.side {
+                        Side::Bid => assert!(order.price <= 1000),
+                        Side::Ask => assert!(order.price >= 1000),
+                    }
+                    assert!(order.price.abs_diff(1000) < 100);
+                }
+                _ => panic!("Only new orders should have been placed"),
+            }
+        }
+    }
 }
@@ -0,0 +1,185 @@
+//! Execution agent that targets a share of market volume
+use super::Agent;
+use crate::types::{OrderId, Side, TraderId, Vol};
+use crate::Env;
+use rand::RngCore;
+
+/// Percentage-of-volume execution agent
+///
+/// Agent executing a fixed parent quantity on one
+/// side of the market, with its trading scaled to
+/// the activity of the market. Each step the agent
+/// submits a market order sized to a fraction (the
+/// participation rate) of the total trade volume of
+/// the previous step (rounded down), capped at the
+/// unfilled volume of the parent quantity. The agent
+/// stops trading once the parent quantity is filled.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::agents::{Agent, AgentSet, PovAgent};
+/// use bourse_de::types::Side;
+/// use bourse_de::{sim_runner, Env};
+///
+/// #[derive(AgentSet)]
+/// struct Agents {
+///     pub a: PovAgent,
+/// }
+///
+/// let mut env = Env::new(0, 1, 1_000_000, true);
+///
+/// let mut agents = Agents {
+///     a: PovAgent::new(0, Side::Bid, 0.1, 1_000),
+/// };
+///
+/// sim_runner(&mut env, &mut agents, 101, 10, false);
+/// ```
+pub struct PovAgent {
+    trader_id: TraderId,
+    side: Side,
+    participation_rate: f64,
+    parent_vol: Vol,
+    orders: Vec<OrderId>,
+}
+
+impl PovAgent {
+    /// Initialise a percentage-of-volume agent
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the agent
+    /// - `side` - Side the agent trades on
+    /// - `participation_rate` - Target fraction of
+    ///   the market trade volume
+    /// - `parent_vol` - Total volume to execute
+    ///
+    pub fn new(trader_id: TraderId, side: Side, participation_rate: f64, parent_vol: Vol) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&participation_rate),
+            "Participation rate must be in the range [0, 1]"
+        );
+
+        Self {
+            trader_id,
+            side,
+            participation_rate,
+            parent_vol,
+            orders: Vec::new(),
+        }
+    }
+
+    /// Get the volume of the parent quantity filled so far
+    ///
+    /// # Arguments
+    ///
+    /// - `env` - Simulation environment
+    ///
+    pub fn filled_vol(&self, env: &Env) -> Vol {
+        self.orders
+            .iter()
+            .map(|x| {
+                let order = env.order(*x);
+                order.start_vol - order.vol
+            })
+            .sum()
+    }
+}
+
+impl Agent for PovAgent {
+    fn update<R: RngCore>(&mut self, env: &mut Env, _rng: &mut R) {
+        let remaining = self.parent_vol - self.filled_vol(env);
+
+        if remaining == 0 {
+            return;
+        }
+
+        let market_vol = match env.get_trade_vols().last() {
+            Some(v) => f64::from(*v),
+            None => return,
+        };
+        let vol = ((self.participation_rate * market_vol).floor() as Vol).min(remaining);
+
+        if vol > 0 {
+            let order_id = env
+                .place_order(self.side, vol, self.trader_id, None)
+                .unwrap();
+            self.orders.push(order_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bourse_book::types::Event;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoroshiro128StarStar;
+
+    fn submitted_vol(env: &Env) -> Vol {
+        env.get_transactions()
+            .iter()
+            .map(|x| match x {
+                Event::New { order_id } => env.order(*order_id).vol,
+                _ => panic!("Only new orders should have been placed"),
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_pov_orders() {
+        let mut env = Env::new(0, 1, 1000, true);
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+        let mut agent = PovAgent::new(1, Side::Bid, 0.1, 25);
+
+        env.place_order(Side::Ask, 1000, 0, Some(100)).unwrap();
+        env.step(&mut rng);
+
+        // No trades in the previous step
+        agent.update(&mut env, &mut rng);
+        assert!(env.get_transactions().is_empty());
+
+        // High volume step
+        env.place_order(Side::Bid, 200, 0, None).unwrap();
+        env.step(&mut rng);
+
+        agent.update(&mut env, &mut rng);
+        let high_vol = submitted_vol(&env);
+        assert!(high_vol == 20);
+        env.step(&mut rng);
+
+        // Low volume step
+        env.place_order(Side::Bid, 50, 0, None).unwrap();
+        env.step(&mut rng);
+
+        agent.update(&mut env, &mut rng);
+        let low_vol = submitted_vol(&env);
+        assert!(low_vol == 5);
+        assert!(high_vol > low_vol);
+        env.step(&mut rng);
+
+        assert!(agent.filled_vol(&env) == 25);
+
+        // Parent quantity filled
+        env.place_order(Side::Bid, 200, 0, None).unwrap();
+        env.step(&mut rng);
+
+        agent.update(&mut env, &mut rng);
+        assert!(env.get_transactions().is_empty());
+    }
+
+    #[test]
+    fn test_pov_capped_at_parent_vol() {
+        let mut env = Env::new(0, 1, 1000, true);
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+        let mut agent = PovAgent::new(1, Side::Ask, 0.5, 30);
+
+        env.place_order(Side::Bid, 1000, 0, Some(100)).unwrap();
+        env.step(&mut rng);
+        env.place_order(Side::Ask, 100, 0, None).unwrap();
+        env.step(&mut rng);
+
+        agent.update(&mut env, &mut rng);
+        assert!(submitted_vol(&env) == 30);
+    }
+}
@@ -1,6 +1,7 @@
+use super::common::MarketInstructions;
 use super::{Agent, MarketAgent};
 use crate::types::{AssetIdx, MarketOrderId, OrderId, Price, Side, Status, TraderId, Vol};
-use crate::{Env, MarketEnv};
+use crate::{Env, MarketEnv, SharedMarketEnv};
 use rand::seq::SliceRandom;
 use rand::Rng;
 use rand::RngCore;
@@ -201,46 +202,81 @@ impl RandomMarketAgents {
     }
 }
 
+impl RandomMarketAgents {
+    /// Check which agents have an active order
+    fn observe<const M: usize, const N: usize>(&self, env: &MarketEnv<M, N>) -> Vec<bool> {
+        self.orders
+            .iter()
+            .map(|x| x.is_some_and(|order_id| env.order_status(order_id) == Status::Active))
+            .collect()
+    }
+
+    /// Decide on cancellations and new orders
+    ///
+    /// Returns the instructions, and the indices
+    /// of the agents placing new orders.
+    fn decide<R: RngCore>(
+        &mut self,
+        rng: &mut R,
+        active: &[bool],
+    ) -> (MarketInstructions, Vec<usize>) {
+        let mut instructions = MarketInstructions::new(self.asset);
+        let mut placed = Vec::new();
+
+        for (n, (order, is_active)) in self.orders.iter_mut().zip(active.iter()).enumerate() {
+            let p = rng.gen::<f32>();
+
+            if p < self.activity_rate {
+                if *is_active {
+                    instructions.cancel(&[order.unwrap()]);
+                    *order = None;
+                } else {
+                    let side = [Side::Ask, Side::Bid].choose(rng).unwrap();
+                    let tick = rng.gen_range(self.tick_range.0..self.tick_range.1);
+                    let vol = rng.gen_range(self.vol_range.0..self.vol_range.1);
+                    instructions.place(
+                        *side,
+                        vol,
+                        TraderId::try_from(n).unwrap(),
+                        Some(tick * self.tick_size),
+                    );
+                    placed.push(n);
+                }
+            }
+        }
+
+        (instructions, placed)
+    }
+
+    /// Record the ids of newly placed orders
+    fn record_orders(&mut self, placed: Vec<usize>, order_ids: Vec<MarketOrderId>) {
+        for (n, order_id) in placed.into_iter().zip(order_ids) {
+            self.orders[n] = Some(order_id);
+        }
+    }
+}
+
 impl MarketAgent for RandomMarketAgents {
     fn update<R: RngCore, const M: usize, const N: usize>(
         &mut self,
         env: &mut MarketEnv<M, N>,
         rng: &mut R,
     ) {
-        let new_orders: Vec<Option<MarketOrderId>> = self
-            .orders
-            .iter_mut()
-            .enumerate()
-            .map(|(n, i)| {
-                let p = rng.gen::<f32>();
-
-                match p < self.activity_rate {
-                    true => {
-                        if (i.is_some()) && (env.order_status(i.unwrap()) == Status::Active) {
-                            env.cancel_order(i.unwrap());
-                            None
-                        } else {
-                            let side = [Side::Ask, Side::Bid].choose(rng).unwrap();
-                            let tick = rng.gen_range(self.tick_range.0..self.tick_range.1);
-                            let vol = rng.gen_range(self.vol_range.0..self.vol_range.1);
-                            Some(
-                                env.place_order(
-                                    self.asset,
-                                    *side,
-                                    vol,
-                                    TraderId::try_from(n).unwrap(),
-                                    Some(tick * self.tick_size),
-                                )
-                                .unwrap(),
-                            )
-                        }
-                    }
-                    false => *i,
-                }
-            })
-            .collect();
+        let active = self.observe(env);
+        let (instructions, placed) = self.decide(rng, &active);
+        let order_ids = instructions.submit(env).unwrap();
+        self.record_orders(placed, order_ids);
+    }
 
-        self.orders = new_orders;
+    fn par_update<R: RngCore, const M: usize, const N: usize>(
+        &mut self,
+        env: &SharedMarketEnv<M, N>,
+        rng: &mut R,
+    ) {
+        let active = env.with(|env| self.observe(env));
+        let (instructions, placed) = self.decide(rng, &active);
+        let order_ids = env.with(|env| instructions.submit(env)).unwrap();
+        self.record_orders(placed, order_ids);
     }
 }
 
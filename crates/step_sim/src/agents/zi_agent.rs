@@ -0,0 +1,175 @@
+//! Zero-intelligence agent that places orders at random prices
+use super::Agent;
+use crate::types::{Price, Side, TraderId, Vol};
+use crate::Env;
+use rand::Rng;
+use rand::RngCore;
+
+/// Zero-intelligence agent parameters
+pub struct ZeroIntelligenceParams {
+    /// Tick-size of the market
+    pub tick_size: Price,
+    /// Range of prices orders are placed over (inclusive)
+    pub price_range: (Price, Price),
+    /// Range of order volumes (inclusive), if both
+    /// values are equal orders are a fixed size
+    pub vol_range: (Vol, Vol),
+    /// Probability each agent submits an order each step
+    pub p_submit: f32,
+}
+
+/// Zero-intelligence agents
+///
+/// Group of agents following the zero-intelligence
+/// model of Gode & Sunder, a standard non-strategic
+/// baseline. Each step each agent, with a given
+/// probability, submits a limit order:
+///
+/// - On a (uniformly) random side
+/// - At a price uniformly sampled from the price
+///   range (on the tick grid)
+/// - With a volume uniformly sampled from the
+///   volume range
+///
+/// Unlike [RandomAgents](super::RandomAgents) the
+/// agents do not track or cancel their orders.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::agents::{
+///     Agent, AgentSet, ZeroIntelligenceAgent, ZeroIntelligenceParams
+/// };
+/// use bourse_de::{sim_runner, Env};
+///
+/// #[derive(AgentSet)]
+/// struct Agents {
+///     pub a: ZeroIntelligenceAgent,
+/// }
+///
+/// let mut env = Env::new(0, 1, 1_000_000, true);
+///
+/// let params = ZeroIntelligenceParams {
+///     tick_size: 1,
+///     price_range: (90, 110),
+///     vol_range: (10, 10),
+///     p_submit: 0.5,
+/// };
+/// let mut agents = Agents {
+///     a: ZeroIntelligenceAgent::new(0, 10, params),
+/// };
+///
+/// sim_runner(&mut env, &mut agents, 101, 10, false);
+/// ```
+///
+/// # References
+///
+/// 1. Gode, D. K., & Sunder, S. (1993). Allocative efficiency
+///    of markets with zero-intelligence traders.
+///    *Journal of Political Economy*, 101(1), 119-137.
+///
+pub struct ZeroIntelligenceAgent {
+    trader_ids: Vec<TraderId>,
+    tick_range: (Price, Price),
+    params: ZeroIntelligenceParams,
+}
+
+impl ZeroIntelligenceAgent {
+    /// Initialise a group of zero-intelligence agents
+    ///
+    /// # Arguments
+    ///
+    /// - `agent_id_start` - Starting id for
+    ///   agents in this set
+    /// - `n_agents` - Number of agents
+    /// - `params` - Agent parameters
+    ///
+    pub fn new(agent_id_start: TraderId, n_agents: u16, params: ZeroIntelligenceParams) -> Self {
+        assert!(params.price_range.0 <= params.price_range.1);
+        assert!(params.vol_range.0 <= params.vol_range.1);
+
+        let trader_ids = (agent_id_start..agent_id_start + TraderId::from(n_agents)).collect();
+        let tick_range = (
+            params.price_range.0.div_ceil(params.tick_size),
+            params.price_range.1 / params.tick_size,
+        );
+        assert!(
+            tick_range.0 <= tick_range.1,
+            "Price range must contain a valid tick"
+        );
+
+        Self {
+            trader_ids,
+            tick_range,
+            params,
+        }
+    }
+}
+
+impl Agent for ZeroIntelligenceAgent {
+    fn update<R: RngCore>(&mut self, env: &mut Env, rng: &mut R) {
+        for trader_id in self.trader_ids.iter() {
+            if rng.gen::<f32>() < self.params.p_submit {
+                let side = match rng.gen_bool(0.5) {
+                    true => Side::Bid,
+                    false => Side::Ask,
+                };
+                let price =
+                    rng.gen_range(self.tick_range.0..=self.tick_range.1) * self.params.tick_size;
+                let vol = rng.gen_range(self.params.vol_range.0..=self.params.vol_range.1);
+                env.place_order(side, vol, *trader_id, Some(price)).unwrap();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bourse_book::types::Event;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoroshiro128StarStar;
+
+    #[test]
+    fn test_zi_orders() {
+        let mut env = Env::new(0, 2, 1000, true);
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+
+        let params = ZeroIntelligenceParams {
+            tick_size: 2,
+            price_range: (91, 110),
+            vol_range: (5, 10),
+            p_submit: 1.0,
+        };
+        let mut agents = ZeroIntelligenceAgent::new(0, 200, params);
+
+        agents.update(&mut env, &mut rng);
+
+        assert!(env.get_transactions().len() == 200);
+
+        let mut n_bids = 0;
+
+        for event in env.get_transactions().iter() {
+            match event {
+                Event::New { order_id } => {
+                    let order = env.order(*order_id);
+                    assert!((92..=110).contains(&order.price));
+                    assert!(order.price % 2 == 0);
+                    assert!((5..=10).contains(&order.vol));
+                    if order.side == Side::Bid {
+                        n_bids += 1;
+                    }
+                }
+                _ => panic!("Only new orders should have been placed"),
+            }
+        }
+
+        assert!((70..=130).contains(&n_bids));
+
+        env.step(&mut rng);
+
+        agents.params.p_submit = 0.0;
+        agents.update(&mut env, &mut rng);
+        assert!(env.get_transactions().is_empty());
+    }
+}
@@ -1,11 +1,12 @@
 //! Market data recording
-use crate::types::{Level2Data, OrderCount, Price, Vol};
+use crate::types::{Event, Level2Data, Nanos, Order, OrderCount, OrderId, Price, Trade, Vol};
 use std::array;
 
 /// Market data history recording
 ///
 /// History of level 2 data over the course of
 /// the existence of this environment.
+#[derive(Clone)]
 pub struct Level2DataRecords<const N: usize> {
     /// Touch price histories
     pub prices: (Vec<Price>, Vec<Price>),
@@ -40,6 +41,20 @@ impl<const N: usize> Level2DataRecords<N> {
         }
     }
 
+    /// Clear all recorded histories
+    pub fn clear(&mut self) {
+        self.prices.0.clear();
+        self.prices.1.clear();
+        self.volumes.0.clear();
+        self.volumes.1.clear();
+        for i in 0..N {
+            self.volumes_at_levels.0[i].clear();
+            self.volumes_at_levels.1[i].clear();
+            self.orders_at_levels.0[i].clear();
+            self.orders_at_levels.1[i].clear();
+        }
+    }
+
     /// Append a record to the history
     pub fn append_record(&mut self, record: &Level2Data<N>) {
         self.prices.0.push(record.bid_price);
@@ -55,3 +70,197 @@ impl<const N: usize> Level2DataRecords<N> {
         }
     }
 }
+
+/// Owned struct-of-arrays copy of recorded histories
+///
+/// Bundles all the per-step series recorded by a
+/// simulation environment into one struct, for
+/// example for handing off data over FFI. Each
+/// field is a vector with one entry per recorded
+/// step, price level arrays are indexed by the
+/// number of ticks from the touch.
+pub struct HistorySoa<const N: usize> {
+    /// Bid touch prices
+    pub bid_price: Vec<Price>,
+    /// Ask touch prices
+    pub ask_price: Vec<Price>,
+    /// Total bid volumes
+    pub bid_vol: Vec<Vol>,
+    /// Total ask volumes
+    pub ask_vol: Vec<Vol>,
+    /// Per step trade volumes
+    pub trade_vol: Vec<Vol>,
+    /// Volumes at price levels from the bid touch
+    pub bid_vols_at_levels: [Vec<Vol>; N],
+    /// Volumes at price levels from the ask touch
+    pub ask_vols_at_levels: [Vec<Vol>; N],
+    /// Number of orders at price levels from the bid touch
+    pub bid_orders_at_levels: [Vec<OrderCount>; N],
+    /// Number of orders at price levels from the ask touch
+    pub ask_orders_at_levels: [Vec<OrderCount>; N],
+}
+
+impl<const N: usize> HistorySoa<N> {
+    /// Copy level 2 data records and trade volumes
+    ///
+    /// # Arguments
+    ///
+    /// - `records` - Level 2 data history
+    /// - `trade_vols` - Per step trade volumes
+    ///
+    pub fn new(records: &Level2DataRecords<N>, trade_vols: &[Vol]) -> Self {
+        Self {
+            bid_price: records.prices.0.clone(),
+            ask_price: records.prices.1.clone(),
+            bid_vol: records.volumes.0.clone(),
+            ask_vol: records.volumes.1.clone(),
+            trade_vol: trade_vols.to_vec(),
+            bid_vols_at_levels: records.volumes_at_levels.0.clone(),
+            ask_vols_at_levels: records.volumes_at_levels.1.clone(),
+            bid_orders_at_levels: records.orders_at_levels.0.clone(),
+            ask_orders_at_levels: records.orders_at_levels.1.clone(),
+        }
+    }
+}
+
+/// Change in level 2 data between two snapshots
+///
+/// Contains the new touch prices along with
+/// the price levels whose volume or number of
+/// orders changed. Levels are indexed by the
+/// number of ticks from the (new) touch.
+pub struct L2Delta {
+    /// Bid touch price
+    pub bid_price: Price,
+    /// Ask touch price
+    pub ask_price: Price,
+    /// Changed bid levels as (level, volume, number of orders)
+    pub bid_changes: Vec<(usize, Vol, OrderCount)>,
+    /// Changed ask levels as (level, volume, number of orders)
+    pub ask_changes: Vec<(usize, Vol, OrderCount)>,
+}
+
+impl L2Delta {
+    /// Compare two level 2 snapshots
+    ///
+    /// # Arguments
+    ///
+    /// - `prev` - Previous level 2 data
+    /// - `current` - Current level 2 data
+    ///
+    pub fn new<const N: usize>(prev: &Level2Data<N>, current: &Level2Data<N>) -> Self {
+        Self {
+            bid_price: current.bid_price,
+            ask_price: current.ask_price,
+            bid_changes: level_changes(&prev.bid_price_levels, &current.bid_price_levels),
+            ask_changes: level_changes(&prev.ask_price_levels, &current.ask_price_levels),
+        }
+    }
+
+    /// Check if no price levels changed
+    pub fn is_empty(&self) -> bool {
+        self.bid_changes.is_empty() && self.ask_changes.is_empty()
+    }
+}
+
+fn level_changes<const N: usize>(
+    prev: &[(Vol, OrderCount); N],
+    current: &[(Vol, OrderCount); N],
+) -> Vec<(usize, Vol, OrderCount)> {
+    prev.iter()
+        .zip(current.iter())
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+        .map(|(i, (_, b))| (i, b.0, b.1))
+        .collect()
+}
+
+/// Market data recorded by a single simulation step
+///
+/// See [Env::step_and_snapshot](crate::Env::step_and_snapshot).
+pub struct StepOutput<const N: usize> {
+    /// Level 2 data at the end of the step
+    pub level_2_data: Level2Data<N>,
+    /// Trades that took place during the step
+    pub trades: Vec<Trade>,
+    /// Trade volume during the step
+    pub trade_vol: Vol,
+}
+
+/// Instructions processed by a single simulation step
+///
+/// Recorded by an [Env](crate::Env) with event
+/// recording enabled, see
+/// [Env::set_record_events](crate::Env::set_record_events).
+#[derive(Clone, Default)]
+pub struct StepEvents {
+    /// Orders created since the previous step
+    /// (as they were at the start of the step)
+    pub orders: Vec<Order>,
+    /// Ids of the created market orders to be
+    /// parked if the book is empty
+    pub parked: Vec<OrderId>,
+    /// Transactions in the order they were processed
+    pub events: Vec<Event<OrderId>>,
+}
+
+/// Depth of the order book recorded in snapshots
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SnapshotDepth {
+    /// Only the touch price levels are recorded
+    TouchOnly,
+    /// The first `n` price levels from the touch
+    /// are recorded
+    TopN(usize),
+    /// All tracked price levels are recorded
+    #[default]
+    Full,
+}
+
+/// Order book snapshot recorded at a simulated time
+#[derive(Clone)]
+pub struct Snapshot {
+    /// Simulated time the snapshot was recorded
+    pub time: Nanos,
+    /// Bid touch price
+    pub bid_price: Price,
+    /// Ask touch price
+    pub ask_price: Price,
+    /// Bid total volume
+    pub bid_vol: Vol,
+    /// Ask total volume
+    pub ask_vol: Vol,
+    /// Volume and number of bid orders at price-levels
+    pub bid_price_levels: Vec<(Vol, OrderCount)>,
+    /// Volume and number of ask orders at price-levels
+    pub ask_price_levels: Vec<(Vol, OrderCount)>,
+}
+
+impl Snapshot {
+    /// Create a snapshot from level 2 data
+    ///
+    /// # Arguments
+    ///
+    /// - `time` - Time of the snapshot
+    /// - `data` - Level 2 market data
+    /// - `depth` - Number of price levels to record
+    ///
+    pub fn new<const N: usize>(time: Nanos, data: &Level2Data<N>, depth: SnapshotDepth) -> Self {
+        let n = match depth {
+            SnapshotDepth::TouchOnly => 1,
+            SnapshotDepth::TopN(n) => n,
+            SnapshotDepth::Full => N,
+        };
+        let n = n.min(N);
+
+        Self {
+            time,
+            bid_price: data.bid_price,
+            ask_price: data.ask_price,
+            bid_vol: data.bid_vol,
+            ask_vol: data.ask_vol,
+            bid_price_levels: data.bid_price_levels[..n].to_vec(),
+            ask_price_levels: data.ask_price_levels[..n].to_vec(),
+        }
+    }
+}
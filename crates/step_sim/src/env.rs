@@ -4,14 +4,50 @@
 //! functionality to process instructions
 //! submitted by agents and to track market data
 //!
-use super::data::Level2DataRecords;
+use super::data::{
+    HistorySoa, L2Delta, Level2DataRecords, Snapshot, SnapshotDepth, StepEvents, StepOutput,
+};
 use crate::types::{
-    Event, Level2Data, Nanos, Order, OrderCount, OrderId, Price, Side, Status, Trade, TraderId, Vol,
+    Event, Level2Data, Nanos, Order, OrderCount, OrderId, Price, SelfTradePrevention, Side, Status,
+    Trade, TraderId, Vol,
 };
 use bourse_book::{OrderBook, OrderError};
 use rand::seq::SliceRandom;
 use rand::RngCore;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::mem;
+use std::time::{Duration, SystemTime};
+
+/// Granularity of order expiry enforcement
+///
+/// Determines when orders with an expiry time
+/// are checked and cancelled during a step.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ExpiryEnforcement {
+    /// Expired orders are cancelled at the step
+    /// boundaries, i.e. at the start of each step
+    /// and once time has been jumped forward to the
+    /// end of the step (before market data is recorded)
+    #[default]
+    PerStep,
+    /// Expired orders are cancelled as time is
+    /// advanced for each processed event, and at
+    /// the end of each step
+    PerEvent,
+}
+
+/// Reference price of dynamic price bands
+///
+/// Price the band of allowed prices is centred
+/// on, updated at the end of each step.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BandReference {
+    /// Price of the most recent trade
+    LastTrade,
+    /// Mean price of the given number of most
+    /// recent trades, rounded to the tick-size
+    MovingAverage(usize),
+}
 
 /// Discrete event simulation environment
 ///
@@ -55,6 +91,7 @@ use std::mem;
 /// let env = Env::<5>::new(0, 1, 1_000, true);
 /// ```
 ///
+#[derive(Clone)]
 pub struct Env<const LEVELS: usize = 10> {
     /// Time-length of each simulation step
     step_size: Nanos,
@@ -62,12 +99,65 @@ pub struct Env<const LEVELS: usize = 10> {
     order_book: OrderBook<LEVELS>,
     /// Per step trade volume histories
     trade_vols: Vec<Vol>,
+    /// Per step signed (buyer minus seller
+    /// initiated) trade volume histories
+    signed_trade_vols: Vec<i64>,
     /// Transaction queue
     transactions: Vec<Event<OrderId>>,
+    /// Delayed transactions, keyed by the
+    /// step they will be processed at
+    delayed_transactions: BTreeMap<usize, Vec<Event<OrderId>>>,
+    /// Market orders to be parked if the opposite
+    /// side of the book is empty
+    park_if_empty: HashSet<OrderId>,
+    /// Parked market orders waiting for liquidity
+    parked_orders: Vec<OrderId>,
     /// Current level 2 market data
     level_2_data: Level2Data<LEVELS>,
+    /// Level 2 market data at the previous step
+    prev_level_2_data: Level2Data<LEVELS>,
     /// Level 2 data history
     level_2_data_records: Level2DataRecords<LEVELS>,
+    /// Order expiry enforcement mode
+    expiry_enforcement: ExpiryEnforcement,
+    /// Number of steps between recorded snapshots
+    snapshot_interval: Option<usize>,
+    /// Number of price levels recorded in snapshots
+    snapshot_depth: SnapshotDepth,
+    /// Number of steps since the environment was reset
+    n_steps: usize,
+    /// Recorded order book snapshots
+    snapshots: Vec<Snapshot>,
+    /// Handling of crossing orders submitted by
+    /// the same trader in the same step
+    self_cross_prevention: SelfTradePrevention,
+    /// Maximum number of instructions processed
+    /// per step for throttled traders
+    trader_throttles: HashMap<TraderId, usize>,
+    /// Number of instructions dropped by
+    /// throttling for each trader
+    throttled_counts: HashMap<TraderId, usize>,
+    /// Flag if best order ids are recorded each step
+    record_best_orders: bool,
+    /// Per step ids of the highest priority bid and ask orders
+    best_order_history: Vec<(Option<OrderId>, Option<OrderId>)>,
+    /// Per step start and end indices of trades
+    trades_per_step: Vec<(usize, usize)>,
+    /// Width (in ticks) and reference price of
+    /// dynamic price bands
+    dynamic_band: Option<(Price, BandReference)>,
+    /// Flag if processed instructions are recorded
+    record_events: bool,
+    /// Per step record of processed instructions
+    event_log: Vec<StepEvents>,
+    /// Number of orders created at the start of
+    /// the previous step
+    logged_orders: usize,
+    /// Wall-clock time of simulated time zero
+    epoch: SystemTime,
+    /// Flag if transactions are sorted before
+    /// they are shuffled
+    stable_shuffle: bool,
 }
 
 impl<const LEVELS: usize> Env<LEVELS> {
@@ -82,56 +172,693 @@ impl<const LEVELS: usize> Env<LEVELS> {
     ///   otherwise no trades will take place
     ///
     pub fn new(start_time: Nanos, tick_size: Price, step_size: Nanos, trading: bool) -> Self {
-        let order_book = OrderBook::new(start_time, tick_size, trading);
+        Self::with_lot_size(start_time, tick_size, 1, step_size, trading)
+    }
+
+    /// Initialise an empty environment with a lot size
+    ///
+    /// # Arguments
+    ///
+    /// - `start_time` - Simulation start time
+    /// - `tick_size` - Market tick size
+    /// - `lot_size` - Market lot size, order volumes
+    ///   must be a multiple of the lot size
+    /// - `step_size` - Simulated step time-length
+    /// - `trading` - Flag if `true` orders will be matched,
+    ///   otherwise no trades will take place
+    ///
+    pub fn with_lot_size(
+        start_time: Nanos,
+        tick_size: Price,
+        lot_size: Vol,
+        step_size: Nanos,
+        trading: bool,
+    ) -> Self {
+        let order_book = OrderBook::new(start_time, tick_size, lot_size, trading);
         let level_2_data = order_book.level_2_data();
+        let prev_level_2_data = order_book.level_2_data();
         Self {
             step_size,
             order_book,
             trade_vols: Vec::new(),
+            signed_trade_vols: Vec::new(),
             transactions: Vec::new(),
+            delayed_transactions: BTreeMap::new(),
+            park_if_empty: HashSet::new(),
+            parked_orders: Vec::new(),
             level_2_data,
+            prev_level_2_data,
             level_2_data_records: Level2DataRecords::new(),
+            expiry_enforcement: ExpiryEnforcement::PerStep,
+            snapshot_interval: None,
+            snapshot_depth: SnapshotDepth::Full,
+            n_steps: 0,
+            snapshots: Vec::new(),
+            self_cross_prevention: SelfTradePrevention::None,
+            trader_throttles: HashMap::new(),
+            throttled_counts: HashMap::new(),
+            record_best_orders: false,
+            best_order_history: Vec::new(),
+            trades_per_step: Vec::new(),
+            dynamic_band: None,
+            record_events: false,
+            event_log: Vec::new(),
+            logged_orders: 0,
+            epoch: SystemTime::UNIX_EPOCH,
+            stable_shuffle: false,
+        }
+    }
+
+    /// Set when expired orders are cancelled
+    ///
+    /// By default expired orders are cancelled at
+    /// the boundaries of each step, enforcing expiry
+    /// for each processed event is more precise, but
+    /// slower.
+    ///
+    /// # Arguments
+    ///
+    /// - `expiry_enforcement` - Expiry enforcement mode
+    ///
+    pub fn set_expiry_enforcement(&mut self, expiry_enforcement: ExpiryEnforcement) {
+        self.expiry_enforcement = expiry_enforcement;
+    }
+
+    /// Set handling of self-crossing orders within a step
+    ///
+    /// Before the transactions in a step are processed
+    /// new orders from the same trader that would cross
+    /// each other are detected, and the self-trade
+    /// prevention mode applied. The order processed
+    /// first in the (shuffled) queue is treated as the
+    /// resting order, and the later as the aggressor.
+    /// By default ([SelfTradePrevention::None]) orders
+    /// are not checked.
+    ///
+    /// # Arguments
+    ///
+    /// - `mode` - Self-trade prevention mode
+    ///
+    pub fn set_self_cross_prevention(&mut self, mode: SelfTradePrevention) {
+        self.self_cross_prevention = mode;
+    }
+
+    /// Limit the number of instructions processed per step for a trader
+    ///
+    /// Instructions (new orders, cancellations and
+    /// modifications) from the trader are processed in
+    /// the (shuffled) order of the transaction queue,
+    /// with any in excess of the limit dropped, and
+    /// counted (see [Env::num_throttled]). New orders
+    /// that are dropped are cancelled. By default the
+    /// number of instructions is not limited.
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the trader
+    /// - `max_per_step` - Maximum number of instructions
+    ///   per step, if `None` the limit is removed
+    ///
+    pub fn set_trader_throttle(&mut self, trader_id: TraderId, max_per_step: Option<usize>) {
+        match max_per_step {
+            Some(n) => self.trader_throttles.insert(trader_id, n),
+            None => self.trader_throttles.remove(&trader_id),
+        };
+    }
+
+    /// Get the number of instructions of a trader dropped by throttling
+    ///
+    /// See [Env::set_trader_throttle].
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the trader
+    ///
+    pub fn num_throttled(&self, trader_id: TraderId) -> usize {
+        self.throttled_counts.get(&trader_id).copied().unwrap_or(0)
+    }
+
+    /// Make the transaction shuffle independent of submission order
+    ///
+    /// If `true` the transaction queue is sorted by
+    /// trader id, and then order id, before it is
+    /// shuffled each step, so the processing order only
+    /// depends on the random generator and the
+    /// instructions of each trader, and not on the order
+    /// the traders submitted their instructions in. By
+    /// default the queue is shuffled in submission order.
+    ///
+    /// # Arguments
+    ///
+    /// - `stable_shuffle` - Flag if transactions are
+    ///   sorted before being shuffled
+    ///
+    pub fn set_stable_shuffle(&mut self, stable_shuffle: bool) {
+        self.stable_shuffle = stable_shuffle;
+    }
+
+    /// Set the wall-clock time of simulated time zero
+    ///
+    /// Simulated times are treated as nanoseconds from the
+    /// epoch when converted to wall-clock timestamps (see
+    /// [Env::timestamp_for]). By default the epoch is the
+    /// Unix epoch.
+    ///
+    /// # Arguments
+    ///
+    /// - `start` - Wall-clock time of simulated time zero
+    ///
+    pub fn set_epoch(&mut self, start: SystemTime) {
+        self.epoch = start;
+    }
+
+    /// Convert a simulated time to a wall-clock timestamp
+    ///
+    /// Maps a simulated time (e.g. of a trade or order)
+    /// to a wall-clock time, treating simulated time as
+    /// nanoseconds from the epoch set with [Env::set_epoch].
+    ///
+    /// # Arguments
+    ///
+    /// - `t` - Simulated time
+    ///
+    pub fn timestamp_for(&self, t: Nanos) -> SystemTime {
+        self.epoch + Duration::from_nanos(t)
+    }
+
+    /// Set how often order book snapshots are recorded
+    ///
+    /// Snapshots are recorded at the end of every
+    /// `interval` steps, by default no snapshots are
+    /// recorded.
+    ///
+    /// # Arguments
+    ///
+    /// - `interval` - Number of steps between snapshots,
+    ///   if `None` snapshots will not be recorded
+    ///
+    pub fn set_snapshot_interval(&mut self, interval: Option<usize>) {
+        assert!(interval != Some(0), "Snapshot interval must be non-zero");
+        self.snapshot_interval = interval;
+    }
+
+    /// Set how much of the order book snapshots record
+    ///
+    /// By default snapshots record all the price levels
+    /// tracked by the environment, recording fewer levels
+    /// reduces the memory used by recorded snapshots.
+    ///
+    /// # Arguments
+    ///
+    /// - `depth` - Number of price levels to record
+    ///
+    pub fn set_snapshot_depth(&mut self, depth: SnapshotDepth) {
+        self.snapshot_depth = depth;
+    }
+
+    /// Set if the ids of the best orders are recorded
+    ///
+    /// If enabled the ids of the highest priority bid
+    /// and ask orders are recorded at the end of each
+    /// step, see [Env::get_best_order_history]. By
+    /// default these are not recorded.
+    ///
+    /// # Arguments
+    ///
+    /// - `record` - Flag if `true` best order ids
+    ///   will be recorded
+    ///
+    pub fn set_record_best_orders(&mut self, record: bool) {
+        self.record_best_orders = record;
+    }
+
+    /// Set if the instructions processed each step are recorded
+    ///
+    /// If enabled the orders created and the (shuffled)
+    /// order transactions are processed in are recorded
+    /// each step, see [Env::get_event_log]. The record
+    /// can be used to exactly replay a simulation with
+    /// [Env::replay_from_env_log]. To be replayed,
+    /// recording should be enabled before the first
+    /// step. By default events are not recorded.
+    ///
+    /// # Arguments
+    ///
+    /// - `record` - Flag if `true` events will be recorded
+    ///
+    pub fn set_record_events(&mut self, record: bool) {
+        self.record_events = record;
+    }
+
+    /// Get the per step record of processed instructions
+    ///
+    /// See [Env::set_record_events].
+    pub fn get_event_log(&self) -> &Vec<StepEvents> {
+        &self.event_log
+    }
+
+    /// Set a price band relative to a rolling reference price
+    ///
+    /// At the end of each step the order book price
+    /// band (see [OrderBook::set_price_band]) is set to
+    /// `ticks` ticks either side of the reference price.
+    /// Limit orders priced outside the band are then
+    /// rejected, and trades do not take place outside
+    /// the band, until the reference price moves. The
+    /// band is not set until a trade has taken place.
+    ///
+    /// # Arguments
+    ///
+    /// - `band` - Width of the band either side of
+    ///   the reference price, in ticks, and the
+    ///   reference price the band is centred on. If
+    ///   `None` the band is removed.
+    ///
+    pub fn set_dynamic_band(&mut self, band: Option<(Price, BandReference)>) {
+        if let Some((_, BandReference::MovingAverage(n))) = band {
+            assert!(n > 0, "Moving average window must be non-zero");
+        }
+        self.dynamic_band = band;
+        match band {
+            Some(_) => self.update_price_band(),
+            None => self.order_book.set_price_band(None),
+        }
+    }
+
+    /// Update the order book price band from the reference price
+    fn update_price_band(&mut self) {
+        let (ticks, reference) = match self.dynamic_band {
+            Some(x) => x,
+            None => return,
+        };
+        let trades = self.order_book.get_trades();
+        let tick_size = self.order_book.get_tick_size();
+        let reference_price = match reference {
+            BandReference::LastTrade => trades.last().map(|x| x.price),
+            BandReference::MovingAverage(n) => {
+                let window = &trades[trades.len().saturating_sub(n)..];
+                match window.is_empty() {
+                    true => None,
+                    false => {
+                        let total: u64 = window.iter().map(|x| u64::from(x.price)).sum();
+                        let d = window.len() as u64 * u64::from(tick_size);
+                        Some(Price::try_from((total + d / 2) / d).unwrap() * tick_size)
+                    }
+                }
+            }
+        };
+        if let Some(p) = reference_price {
+            let width = ticks.saturating_mul(tick_size);
+            self.order_book
+                .set_price_band(Some((p.saturating_sub(width), p.saturating_add(width))));
         }
     }
 
+    /// Branch the environment
+    ///
+    /// Returns an independent copy of the environment,
+    /// including the order book, recorded histories and
+    /// queued transactions, that can be updated separately
+    /// from this environment, e.g. to explore alternative
+    /// continuations of a simulation.
+    ///
+    pub fn branch(&self) -> Self {
+        self.clone()
+    }
+
+    /// Reset the environment to an empty state
+    ///
+    /// Clears the order book, recorded histories and
+    /// transaction queue, keeping the tick-size,
+    /// step-size and trading flag of the environment.
+    ///
+    /// # Arguments
+    ///
+    /// - `start_time` - Simulation start time
+    ///
+    pub fn reset(&mut self, start_time: Nanos) {
+        self.order_book.reset(start_time);
+        self.trade_vols.clear();
+        self.signed_trade_vols.clear();
+        self.transactions.clear();
+        self.delayed_transactions.clear();
+        self.park_if_empty.clear();
+        self.parked_orders.clear();
+        self.level_2_data = self.order_book.level_2_data();
+        self.prev_level_2_data = self.order_book.level_2_data();
+        self.level_2_data_records.clear();
+        self.n_steps = 0;
+        self.snapshots.clear();
+        self.best_order_history.clear();
+        self.trades_per_step.clear();
+        self.throttled_counts.clear();
+        self.event_log.clear();
+        self.logged_orders = 0;
+    }
+
+    /// Clear recorded market data histories
+    ///
+    /// Clears the level 2 data, trade volume, snapshot,
+    /// best order and per step trade range histories, but
+    /// keeps the current state of the order book (including
+    /// orders and trades) and the transaction queue.
+    ///
+    pub fn clear_history(&mut self) {
+        self.trade_vols.clear();
+        self.signed_trade_vols.clear();
+        self.level_2_data_records.clear();
+        self.snapshots.clear();
+        self.best_order_history.clear();
+        self.trades_per_step.clear();
+    }
+
     /// Update the state of the simulation
     ///
     /// Each step of the simulation:
     ///
     /// - The cumulative trade volume is reset
-    /// - The transaction queue is shuffled
+    /// - Expired orders are cancelled
+    /// - Parked market orders are placed if there
+    ///   is now volume on the opposite side of the book
+    /// - Delayed transactions due this step are added
+    ///   to the transaction queue
+    /// - The transaction queue is shuffled (after being
+    ///   sorted if [Env::set_stable_shuffle] is set)
+    /// - Instructions in excess of trader throttles
+    ///   are dropped (if set)
+    /// - Self-crossing orders are handled (if enabled)
     /// - The transactions are processed, updating
     ///   the state of the market
     /// - Time is jumped forward to the next step
+    /// - Orders that expired during the step are
+    ///   cancelled
     /// - Market data for the step is recorded
     ///
     /// Note that when each event is processed time
     /// is incremented by 1 time unit (to ensure
-    /// orders have a unique index).
+    /// orders have a unique index). With
+    /// [ExpiryEnforcement::PerEvent] expired orders are
+    /// also cancelled each time the time is incremented.
     ///
     /// # Arguments
     ///
     /// - `rng` - Random generator
     ///
     pub fn step<R: RngCore>(&mut self, rng: &mut R) {
+        let mut transactions = mem::take(&mut self.transactions);
+        if let Some(delayed) = self.delayed_transactions.remove(&self.n_steps) {
+            transactions.extend(delayed);
+        }
+        if self.stable_shuffle {
+            transactions.sort_by_key(|t| {
+                let order_id = match *t {
+                    Event::New { order_id }
+                    | Event::Cancellation { order_id }
+                    | Event::Modify { order_id, .. }
+                    | Event::Reduce { order_id, .. } => order_id,
+                };
+                (self.order_book.order(order_id).trader_id, order_id)
+            });
+        }
+        transactions.shuffle(rng);
+        self.process_step(transactions);
+    }
+
+    /// Replay instructions recorded by another environment
+    ///
+    /// Steps the environment through the instructions
+    /// recorded by an environment (see
+    /// [Env::set_record_events]), creating the same orders
+    /// and processing transactions in the recorded order
+    /// (i.e. bypassing the shuffle of the transaction
+    /// queue). Replaying on an environment initialised
+    /// and configured the same as the recording
+    /// environment reproduces the recorded simulation
+    /// exactly, independent of the random generator.
+    ///
+    /// # Arguments
+    ///
+    /// - `log` - Per step recorded instructions
+    ///
+    pub fn replay_from_env_log(&mut self, log: &[StepEvents]) {
+        for step in log.iter() {
+            for order in step.orders.iter() {
+                self.order_book.restore_order(*order);
+            }
+            self.park_if_empty.extend(step.parked.iter());
+            let mut transactions = mem::take(&mut self.transactions);
+            transactions.extend(step.events.iter());
+            self.process_step(transactions);
+        }
+    }
+
+    /// Process the transactions of a step and update the environment
+    fn process_step(&mut self, transactions: Vec<Event<OrderId>>) {
+        if self.record_events {
+            let n_orders = self.order_book.num_orders();
+            let orders: Vec<Order> = (self.logged_orders..n_orders)
+                .map(|i| *self.order_book.order(i))
+                .collect();
+            let mut parked: Vec<OrderId> = orders
+                .iter()
+                .map(|x| x.order_id)
+                .filter(|x| self.park_if_empty.contains(x))
+                .collect();
+            parked.sort_unstable();
+            self.event_log.push(StepEvents {
+                orders,
+                parked,
+                events: transactions.clone(),
+            });
+            self.logged_orders = n_orders;
+        }
+
         let start_time = self.order_book.get_time();
         self.order_book.reset_trade_vol();
+        let trades_start = self.order_book.get_trades().len();
+        self.order_book.expire_orders();
 
-        let mut transactions = mem::take(&mut self.transactions);
-        transactions.shuffle(rng);
+        let per_event_expiry = self.expiry_enforcement == ExpiryEnforcement::PerEvent;
+        let mut i: Nanos = 0;
+
+        for order_id in mem::take(&mut self.parked_orders).into_iter() {
+            if self.order_book.order(order_id).status != Status::New {
+                continue;
+            }
+            if self.opposite_vol(order_id) > 0 {
+                self.order_book.set_time(start_time + i);
+                self.order_book.place_order(order_id);
+                i += 1;
+            } else {
+                self.parked_orders.push(order_id);
+            }
+        }
+
+        let transactions = match self.trader_throttles.is_empty() {
+            true => transactions,
+            false => self.throttle(transactions),
+        };
+
+        if self.self_cross_prevention != SelfTradePrevention::None {
+            self.prevent_self_crossing(&transactions);
+        }
+
+        for t in transactions.into_iter() {
+            self.order_book.set_time(start_time + i);
+            i += 1;
+
+            if per_event_expiry {
+                self.order_book.expire_orders();
+            }
+
+            if let Event::New { order_id } = t {
+                if self.park_if_empty.remove(&order_id)
+                    && (self.order_book.order(order_id).status == Status::New)
+                    && (self.opposite_vol(order_id) == 0)
+                {
+                    self.parked_orders.push(order_id);
+                    continue;
+                }
+            }
 
-        for (i, t) in transactions.into_iter().enumerate() {
-            self.order_book
-                .set_time(start_time + Nanos::try_from(i).unwrap());
             self.order_book.process_event(t);
         }
 
         self.order_book.set_time(start_time + self.step_size);
+        self.order_book.expire_orders();
+
+        self.update_price_band();
 
         // Update data records
-        self.level_2_data = self.order_book.level_2_data();
+        self.prev_level_2_data =
+            mem::replace(&mut self.level_2_data, self.order_book.level_2_data());
         self.level_2_data_records.append_record(&self.level_2_data);
         self.trade_vols.push(self.order_book.get_trade_vol());
+        self.signed_trade_vols.push(
+            self.order_book.get_trades()[trades_start..]
+                .iter()
+                .map(|x| match x.aggressor_side() {
+                    Side::Bid => i64::from(x.vol),
+                    Side::Ask => -i64::from(x.vol),
+                })
+                .sum(),
+        );
+        self.trades_per_step
+            .push((trades_start, self.order_book.get_trades().len()));
+
+        if self.record_best_orders {
+            let (bid, ask) = self.order_book.best_orders();
+            self.best_order_history
+                .push((bid.map(|x| x.order_id), ask.map(|x| x.order_id)));
+        }
+
+        self.n_steps += 1;
+        if let Some(interval) = self.snapshot_interval {
+            if self.n_steps.is_multiple_of(interval) {
+                self.record_snapshot();
+            }
+        }
+    }
+
+    /// Update the state of the simulation and return the step data
+    ///
+    /// Convenience wrapper around [Env::step] returning
+    /// the market data recorded by the step, i.e. the
+    /// new level 2 data, the trades that took place
+    /// during the step and the step trade volume.
+    ///
+    /// # Arguments
+    ///
+    /// - `rng` - Random generator
+    ///
+    pub fn step_and_snapshot<R: RngCore>(&mut self, rng: &mut R) -> StepOutput<LEVELS> {
+        self.step(rng);
+        StepOutput {
+            level_2_data: self.level_2_data.clone(),
+            trades: self.trades_last_step().to_vec(),
+            trade_vol: self.order_book.get_trade_vol(),
+        }
+    }
+
+    /// Record a snapshot of the current state of the order book
+    pub fn record_snapshot(&mut self) {
+        self.snapshots.push(Snapshot::new(
+            self.order_book.get_time(),
+            &self.order_book.level_2_data(),
+            self.snapshot_depth,
+        ));
+    }
+
+    /// Drop instructions in excess of per-trader limits
+    ///
+    /// Instructions of throttled traders (see
+    /// [Env::set_trader_throttle]) past the per step cap
+    /// are removed from the batch and added to the
+    /// trader's dropped count, and dropped new orders are
+    /// cancelled. Returns the accepted instructions.
+    fn throttle(&mut self, transactions: Vec<Event<OrderId>>) -> Vec<Event<OrderId>> {
+        let mut counts: HashMap<TraderId, usize> = HashMap::new();
+        let mut accepted = Vec::with_capacity(transactions.len());
+
+        for t in transactions.into_iter() {
+            let order_id = match t {
+                Event::New { order_id }
+                | Event::Cancellation { order_id }
+                | Event::Modify { order_id, .. }
+                | Event::Reduce { order_id, .. } => order_id,
+            };
+            let trader_id = self.order_book.order(order_id).trader_id;
+
+            if let Some(max_per_step) = self.trader_throttles.get(&trader_id) {
+                let count = counts.entry(trader_id).or_insert(0);
+                if *count >= *max_per_step {
+                    *self.throttled_counts.entry(trader_id).or_insert(0) += 1;
+                    if let Event::New { order_id } = t {
+                        self.order_book.cancel_order(order_id);
+                    }
+                    continue;
+                }
+                *count += 1;
+            }
+
+            accepted.push(t);
+        }
+
+        accepted
+    }
+
+    /// Apply self-trade prevention to crossing new orders in a batch
+    fn prevent_self_crossing(&mut self, transactions: &[Event<OrderId>]) {
+        let mut pending: HashMap<TraderId, Vec<OrderId>> = HashMap::new();
+
+        for t in transactions.iter() {
+            if let Event::New { order_id } = *t {
+                let trader_id = self.order_book.order(order_id).trader_id;
+                let earlier = pending.entry(trader_id).or_default();
+
+                for resting_id in earlier.iter().copied() {
+                    let agg = *self.order_book.order(order_id);
+                    let resting = *self.order_book.order(resting_id);
+
+                    if agg.status != Status::New {
+                        break;
+                    }
+                    if resting.status != Status::New || resting.side == agg.side {
+                        continue;
+                    }
+
+                    let crosses = match agg.side {
+                        Side::Bid => agg.price >= resting.price,
+                        Side::Ask => agg.price <= resting.price,
+                    };
+
+                    if !crosses {
+                        continue;
+                    }
+
+                    match self.self_cross_prevention {
+                        SelfTradePrevention::None => (),
+                        SelfTradePrevention::CancelResting => {
+                            self.order_book.cancel_order(resting_id);
+                        }
+                        SelfTradePrevention::CancelAggressor => {
+                            self.order_book.cancel_order(order_id);
+                        }
+                        SelfTradePrevention::DecrementBoth => {
+                            if resting.vol <= agg.vol {
+                                self.order_book.cancel_order(resting_id);
+                            }
+                            if agg.vol <= resting.vol {
+                                self.order_book.cancel_order(order_id);
+                            }
+                            if resting.vol < agg.vol {
+                                self.order_book.modify_order(
+                                    order_id,
+                                    None,
+                                    Some(agg.vol - resting.vol),
+                                );
+                            } else if agg.vol < resting.vol {
+                                self.order_book.modify_order(
+                                    resting_id,
+                                    None,
+                                    Some(resting.vol - agg.vol),
+                                );
+                            }
+                        }
+                    }
+                }
+
+                earlier.push(order_id);
+            }
+        }
+    }
+
+    /// Get the volume on the opposite side of the book to an order
+    fn opposite_vol(&self, order_id: OrderId) -> Vol {
+        match self.order_book.order(order_id).side {
+            Side::Bid => self.order_book.ask_vol(),
+            Side::Ask => self.order_book.bid_vol(),
+        }
     }
 
     /// Enable trading
@@ -175,55 +902,331 @@ impl<const LEVELS: usize> Env<LEVELS> {
         Ok(order_id)
     }
 
-    /// Submit an instruction to cancel an order
+    /// Create a new order processed after a delay
     ///
-    /// Note that this does not immediately delete
-    /// the order but submits an instruction to cancel
-    /// the order that will be processed during the
-    /// next update
+    /// Submits an instruction to place an order that is
+    /// processed `delay` steps after the next step, e.g.
+    /// to model the latency of an agent. Delayed
+    /// instructions are shuffled with the other
+    /// instructions of the step they are processed at.
+    /// A delay of `0` is equivalent to [Env::place_order].
+    ///
+    /// Returns the id of the newly create order.
     ///
     /// # Arguments
     ///
-    /// - `order_id` - Id of the order to cancel
+    /// - `side` - Side to place order
+    /// - `vol` - Volume of the order
+    /// - `trader_id` - Id of the trader/agent
+    ///   placing the order
+    /// - `price` - Order price, if None the
+    ///   order will be treated as a market order
+    /// - `delay` - Number of steps the order
+    ///   is delayed by
     ///
-    pub fn cancel_order(&mut self, order_id: OrderId) {
-        self.transactions.push(Event::Cancellation { order_id })
+    pub fn place_order_with_delay(
+        &mut self,
+        side: Side,
+        vol: Vol,
+        trader_id: TraderId,
+        price: Option<Price>,
+        delay: u64,
+    ) -> Result<OrderId, OrderError> {
+        let order_id = self.order_book.create_order(side, vol, trader_id, price)?;
+        let event = Event::New { order_id };
+        match delay {
+            0 => self.transactions.push(event),
+            _ => self
+                .delayed_transactions
+                .entry(self.n_steps + usize::try_from(delay).unwrap())
+                .or_default()
+                .push(event),
+        }
+        Ok(order_id)
     }
 
-    /// Submit an instruction to modify an order
+    /// Create a new market order that is parked if it cannot trade
     ///
-    /// Note that this does not immediately modify
-    /// the order but submits an instruction to modify
-    /// the order that will be processed during the
-    /// next update
+    /// Submits an instruction to place a market order,
+    /// if the opposite side of the book is empty when
+    /// the order is processed, rather than being
+    /// cancelled the order is parked and re-attempted
+    /// at the start of subsequent steps, until there
+    /// is volume to trade against or it is cancelled.
+    ///
+    /// Once placed the order behaves as a normal market
+    /// order, i.e. any volume that cannot be filled
+    /// is cancelled.
+    ///
+    /// Returns the id of the newly create order.
     ///
     /// # Arguments
     ///
-    /// - `order_id` - Id of the order to modify
-    /// - `new_price` - New price of the order,
-    ///   if `None` the original price will be kept
-    /// - `new_vol` - New volume of the order,
-    ///   if `None` the original price will be kept
+    /// - `side` - Side to place order
+    /// - `vol` - Volume of the order
+    /// - `trader_id` - Id of the trader/agent
+    ///   placing the order
     ///
-    pub fn modify_order(
+    pub fn place_market_order_park_if_empty(
         &mut self,
-        order_id: OrderId,
-        new_price: Option<Price>,
-        new_vol: Option<Vol>,
-    ) {
-        self.transactions.push(Event::Modify {
-            order_id,
-            new_price,
-            new_vol,
-        })
-    }
-
-    /// Get reference to bid-ask price histories
-    pub fn get_prices(&self) -> &(Vec<Price>, Vec<Price>) {
-        &self.level_2_data_records.prices
+        side: Side,
+        vol: Vol,
+        trader_id: TraderId,
+    ) -> Result<OrderId, OrderError> {
+        let order_id = self.place_order(side, vol, trader_id, None)?;
+        self.park_if_empty.insert(order_id);
+        Ok(order_id)
     }
 
-    /// Get bid-ask volume histories
+    /// Create a new limit order with an expiry time
+    ///
+    /// Submits an instruction to place a limit order
+    /// that will be cancelled once the simulated time
+    /// reaches `expiry`, see [ExpiryEnforcement]
+    /// for when expiry is checked.
+    ///
+    /// Returns the id of the newly create order.
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Side to place order
+    /// - `vol` - Volume of the order
+    /// - `trader_id` - Id of the trader/agent
+    ///   placing the order
+    /// - `price` - Price of the order
+    /// - `expiry` - Time the order expires
+    ///
+    pub fn place_order_with_expiry(
+        &mut self,
+        side: Side,
+        vol: Vol,
+        trader_id: TraderId,
+        price: Price,
+        expiry: Nanos,
+    ) -> Result<OrderId, OrderError> {
+        let order_id = self.place_order(side, vol, trader_id, Some(price))?;
+        self.order_book.set_order_expiry(order_id, expiry);
+        Ok(order_id)
+    }
+
+    /// Create a new post-only limit order
+    ///
+    /// Submits an instruction to place a limit order
+    /// that is rejected, rather than trading, if it
+    /// would cross the book when processed.
+    ///
+    /// Returns the id of the newly create order.
+    ///
+    /// # Arguments
+    ///
+    /// - `side` - Side to place order
+    /// - `vol` - Volume of the order
+    /// - `trader_id` - Id of the trader/agent
+    ///   placing the order
+    /// - `price` - Price of the order
+    ///
+    pub fn place_limit_order_post_only(
+        &mut self,
+        side: Side,
+        vol: Vol,
+        trader_id: TraderId,
+        price: Price,
+    ) -> Result<OrderId, OrderError> {
+        let order_id = self
+            .order_book
+            .create_post_only_order(side, vol, trader_id, price)?;
+        self.transactions.push(Event::New { order_id });
+        Ok(order_id)
+    }
+
+    /// Get ids of currently parked market orders
+    pub fn get_parked_orders(&self) -> &Vec<OrderId> {
+        &self.parked_orders
+    }
+
+    /// Submit an instruction to cancel an order
+    ///
+    /// Note that this does not immediately delete
+    /// the order but submits an instruction to cancel
+    /// the order that will be processed during the
+    /// next update
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the order to cancel
+    ///
+    pub fn cancel_order(&mut self, order_id: OrderId) {
+        self.transactions.push(Event::Cancellation { order_id })
+    }
+
+    /// Submit an instruction to modify an order
+    ///
+    /// Note that this does not immediately modify
+    /// the order but submits an instruction to modify
+    /// the order that will be processed during the
+    /// next update
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the order to modify
+    /// - `new_price` - New price of the order,
+    ///   if `None` the original price will be kept
+    /// - `new_vol` - New volume of the order,
+    ///   if `None` the original price will be kept
+    ///
+    pub fn modify_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_vol: Option<Vol>,
+    ) {
+        self.transactions.push(Event::Modify {
+            order_id,
+            new_price,
+            new_vol,
+        })
+    }
+
+    /// Submit an instruction to reduce the volume of an order
+    ///
+    /// Reduces the remaining volume of the order by
+    /// an amount, keeping its priority, or cancels it
+    /// if the amount is greater than or equal to the
+    /// remaining volume when the instruction is
+    /// processed during the next update (see
+    /// [OrderBook::reduce_order]).
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the order to reduce
+    /// - `amount` - Volume to remove from the order
+    ///
+    pub fn reduce_order(&mut self, order_id: OrderId, amount: Vol) {
+        self.transactions.push(Event::Reduce { order_id, amount })
+    }
+
+    /// Get reference to bid-ask price histories
+    pub fn get_prices(&self) -> &(Vec<Price>, Vec<Price>) {
+        &self.level_2_data_records.prices
+    }
+
+    /// Get mid-price history
+    ///
+    /// Returns the mid-point of the bid and ask
+    /// touch prices at each recorded step, with `NaN`
+    /// values for steps where either side of the book
+    /// was empty.
+    pub fn get_mid_prices(&self) -> Vec<f64> {
+        let (bids, asks) = self.get_prices();
+        bids.iter()
+            .zip(asks.iter())
+            .map(|(b, a)| match (*b > 0) & (*a < Price::MAX) {
+                true => f64::from(*b) + 0.5 * (f64::from(*a) - f64::from(*b)),
+                false => f64::NAN,
+            })
+            .collect()
+    }
+
+    /// Get micro-price history
+    ///
+    /// Returns the touch prices weighted by the volume
+    /// at the opposite touch, i.e.
+    /// `(bid * ask_vol + ask * bid_vol) / (bid_vol + ask_vol)`,
+    /// at each recorded step. Falls back to the mid-price
+    /// (see [Env::get_mid_prices]) where there is no volume
+    /// at a touch, so values are `NaN` for steps where
+    /// either side of the book was empty.
+    pub fn get_micro_prices(&self) -> Vec<f64> {
+        let (bids, asks) = self.get_prices();
+        let (bid_vols, ask_vols) = self.get_touch_volumes();
+        bids.iter()
+            .zip(asks.iter())
+            .zip(bid_vols.iter().zip(ask_vols.iter()))
+            .zip(self.get_mid_prices())
+            .map(|(((b, a), (bv, av)), mid)| {
+                let (b, a) = (f64::from(*b), f64::from(*a));
+                let (bv, av) = (f64::from(*bv), f64::from(*av));
+                match (bv > 0.0) & (av > 0.0) {
+                    true => (b * av + a * bv) / (bv + av),
+                    false => mid,
+                }
+            })
+            .collect()
+    }
+
+    /// Aggregate recorded market data into OHLCV bars
+    ///
+    /// Splits the recorded steps into `bars` buckets of
+    /// equal numbers of steps, with the final bucket
+    /// absorbing any remaining steps if the number of
+    /// steps is not divisible by `bars`. Returns the open,
+    /// high, low and close of the mid-price (rounded down
+    /// to an integer price) and the total trade volume of
+    /// each bucket. Note that the mid-price of steps where
+    /// either side of the book was empty is not meaningful.
+    ///
+    /// Returns an empty vector if no steps have been
+    /// recorded, and one bar per step if `bars` is greater
+    /// than the number of recorded steps.
+    ///
+    /// # Arguments
+    ///
+    /// - `bars` - Number of bars
+    ///
+    pub fn ohlcv(&self, bars: usize) -> Vec<(Price, Price, Price, Price, Vol)> {
+        let n_steps = self.trade_vols.len();
+
+        if n_steps == 0 || bars == 0 {
+            return Vec::new();
+        }
+
+        let (bids, asks) = self.get_prices();
+        let mid_prices: Vec<Price> = bids
+            .iter()
+            .zip(asks.iter())
+            .map(|(b, a)| b.midpoint(*a))
+            .collect();
+
+        let bars = bars.min(n_steps);
+        let width = n_steps / bars;
+
+        (0..bars)
+            .map(|i| {
+                let start = i * width;
+                let end = match i == bars - 1 {
+                    true => n_steps,
+                    false => start + width,
+                };
+                let prices = &mid_prices[start..end];
+                (
+                    prices[0],
+                    *prices.iter().max().unwrap(),
+                    *prices.iter().min().unwrap(),
+                    prices[prices.len() - 1],
+                    self.trade_vols[start..end].iter().sum(),
+                )
+            })
+            .collect()
+    }
+
+    /// Get quoted spread history
+    ///
+    /// Returns the difference between the ask
+    /// and bid touch prices at each recorded step,
+    /// with `NaN` values for steps where either
+    /// side of the book was empty.
+    pub fn get_spreads(&self) -> Vec<f64> {
+        let (bids, asks) = self.get_prices();
+        bids.iter()
+            .zip(asks.iter())
+            .map(|(b, a)| match (*b > 0) & (*a < Price::MAX) {
+                true => f64::from(*a) - f64::from(*b),
+                false => f64::NAN,
+            })
+            .collect()
+    }
+
+    /// Get bid-ask volume histories
     pub fn get_volumes(&self) -> &(Vec<Vol>, Vec<Vol>) {
         &self.level_2_data_records.volumes
     }
@@ -249,6 +1252,138 @@ impl<const LEVELS: usize> Env<LEVELS> {
         &self.trade_vols
     }
 
+    /// Get per step signed trade volume histories
+    ///
+    /// Net aggressive volume of each step, i.e. the
+    /// volume of trades with a buy aggressor minus the
+    /// volume of trades with a sell aggressor (see
+    /// [Trade::aggressor_side]).
+    pub fn get_signed_trade_vols(&self) -> &Vec<i64> {
+        &self.signed_trade_vols
+    }
+
+    /// Get log-returns of the recorded mid-price
+    ///
+    /// Returns the log-returns between consecutive
+    /// recorded steps. Steps where the mid-price is
+    /// undefined (i.e. one side of the book is empty)
+    /// are skipped, along with the following return.
+    pub fn mid_returns(&self) -> Vec<f64> {
+        let (bids, asks) = self.get_prices();
+        let mids: Vec<Option<f64>> = bids
+            .iter()
+            .zip(asks.iter())
+            .map(|(b, a)| match (*b > 0) & (*a < Price::MAX) {
+                true => Some(0.5 * (f64::from(*b) + f64::from(*a))),
+                false => None,
+            })
+            .collect();
+
+        mids.windows(2)
+            .filter_map(|x| match (x[0], x[1]) {
+                (Some(a), Some(b)) => Some((b / a).ln()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Get the autocorrelation of mid-price returns
+    ///
+    /// Sample autocorrelation of the returns
+    /// given by [Env::mid_returns] at the given lag.
+    /// Returns `0.0` if there are not enough returns
+    /// or the returns have zero variance.
+    ///
+    /// # Arguments
+    ///
+    /// - `lag` - Number of steps to lag returns by
+    ///
+    pub fn return_autocorr(&self, lag: usize) -> f64 {
+        let returns = self.mid_returns();
+
+        if returns.len() <= lag {
+            return 0.0;
+        }
+
+        let n = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let var: f64 = returns.iter().map(|x| (x - mean).powi(2)).sum();
+
+        if var == 0.0 {
+            return 0.0;
+        }
+
+        let cov: f64 = returns
+            .iter()
+            .zip(returns.iter().skip(lag))
+            .map(|(a, b)| (a - mean) * (b - mean))
+            .sum();
+
+        cov / var
+    }
+
+    /// Estimate the Kyle's lambda price impact coefficient
+    ///
+    /// Estimates the linear price impact of order flow
+    /// from an ordinary least squares regression
+    ///
+    /// ```notrust
+    /// mid[t] - mid[t-1] = alpha + lambda * q[t] + e[t]
+    /// ```
+    ///
+    /// of the change in the recorded mid-price over each
+    /// step on the signed order flow `q` of the step, i.e.
+    /// the volume of buyer-initiated trades minus the
+    /// volume of seller-initiated trades (see
+    /// [Env::get_signed_trade_vols]), giving
+    /// `lambda = cov(dmid, q) / var(q)`. Steps where the
+    /// mid-price is undefined at the start or end of the
+    /// step (i.e. one side of the book is empty) are
+    /// excluded. Returns `0.0` if there are fewer than
+    /// two steps, or the order flow has zero variance.
+    ///
+    pub fn kyle_lambda(&self) -> f64 {
+        let (bids, asks) = self.get_prices();
+        let mids: Vec<Option<f64>> = bids
+            .iter()
+            .zip(asks.iter())
+            .map(|(b, a)| match (*b > 0) & (*a < Price::MAX) {
+                true => Some(0.5 * (f64::from(*b) + f64::from(*a))),
+                false => None,
+            })
+            .collect();
+
+        let (dmids, flows): (Vec<f64>, Vec<f64>) = mids
+            .windows(2)
+            .zip(self.signed_trade_vols.iter().skip(1))
+            .filter_map(|(x, q)| match (x[0], x[1]) {
+                (Some(a), Some(b)) => Some((b - a, *q as f64)),
+                _ => None,
+            })
+            .unzip();
+
+        if flows.len() < 2 {
+            return 0.0;
+        }
+
+        let n = flows.len() as f64;
+        let mean_dmid = dmids.iter().sum::<f64>() / n;
+        let mean_flow = flows.iter().sum::<f64>() / n;
+        let var: f64 = flows.iter().map(|x| (x - mean_flow).powi(2)).sum();
+
+        if var == 0.0 {
+            return 0.0;
+        }
+
+        let cov: f64 = dmids
+            .iter()
+            .zip(flows.iter())
+            .map(|(d, q)| (d - mean_dmid) * (q - mean_flow))
+            .sum();
+
+        cov / var
+    }
+
     /// Get references to order data
     pub fn get_orders(&self) -> Vec<&Order> {
         self.order_book.get_orders()
@@ -264,11 +1399,53 @@ impl<const LEVELS: usize> Env<LEVELS> {
         &self.level_2_data_records
     }
 
+    /// Get an owned copy of all recorded histories
+    ///
+    /// Returns level 2 data and trade volume
+    /// histories as a single [HistorySoa].
+    pub fn history_soa(&self) -> HistorySoa<LEVELS> {
+        HistorySoa::new(&self.level_2_data_records, &self.trade_vols)
+    }
+
     /// Get reference to trade data
     pub fn get_trades(&self) -> &Vec<Trade> {
         self.order_book.get_trades()
     }
 
+    /// Get the range of trades that took place during each step
+    ///
+    /// Returns the start (inclusive) and end (exclusive)
+    /// indices into the trade records (see [Env::get_trades])
+    /// of the trades that took place during each step,
+    /// aligned with the level 2 data history.
+    ///
+    pub fn get_step_trade_ranges(&self) -> &Vec<(usize, usize)> {
+        &self.trades_per_step
+    }
+
+    /// Get trades that took place during a step
+    ///
+    /// # Arguments
+    ///
+    /// - `step` - Index of the step (in the
+    ///   recorded history)
+    ///
+    pub fn get_trades_in_step(&self, step: usize) -> &[Trade] {
+        let (start, end) = self.trades_per_step[step];
+        &self.order_book.get_trades()[start..end]
+    }
+
+    /// Get trades that took place during the most recent step
+    ///
+    /// Returns an empty slice if there are no
+    /// recorded steps (e.g. after [Env::clear_history]).
+    pub fn trades_last_step(&self) -> &[Trade] {
+        match self.trades_per_step.last() {
+            Some((start, end)) => &self.order_book.get_trades()[*start..*end],
+            None => &[],
+        }
+    }
+
     /// Get a reference to an order by id
     ///
     /// # Arguments
@@ -289,11 +1466,134 @@ impl<const LEVELS: usize> Env<LEVELS> {
         self.order_book.order(order_id).status
     }
 
+    /// Get the orders of a trader resting on the book
+    ///
+    /// See [OrderBook::active_orders_for]. Note that
+    /// orders submitted this step are not placed on
+    /// the book until the next call to [Env::step].
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the trader
+    ///
+    pub fn active_orders_for(&self, trader_id: TraderId) -> Vec<&Order> {
+        self.order_book.active_orders_for(trader_id)
+    }
+
     /// Reference to current level-2 market data
     pub fn level_2_data(&self) -> &Level2Data<LEVELS> {
         &self.level_2_data
     }
 
+    /// Estimate the probability a resting order is filled
+    ///
+    /// Heuristic estimate of the probability the order
+    /// is filled over a number of steps, see
+    /// [OrderBook::estimate_fill_probability].
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the order
+    /// - `horizon_steps` - Number of steps horizon
+    ///   of the estimate
+    ///
+    pub fn estimate_fill_probability(&self, order_id: OrderId, horizon_steps: u64) -> f64 {
+        self.order_book
+            .estimate_fill_probability(order_id, horizon_steps * self.step_size)
+    }
+
+    /// Get the recorded history of best order ids
+    ///
+    /// Returns the ids of the highest priority bid and
+    /// ask orders at the end of each step (`None` if a
+    /// side was empty). Only recorded if enabled with
+    /// [Env::set_record_best_orders].
+    ///
+    pub fn get_best_order_history(&self) -> &Vec<(Option<OrderId>, Option<OrderId>)> {
+        &self.best_order_history
+    }
+
+    /// Get the volume weighted average price of trades
+    ///
+    /// See [OrderBook::vwap].
+    ///
+    /// # Arguments
+    ///
+    /// - `window` - Optional half-open time window
+    ///   `[start, end)` of trades to include
+    ///
+    pub fn vwap(&self, window: Option<(Nanos, Nanos)>) -> Option<f64> {
+        self.order_book.vwap(window)
+    }
+
+    /// Get the current volume imbalance of the book
+    ///
+    /// See [OrderBook::book_imbalance].
+    ///
+    /// # Arguments
+    ///
+    /// - `levels` - Number of price levels from the touch
+    ///   to include, limited to `LEVELS`
+    ///
+    pub fn book_imbalance(&self, levels: usize) -> f64 {
+        self.order_book.book_imbalance(levels)
+    }
+
+    /// Get the current touch volume weighted mid-price
+    ///
+    /// See [OrderBook::weighted_mid_price].
+    pub fn weighted_mid_price(&self) -> f64 {
+        self.order_book.weighted_mid_price()
+    }
+
+    /// Get recorded order book snapshots
+    pub fn get_snapshots(&self) -> &Vec<Snapshot> {
+        &self.snapshots
+    }
+
+    /// Get the order book snapshot as of a simulated time
+    ///
+    /// Returns the most recent snapshot recorded at or
+    /// before the time, or `None` if no snapshot had
+    /// been recorded by the time.
+    ///
+    /// # Arguments
+    ///
+    /// - `t` - Simulated time
+    ///
+    pub fn snapshot_at(&self, t: Nanos) -> Option<&Snapshot> {
+        let idx = self.snapshots.partition_point(|x| x.time <= t);
+        match idx {
+            0 => None,
+            i => Some(&self.snapshots[i - 1]),
+        }
+    }
+
+    /// Change in level 2 data over the last step
+    ///
+    /// Compares the current level 2 data to the
+    /// data at the end of the previous step,
+    /// returning the new touch prices and any
+    /// price levels that changed, see [L2Delta].
+    ///
+    pub fn l2_delta_last_step(&self) -> L2Delta {
+        L2Delta::new(&self.prev_level_2_data, &self.level_2_data)
+    }
+
+    /// Get the number of transactions queued for the next step
+    pub fn num_transactions(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Get the transactions queued for the next step
+    ///
+    /// Returns the order instructions submitted since
+    /// the last step, in the order they were submitted
+    /// (they are shuffled when the step is processed).
+    pub fn pending_transactions(&self) -> &[Event<OrderId>] {
+        self.transactions.as_slice()
+    }
+
     #[cfg(test)]
     pub fn get_transactions(&self) -> &Vec<Event<OrderId>> {
         &self.transactions
@@ -302,7 +1602,8 @@ impl<const LEVELS: usize> Env<LEVELS> {
 
 #[cfg(test)]
 mod tests {
-    use bourse_book::types::Status;
+    use assert_approx_eq::assert_approx_eq;
+    use bourse_book::types::{Status, TimeInForce};
     use rand_xoshiro::rand_core::SeedableRng;
     use rand_xoshiro::Xoroshiro128StarStar as Rng;
 
@@ -366,4 +1667,1150 @@ mod tests {
         let trade_vols = env.get_trade_vols();
         assert!(*trade_vols == vec![0, 0, 30]);
     }
+
+    fn mid_price_env(mids: &[Price]) -> Env {
+        let mut env: Env = Env::new(0, 1, 1000, false);
+        let mut rng = Rng::seed_from_u64(101);
+        let mut orders = Vec::new();
+
+        for m in mids {
+            for order_id in orders.drain(..) {
+                env.cancel_order(order_id);
+            }
+            orders.push(env.place_order(Side::Bid, 10, 101, Some(m - 1)).unwrap());
+            orders.push(env.place_order(Side::Ask, 10, 101, Some(m + 1)).unwrap());
+            env.step(&mut rng);
+        }
+
+        env
+    }
+
+    #[test]
+    fn test_lot_size() {
+        let mut env: Env = Env::with_lot_size(0, 1, 10, 1000, true);
+
+        assert!(matches!(
+            env.place_order(Side::Bid, 15, 101, Some(10)),
+            Err(OrderError::LotError {
+                vol: 15,
+                lot_size: 10
+            })
+        ));
+        assert!(env.place_order(Side::Bid, 20, 101, Some(10)).is_ok());
+    }
+
+    #[test]
+    fn test_mid_returns() {
+        let env = mid_price_env(&[100, 110, 121]);
+        let returns = env.mid_returns();
+
+        assert!(returns.len() == 2);
+        assert_approx_eq!(returns[0], 1.1f64.ln());
+        assert_approx_eq!(returns[1], 1.1f64.ln());
+
+        let trending = mid_price_env(&[100, 101, 103, 106, 110, 115, 119, 122, 124, 125, 125]);
+        assert!(trending.return_autocorr(1) > 0.0);
+
+        let reverting = mid_price_env(&[100, 104, 100, 104, 100, 104, 100, 104, 100, 104]);
+        assert!(reverting.return_autocorr(1) < 0.0);
+
+        let empty: Env = Env::new(0, 1, 1000, true);
+        assert!(empty.mid_returns().is_empty());
+        assert!(empty.return_autocorr(1) == 0.0);
+    }
+
+    #[test]
+    fn test_signed_trade_vols() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Bid, 20, 101, Some(99)).unwrap();
+        env.place_order(Side::Ask, 20, 101, Some(101)).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(Side::Bid, 5, 102, None).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(Side::Bid, 3, 102, None).unwrap();
+        env.place_order(Side::Ask, 10, 103, Some(99)).unwrap();
+        env.step(&mut rng);
+
+        assert!(*env.get_signed_trade_vols() == vec![0, 5, -7]);
+        assert!(env.get_trade_vols()[2] == 13);
+
+        env.clear_history();
+        assert!(env.get_signed_trade_vols().is_empty());
+    }
+
+    #[test]
+    fn test_kyle_lambda() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        assert!(env.kyle_lambda() == 0.0);
+
+        // Ladders of 10 lots per tick, so the mid-price
+        // moves by half a tick for every 10 lots traded
+        for i in 0..50 {
+            env.place_order(Side::Bid, 10, 101, Some(99 - i)).unwrap();
+            env.place_order(Side::Ask, 10, 101, Some(101 + i)).unwrap();
+        }
+        env.step(&mut rng);
+
+        let flows = [
+            Some((Side::Bid, 20)),
+            Some((Side::Ask, 20)),
+            Some((Side::Bid, 40)),
+            None,
+            Some((Side::Ask, 30)),
+            Some((Side::Bid, 10)),
+            Some((Side::Ask, 40)),
+            Some((Side::Bid, 60)),
+        ];
+
+        for flow in flows {
+            if let Some((side, vol)) = flow {
+                env.place_order(side, vol, 102, None).unwrap();
+            }
+            env.step(&mut rng);
+        }
+
+        assert_approx_eq!(env.kyle_lambda(), 0.05);
+    }
+
+    #[test]
+    fn test_spreads() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Bid, 10, 101, Some(100)).unwrap();
+        env.step(&mut rng);
+        env.place_order(Side::Ask, 10, 101, Some(104)).unwrap();
+        env.step(&mut rng);
+        env.place_order(Side::Bid, 10, 101, Some(101)).unwrap();
+        env.step(&mut rng);
+
+        let spreads = env.get_spreads();
+
+        assert!(spreads.len() == 3);
+        assert!(spreads[0].is_nan());
+        assert!(spreads[1] == 4.0);
+        assert!(spreads[2] == 3.0);
+    }
+
+    fn expiring_order_env(expiry_enforcement: ExpiryEnforcement) -> (Env, OrderId) {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        env.set_expiry_enforcement(expiry_enforcement);
+        let mut rng = Rng::seed_from_u64(101);
+
+        let order_id = env
+            .place_order_with_expiry(Side::Ask, 10, 101, 102, 1001)
+            .unwrap();
+        assert!(env.order(order_id).time_in_force == TimeInForce::GoodTillDate);
+        env.step(&mut rng);
+
+        assert!(env.order_status(order_id) == Status::Active);
+
+        env.place_order(Side::Bid, 10, 102, Some(98)).unwrap();
+        env.place_order(Side::Bid, 10, 102, Some(99)).unwrap();
+        env.step(&mut rng);
+
+        (env, order_id)
+    }
+
+    #[test]
+    fn test_per_event_expiry() {
+        let (mut env, order_id) = expiring_order_env(ExpiryEnforcement::PerEvent);
+
+        assert!(env.order_status(order_id) == Status::Cancelled);
+        assert!(env.order(order_id).end_time == 1001);
+        assert!(env.level_2_data().ask_vol == 0);
+
+        env.step(&mut Rng::seed_from_u64(101));
+        assert!(env.order(order_id).end_time == 1001);
+    }
+
+    #[test]
+    fn test_per_step_expiry() {
+        let (mut env, order_id) = expiring_order_env(ExpiryEnforcement::PerStep);
+
+        // Order is swept at the end of the step it expired in
+        assert!(env.order_status(order_id) == Status::Cancelled);
+        assert!(env.order(order_id).end_time == 2000);
+        assert!(env.level_2_data().ask_vol == 0);
+
+        env.step(&mut Rng::seed_from_u64(101));
+        assert!(env.order(order_id).end_time == 2000);
+    }
+
+    #[test]
+    fn test_expired_order_matching() {
+        // Order expiring mid-step can still be matched
+        // against until the end of the step with per-step
+        // enforcement, but not with per-event enforcement
+        for (expiry_enforcement, n_trades, bid_vol) in [
+            (ExpiryEnforcement::PerStep, 2, 0),
+            (ExpiryEnforcement::PerEvent, 1, 5),
+        ] {
+            let mut env: Env = Env::new(0, 1, 1000, true);
+            env.set_expiry_enforcement(expiry_enforcement);
+            let mut rng = Rng::seed_from_u64(101);
+
+            let order_id = env
+                .place_order_with_expiry(Side::Ask, 10, 101, 102, 1001)
+                .unwrap();
+            env.step(&mut rng);
+
+            env.place_order(Side::Bid, 5, 102, Some(102)).unwrap();
+            env.place_order(Side::Bid, 5, 102, Some(102)).unwrap();
+            env.step(&mut rng);
+
+            assert!(env.get_trades().len() == n_trades);
+            assert!(env.order(order_id).status != Status::Active);
+            assert!(env.level_2_data().ask_vol == 0);
+            assert!(env.level_2_data().bid_vol == bid_vol);
+        }
+    }
+
+    #[test]
+    fn test_l2_delta() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Bid, 10, 101, Some(100)).unwrap();
+        env.place_order(Side::Bid, 10, 101, Some(98)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(102)).unwrap();
+        env.step(&mut rng);
+
+        let delta = env.l2_delta_last_step();
+
+        assert!((delta.bid_price, delta.ask_price) == (100, 102));
+        assert!(delta.bid_changes == vec![(0, 10, 1), (2, 10, 1)]);
+        assert!(delta.ask_changes == vec![(0, 10, 1)]);
+
+        env.place_order(Side::Bid, 5, 101, Some(98)).unwrap();
+        env.step(&mut rng);
+
+        let delta = env.l2_delta_last_step();
+
+        assert!((delta.bid_price, delta.ask_price) == (100, 102));
+        assert!(delta.bid_changes == vec![(2, 15, 2)]);
+        assert!(delta.ask_changes.is_empty());
+
+        env.step(&mut rng);
+
+        assert!(env.l2_delta_last_step().is_empty());
+    }
+
+    #[test]
+    fn test_parked_market_order() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        let parked_id = env
+            .place_market_order_park_if_empty(Side::Bid, 10, 101)
+            .unwrap();
+        let market_id = env.place_order(Side::Bid, 10, 101, None).unwrap();
+        env.step(&mut rng);
+
+        assert!(env.order_status(parked_id) == Status::New);
+        assert!(env.order_status(market_id) == Status::Cancelled);
+        assert!(env.get_parked_orders() == &vec![parked_id]);
+
+        env.step(&mut rng);
+        assert!(env.order_status(parked_id) == Status::New);
+
+        env.place_order(Side::Ask, 15, 102, Some(100)).unwrap();
+        env.step(&mut rng);
+
+        assert!(env.order_status(parked_id) == Status::New);
+        assert!(env.get_orderbook().ask_vol() == 15);
+
+        env.step(&mut rng);
+
+        assert!(env.order_status(parked_id) == Status::Filled);
+        assert!(env.get_parked_orders().is_empty());
+        assert!(env.get_orderbook().ask_vol() == 5);
+        assert!(env.get_trades().len() == 1);
+        assert!(env.get_trades()[0].active_order_id == parked_id);
+        assert!(*env.get_trade_vols() == vec![0, 0, 0, 10]);
+    }
+
+    #[test]
+    fn test_cancel_parked_market_order() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        let parked_id = env
+            .place_market_order_park_if_empty(Side::Ask, 10, 101)
+            .unwrap();
+        env.step(&mut rng);
+
+        assert!(env.order_status(parked_id) == Status::New);
+
+        env.cancel_order(parked_id);
+        env.place_order(Side::Bid, 15, 102, Some(100)).unwrap();
+        env.step(&mut rng);
+        env.step(&mut rng);
+
+        assert!(env.order_status(parked_id) == Status::Cancelled);
+        assert!(env.get_parked_orders().is_empty());
+        assert!(env.get_trades().is_empty());
+        assert!(env.get_orderbook().bid_vol() == 15);
+    }
+
+    #[test]
+    fn test_history_soa() {
+        let mut env: Env<4> = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Bid, 10, 101, Some(10)).unwrap();
+        env.place_order(Side::Bid, 10, 101, Some(9)).unwrap();
+        env.place_order(Side::Ask, 20, 101, Some(20)).unwrap();
+        env.step(&mut rng);
+        env.place_order(Side::Bid, 5, 101, None).unwrap();
+        env.place_order(Side::Ask, 20, 101, Some(21)).unwrap();
+        env.step(&mut rng);
+
+        let soa = env.history_soa();
+        let records = env.get_level_2_data_history();
+
+        assert!(soa.bid_price == env.get_prices().0);
+        assert!(soa.ask_price == env.get_prices().1);
+        assert!(soa.bid_vol == env.get_volumes().0);
+        assert!(soa.ask_vol == env.get_volumes().1);
+        assert!(&soa.trade_vol == env.get_trade_vols());
+        assert!(soa.trade_vol == vec![0, 5]);
+        assert!(soa.bid_vols_at_levels == records.volumes_at_levels.0);
+        assert!(soa.ask_vols_at_levels == records.volumes_at_levels.1);
+        assert!(soa.bid_orders_at_levels == records.orders_at_levels.0);
+        assert!(soa.ask_orders_at_levels == records.orders_at_levels.1);
+        assert!(&soa.bid_vols_at_levels[0] == env.get_touch_volumes().0);
+        assert!(&soa.ask_orders_at_levels[0] == env.get_touch_order_counts().1);
+    }
+
+    #[test]
+    fn test_reset() {
+        let step_size: Nanos = 1000;
+        let mut env: Env = Env::new(0, 2, step_size, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Bid, 10, 101, Some(10)).unwrap();
+        env.place_order(Side::Ask, 20, 101, Some(20)).unwrap();
+        env.step(&mut rng);
+        env.place_order(Side::Bid, 10, 101, Some(12)).unwrap();
+
+        env.reset(500);
+
+        assert!(env.transactions.is_empty());
+        assert!(env.get_orderbook().get_time() == 500);
+        assert!(env.get_orderbook().bid_ask() == (0, Price::MAX));
+        assert!(env.get_orders().is_empty());
+        assert!(env.get_trade_vols().is_empty());
+        assert!(env.get_prices().0.is_empty());
+
+        env.place_order(Side::Bid, 10, 101, Some(10)).unwrap();
+        env.step(&mut rng);
+
+        assert!(env.get_orderbook().bid_ask() == (10, Price::MAX));
+        assert!(env.get_orderbook().get_time() == 500 + step_size);
+        assert!(env.get_prices().0 == vec![10]);
+    }
+
+    #[test]
+    fn test_snapshot_at() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.set_snapshot_interval(Some(2));
+
+        env.place_order(Side::Bid, 10, 101, Some(50)).unwrap();
+        env.step(&mut rng);
+        assert!(env.get_snapshots().is_empty());
+
+        env.step(&mut rng);
+        env.place_order(Side::Ask, 10, 101, Some(60)).unwrap();
+        env.step(&mut rng);
+        env.step(&mut rng);
+
+        assert!(env.get_snapshots().len() == 2);
+        assert!(env.get_snapshots()[0].time == 2000);
+        assert!(env.get_snapshots()[1].time == 4000);
+
+        assert!(env.snapshot_at(1999).is_none());
+
+        let snapshot = env.snapshot_at(2000).unwrap();
+        assert!(snapshot.time == 2000);
+        assert!(snapshot.bid_price == 50);
+        assert!(snapshot.ask_price == Price::MAX);
+
+        let snapshot = env.snapshot_at(3500).unwrap();
+        assert!(snapshot.time == 2000);
+
+        let snapshot = env.snapshot_at(10_000).unwrap();
+        assert!(snapshot.time == 4000);
+        assert!(snapshot.ask_price == 60);
+
+        env.reset(0);
+        assert!(env.get_snapshots().is_empty());
+        assert!(env.snapshot_at(4000).is_none());
+    }
+
+    #[test]
+    fn test_self_cross_prevention() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        // Without prevention the orders self-trade
+        env.place_order(Side::Bid, 10, 101, Some(50)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(50)).unwrap();
+        env.step(&mut rng);
+        assert!(env.get_trades().len() == 1);
+
+        env.reset(0);
+        env.set_self_cross_prevention(SelfTradePrevention::CancelAggressor);
+
+        let a = env.place_order(Side::Bid, 10, 101, Some(50)).unwrap();
+        let b = env.place_order(Side::Ask, 10, 101, Some(48)).unwrap();
+        // Non-crossing order from the same trader
+        let c = env.place_order(Side::Ask, 10, 101, Some(55)).unwrap();
+        env.step(&mut rng);
+
+        assert!(env.get_trades().is_empty());
+        let statuses = (env.order_status(a), env.order_status(b));
+        assert!(
+            statuses == (Status::Cancelled, Status::Active)
+                || statuses == (Status::Active, Status::Cancelled)
+        );
+        assert!(env.order_status(c) == Status::Active);
+
+        env.reset(0);
+        env.set_self_cross_prevention(SelfTradePrevention::DecrementBoth);
+
+        let a = env.place_order(Side::Bid, 10, 101, Some(50)).unwrap();
+        let b = env.place_order(Side::Ask, 4, 101, None).unwrap();
+        env.step(&mut rng);
+
+        assert!(env.get_trades().is_empty());
+        assert!(env.order_status(a) == Status::Active);
+        assert!(env.order(a).vol == 6);
+        assert!(env.order(a).start_vol == 6);
+        assert!(env.order_status(b) == Status::Cancelled);
+    }
+
+    #[test]
+    fn test_snapshot_depth() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.set_snapshot_interval(Some(1));
+
+        for i in 0..5 {
+            env.place_order(Side::Bid, 10, 101, Some(50 - i)).unwrap();
+            env.place_order(Side::Ask, 10, 101, Some(60 + i)).unwrap();
+        }
+        env.step(&mut rng);
+
+        env.set_snapshot_depth(SnapshotDepth::TouchOnly);
+        env.step(&mut rng);
+
+        env.set_snapshot_depth(SnapshotDepth::TopN(3));
+        env.step(&mut rng);
+
+        let snapshots = env.get_snapshots();
+
+        assert!(snapshots[0].bid_price_levels.len() == 10);
+        assert!(snapshots[0].ask_price_levels.len() == 10);
+
+        assert!(snapshots[1].bid_price == 50);
+        assert!(snapshots[1].ask_price == 60);
+        assert!(snapshots[1].bid_price_levels == vec![(10, 1)]);
+        assert!(snapshots[1].ask_price_levels == vec![(10, 1)]);
+
+        assert!(snapshots[2].bid_price_levels == vec![(10, 1); 3]);
+        assert!(snapshots[2].ask_price_levels == vec![(10, 1); 3]);
+    }
+
+    #[test]
+    fn test_best_order_history() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.step(&mut rng);
+        assert!(env.get_best_order_history().is_empty());
+
+        env.set_record_best_orders(true);
+        env.step(&mut rng);
+
+        let bid_0 = env.place_order(Side::Bid, 10, 101, Some(50)).unwrap();
+        let bid_1 = env.place_order(Side::Bid, 10, 101, Some(49)).unwrap();
+        let ask_0 = env.place_order(Side::Ask, 10, 101, Some(60)).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(Side::Ask, 10, 102, None).unwrap();
+        env.step(&mut rng);
+
+        let history = env.get_best_order_history();
+        assert!(history.len() == 3);
+        assert!(history[0] == (None, None));
+        assert!(history[1] == (Some(bid_0), Some(ask_0)));
+        assert!(history[2] == (Some(bid_1), Some(ask_0)));
+
+        env.clear_history();
+        assert!(env.get_best_order_history().is_empty());
+    }
+
+    #[test]
+    fn test_post_only_order() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Ask, 10, 101, Some(60)).unwrap();
+        env.step(&mut rng);
+
+        let crossing = env
+            .place_limit_order_post_only(Side::Bid, 10, 102, 60)
+            .unwrap();
+        let passive = env
+            .place_limit_order_post_only(Side::Bid, 10, 102, 59)
+            .unwrap();
+        env.step(&mut rng);
+
+        assert!(env.order_status(crossing) == Status::Rejected);
+        assert!(env.order_status(passive) == Status::Active);
+        assert!(env.get_trades().is_empty());
+        assert!(env.get_orderbook().bid_ask() == (59, 60));
+    }
+
+    #[test]
+    fn test_step_and_snapshot() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Ask, 10, 101, Some(60)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(61)).unwrap();
+        let output = env.step_and_snapshot(&mut rng);
+
+        assert!(output.trades.is_empty());
+        assert!(output.trade_vol == 0);
+        assert!(output.level_2_data == *env.level_2_data());
+
+        env.place_order(Side::Bid, 15, 102, None).unwrap();
+        let output = env.step_and_snapshot(&mut rng);
+
+        assert!(output.trades.len() == 2);
+        assert!(output.trade_vol == 15);
+        assert!(output.level_2_data == *env.level_2_data());
+        assert!(output.level_2_data.ask_vol == 5);
+        assert!(output.trades.len() == env.trades_last_step().len());
+        for (a, b) in output.trades.iter().zip(env.trades_last_step()) {
+            assert!(a.active_order_id == b.active_order_id);
+            assert!(a.passive_order_id == b.passive_order_id);
+            assert!(a.vol == b.vol);
+        }
+
+        let output = env.step_and_snapshot(&mut rng);
+        assert!(output.trades.is_empty());
+        assert!(env.trades_last_step().is_empty());
+        assert!(env.get_trades().len() == 2);
+    }
+
+    #[test]
+    fn test_branch() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Bid, 10, 101, Some(100)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(110)).unwrap();
+        env.step(&mut rng);
+        env.place_order(Side::Ask, 5, 102, None).unwrap();
+
+        let mut branch = env.branch();
+        assert!(branch.get_transactions().len() == 1);
+
+        // Step the branch with an extra instruction
+        branch.place_order(Side::Bid, 10, 102, None).unwrap();
+        branch.step(&mut rng);
+
+        assert!(branch.get_trades().len() == 2);
+        assert!(branch.level_2_data().bid_vol == 5);
+        assert!(branch.level_2_data().ask_vol == 0);
+        assert!(branch.get_prices().0.len() == 2);
+
+        // Original is unaffected
+        assert!(env.get_trades().is_empty());
+        assert!(env.get_transactions().len() == 1);
+        assert!(env.get_prices().0.len() == 1);
+        assert!(env.get_orders().len() == 3);
+
+        env.step(&mut rng);
+
+        assert!(env.get_trades().len() == 1);
+        assert!(env.level_2_data().bid_vol == 5);
+        assert!(env.level_2_data().ask_vol == 10);
+        assert!(branch.get_trades().len() == 2);
+        assert!(branch.get_orders().len() == 4);
+    }
+
+    #[test]
+    fn test_vwap() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        assert!(env.vwap(None).is_none());
+
+        env.place_order(Side::Ask, 10, 101, Some(100)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(104)).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(Side::Bid, 15, 102, None).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(Side::Bid, 5, 102, None).unwrap();
+        env.step(&mut rng);
+
+        assert!(env.vwap(None) == Some((1000.0 + 1040.0) / 20.0));
+        assert!(env.vwap(Some((1000, 2000))) == Some((1000.0 + 520.0) / 15.0));
+        assert!(env.vwap(Some((2000, 3000))) == Some(104.0));
+        assert!(env.vwap(Some((0, 1000))).is_none());
+    }
+
+    #[test]
+    fn test_stable_shuffle() {
+        let processed = |env: &Env| {
+            env.get_event_log()
+                .iter()
+                .flat_map(|x| x.events.iter())
+                .map(|x| match x {
+                    Event::New { order_id } => {
+                        let order = env.order(*order_id);
+                        (order.trader_id, order.price)
+                    }
+                    _ => panic!("Only new orders should have been placed"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut env_a: Env = Env::new(0, 1, 1000, true);
+        let mut env_b: Env = Env::new(0, 1, 1000, true);
+
+        for env in [&mut env_a, &mut env_b] {
+            env.set_stable_shuffle(true);
+            env.set_record_events(true);
+        }
+
+        // Same instructions submitted in a different order
+        for trader_id in 0..5 {
+            env_a
+                .place_order(Side::Bid, 10, trader_id, Some(10))
+                .unwrap();
+            env_a
+                .place_order(Side::Bid, 10, trader_id, Some(20))
+                .unwrap();
+        }
+        for trader_id in (0..5).rev() {
+            env_b
+                .place_order(Side::Bid, 10, trader_id, Some(10))
+                .unwrap();
+        }
+        for trader_id in 0..5 {
+            env_b
+                .place_order(Side::Bid, 10, trader_id, Some(20))
+                .unwrap();
+        }
+
+        env_a.step(&mut Rng::seed_from_u64(101));
+        env_b.step(&mut Rng::seed_from_u64(101));
+
+        assert!(processed(&env_a) == processed(&env_b));
+
+        // Order depends on submission order without sorting
+        let mut env_c: Env = Env::new(0, 1, 1000, true);
+        env_c.set_record_events(true);
+        for trader_id in (0..5).rev() {
+            env_c
+                .place_order(Side::Bid, 10, trader_id, Some(10))
+                .unwrap();
+        }
+        for trader_id in 0..5 {
+            env_c
+                .place_order(Side::Bid, 10, trader_id, Some(20))
+                .unwrap();
+        }
+        env_c.step(&mut Rng::seed_from_u64(101));
+
+        assert!(processed(&env_a) != processed(&env_c));
+    }
+
+    #[test]
+    fn test_timestamp_for() {
+        let mut env: Env = Env::new(0, 1, 1_000_000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        assert!(env.timestamp_for(10) == SystemTime::UNIX_EPOCH + Duration::from_nanos(10));
+
+        let epoch = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        env.set_epoch(epoch);
+
+        env.place_order(Side::Ask, 10, 101, Some(100)).unwrap();
+        env.step(&mut rng);
+        env.place_order(Side::Bid, 10, 102, None).unwrap();
+        env.step(&mut rng);
+
+        let trade = env.get_trades()[0];
+        assert!(trade.t == 1_000_000);
+        assert!(env.timestamp_for(trade.t) == epoch + Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_trader_throttle() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.set_trader_throttle(101, Some(2));
+
+        let orders: Vec<OrderId> = (0..5)
+            .map(|i| env.place_order(Side::Bid, 10, 101, Some(90 + i)).unwrap())
+            .collect();
+        env.place_order(Side::Ask, 10, 102, Some(110)).unwrap();
+        env.place_order(Side::Ask, 10, 102, Some(111)).unwrap();
+        env.place_order(Side::Ask, 10, 102, Some(112)).unwrap();
+
+        env.step(&mut rng);
+
+        let statuses: Vec<Status> = orders.iter().map(|x| env.order_status(*x)).collect();
+        assert!(statuses.iter().filter(|x| **x == Status::Active).count() == 2);
+        assert!(statuses.iter().filter(|x| **x == Status::Cancelled).count() == 3);
+        assert!(env.num_throttled(101) == 3);
+        // Other traders are not limited
+        assert!(env.get_orderbook().ask_vol() == 30);
+        assert!(env.num_throttled(102) == 0);
+
+        // Cancellations are also counted
+        for order_id in orders.iter() {
+            env.cancel_order(*order_id);
+        }
+        env.step(&mut rng);
+        assert!(env.num_throttled(101) == 6);
+
+        env.set_trader_throttle(101, None);
+        let bid_vol = env.get_orderbook().bid_vol();
+        env.place_order(Side::Bid, 10, 101, Some(90)).unwrap();
+        env.place_order(Side::Bid, 10, 101, Some(91)).unwrap();
+        env.place_order(Side::Bid, 10, 101, Some(92)).unwrap();
+        env.step(&mut rng);
+        assert!(env.num_throttled(101) == 6);
+        assert!(env.get_orderbook().bid_vol() == bid_vol + 30);
+    }
+
+    #[test]
+    fn test_place_order_with_delay() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        let a = env
+            .place_order_with_delay(Side::Ask, 10, 101, Some(100), 0)
+            .unwrap();
+        let b = env
+            .place_order_with_delay(Side::Ask, 10, 102, Some(101), 2)
+            .unwrap();
+        let c = env
+            .place_order_with_delay(Side::Ask, 10, 103, Some(102), 1)
+            .unwrap();
+
+        // Delayed orders are not queued for the next step
+        assert!(env.pending_transactions() == [Event::New { order_id: a }]);
+
+        env.step(&mut rng);
+        assert!(env.order_status(a) == Status::Active);
+        assert!(env.order_status(b) == Status::New);
+        assert!(env.order_status(c) == Status::New);
+
+        env.step(&mut rng);
+        assert!(env.order_status(b) == Status::New);
+        assert!(env.order_status(c) == Status::Active);
+        assert!(env.order(c).arr_time == 1000);
+
+        let d = env
+            .place_order_with_delay(Side::Ask, 10, 104, Some(103), 1)
+            .unwrap();
+
+        env.step(&mut rng);
+        assert!(env.order_status(b) == Status::Active);
+        assert!(env.order(b).arr_time >= 2000);
+        assert!(env.order_status(d) == Status::New);
+
+        env.step(&mut rng);
+        assert!(env.order_status(d) == Status::Active);
+        assert!(env.get_orderbook().ask_vol() == 40);
+    }
+
+    #[test]
+    fn test_reduce_order() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        let a = env.place_order(Side::Ask, 20, 101, Some(100)).unwrap();
+        env.step(&mut rng);
+
+        // Order partially fills before the reduction is processed
+        env.place_order(Side::Bid, 5, 102, None).unwrap();
+        env.step(&mut rng);
+        env.reduce_order(a, 10);
+        env.step(&mut rng);
+
+        assert!(env.order(a).vol == 5);
+        assert!(env.order_status(a) == Status::Active);
+
+        env.reduce_order(a, 10);
+        env.step(&mut rng);
+
+        assert!(env.order_status(a) == Status::Cancelled);
+        assert!(env.get_orderbook().ask_vol() == 0);
+    }
+
+    #[test]
+    fn test_pending_transactions() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        assert!(env.pending_transactions().is_empty());
+
+        let a = env.place_order(Side::Bid, 10, 101, Some(100)).unwrap();
+        let b = env.place_order(Side::Ask, 10, 102, Some(105)).unwrap();
+
+        assert!(
+            env.pending_transactions() == [Event::New { order_id: a }, Event::New { order_id: b }]
+        );
+
+        env.step(&mut rng);
+
+        assert!(env.pending_transactions().is_empty());
+
+        env.cancel_order(a);
+        env.modify_order(b, Some(104), None);
+
+        assert!(
+            env.pending_transactions()
+                == [
+                    Event::Cancellation { order_id: a },
+                    Event::Modify {
+                        order_id: b,
+                        new_price: Some(104),
+                        new_vol: None
+                    }
+                ]
+        );
+    }
+
+    #[test]
+    fn test_active_orders_for() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        let a = env.place_order(Side::Bid, 10, 101, Some(100)).unwrap();
+        let b = env.place_order(Side::Bid, 10, 101, Some(99)).unwrap();
+        env.place_order(Side::Ask, 10, 102, Some(105)).unwrap();
+
+        assert!(env.active_orders_for(101).is_empty());
+
+        env.step(&mut rng);
+
+        let mut ids: Vec<OrderId> = env
+            .active_orders_for(101)
+            .iter()
+            .map(|x| x.order_id)
+            .collect();
+        ids.sort();
+        assert!(ids == vec![a, b]);
+
+        env.cancel_order(a);
+        env.step(&mut rng);
+
+        assert!(env.active_orders_for(101)[0].order_id == b);
+
+        env.place_order(Side::Ask, 10, 102, None).unwrap();
+        env.step(&mut rng);
+
+        assert!(env.active_orders_for(101).is_empty());
+        assert!(env.active_orders_for(102).len() == 1);
+    }
+
+    #[test]
+    fn test_book_imbalance() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Bid, 30, 101, Some(100)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(101)).unwrap();
+
+        // Orders are not placed until the step
+        assert!(env.book_imbalance(10) == 0.0);
+        assert!(env.weighted_mid_price() == 0.0);
+
+        env.step(&mut rng);
+
+        assert!(env.book_imbalance(10) == 0.5);
+        assert!(env.weighted_mid_price() == (100.0 * 10.0 + 101.0 * 30.0) / 40.0);
+    }
+
+    #[test]
+    fn test_ohlcv() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        assert!(env.ohlcv(2).is_empty());
+
+        env.place_order(Side::Bid, 10, 101, Some(50)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(60)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(70)).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(Side::Bid, 10, 102, None).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(Side::Bid, 10, 101, Some(52)).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(Side::Ask, 5, 102, None).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(Side::Ask, 10, 102, None).unwrap();
+        env.step(&mut rng);
+
+        // Mid-prices 55, 60, 61, 61, 60
+        assert!(env.ohlcv(0).is_empty());
+        assert!(env.ohlcv(1) == vec![(55, 61, 55, 60, 25)]);
+        assert!(env.ohlcv(2) == vec![(55, 60, 55, 60, 10), (61, 61, 60, 60, 15)]);
+        assert!(env.ohlcv(10).len() == 5);
+        assert!(env.ohlcv(10)[3] == (61, 61, 61, 61, 5));
+    }
+
+    #[test]
+    fn test_ohlcv_crossed_book() {
+        let mut env: Env = Env::new(0, 1, 1000, false);
+        let mut rng = Rng::seed_from_u64(101);
+
+        // Orders do not match with trading disabled
+        env.place_order(Side::Bid, 10, 101, Some(60)).unwrap();
+        env.place_order(Side::Ask, 10, 102, Some(50)).unwrap();
+        env.step(&mut rng);
+
+        assert!(env.get_orderbook().bid_ask() == (60, 50));
+        assert!(env.ohlcv(1) == vec![(55, 55, 55, 55, 0)]);
+    }
+
+    #[test]
+    fn test_step_trade_ranges() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Ask, 10, 101, Some(60)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(61)).unwrap();
+        env.step(&mut rng);
+
+        let market_0 = env.place_order(Side::Bid, 15, 102, None).unwrap();
+        env.step(&mut rng);
+
+        env.step(&mut rng);
+
+        let market_1 = env.place_order(Side::Bid, 5, 102, None).unwrap();
+        env.step(&mut rng);
+
+        assert!(*env.get_step_trade_ranges() == vec![(0, 0), (0, 2), (2, 2), (2, 3)]);
+        assert!(env.get_trades_in_step(0).is_empty());
+        assert!(env.get_trades_in_step(1).len() == 2);
+        assert!(env
+            .get_trades_in_step(1)
+            .iter()
+            .all(|x| x.active_order_id == market_0));
+        assert!(env.get_trades_in_step(2).is_empty());
+        assert!(env.get_trades_in_step(3).len() == 1);
+        assert!(env.get_trades_in_step(3)[0].active_order_id == market_1);
+        assert!(env.trades_last_step().len() == 1);
+        assert!(env.trades_last_step()[0].active_order_id == market_1);
+
+        env.clear_history();
+        assert!(env.get_step_trade_ranges().is_empty());
+        assert!(env.trades_last_step().is_empty());
+
+        env.step(&mut rng);
+        assert!(*env.get_step_trade_ranges() == vec![(3, 3)]);
+    }
+
+    #[test]
+    fn test_dynamic_band() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.set_dynamic_band(Some((5, BandReference::LastTrade)));
+        assert!(env.get_orderbook().get_price_band().is_none());
+
+        env.place_order(Side::Ask, 10, 101, Some(100)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(103)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(108)).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(Side::Bid, 5, 102, None).unwrap();
+        env.step(&mut rng);
+        assert!(env.get_orderbook().get_price_band() == Some((95, 105)));
+
+        // Sharp move is halted at the band edge
+        let sweep = env.place_order(Side::Bid, 25, 102, None).unwrap();
+        env.step(&mut rng);
+
+        assert!(env.order(sweep).status == Status::Cancelled);
+        assert!(env.order(sweep).vol == 10);
+        assert!(env.get_trades().last().unwrap().price == 103);
+        assert!(env.get_orderbook().get_price_band() == Some((98, 108)));
+
+        // Out of band orders are rejected
+        let rejected = env.place_order(Side::Ask, 10, 101, Some(110)).unwrap();
+        env.step(&mut rng);
+        assert!(env.order_status(rejected) == Status::Rejected);
+
+        // Reference catches up
+        env.place_order(Side::Bid, 5, 102, None).unwrap();
+        env.step(&mut rng);
+        assert!(env.get_orderbook().get_price_band() == Some((103, 113)));
+
+        let accepted = env.place_order(Side::Ask, 10, 101, Some(110)).unwrap();
+        env.step(&mut rng);
+        assert!(env.order_status(accepted) == Status::Active);
+
+        // Band is removed and no longer updated
+        env.set_dynamic_band(None);
+        assert!(env.get_orderbook().get_price_band().is_none());
+
+        let accepted = env.place_order(Side::Ask, 10, 101, Some(150)).unwrap();
+        env.place_order(Side::Bid, 5, 102, None).unwrap();
+        env.step(&mut rng);
+        assert!(env.order_status(accepted) == Status::Active);
+        assert!(env.get_orderbook().get_price_band().is_none());
+    }
+
+    #[test]
+    fn test_moving_average_band() {
+        let mut env: Env = Env::new(0, 2, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Ask, 10, 101, Some(100)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(106)).unwrap();
+        env.step(&mut rng);
+        env.place_order(Side::Bid, 15, 102, None).unwrap();
+        env.step(&mut rng);
+
+        env.set_dynamic_band(Some((2, BandReference::MovingAverage(2))));
+        assert!(env.get_orderbook().get_price_band() == Some((100, 108)));
+
+        env.reset(0);
+        assert!(env.get_orderbook().get_price_band().is_none());
+    }
+
+    #[test]
+    fn test_replay_from_env_log() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+        env.set_record_events(true);
+
+        for i in 0..20usize {
+            for _ in 0..5 {
+                let side = match rng.next_u32() % 2 {
+                    0 => Side::Bid,
+                    _ => Side::Ask,
+                };
+                let price = 95 + rng.next_u32() % 10;
+                env.place_order(side, 10, 0, Some(price)).unwrap();
+            }
+            env.place_order(Side::Bid, 15, 1, None).unwrap();
+            env.place_order_with_expiry(Side::Ask, 5, 2, 110, 3000 * i as Nanos)
+                .unwrap();
+            env.place_market_order_park_if_empty(Side::Ask, 15, 3)
+                .unwrap();
+            if i > 0 {
+                env.cancel_order(8 * (i - 1));
+            }
+            env.step(&mut rng);
+        }
+
+        assert!(env.get_event_log().len() == 20);
+        assert!(env.get_event_log()[0].orders.len() == 8);
+        assert!(env.get_event_log()[0].parked == vec![7]);
+
+        // Replay does not use a random generator
+        let mut replay_env: Env = Env::new(0, 1, 1000, true);
+        replay_env.replay_from_env_log(env.get_event_log());
+
+        let trades = env.get_trades();
+        let replay_trades = replay_env.get_trades();
+
+        assert!(!trades.is_empty());
+        assert!(trades.len() == replay_trades.len());
+
+        for (a, b) in trades.iter().zip(replay_trades.iter()) {
+            assert!(
+                (
+                    a.t,
+                    a.side,
+                    a.price,
+                    a.vol,
+                    a.active_order_id,
+                    a.passive_order_id
+                ) == (
+                    b.t,
+                    b.side,
+                    b.price,
+                    b.vol,
+                    b.active_order_id,
+                    b.passive_order_id
+                )
+            );
+        }
+
+        assert!(env.get_prices() == replay_env.get_prices());
+        assert!(
+            env.get_orderbook().status_histogram() == replay_env.get_orderbook().status_histogram()
+        );
+    }
+
+    #[test]
+    fn test_micro_prices() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Bid, 30, 101, Some(100)).unwrap();
+        env.place_order(Side::Bid, 50, 101, Some(98)).unwrap();
+        env.place_order(Side::Ask, 10, 101, Some(110)).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(Side::Ask, 30, 101, Some(110)).unwrap();
+        env.step(&mut rng);
+
+        let micro_prices = env.get_micro_prices();
+
+        assert!(micro_prices.len() == 2);
+        assert_approx_eq!(micro_prices[0], 107.5);
+        assert_approx_eq!(micro_prices[1], 104.2857142857);
+        assert!(env.get_mid_prices() == vec![105.0, 105.0]);
+
+        let mut empty: Env = Env::new(0, 1, 1000, true);
+        empty.step(&mut rng);
+        empty.place_order(Side::Bid, 10, 101, Some(100)).unwrap();
+        empty.step(&mut rng);
+        empty.place_order(Side::Ask, 10, 101, Some(110)).unwrap();
+        empty.step(&mut rng);
+
+        // Undefined with either side of the book empty
+        let micro_prices = empty.get_micro_prices();
+        assert!(micro_prices.len() == 3);
+        assert!(micro_prices[0].is_nan());
+        assert!(micro_prices[1].is_nan());
+        assert_approx_eq!(micro_prices[2], 105.0);
+
+        let mid_prices = empty.get_mid_prices();
+        assert!(mid_prices.len() == 3);
+        assert!(mid_prices[0].is_nan());
+        assert!(mid_prices[1].is_nan());
+        assert_approx_eq!(mid_prices[2], 105.0);
+    }
 }
@@ -70,6 +70,7 @@
 //!     trade_vol: 100,
 //!     price_dist_mu: 0.0,
 //!     price_dist_sigma: 1.0,
+//!     price_reference: agents::PriceReference::Mid,
 //! };
 //!
 //! let mut agents = Agents {
@@ -173,10 +174,21 @@ pub mod agents;
 mod data;
 mod env;
 mod market_env;
+mod observation;
 mod runner;
 
 pub use bourse_book::{types, OrderError};
-pub use data::Level2DataRecords;
-pub use env::Env;
-pub use market_env::MarketEnv;
-pub use runner::{market_sim_runner, sim_runner};
+pub use data::{
+    HistorySoa, L2Delta, Level2DataRecords, Snapshot, SnapshotDepth, StepEvents, StepOutput,
+};
+pub use env::{BandReference, Env, ExpiryEnforcement};
+pub use market_env::{MarketEnv, SharedMarketEnv};
+pub use observation::{ObservationBuilder, ObservationConfig};
+#[doc(hidden)]
+pub use rayon;
+pub use runner::{
+    ensemble_runner, market_sim_runner, market_sim_runner_with_rng, seed_sequence, sensitivity,
+    sim_runner, sim_runner_checked, sim_runner_equilibrate, sim_runner_equilibrate_seeded,
+    sim_runner_observed, sim_runner_observed_seeded, sim_runner_seeded, sim_runner_with_rng,
+    RunError,
+};
@@ -7,11 +7,12 @@
 use super::data::Level2DataRecords;
 use crate::types::{
     AssetIdx, Event, Level2Data, MarketOrderId, Nanos, Order, OrderCount, Price, Side, Status,
-    Trade, TraderId, Vol,
+    Trade, TraderId, TraderSummary, Vol,
 };
 use bourse_book::{Market, OrderError};
 use rand::seq::SliceRandom;
 use rand::RngCore;
+use std::sync::Mutex;
 use std::{array, mem};
 
 /// Multi-asset discrete event simulation environment
@@ -44,6 +45,12 @@ use std::{array, mem};
 /// // Update the state of the market
 /// env.step(&mut rng)
 /// ```
+///
+/// Trader ids share a single namespace across all
+/// assets, i.e. a trader id refers to the same
+/// trader on every order-book, so a trader's
+/// activity can be aggregated across assets
+/// (see [MarketEnv::trader_summary]).
 pub struct MarketEnv<const ASSETS: usize, const LEVELS: usize = 10> {
     /// Time-length of each simulation step
     step_size: Nanos,
@@ -57,6 +64,8 @@ pub struct MarketEnv<const ASSETS: usize, const LEVELS: usize = 10> {
     level_2_data: [Level2Data<LEVELS>; ASSETS],
     /// Level 2 data history
     level_2_data_records: [Level2DataRecords<LEVELS>; ASSETS],
+    /// Flag if transactions are processed in submission order
+    fifo: bool,
 }
 
 impl<const ASSETS: usize, const LEVELS: usize> MarketEnv<ASSETS, LEVELS> {
@@ -76,7 +85,30 @@ impl<const ASSETS: usize, const LEVELS: usize> MarketEnv<ASSETS, LEVELS> {
         step_size: Nanos,
         trading: bool,
     ) -> Self {
-        let market = Market::<ASSETS, LEVELS>::new(start_time, tick_sizes, trading);
+        Self::with_lot_sizes(start_time, tick_sizes, [1; ASSETS], step_size, trading)
+    }
+
+    /// Initialise an empty market environment with lot sizes
+    ///
+    /// # Arguments
+    ///
+    /// - `start_time` - Simulation start time
+    /// - `tick_sizes` - Array of market tick sizes per asset
+    /// - `lot_sizes` - Array of market lot sizes per asset,
+    ///   order volumes must be a multiple of the lot size
+    /// - `step_size` - Simulated step time-length
+    /// - `trading` - Flag if `true` orders will be matched,
+    ///   otherwise no trades will take place
+    ///
+    pub fn with_lot_sizes(
+        start_time: Nanos,
+        tick_sizes: [Price; ASSETS],
+        lot_sizes: [Vol; ASSETS],
+        step_size: Nanos,
+        trading: bool,
+    ) -> Self {
+        let market =
+            Market::<ASSETS, LEVELS>::with_lot_sizes(start_time, tick_sizes, lot_sizes, trading);
         let level_2_data = market.level_2_data();
         Self {
             step_size,
@@ -85,6 +117,44 @@ impl<const ASSETS: usize, const LEVELS: usize> MarketEnv<ASSETS, LEVELS> {
             transactions: Vec::new(),
             level_2_data,
             level_2_data_records: array::from_fn(|_| Level2DataRecords::new()),
+            fifo: false,
+        }
+    }
+
+    /// Set whether transactions are processed in submission order
+    ///
+    /// By default the transaction queue is shuffled each
+    /// step, if `fifo` is `true` transactions are instead
+    /// processed in the order they were submitted, making
+    /// results independent of the random generator
+    /// passed to [MarketEnv::step].
+    ///
+    /// # Arguments
+    ///
+    /// - `fifo` - Flag if transactions should be
+    ///   processed in submission order
+    ///
+    pub fn set_fifo(&mut self, fifo: bool) {
+        self.fifo = fifo;
+    }
+
+    /// Reset the environment to an empty state
+    ///
+    /// Clears all order books, recorded histories and
+    /// the transaction queue, keeping the tick-sizes,
+    /// step-size and trading flag of the environment.
+    ///
+    /// # Arguments
+    ///
+    /// - `start_time` - Simulation start time
+    ///
+    pub fn reset(&mut self, start_time: Nanos) {
+        self.market.reset(start_time);
+        self.transactions.clear();
+        self.level_2_data = self.market.level_2_data();
+        for i in 0..ASSETS {
+            self.trade_vols[i].clear();
+            self.level_2_data_records[i].clear();
         }
     }
 
@@ -93,7 +163,8 @@ impl<const ASSETS: usize, const LEVELS: usize> MarketEnv<ASSETS, LEVELS> {
     /// Each step of the simulation:
     ///
     /// - The cumulative trade volume is reset
-    /// - The transaction queue is shuffled
+    /// - The transaction queue is shuffled (unless
+    ///   FIFO processing is enabled, see [MarketEnv::set_fifo])
     /// - The transactions are processed, updating
     ///   the state of the market
     /// - Time is jumped forward to the next step
@@ -112,7 +183,9 @@ impl<const ASSETS: usize, const LEVELS: usize> MarketEnv<ASSETS, LEVELS> {
         self.market.reset_trade_vols();
 
         let mut transactions = mem::take(&mut self.transactions);
-        transactions.shuffle(rng);
+        if !self.fifo {
+            transactions.shuffle(rng);
+        }
 
         for (i, t) in transactions.into_iter().enumerate() {
             self.market
@@ -218,6 +291,24 @@ impl<const ASSETS: usize, const LEVELS: usize> MarketEnv<ASSETS, LEVELS> {
         })
     }
 
+    /// Submit an instruction to reduce the volume of an order
+    ///
+    /// Reduces the remaining volume of the order by
+    /// an amount, keeping its priority, or cancels it
+    /// if the amount is greater than or equal to the
+    /// remaining volume when the instruction is
+    /// processed during the next update (see
+    /// [Market::reduce_order]).
+    ///
+    /// # Arguments
+    ///
+    /// - `order_id` - Id of the order to reduce
+    /// - `amount` - Volume to remove from the order
+    ///
+    pub fn reduce_order(&mut self, order_id: MarketOrderId, amount: Vol) {
+        self.transactions.push(Event::Reduce { order_id, amount })
+    }
+
     /// Get reference to bid-ask price histories of an asset
     ///
     /// # Arguments
@@ -309,6 +400,31 @@ impl<const ASSETS: usize, const LEVELS: usize> MarketEnv<ASSETS, LEVELS> {
         self.market.get_order_book(asset).get_trades()
     }
 
+    /// Summarise the trades of a trader for each asset
+    ///
+    /// Returns the position, cash flow, traded volume
+    /// and profit and loss of the trader on each
+    /// asset's order-book.
+    ///
+    /// # Arguments
+    ///
+    /// - `trader_id` - Id of the trader
+    ///
+    pub fn trader_summary(&self, trader_id: TraderId) -> [TraderSummary; ASSETS] {
+        self.market.trader_summary(trader_id)
+    }
+
+    /// Get the volume weighted average trade price of each asset
+    ///
+    /// # Arguments
+    ///
+    /// - `window` - Optional half-open time window
+    ///   `[start, end)` of trades to include
+    ///
+    pub fn vwap(&self, window: Option<(Nanos, Nanos)>) -> [Option<f64>; ASSETS] {
+        self.market.vwap(window)
+    }
+
     /// Get a reference to an order by id
     ///
     /// # Arguments
@@ -340,6 +456,48 @@ impl<const ASSETS: usize, const LEVELS: usize> MarketEnv<ASSETS, LEVELS> {
     }
 }
 
+/// Shared handle to a multi-asset environment
+///
+/// Allows agents updated concurrently (see
+/// [MarketAgentSet::par_update](crate::agents::MarketAgentSet::par_update))
+/// to access the same environment. Access to the
+/// environment is synchronised, so agents can read
+/// market data and submit instructions from multiple
+/// threads, but only one agent can access the
+/// environment at a time.
+pub struct SharedMarketEnv<'a, const ASSETS: usize, const LEVELS: usize = 10> {
+    env: Mutex<&'a mut MarketEnv<ASSETS, LEVELS>>,
+}
+
+impl<'a, const ASSETS: usize, const LEVELS: usize> SharedMarketEnv<'a, ASSETS, LEVELS> {
+    /// Initialise a shared handle to an environment
+    ///
+    /// # Arguments
+    ///
+    /// - `env` - Multi-asset simulation environment
+    ///
+    pub fn new(env: &'a mut MarketEnv<ASSETS, LEVELS>) -> Self {
+        Self {
+            env: Mutex::new(env),
+        }
+    }
+
+    /// Access the environment
+    ///
+    /// Calls the function with a mutable reference to
+    /// the environment, blocking until no other agent
+    /// is accessing the environment.
+    ///
+    /// # Arguments
+    ///
+    /// - `f` - Function called with the environment
+    ///
+    pub fn with<T, F: FnOnce(&mut MarketEnv<ASSETS, LEVELS>) -> T>(&self, f: F) -> T {
+        let mut env = self.env.lock().unwrap();
+        f(&mut env)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bourse_book::types::Status;
@@ -406,4 +564,128 @@ mod tests {
         let trade_vols = env.get_trade_vols(0);
         assert!(*trade_vols == vec![0, 0, 30]);
     }
+
+    #[test]
+    fn test_lot_sizes() {
+        let mut env: MarketEnv<2> = MarketEnv::with_lot_sizes(0, [1, 1], [1, 10], 1000, true);
+
+        assert!(env.place_order(0, Side::Bid, 15, 101, Some(10)).is_ok());
+        assert!(matches!(
+            env.place_order(1, Side::Bid, 15, 101, Some(10)),
+            Err(OrderError::LotError {
+                vol: 15,
+                lot_size: 10
+            })
+        ));
+    }
+
+    #[test]
+    fn test_reset() {
+        let step_size: Nanos = 1000;
+        let mut env: MarketEnv<2> = MarketEnv::new(0, [1, 2], step_size, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(0, Side::Bid, 10, 101, Some(10)).unwrap();
+        env.place_order(1, Side::Ask, 20, 101, Some(20)).unwrap();
+        env.step(&mut rng);
+        env.place_order(0, Side::Ask, 10, 101, None).unwrap();
+
+        env.reset(0);
+
+        assert!(env.transactions.is_empty());
+        assert!(env.get_market().get_time() == 0);
+        assert!(env.get_market().bid_asks() == [(0, Price::MAX), (0, Price::MAX)]);
+
+        for i in 0..2 {
+            assert!(env.get_orders(i).is_empty());
+            assert!(env.get_trade_vols(i).is_empty());
+            assert!(env.get_prices(i).0.is_empty());
+            assert!(env.get_prices(i).1.is_empty());
+        }
+
+        env.place_order(0, Side::Bid, 10, 101, Some(10)).unwrap();
+        env.place_order(0, Side::Ask, 10, 101, Some(10)).unwrap();
+        env.place_order(1, Side::Ask, 20, 101, Some(20)).unwrap();
+        assert!(env.place_order(1, Side::Ask, 20, 101, Some(21)).is_err());
+        env.step(&mut rng);
+
+        assert!(env.get_market().bid_asks() == [(0, Price::MAX), (0, 20)]);
+        assert!(env.get_trade_vols(0) == &vec![10]);
+        assert!(env.get_market().get_time() == step_size);
+    }
+
+    #[test]
+    fn test_fifo() {
+        let mut env: MarketEnv<2> = MarketEnv::new(0, [1, 1], 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+        env.set_fifo(true);
+
+        let ids: Vec<MarketOrderId> = (0..20)
+            .map(|i| {
+                env.place_order(i % 2, Side::Bid, 10, 101, Some(10))
+                    .unwrap()
+            })
+            .collect();
+
+        env.step(&mut rng);
+
+        for (i, id) in ids.into_iter().enumerate() {
+            assert!(env.order(id).arr_time == Nanos::try_from(i).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_trader_summary() {
+        let mut env: MarketEnv<2> = MarketEnv::new(0, [1, 1], 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(0, Side::Ask, 10, 0, Some(100)).unwrap();
+        env.place_order(1, Side::Bid, 10, 0, Some(50)).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(0, Side::Bid, 4, 1, None).unwrap();
+        env.place_order(1, Side::Ask, 10, 1, None).unwrap();
+        env.step(&mut rng);
+
+        let summary = env.trader_summary(0);
+
+        assert!(summary[0].position == -4);
+        assert!(summary[0].cash == 400);
+        assert!(summary[0].traded_vol == 4);
+        assert!(summary[0].pnl == 0.0);
+
+        assert!(summary[1].position == 10);
+        assert!(summary[1].cash == -500);
+        assert!(summary[1].traded_vol == 10);
+        assert!(summary[1].pnl == 0.0);
+
+        let summary = env.trader_summary(1);
+        assert!(summary[0].position == 4);
+        assert!(summary[1].position == -10);
+
+        assert!(env.trader_summary(2) == [TraderSummary::default(); 2]);
+    }
+
+    #[test]
+    fn test_vwap() {
+        let mut env: MarketEnv<2> = MarketEnv::new(0, [1, 1], 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        assert!(env.vwap(None) == [None, None]);
+
+        env.place_order(0, Side::Ask, 10, 0, Some(100)).unwrap();
+        env.place_order(0, Side::Ask, 10, 0, Some(104)).unwrap();
+        env.place_order(1, Side::Bid, 10, 0, Some(50)).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(0, Side::Bid, 15, 1, None).unwrap();
+        env.step(&mut rng);
+
+        env.place_order(1, Side::Ask, 10, 1, None).unwrap();
+        env.step(&mut rng);
+
+        assert!(env.vwap(None) == [Some((1000.0 + 520.0) / 15.0), Some(50.0)]);
+        assert!(env.vwap(Some((1000, 2000))) == [Some((1000.0 + 520.0) / 15.0), None]);
+        assert!(env.vwap(Some((2000, 3000))) == [None, Some(50.0)]);
+    }
 }
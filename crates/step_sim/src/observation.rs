@@ -0,0 +1,228 @@
+//! Construction of fixed-size observation vectors
+//!
+//! Assembles features of the current state of a
+//! simulation environment into a single vector,
+//! for example as the observation of a reinforcement
+//! learning agent.
+//!
+use crate::types::TraderId;
+use crate::Env;
+
+/// Observation features configuration
+pub struct ObservationConfig {
+    /// Include the touch prices and volumes
+    pub touch: bool,
+    /// Include the bid-ask spread
+    pub spread: bool,
+    /// Include the order imbalance
+    pub imbalance: bool,
+    /// Number of price levels (from the touch) to
+    /// include the volumes of
+    pub levels: usize,
+    /// Id of the trader to include the inventory
+    /// (net position) of, if any
+    pub inventory: Option<TraderId>,
+    /// Scale prices are divided by
+    pub price_scale: f64,
+    /// Scale volumes are divided by
+    pub vol_scale: f64,
+}
+
+impl Default for ObservationConfig {
+    fn default() -> Self {
+        Self {
+            touch: true,
+            spread: true,
+            imbalance: true,
+            levels: 0,
+            inventory: None,
+            price_scale: 1.0,
+            vol_scale: 1.0,
+        }
+    }
+}
+
+/// Observation vector builder
+///
+/// Builds a fixed-size observation vector from the
+/// current state of an [Env], containing (in order)
+/// the enabled features:
+///
+/// - Touch: bid price, ask price, bid touch
+///   volume and ask touch volume
+/// - Spread: ask price minus the bid price
+/// - Imbalance: bid minus ask total volume divided
+///   by the total volume (`0` if the book is empty)
+/// - Levels: the bid and ask volume at each price
+///   level, interleaved as `[bid_0, ask_0, bid_1, ...]`
+/// - Inventory: the net position of the trader
+///
+/// Prices (including the spread) are divided by the
+/// configured price scale, and volumes (including the
+/// inventory) by the volume scale. Note that the ask
+/// price is [Price::MAX](crate::types::Price) when there
+/// are no ask orders.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::{Env, ObservationBuilder, ObservationConfig};
+///
+/// let env: Env = Env::new(0, 1, 1_000, true);
+///
+/// let builder = ObservationBuilder::new(ObservationConfig {
+///     levels: 5,
+///     ..Default::default()
+/// });
+///
+/// let observation = builder.build(&env);
+/// assert!(observation.len() == builder.len());
+/// ```
+pub struct ObservationBuilder {
+    config: ObservationConfig,
+}
+
+impl ObservationBuilder {
+    /// Initialise an observation builder
+    ///
+    /// # Arguments
+    ///
+    /// - `config` - Observation features configuration
+    ///
+    pub fn new(config: ObservationConfig) -> Self {
+        assert!(
+            config.price_scale > 0.0 && config.vol_scale > 0.0,
+            "Scales must be positive"
+        );
+        Self { config }
+    }
+
+    /// Length of observation vectors
+    pub fn len(&self) -> usize {
+        4 * usize::from(self.config.touch)
+            + usize::from(self.config.spread)
+            + usize::from(self.config.imbalance)
+            + 2 * self.config.levels
+            + usize::from(self.config.inventory.is_some())
+    }
+
+    /// Check if observation vectors are empty (i.e.
+    /// no features are enabled)
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Build an observation from the current state of an environment
+    ///
+    /// # Arguments
+    ///
+    /// - `env` - Simulation environment
+    ///
+    pub fn build<const LEVELS: usize>(&self, env: &Env<LEVELS>) -> Vec<f64> {
+        assert!(
+            self.config.levels <= LEVELS,
+            "Number of levels cannot exceed the levels recorded by the environment"
+        );
+
+        let price_scale = self.config.price_scale;
+        let vol_scale = self.config.vol_scale;
+        let data = env.level_2_data();
+        let bid_price = f64::from(data.bid_price);
+        let ask_price = f64::from(data.ask_price);
+
+        let mut observation = Vec::with_capacity(self.len());
+
+        if self.config.touch {
+            observation.push(bid_price / price_scale);
+            observation.push(ask_price / price_scale);
+            observation.push(f64::from(data.bid_price_levels[0].0) / vol_scale);
+            observation.push(f64::from(data.ask_price_levels[0].0) / vol_scale);
+        }
+
+        if self.config.spread {
+            observation.push((ask_price - bid_price) / price_scale);
+        }
+
+        if self.config.imbalance {
+            let bid_vol = f64::from(data.bid_vol);
+            let ask_vol = f64::from(data.ask_vol);
+            let total = bid_vol + ask_vol;
+            observation.push(match total > 0.0 {
+                true => (bid_vol - ask_vol) / total,
+                false => 0.0,
+            });
+        }
+
+        for i in 0..self.config.levels {
+            observation.push(f64::from(data.bid_price_levels[i].0) / vol_scale);
+            observation.push(f64::from(data.ask_price_levels[i].0) / vol_scale);
+        }
+
+        if let Some(trader_id) = self.config.inventory {
+            let position = env.get_orderbook().trader_summary(trader_id).position;
+            observation.push(position as f64 / vol_scale);
+        }
+
+        observation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Side;
+    use rand_xoshiro::rand_core::SeedableRng;
+    use rand_xoshiro::Xoroshiro128StarStar as Rng;
+
+    #[test]
+    fn test_observation() {
+        let mut env: Env = Env::new(0, 1, 1000, true);
+        let mut rng = Rng::seed_from_u64(101);
+
+        env.place_order(Side::Bid, 10, 101, Some(50)).unwrap();
+        env.place_order(Side::Bid, 20, 101, Some(49)).unwrap();
+        env.place_order(Side::Ask, 30, 101, Some(60)).unwrap();
+        env.step(&mut rng);
+        env.place_order(Side::Ask, 5, 102, None).unwrap();
+        env.step(&mut rng);
+
+        let builder = ObservationBuilder::new(ObservationConfig {
+            levels: 2,
+            inventory: Some(102),
+            ..Default::default()
+        });
+
+        let observation = builder.build(&env);
+
+        assert!(builder.len() == 11);
+        assert!(observation.len() == 11);
+        assert!(
+            observation
+                == vec![
+                    50.0,
+                    60.0,
+                    5.0,
+                    30.0,
+                    10.0,
+                    -5.0 / 55.0,
+                    5.0,
+                    30.0,
+                    20.0,
+                    0.0,
+                    -5.0
+                ]
+        );
+
+        let builder = ObservationBuilder::new(ObservationConfig {
+            touch: false,
+            imbalance: false,
+            levels: 1,
+            price_scale: 10.0,
+            vol_scale: 5.0,
+            ..Default::default()
+        });
+
+        assert!(builder.len() == 3);
+        assert!(builder.build(&env) == vec![1.0, 1.0, 6.0]);
+    }
+}
@@ -3,8 +3,30 @@ use super::agents::{AgentSet, MarketAgentSet};
 use super::env::Env;
 use super::market_env::MarketEnv;
 use kdam::tqdm;
-use rand_xoshiro::rand_core::SeedableRng;
-use rand_xoshiro::Xoroshiro128StarStar;
+use rand_xoshiro::rand_core::{RngCore, SeedableRng};
+use rand_xoshiro::{SplitMix64, Xoroshiro128StarStar};
+use std::fmt;
+
+/// Simulation run errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunError {
+    /// No transactions were submitted by the agents
+    /// over the course of the run
+    NoTransactions { n_steps: u64 },
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::NoTransactions { n_steps } => write!(
+                f,
+                "No transactions were submitted over {} steps, check agents are \
+                updating the environment",
+                n_steps
+            ),
+        }
+    }
+}
 
 /// Run a simulation for a fixed number of steps
 ///
@@ -50,19 +72,399 @@ pub fn sim_runner<A: AgentSet>(
     n_steps: u64,
     show_progress: bool,
 ) {
+    sim_runner_seeded::<A, Xoroshiro128StarStar>(env, agents, seed, n_steps, show_progress);
+}
+
+/// Run a simulation with a seeded random generator type
+///
+/// Equivalent to [sim_runner] but with a choice of
+/// the type of the random generator, which is seeded
+/// from `seed`.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::{Env, sim_runner_seeded};
+/// use bourse_de::agents::AgentSet;
+/// use rand::RngCore;
+/// use rand_xoshiro::SplitMix64;
+///
+/// // Dummy agent-type
+/// struct Agents{}
+///
+/// impl AgentSet for Agents {
+///     fn update<R: RngCore>(
+///         &mut self, env: &mut Env, _rng: &mut R
+///     ) {}
+/// }
+///
+/// let mut env = bourse_de::Env::new(0, 1, 1_000, true);
+/// let mut agents = Agents{};
+///
+/// sim_runner_seeded::<_, SplitMix64>(&mut env, &mut agents, 101, 100, false)
+/// ```
+///
+/// # Arguments
+///
+/// - `env` - Simulation environment
+/// - `agents` - Agent(s) implementing the [AgentSet] trait
+/// - `seed` - Random seed
+/// - `n_steps` - Number of simulation steps
+/// - `show_progress` - Show progress bar
+///
+pub fn sim_runner_seeded<A: AgentSet, R: RngCore + SeedableRng>(
+    env: &mut Env,
+    agents: &mut A,
+    seed: u64,
+    n_steps: u64,
+    show_progress: bool,
+) {
+    let mut rng = R::seed_from_u64(seed);
+    sim_runner_with_rng(env, agents, &mut rng, n_steps, show_progress);
+}
+
+/// Run a simulation with a given random generator
+///
+/// Equivalent to [sim_runner] but draws random
+/// values from `rng` rather than a generator seeded
+/// internally, allowing the choice of generator and
+/// continuing a random stream across runs.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::{Env, sim_runner_with_rng};
+/// use bourse_de::agents::AgentSet;
+/// use rand::{RngCore, SeedableRng};
+/// use rand_xoshiro::SplitMix64;
+///
+/// // Dummy agent-type
+/// struct Agents{}
+///
+/// impl AgentSet for Agents {
+///     fn update<R: RngCore>(
+///         &mut self, env: &mut Env, _rng: &mut R
+///     ) {}
+/// }
+///
+/// let mut env = bourse_de::Env::new(0, 1, 1_000, true);
+/// let mut agents = Agents{};
+/// let mut rng = SplitMix64::seed_from_u64(101);
+///
+/// sim_runner_with_rng(&mut env, &mut agents, &mut rng, 100, false)
+/// ```
+///
+/// # Arguments
+///
+/// - `env` - Simulation environment
+/// - `agents` - Agent(s) implementing the [AgentSet] trait
+/// - `rng` - Random generator
+/// - `n_steps` - Number of simulation steps
+/// - `show_progress` - Show progress bar
+///
+pub fn sim_runner_with_rng<A: AgentSet, R: RngCore>(
+    env: &mut Env,
+    agents: &mut A,
+    rng: &mut R,
+    n_steps: u64,
+    show_progress: bool,
+) {
+    run_steps(env, agents, rng, n_steps, show_progress);
+}
+
+/// Run a simulation, checking that agents submitted transactions
+///
+/// Equivalent to [sim_runner], but counts the transactions
+/// (e.g. new orders and cancellations) submitted to the
+/// environment over the run. A run where no transactions
+/// were submitted is likely a misconfiguration, e.g. agents
+/// that are not wired up to the environment. In this case
+/// if `strict` is `true` an error is returned, otherwise
+/// a warning is printed to stderr.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::{Env, RunError, sim_runner_checked};
+/// use bourse_de::agents::AgentSet;
+/// use rand::RngCore;
+///
+/// // Dummy agent-type
+/// struct Agents{}
+///
+/// impl AgentSet for Agents {
+///     fn update<R: RngCore>(
+///         &mut self, env: &mut Env, _rng: &mut R
+///     ) {}
+/// }
+///
+/// let mut env = bourse_de::Env::new(0, 1, 1_000, true);
+/// let mut agents = Agents{};
+///
+/// let result = sim_runner_checked(&mut env, &mut agents, 101, 100, false, true);
+/// assert!(result == Err(RunError::NoTransactions { n_steps: 100 }));
+/// ```
+///
+/// # Arguments
+///
+/// - `env` - Simulation environment
+/// - `agents` - Agent(s) implementing the [AgentSet] trait
+/// - `seed` - Random seed
+/// - `n_steps` - Number of simulation steps
+/// - `show_progress` - Show progress bar
+/// - `strict` - Return an error (rather than printing a
+///   warning) if no transactions were submitted
+///
+pub fn sim_runner_checked<A: AgentSet>(
+    env: &mut Env,
+    agents: &mut A,
+    seed: u64,
+    n_steps: u64,
+    show_progress: bool,
+    strict: bool,
+) -> Result<(), RunError> {
     let mut rng = Xoroshiro128StarStar::seed_from_u64(seed);
+    let mut counted = CountingAgents {
+        agents,
+        n_transactions: 0,
+    };
+    run_steps(env, &mut counted, &mut rng, n_steps, show_progress);
+
+    if counted.n_transactions == 0 && n_steps > 0 {
+        let err = RunError::NoTransactions { n_steps };
+        match strict {
+            true => return Err(err),
+            false => eprintln!("Warning: {}", err),
+        }
+    }
+
+    Ok(())
+}
+
+/// Agent set wrapper counting submitted transactions
+struct CountingAgents<'a, A: AgentSet> {
+    agents: &'a mut A,
+    n_transactions: usize,
+}
+
+impl<A: AgentSet> AgentSet for CountingAgents<'_, A> {
+    fn update<R: RngCore>(&mut self, env: &mut Env, rng: &mut R) {
+        self.agents.update(env, rng);
+        self.n_transactions += env.num_transactions();
+    }
+}
+
+/// Run a simulation after an initial warm-up period
+///
+/// Runs the simulation for `warmup` steps, then
+/// clears the recorded market data histories (see
+/// [Env::clear_history]) but keeps the state of the
+/// order book, before running a further `n_steps`
+/// steps. Recorded data then starts from the state
+/// of the market at the end of the warm-up.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::{Env, sim_runner_equilibrate};
+/// use bourse_de::agents::AgentSet;
+/// use rand::RngCore;
+///
+/// // Dummy agent-type
+/// struct Agents{}
+///
+/// impl AgentSet for Agents {
+///     fn update<R: RngCore>(
+///         &mut self, env: &mut Env, _rng: &mut R
+///     ) {}
+/// }
+///
+/// let mut env = bourse_de::Env::new(0, 1, 1_000, true);
+/// let mut agents = Agents{};
+///
+/// // Warm-up for 50 steps then run for 100 steps
+/// sim_runner_equilibrate(&mut env, &mut agents, 101, 50, 100, false)
+/// ```
+///
+/// # Arguments
+///
+/// - `env` - Simulation environment
+/// - `agents` - Agent(s) implementing the [AgentSet] trait
+/// - `seed` - Random seed
+/// - `warmup` - Number of warm-up steps
+/// - `n_steps` - Number of recorded simulation steps
+/// - `show_progress` - Show progress bar
+///
+pub fn sim_runner_equilibrate<A: AgentSet>(
+    env: &mut Env,
+    agents: &mut A,
+    seed: u64,
+    warmup: u64,
+    n_steps: u64,
+    show_progress: bool,
+) {
+    sim_runner_equilibrate_seeded::<A, Xoroshiro128StarStar>(
+        env,
+        agents,
+        seed,
+        warmup,
+        n_steps,
+        show_progress,
+    );
+}
+
+/// Run a simulation after a warm-up with a seeded random generator type
+///
+/// Equivalent to [sim_runner_equilibrate] but with a
+/// choice of the type of the random generator, which
+/// is seeded from `seed`.
+///
+/// # Arguments
+///
+/// - `env` - Simulation environment
+/// - `agents` - Agent(s) implementing the [AgentSet] trait
+/// - `seed` - Random seed
+/// - `warmup` - Number of warm-up steps
+/// - `n_steps` - Number of recorded simulation steps
+/// - `show_progress` - Show progress bar
+///
+pub fn sim_runner_equilibrate_seeded<A: AgentSet, R: RngCore + SeedableRng>(
+    env: &mut Env,
+    agents: &mut A,
+    seed: u64,
+    warmup: u64,
+    n_steps: u64,
+    show_progress: bool,
+) {
+    let mut rng = R::seed_from_u64(seed);
+    run_steps(env, agents, &mut rng, warmup, show_progress);
+    env.clear_history();
+    run_steps(env, agents, &mut rng, n_steps, show_progress);
+}
 
+/// Run a simulation calling an observer after each step
+///
+/// Each step updates the state of the agents and
+/// then the environment, after which `on_step` is
+/// called with the index of the completed step and
+/// a reference to the environment, e.g. for logging
+/// or accumulating metrics over the course of the run.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::{Env, sim_runner_observed};
+/// use bourse_de::agents::AgentSet;
+/// use rand::RngCore;
+///
+/// // Dummy agent-type
+/// struct Agents{}
+///
+/// impl AgentSet for Agents {
+///     fn update<R: RngCore>(
+///         &mut self, env: &mut Env, _rng: &mut R
+///     ) {}
+/// }
+///
+/// let mut env = bourse_de::Env::new(0, 1, 1_000, true);
+/// let mut agents = Agents{};
+/// let mut volumes = Vec::new();
+///
+/// sim_runner_observed(
+///     &mut env,
+///     &mut agents,
+///     101,
+///     100,
+///     false,
+///     |_, env| volumes.push(env.get_orderbook().get_trade_vol()),
+/// );
+/// ```
+///
+/// # Arguments
+///
+/// - `env` - Simulation environment
+/// - `agents` - Agent(s) implementing the [AgentSet] trait
+/// - `seed` - Random seed
+/// - `n_steps` - Number of simulation steps
+/// - `show_progress` - Show progress bar
+/// - `on_step` - Function called after each step
+///
+pub fn sim_runner_observed<A: AgentSet, F: FnMut(u64, &Env)>(
+    env: &mut Env,
+    agents: &mut A,
+    seed: u64,
+    n_steps: u64,
+    show_progress: bool,
+    on_step: F,
+) {
+    sim_runner_observed_seeded::<A, Xoroshiro128StarStar, F>(
+        env,
+        agents,
+        seed,
+        n_steps,
+        show_progress,
+        on_step,
+    );
+}
+
+/// Run a simulation calling an observer with a seeded random generator type
+///
+/// Equivalent to [sim_runner_observed] but with a
+/// choice of the type of the random generator, which
+/// is seeded from `seed`.
+///
+/// # Arguments
+///
+/// - `env` - Simulation environment
+/// - `agents` - Agent(s) implementing the [AgentSet] trait
+/// - `seed` - Random seed
+/// - `n_steps` - Number of simulation steps
+/// - `show_progress` - Show progress bar
+/// - `on_step` - Function called after each step
+///
+pub fn sim_runner_observed_seeded<A: AgentSet, R: RngCore + SeedableRng, F: FnMut(u64, &Env)>(
+    env: &mut Env,
+    agents: &mut A,
+    seed: u64,
+    n_steps: u64,
+    show_progress: bool,
+    on_step: F,
+) {
+    let mut rng = R::seed_from_u64(seed);
+    run_observed_steps(env, agents, &mut rng, n_steps, show_progress, on_step);
+}
+
+fn run_steps<A: AgentSet, R: RngCore>(
+    env: &mut Env,
+    agents: &mut A,
+    rng: &mut R,
+    n_steps: u64,
+    show_progress: bool,
+) {
+    run_observed_steps(env, agents, rng, n_steps, show_progress, |_, _| {});
+}
+
+fn run_observed_steps<A: AgentSet, R: RngCore, F: FnMut(u64, &Env)>(
+    env: &mut Env,
+    agents: &mut A,
+    rng: &mut R,
+    n_steps: u64,
+    show_progress: bool,
+    mut on_step: F,
+) {
     match show_progress {
         true => {
-            for _ in tqdm!(0..n_steps) {
-                agents.update(env, &mut rng);
-                env.step(&mut rng);
+            for i in tqdm!(0..n_steps) {
+                agents.update(env, rng);
+                env.step(rng);
+                on_step(i, env);
             }
         }
         false => {
-            for _ in 0..n_steps {
-                agents.update(env, &mut rng);
-                env.step(&mut rng);
+            for i in 0..n_steps {
+                agents.update(env, rng);
+                env.step(rng);
+                on_step(i, env);
             }
         }
     }
@@ -113,19 +515,429 @@ pub fn market_sim_runner<A: MarketAgentSet, const M: usize, const N: usize>(
     show_progress: bool,
 ) {
     let mut rng = Xoroshiro128StarStar::seed_from_u64(seed);
+    market_sim_runner_with_rng(env, agents, &mut rng, n_steps, show_progress);
+}
 
+/// Run a multi-asset simulation with a given random generator
+///
+/// Equivalent to [market_sim_runner] but draws random
+/// values from `rng` rather than a generator seeded
+/// internally, allowing the choice of generator and
+/// continuing a random stream across runs.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::{MarketEnv, market_sim_runner_with_rng};
+/// use bourse_de::agents::MarketAgentSet;
+/// use rand::{RngCore, SeedableRng};
+/// use rand_xoshiro::Xoroshiro128StarStar;
+///
+/// // Dummy agent-type
+/// struct Agents{}
+///
+/// impl MarketAgentSet for Agents {
+///     fn update<R: RngCore, const M: usize, const N: usize>(
+///         &mut self, _env: &mut MarketEnv<M, N>, _rng: &mut R
+///     ) {}
+/// }
+///
+/// let mut env = bourse_de::MarketEnv::<2>::new(0, [1, 1], 1_000, true);
+/// let mut agents = Agents{};
+/// let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+///
+/// market_sim_runner_with_rng(&mut env, &mut agents, &mut rng, 100, false)
+/// ```
+///
+/// # Arguments
+///
+/// - `env` - Simulation environment
+/// - `agents` - Agent(s) implementing the [MarketAgentSet] trait
+/// - `rng` - Random generator
+/// - `n_steps` - Number of simulation steps
+/// - `show_progress` - Show progress bar
+///
+pub fn market_sim_runner_with_rng<A: MarketAgentSet, R: RngCore, const M: usize, const N: usize>(
+    env: &mut MarketEnv<M, N>,
+    agents: &mut A,
+    rng: &mut R,
+    n_steps: u64,
+    show_progress: bool,
+) {
     match show_progress {
         true => {
             for _ in tqdm!(0..n_steps) {
-                agents.update(env, &mut rng);
-                env.step(&mut rng);
+                agents.update(env, rng);
+                env.step(rng);
             }
         }
         false => {
             for _ in 0..n_steps {
-                agents.update(env, &mut rng);
-                env.step(&mut rng);
+                agents.update(env, rng);
+                env.step(rng);
             }
         }
     }
 }
+
+/// Generate a sequence of seeds from a single master seed
+///
+/// Deterministically generates `n` seeds using a
+/// SplitMix64 generator seeded from `master`, for use
+/// when running multiple simulations, avoiding
+/// hand-picked (and potentially correlated) seeds.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::seed_sequence;
+///
+/// let seeds = seed_sequence(101, 10);
+/// assert!(seeds.len() == 10);
+/// ```
+///
+/// # Arguments
+///
+/// - `master` - Master random seed
+/// - `n` - Number of seeds to generate
+///
+pub fn seed_sequence(master: u64, n: usize) -> Vec<u64> {
+    let mut rng = SplitMix64::seed_from_u64(master);
+    (0..n).map(|_| rng.next_u64()).collect()
+}
+
+/// Run a simulation for each of a set of seeds
+///
+/// For each seed a new environment and set of agents
+/// is initialised by `build`, the simulation run for
+/// `n_steps` steps, and a summary of the run
+/// extracted from the final state of the environment
+/// by `extract`.
+///
+/// # Examples
+///
+/// ```
+/// use bourse_de::{Env, ensemble_runner, seed_sequence};
+/// use bourse_de::agents::AgentSet;
+/// use rand::RngCore;
+///
+/// // Dummy agent-type
+/// struct Agents{}
+///
+/// impl AgentSet for Agents {
+///     fn update<R: RngCore>(
+///         &mut self, env: &mut Env, _rng: &mut R
+///     ) {}
+/// }
+///
+/// let trade_vols = ensemble_runner(
+///     || (Env::new(0, 1, 1_000, true), Agents{}),
+///     &seed_sequence(101, 10),
+///     100,
+///     |env| env.get_trade_vols().iter().sum::<u32>(),
+/// );
+///
+/// assert!(trade_vols.len() == 10);
+/// ```
+///
+/// # Arguments
+///
+/// - `build` - Function initialising an environment
+///   and agents for each run
+/// - `seeds` - Random seeds of each run
+/// - `n_steps` - Number of simulation steps
+/// - `extract` - Function extracting a summary
+///   from the environment at the end of a run
+///
+pub fn ensemble_runner<A, B, E, T>(
+    mut build: B,
+    seeds: &[u64],
+    n_steps: u64,
+    mut extract: E,
+) -> Vec<T>
+where
+    A: AgentSet,
+    B: FnMut() -> (Env, A),
+    E: FnMut(&Env) -> T,
+{
+    seeds
+        .iter()
+        .map(|seed| {
+            let (mut env, mut agents) = build();
+            sim_runner(&mut env, &mut agents, *seed, n_steps, false);
+            extract(&env)
+        })
+        .collect()
+}
+
+/// Run a simulation with perturbed seeds
+///
+/// Runs the simulation from `base_seed` and then from
+/// `n_perturbations` further seeds generated from it
+/// (see [seed_sequence]), returning the summary of
+/// each run (with the base run first). Used to gauge
+/// how sensitive the outcome of a simulation is to
+/// the random stream.
+///
+/// # Arguments
+///
+/// - `build` - Function initialising an environment
+///   and agents for each run
+/// - `base_seed` - Random seed of the base run
+/// - `n_perturbations` - Number of additional
+///   perturbed runs
+/// - `n_steps` - Number of simulation steps
+/// - `extract` - Function extracting a summary
+///   from the environment at the end of a run
+///
+pub fn sensitivity<A, B, E, T>(
+    build: B,
+    base_seed: u64,
+    n_perturbations: usize,
+    n_steps: u64,
+    extract: E,
+) -> Vec<T>
+where
+    A: AgentSet,
+    B: FnMut() -> (Env, A),
+    E: FnMut(&Env) -> T,
+{
+    let mut seeds = vec![base_seed];
+    seeds.extend(seed_sequence(base_seed, n_perturbations));
+    ensemble_runner(build, &seeds, n_steps, extract)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Side;
+    use rand::RngCore;
+
+    struct LiquidityAgent {
+        n: u32,
+    }
+
+    impl AgentSet for LiquidityAgent {
+        fn update<R: RngCore>(&mut self, env: &mut Env, _rng: &mut R) {
+            if self.n < 5 {
+                env.place_order(Side::Bid, 10, 0, Some(100 - self.n))
+                    .unwrap();
+                env.place_order(Side::Ask, 10, 0, Some(101 + self.n))
+                    .unwrap();
+            }
+            self.n += 1;
+        }
+    }
+
+    struct RandomMarketAgent {}
+
+    impl MarketAgentSet for RandomMarketAgent {
+        fn update<R: RngCore, const M: usize, const N: usize>(
+            &mut self,
+            env: &mut MarketEnv<M, N>,
+            rng: &mut R,
+        ) {
+            for asset in 0..M {
+                let side = match rng.next_u32() % 2 {
+                    0 => Side::Bid,
+                    _ => Side::Ask,
+                };
+                let price = 95 + rng.next_u32() % 10;
+                env.place_order(asset, side, 10, 0, Some(price)).unwrap();
+            }
+        }
+    }
+
+    struct RandomAgent {}
+
+    impl AgentSet for RandomAgent {
+        fn update<R: RngCore>(&mut self, env: &mut Env, rng: &mut R) {
+            let side = match rng.next_u32() % 2 {
+                0 => Side::Bid,
+                _ => Side::Ask,
+            };
+            let price = 95 + rng.next_u32() % 10;
+            env.place_order(side, 10, 0, Some(price)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_runner_with_rng() {
+        let mut env_a = Env::new(0, 1, 1_000, true);
+        sim_runner(&mut env_a, &mut RandomAgent {}, 101, 20, false);
+
+        let mut env_b = Env::new(0, 1, 1_000, true);
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+        sim_runner_with_rng(&mut env_b, &mut RandomAgent {}, &mut rng, 20, false);
+
+        assert!(!env_a.get_trades().is_empty());
+        assert!(env_a.get_trade_vols() == env_b.get_trade_vols());
+        assert!(env_a.get_prices() == env_b.get_prices());
+
+        let mut env_c = Env::new(0, 1, 1_000, true);
+        let mut rng = SplitMix64::seed_from_u64(101);
+        sim_runner_with_rng(&mut env_c, &mut RandomAgent {}, &mut rng, 20, false);
+
+        assert!(env_c.get_orders().len() == 20);
+        assert!(env_a.get_prices() != env_c.get_prices());
+    }
+
+    #[test]
+    fn test_seeded_runners() {
+        let mut env_a = Env::new(0, 1, 1_000, true);
+        sim_runner(&mut env_a, &mut RandomAgent {}, 101, 20, false);
+
+        let mut env_b = Env::new(0, 1, 1_000, true);
+        sim_runner_seeded::<_, Xoroshiro128StarStar>(
+            &mut env_b,
+            &mut RandomAgent {},
+            101,
+            20,
+            false,
+        );
+
+        assert!(env_a.get_prices() == env_b.get_prices());
+
+        let mut env_c = Env::new(0, 1, 1_000, true);
+        sim_runner_seeded::<_, SplitMix64>(&mut env_c, &mut RandomAgent {}, 101, 20, false);
+
+        let mut env_d = Env::new(0, 1, 1_000, true);
+        let mut rng = SplitMix64::seed_from_u64(101);
+        sim_runner_with_rng(&mut env_d, &mut RandomAgent {}, &mut rng, 20, false);
+
+        assert!(env_c.get_prices() == env_d.get_prices());
+        assert!(env_a.get_prices() != env_c.get_prices());
+
+        let mut env_e = Env::new(0, 1, 1_000, true);
+        sim_runner_equilibrate_seeded::<_, SplitMix64>(
+            &mut env_e,
+            &mut RandomAgent {},
+            101,
+            5,
+            15,
+            false,
+        );
+
+        assert!(env_e.get_prices().0[..] == env_c.get_prices().0[5..]);
+
+        let mut env_f = Env::new(0, 1, 1_000, true);
+        let mut steps = Vec::new();
+        sim_runner_observed_seeded::<_, SplitMix64, _>(
+            &mut env_f,
+            &mut RandomAgent {},
+            101,
+            20,
+            false,
+            |i, _| steps.push(i),
+        );
+
+        assert!(steps == (0..20).collect::<Vec<u64>>());
+        assert!(env_f.get_prices() == env_c.get_prices());
+    }
+
+    #[test]
+    fn test_market_runner_with_rng() {
+        let mut env_a = MarketEnv::<2>::new(0, [1, 1], 1_000, true);
+        market_sim_runner(&mut env_a, &mut RandomMarketAgent {}, 101, 20, false);
+
+        let mut env_b = MarketEnv::<2>::new(0, [1, 1], 1_000, true);
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+        market_sim_runner_with_rng(&mut env_b, &mut RandomMarketAgent {}, &mut rng, 20, false);
+
+        for i in 0..2 {
+            assert!(!env_a.get_trades(i).is_empty());
+            assert!(env_a.get_trades(i).len() == env_b.get_trades(i).len());
+            assert!(env_a.get_trade_vols(i) == env_b.get_trade_vols(i));
+            assert!(env_a.get_prices(i) == env_b.get_prices(i));
+            assert!(env_a.get_volumes(i) == env_b.get_volumes(i));
+        }
+    }
+
+    struct NoOpAgent {}
+
+    impl AgentSet for NoOpAgent {
+        fn update<R: RngCore>(&mut self, _env: &mut Env, _rng: &mut R) {}
+    }
+
+    #[test]
+    fn test_runner_checked() {
+        let mut env = Env::new(0, 1, 1_000, true);
+        let result = sim_runner_checked(&mut env, &mut NoOpAgent {}, 101, 10, false, true);
+
+        assert!(result == Err(RunError::NoTransactions { n_steps: 10 }));
+        assert!(env.get_orderbook().get_time() == 10_000);
+
+        let mut env = Env::new(0, 1, 1_000, true);
+        let result = sim_runner_checked(&mut env, &mut NoOpAgent {}, 101, 10, false, false);
+
+        assert!(result.is_ok());
+
+        let mut env = Env::new(0, 1, 1_000, true);
+        let result = sim_runner_checked(&mut env, &mut RandomAgent {}, 101, 10, false, true);
+
+        assert!(result.is_ok());
+        assert!(env.get_orders().len() == 10);
+    }
+
+    #[test]
+    fn test_seed_sequence() {
+        let seeds = seed_sequence(101, 20);
+
+        assert!(seeds.len() == 20);
+        assert!(seeds == seed_sequence(101, 20));
+        assert!(seeds[..10] == seed_sequence(101, 10));
+        assert!(seeds != seed_sequence(102, 20));
+
+        let mut unique = seeds.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert!(unique.len() == 20);
+    }
+
+    #[test]
+    fn test_observed() {
+        let mut env = Env::new(0, 1, 1_000, true);
+        let mut agents = LiquidityAgent { n: 0 };
+        let mut steps = Vec::new();
+        let mut mid_prices = Vec::new();
+
+        sim_runner_observed(&mut env, &mut agents, 101, 10, false, |i, env| {
+            steps.push(i);
+            mid_prices.push(env.get_orderbook().mid_price());
+        });
+
+        assert!(steps == (0..10).collect::<Vec<u64>>());
+        assert!(mid_prices == env.get_mid_prices());
+    }
+
+    #[test]
+    fn test_equilibrate() {
+        let mut env = Env::new(0, 1, 1_000, true);
+        let mut agents = LiquidityAgent { n: 0 };
+
+        sim_runner_equilibrate(&mut env, &mut agents, 101, 5, 20, false);
+
+        assert!(env.get_prices().0.len() == 20);
+        assert!(env.get_trade_vols().len() == 20);
+        assert!(env.get_orderbook().get_time() == 25_000);
+        assert!(env.level_2_data().bid_vol == 50);
+        assert!(env.level_2_data().ask_vol == 50);
+        assert!(env.get_prices().0.iter().all(|x| *x == 100));
+        assert!(env.get_prices().1.iter().all(|x| *x == 101));
+    }
+
+    #[test]
+    fn test_sensitivity() {
+        let build = || (Env::new(0, 1, 1_000, true), LiquidityAgent { n: 0 });
+        let extract = |env: &Env| (env.get_mid_prices(), env.level_2_data().bid_vol);
+
+        let summaries = sensitivity(build, 101, 5, 10, extract);
+
+        assert!(summaries.len() == 6);
+        assert!(summaries.iter().all(|x| *x == summaries[0]));
+        assert!(summaries[0].1 == 50);
+
+        let summaries = ensemble_runner(build, &seed_sequence(101, 3), 10, extract);
+        assert!(summaries.len() == 3);
+    }
+}
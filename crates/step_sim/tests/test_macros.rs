@@ -1,10 +1,17 @@
 use bourse_book::types::{Price, Side};
-use bourse_de::agents::{Agent, AgentSet, MarketAgent, MarketAgentSet};
+use bourse_de::agents::{
+    Agent, AgentSet, MarketAgent, MarketAgentSet, MomentumMarketAgent, MomentumParams,
+    NoiseAgentParams, NoiseMarketAgent, PriceReference, RandomMarketAgents,
+};
+use bourse_de::rayon;
 use bourse_de::types::AssetIdx;
-use bourse_de::{Env, MarketEnv};
+use bourse_de::{Env, MarketEnv, SharedMarketEnv};
 use rand::RngCore;
 use rand_xoshiro::rand_core::SeedableRng;
 use rand_xoshiro::Xoroshiro128StarStar;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 struct TestAgent {
     side: Side,
@@ -112,3 +119,215 @@ fn test_market_agent_macro() {
     assert!(env.get_market().bid_vols() == [20, 20]);
     assert!(env.get_market().bid_asks() == [(20, 40), (60, 80)]);
 }
+
+#[test]
+fn test_market_agent_par_update() {
+    #[derive(MarketAgentSet)]
+    #[market_agent_set(parallel)]
+    struct TestAgents {
+        pub a: MarketTestAgent,
+        pub b: MarketTestAgent,
+        pub c: MarketTestAgent,
+        pub d: MarketTestAgent,
+    }
+
+    let new_agents = || TestAgents {
+        a: MarketTestAgent::new(0, Side::Bid, 20),
+        b: MarketTestAgent::new(1, Side::Ask, 40),
+        c: MarketTestAgent::new(2, Side::Bid, 60),
+        d: MarketTestAgent::new(3, Side::Ask, 80),
+    };
+
+    let mut seq_env = MarketEnv::<4>::new(0, [1, 1, 1, 1], 1000, true);
+    let mut par_env = MarketEnv::<4>::new(0, [1, 1, 1, 1], 1000, true);
+    let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+    let mut seq_agents = new_agents();
+    let mut par_agents = new_agents();
+
+    for _ in 0..5 {
+        seq_agents.update(&mut seq_env, &mut rng);
+        par_agents.par_update(&mut par_env, &mut rng);
+
+        for asset in 0..4 {
+            let seq_orders: Vec<_> = seq_env
+                .get_orders(asset)
+                .iter()
+                .map(|x| (x.order_id, x.side, x.price, x.vol, x.status))
+                .collect();
+            let par_orders: Vec<_> = par_env
+                .get_orders(asset)
+                .iter()
+                .map(|x| (x.order_id, x.side, x.price, x.vol, x.status))
+                .collect();
+            assert!(seq_orders == par_orders);
+        }
+
+        seq_env.step(&mut rng);
+        par_env.step(&mut rng);
+    }
+
+    assert!(par_env.get_market().bid_vols() == [50, 0, 50, 0]);
+    assert!(par_env.get_market().ask_vols() == [0, 50, 0, 50]);
+    assert!(seq_env.get_market().bid_asks() == par_env.get_market().bid_asks());
+}
+
+struct BarrierAgent {
+    asset: AssetIdx,
+    arrived: Arc<AtomicUsize>,
+    overlapped: bool,
+}
+
+impl MarketAgent for BarrierAgent {
+    fn update<R: RngCore, const M: usize, const N: usize>(
+        &mut self,
+        env: &mut MarketEnv<M, N>,
+        _rng: &mut R,
+    ) {
+        env.place_order(self.asset, Side::Bid, 10, 101, Some(20))
+            .unwrap();
+    }
+
+    fn par_update<R: RngCore, const M: usize, const N: usize>(
+        &mut self,
+        env: &SharedMarketEnv<M, N>,
+        _rng: &mut R,
+    ) {
+        // Wait (without holding the environment) until
+        // the other agent has also started its update
+        self.arrived.fetch_add(1, Ordering::SeqCst);
+        let start = Instant::now();
+        while self.arrived.load(Ordering::SeqCst) < 2 && start.elapsed() < Duration::from_secs(5) {
+            std::thread::yield_now();
+        }
+        self.overlapped = self.arrived.load(Ordering::SeqCst) == 2;
+
+        env.with(|env| {
+            env.place_order(self.asset, Side::Bid, 10, 101, Some(20))
+                .unwrap()
+        });
+    }
+}
+
+#[test]
+fn test_market_agent_par_update_overlaps() {
+    #[derive(MarketAgentSet)]
+    #[market_agent_set(parallel)]
+    struct TestAgents {
+        pub a: BarrierAgent,
+        pub b: BarrierAgent,
+    }
+
+    let arrived = Arc::new(AtomicUsize::new(0));
+    let new_agent = |asset| BarrierAgent {
+        asset,
+        arrived: arrived.clone(),
+        overlapped: false,
+    };
+    let mut agents = TestAgents {
+        a: new_agent(0),
+        b: new_agent(1),
+    };
+
+    let mut env = MarketEnv::<2>::new(0, [1, 1], 1000, true);
+    let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+
+    // Ensure the agents can run on separate threads
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .build()
+        .unwrap();
+    pool.install(|| agents.par_update(&mut env, &mut rng));
+
+    assert!(agents.a.overlapped && agents.b.overlapped);
+    assert!(env.get_market().bid_vols() == [0, 0]);
+    env.step(&mut rng);
+    assert!(env.get_market().bid_vols() == [10, 10]);
+}
+
+fn noise_params() -> NoiseAgentParams {
+    NoiseAgentParams {
+        tick_size: 1,
+        p_limit: 0.5,
+        p_market: 0.2,
+        p_cancel: 0.1,
+        trade_vol: 10,
+        price_dist_mu: 0.0,
+        price_dist_sigma: 1.0,
+        price_reference: PriceReference::Mid,
+    }
+}
+
+/// Check parallel updates of an agent match sequential updates
+///
+/// Background noise agents (with ids from 1000) move
+/// the price while the agents are updated.
+fn check_par_update<A: MarketAgent, F: Fn() -> A>(new_agent: F) {
+    let mut envs = [
+        MarketEnv::<1>::new(0, [1], 1000, true),
+        MarketEnv::<1>::new(0, [1], 1000, true),
+    ];
+    let mut agents = [new_agent(), new_agent()];
+    let mut noise = [
+        NoiseMarketAgent::new(0, 1000, 10, noise_params()),
+        NoiseMarketAgent::new(0, 1000, 10, noise_params()),
+    ];
+    let mut rngs = [
+        Xoroshiro128StarStar::seed_from_u64(101),
+        Xoroshiro128StarStar::seed_from_u64(101),
+    ];
+
+    for env in envs.iter_mut() {
+        env.place_order(0, Side::Bid, 100, 1000, Some(95)).unwrap();
+        env.place_order(0, Side::Ask, 100, 1000, Some(105)).unwrap();
+    }
+
+    for _ in 0..20 {
+        for i in 0..2 {
+            noise[i].update(&mut envs[i], &mut rngs[i]);
+        }
+        agents[0].update(&mut envs[0], &mut rngs[0]);
+        agents[1].par_update(&SharedMarketEnv::new(&mut envs[1]), &mut rngs[1]);
+
+        for i in 0..2 {
+            envs[i].step(&mut rngs[i]);
+        }
+
+        let orders: Vec<Vec<_>> = envs
+            .iter()
+            .map(|env| {
+                env.get_orders(0)
+                    .iter()
+                    .map(|x| (x.side, x.price, x.vol, x.trader_id, x.status))
+                    .collect()
+            })
+            .collect();
+        assert!(orders[0] == orders[1]);
+    }
+
+    // Check the agents placed orders
+    assert!(envs[0].get_orders(0).iter().any(|x| x.trader_id < 1000));
+}
+
+#[test]
+fn test_built_in_par_update() {
+    check_par_update(|| NoiseMarketAgent::new(0, 1, 10, noise_params()));
+    check_par_update(|| {
+        MomentumMarketAgent::new(
+            1,
+            10,
+            0,
+            MomentumParams {
+                tick_size: 1,
+                p_cancel: 0.1,
+                trade_vol: 10,
+                decay: 1.0,
+                demand: 5.0,
+                scale: 0.5,
+                order_ratio: 1.0,
+                price_dist_mu: 0.0,
+                price_dist_sigma: 1.0,
+            },
+        )
+    });
+    check_par_update(|| RandomMarketAgents::new(0, 10, (90, 110), (10, 20), 1, 0.8));
+}
@@ -75,6 +75,9 @@ python_apigen_default_groups = [
     (r"class:.*BaseNumpyAgent.*", "base_numpy_agent_class"),
     (r"method:.*BaseNumpyAgent.*", "BaseNumpyAgent Methods"),
     (r"attribute:.*BaseNumpyAgent.*", "BaseNumpyAgent Attributes"),
+    (r"class:.*AgentConfig.*", "native_agents"),
+    (r"method:.*AgentConfig.*", "native_agents"),
+    (r"function:.*run_agents.*", "native_agents"),
     (r"class:.*RandomAgent.*", "random_agent_class"),
     (r"method:.*RandomAgent.*", "RandomAgent Methods"),
     (r"attribute:.*RandomAgent.*", "RandomAgent Attributes"),
@@ -87,6 +90,8 @@ python_apigen_default_groups = [
     (r"class:.*StepEnv.*", "step_env_class"),
     (r"method:.*StepEnv.*", "StepEnv Methods"),
     (r"attribute:.*StepEnv.*", "StepEnv Attributes"),
+    (r"class:.*ObservationBuilder.*", "step_env_class"),
+    (r"method:.*ObservationBuilder.*", "ObservationBuilder Methods"),
     (r"class:.*StepEnvNumpy.*", "step_env_numpy_class"),
     (r"method:.*StepEnvNumpy.*", "StepEnvNumpy Methods"),
     (r"attribute:.*StepEnvNumpy.*", "StepEnvNumpy Attributes"),
@@ -0,0 +1,451 @@
+use super::types::{cast_market_data, cast_trade, PySimResults};
+use bourse_book::types::{Nanos, Price, TraderId, Vol};
+use bourse_de::agents::{
+    Agent, AgentSet, MomentumAgent, MomentumParams, NoiseAgent, NoiseAgentParams, PriceReference,
+    RandomAgents,
+};
+use bourse_de::{seed_sequence, sim_runner, Env};
+use pyo3::prelude::*;
+use rand_xoshiro::rand_core::{RngCore, SeedableRng};
+use rand_xoshiro::Xoroshiro128StarStar;
+
+/// Noise agent configuration
+///
+/// Configuration of a group of native (Rust) agents
+/// that randomly place and cancel limit and market
+/// orders, for use with :py:func:`bourse.core.run_agents`.
+///
+/// Each step:
+///
+/// - Any currently live orders are randomly selected for
+///   cancellation
+/// - Each agent randomly chooses to place a limit order,
+///   on a random side with a price above/below the
+///   mid-price by a distance sampled from a log-normal
+///   distribution
+/// - Each agent randomly chooses to place a market order
+///   on a random side
+///
+/// Parameters
+/// ----------
+/// agent_id_start: int
+///     Starting trader id of agents in this group.
+/// n_agents: int
+///     Number of agents in the group.
+/// p_limit: float
+///     Probability each agent places a limit order each step.
+/// p_market: float
+///     Probability each agent places a market order each step.
+/// p_cancel: float
+///     Probability of cancelling a live order.
+/// trade_vol: int
+///     Size of orders placed by the agents.
+/// price_dist_mu: float
+///     Mean of the log-normal distribution limit-order
+///     prices are sampled from.
+/// price_dist_sigma: float
+///     Width of the log-normal distribution limit-order
+///     prices are sampled from.
+/// seed: int, optional
+///     Random seed of the group, if not provided a seed
+///     is derived from the simulation seed.
+///
+#[pyclass]
+#[derive(Clone)]
+pub struct NoiseAgentConfig {
+    agent_id_start: TraderId,
+    n_agents: u16,
+    p_limit: f32,
+    p_market: f32,
+    p_cancel: f32,
+    trade_vol: Vol,
+    price_dist_mu: f64,
+    price_dist_sigma: f64,
+    seed: Option<u64>,
+}
+
+#[pymethods]
+impl NoiseAgentConfig {
+    #[new]
+    #[pyo3(signature = (
+        agent_id_start,
+        n_agents,
+        p_limit,
+        p_market,
+        p_cancel,
+        trade_vol,
+        price_dist_mu=0.0,
+        price_dist_sigma=1.0,
+        seed=None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        agent_id_start: TraderId,
+        n_agents: u16,
+        p_limit: f32,
+        p_market: f32,
+        p_cancel: f32,
+        trade_vol: Vol,
+        price_dist_mu: f64,
+        price_dist_sigma: f64,
+        seed: Option<u64>,
+    ) -> Self {
+        Self {
+            agent_id_start,
+            n_agents,
+            p_limit,
+            p_market,
+            p_cancel,
+            trade_vol,
+            price_dist_mu,
+            price_dist_sigma,
+            seed,
+        }
+    }
+}
+
+impl NoiseAgentConfig {
+    fn build(&self, tick_size: Price) -> NoiseAgent {
+        let params = NoiseAgentParams {
+            tick_size,
+            p_limit: self.p_limit,
+            p_market: self.p_market,
+            p_cancel: self.p_cancel,
+            trade_vol: self.trade_vol,
+            price_dist_mu: self.price_dist_mu,
+            price_dist_sigma: self.price_dist_sigma,
+            price_reference: PriceReference::Mid,
+        };
+        NoiseAgent::new(self.agent_id_start, self.n_agents, params)
+    }
+}
+
+/// Momentum agent configuration
+///
+/// Configuration of a group of native (Rust) agents
+/// that track trends in the mid-price, for use with
+/// :py:func:`bourse.core.run_agents`.
+///
+/// Agents track the momentum of the price, and place
+/// limit and market orders in the direction of the
+/// momentum with a probability scaled by its magnitude.
+///
+/// Parameters
+/// ----------
+/// agent_id_start: int
+///     Starting trader id of agents in this group.
+/// n_agents: int
+///     Number of agents in the group.
+/// p_cancel: float
+///     Probability of cancelling a live order.
+/// trade_vol: int
+///     Size of orders placed by the agents.
+/// decay: float
+///     Momentum decay factor.
+/// demand: float
+///     Agent demand.
+/// scale: float
+///     Momentum tanh scale.
+/// order_ratio: float
+///     Ratio of limit to market order probabilities.
+/// price_dist_mu: float
+///     Mean of the log-normal distribution limit-order
+///     prices are sampled from.
+/// price_dist_sigma: float
+///     Width of the log-normal distribution limit-order
+///     prices are sampled from.
+/// seed: int, optional
+///     Random seed of the group, if not provided a seed
+///     is derived from the simulation seed.
+///
+#[pyclass]
+#[derive(Clone)]
+pub struct MomentumAgentConfig {
+    agent_id_start: TraderId,
+    n_agents: u16,
+    p_cancel: f32,
+    trade_vol: Vol,
+    decay: f64,
+    demand: f64,
+    scale: f64,
+    order_ratio: f64,
+    price_dist_mu: f64,
+    price_dist_sigma: f64,
+    seed: Option<u64>,
+}
+
+#[pymethods]
+impl MomentumAgentConfig {
+    #[new]
+    #[pyo3(signature = (
+        agent_id_start,
+        n_agents,
+        p_cancel,
+        trade_vol,
+        decay,
+        demand,
+        scale,
+        order_ratio,
+        price_dist_mu=0.0,
+        price_dist_sigma=1.0,
+        seed=None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        agent_id_start: TraderId,
+        n_agents: u16,
+        p_cancel: f32,
+        trade_vol: Vol,
+        decay: f64,
+        demand: f64,
+        scale: f64,
+        order_ratio: f64,
+        price_dist_mu: f64,
+        price_dist_sigma: f64,
+        seed: Option<u64>,
+    ) -> Self {
+        Self {
+            agent_id_start,
+            n_agents,
+            p_cancel,
+            trade_vol,
+            decay,
+            demand,
+            scale,
+            order_ratio,
+            price_dist_mu,
+            price_dist_sigma,
+            seed,
+        }
+    }
+}
+
+impl MomentumAgentConfig {
+    fn build(&self, tick_size: Price) -> MomentumAgent {
+        let params = MomentumParams {
+            tick_size,
+            p_cancel: self.p_cancel,
+            trade_vol: self.trade_vol,
+            decay: self.decay,
+            demand: self.demand,
+            scale: self.scale,
+            order_ratio: self.order_ratio,
+            price_dist_mu: self.price_dist_mu,
+            price_dist_sigma: self.price_dist_sigma,
+        };
+        MomentumAgent::new(self.agent_id_start, self.n_agents, params)
+    }
+}
+
+/// Random agent configuration
+///
+/// Configuration of a group of native (Rust) agents
+/// that place orders with uniformly sampled side,
+/// price and volume, for use with
+/// :py:func:`bourse.core.run_agents`. Agents in the
+/// group are assigned trader ids ``0..n_agents``.
+///
+/// Parameters
+/// ----------
+/// n_agents: int
+///     Number of agents in the group.
+/// tick_range: tuple[int, int]
+///     Range of ticks to place orders over.
+/// vol_range: tuple[int, int]
+///     Range of order volumes to sample from.
+/// activity_rate: float
+///     Probability each agent is active each step.
+/// seed: int, optional
+///     Random seed of the group, if not provided a seed
+///     is derived from the simulation seed.
+///
+#[pyclass]
+#[derive(Clone)]
+pub struct RandomAgentConfig {
+    n_agents: usize,
+    tick_range: (Price, Price),
+    vol_range: (Vol, Vol),
+    activity_rate: f32,
+    seed: Option<u64>,
+}
+
+#[pymethods]
+impl RandomAgentConfig {
+    #[new]
+    #[pyo3(signature = (n_agents, tick_range, vol_range, activity_rate, seed=None))]
+    pub fn new(
+        n_agents: usize,
+        tick_range: (Price, Price),
+        vol_range: (Vol, Vol),
+        activity_rate: f32,
+        seed: Option<u64>,
+    ) -> Self {
+        Self {
+            n_agents,
+            tick_range,
+            vol_range,
+            activity_rate,
+            seed,
+        }
+    }
+}
+
+impl RandomAgentConfig {
+    fn build(&self, tick_size: Price) -> RandomAgents {
+        RandomAgents::new(
+            self.n_agents,
+            self.tick_range,
+            self.vol_range,
+            tick_size,
+            self.activity_rate,
+        )
+    }
+}
+
+/// Initialise the random generator of an agent group
+///
+/// Uses the seed of the group if one was
+/// configured, otherwise the derived seed.
+fn group_rng(seed: Option<u64>, derived_seed: u64) -> Xoroshiro128StarStar {
+    Xoroshiro128StarStar::seed_from_u64(seed.unwrap_or(derived_seed))
+}
+
+/// Collection of configured native agents
+///
+/// Each agent group is updated with its own
+/// random generator, so the behaviour of a group
+/// is independent of the other groups.
+struct NativeAgents {
+    noise: Vec<(NoiseAgent, Xoroshiro128StarStar)>,
+    momentum: Vec<(MomentumAgent, Xoroshiro128StarStar)>,
+    random: Vec<(RandomAgents, Xoroshiro128StarStar)>,
+}
+
+impl AgentSet for NativeAgents {
+    fn update<R: RngCore>(&mut self, env: &mut Env, _rng: &mut R) {
+        for (agent, rng) in self.noise.iter_mut() {
+            agent.update(env, rng);
+        }
+        for (agent, rng) in self.momentum.iter_mut() {
+            agent.update(env, rng);
+        }
+        for (agent, rng) in self.random.iter_mut() {
+            agent.update(env, rng);
+        }
+    }
+}
+
+/// run_agents(seed: int, start_time: int, tick_size: int, step_size: int, n_steps: int, noise_agents: list[NoiseAgentConfig] = [], momentum_agents: list[MomentumAgentConfig] = [], random_agents: list[RandomAgentConfig] = [], show_progress: bool = False) -> tuple[dict[str, numpy.ndarray], list[tuple]]
+///
+/// Run a simulation with native agents
+///
+/// Runs a discrete event simulation with the built-in
+/// Rust agents, with the full simulation loop run
+/// natively. Each step the agents are updated (in the
+/// order noise, momentum then random agents) and then
+/// the environment is updated.
+///
+/// Each agent group is updated with its own random
+/// generator, seeded with the seed of the group
+/// configuration if provided, or otherwise with a seed
+/// derived from the simulation seed.
+///
+/// Examples
+/// --------
+///
+/// .. testcode:: run_agents_docstring
+///
+///    import bourse
+///
+///    noise_agents = bourse.core.NoiseAgentConfig(
+///        0, 20, 0.5, 0.1, 0.1, 10, price_dist_sigma=10.0
+///    )
+///
+///    market_data, trades = bourse.core.run_agents(
+///        101, 0, 1, 100_000, 50, noise_agents=[noise_agents]
+///    )
+///
+/// Parameters
+/// ----------
+/// seed: int
+///     Random seed of the simulation, used to shuffle
+///     instructions and derive agent group seeds.
+/// start_time: int
+///     Simulation start time.
+/// tick_size: int
+///     Market tick size.
+/// step_size: int
+///     Simulated time between steps.
+/// n_steps: int
+///     Number of simulation steps.
+/// noise_agents: list[NoiseAgentConfig], optional
+///     Noise agent groups.
+/// momentum_agents: list[MomentumAgentConfig], optional
+///     Momentum agent groups.
+/// random_agents: list[RandomAgentConfig], optional
+///     Random agent groups.
+/// show_progress: bool, optional
+///     If ``True`` a progress bar will be displayed.
+///
+/// Returns
+/// -------
+/// tuple[dict[str, np.ndarray], list[tuple]]
+///     Level 2 market data histories, in the same format
+///     as :py:meth:`bourse.core.StepEnv.get_market_data`,
+///     and trade records, in the same format as
+///     :py:meth:`bourse.core.StepEnv.get_trades`.
+///
+#[pyfunction]
+#[pyo3(signature = (
+    seed,
+    start_time,
+    tick_size,
+    step_size,
+    n_steps,
+    noise_agents=Vec::new(),
+    momentum_agents=Vec::new(),
+    random_agents=Vec::new(),
+    show_progress=false
+))]
+#[allow(clippy::too_many_arguments)]
+pub fn run_agents<'a>(
+    py: Python<'a>,
+    seed: u64,
+    start_time: Nanos,
+    tick_size: Price,
+    step_size: Nanos,
+    n_steps: u64,
+    noise_agents: Vec<NoiseAgentConfig>,
+    momentum_agents: Vec<MomentumAgentConfig>,
+    random_agents: Vec<RandomAgentConfig>,
+    show_progress: bool,
+) -> PyResult<PySimResults<'a>> {
+    let mut env = Env::new(start_time, tick_size, step_size, true);
+
+    let n_groups = noise_agents.len() + momentum_agents.len() + random_agents.len();
+    let mut seeds = seed_sequence(seed, n_groups).into_iter();
+
+    let mut agents = NativeAgents {
+        noise: noise_agents
+            .iter()
+            .zip(seeds.by_ref())
+            .map(|(x, s)| (x.build(tick_size), group_rng(x.seed, s)))
+            .collect(),
+        momentum: momentum_agents
+            .iter()
+            .zip(seeds.by_ref())
+            .map(|(x, s)| (x.build(tick_size), group_rng(x.seed, s)))
+            .collect(),
+        random: random_agents
+            .iter()
+            .zip(seeds.by_ref())
+            .map(|(x, s)| (x.build(tick_size), group_rng(x.seed, s)))
+            .collect(),
+    };
+
+    py.allow_threads(|| sim_runner(&mut env, &mut agents, seed, n_steps, show_progress));
+
+    let trades = env.get_trades().iter().map(cast_trade).collect();
+
+    Ok((cast_market_data(py, &env), trades))
+}
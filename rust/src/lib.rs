@@ -1,3 +1,5 @@
+mod agents;
+mod observation;
 mod order_book;
 mod step_sim;
 mod step_sim_numpy;
@@ -10,6 +12,11 @@ fn core(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<order_book::OrderBook>()?;
     m.add_class::<step_sim::StepEnv>()?;
     m.add_class::<step_sim_numpy::StepEnvNumpy>()?;
+    m.add_class::<agents::NoiseAgentConfig>()?;
+    m.add_class::<agents::MomentumAgentConfig>()?;
+    m.add_class::<agents::RandomAgentConfig>()?;
+    m.add_class::<observation::ObservationBuilder>()?;
     m.add_function(wrap_pyfunction!(order_book::order_book_from_json, m)?)?;
+    m.add_function(wrap_pyfunction!(agents::run_agents, m)?)?;
     Ok(())
 }
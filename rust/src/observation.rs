@@ -0,0 +1,129 @@
+use super::step_sim::StepEnv;
+use bourse_book::types::TraderId;
+use bourse_de::{ObservationBuilder as BaseObservationBuilder, ObservationConfig};
+use numpy::{IntoPyArray, PyArray1};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Observation vector builder
+///
+/// Builds a fixed-size observation array from the
+/// current state of a :py:class:`bourse.core.StepEnv`,
+/// for example as the observation of a reinforcement
+/// learning agent. The array contains (in order) the
+/// enabled features:
+///
+/// - Touch: bid price, ask price, bid touch volume
+///   and ask touch volume
+/// - Spread: ask price minus the bid price
+/// - Imbalance: bid minus ask total volume divided
+///   by the total volume (``0`` if the book is empty)
+/// - Levels: the bid and ask volume at each price
+///   level, interleaved as ``[bid_0, ask_0, bid_1, ...]``
+/// - Inventory: the net position of the trader
+///
+/// Prices (including the spread) are divided by
+/// ``price_scale``, and volumes (including the
+/// inventory) by ``vol_scale``.
+///
+/// Examples
+/// --------
+///
+/// .. testcode:: observation_builder_docstring
+///
+///    import bourse
+///
+///    env = bourse.core.StepEnv(101, 0, 1, 1000)
+///    builder = bourse.core.ObservationBuilder(
+///        levels=5, inventory_trader=101
+///    )
+///
+///    observation = builder.build(env)
+///
+/// Parameters
+/// ----------
+/// touch: bool, optional
+///     Include the touch prices and volumes, default ``True``.
+/// spread: bool, optional
+///     Include the bid-ask spread, default ``True``.
+/// imbalance: bool, optional
+///     Include the order imbalance, default ``True``.
+/// levels: int, optional
+///     Number of price levels to include the volumes
+///     of, default ``0``.
+/// inventory_trader: int, optional
+///     Id of a trader to include the net position of.
+/// price_scale: float, optional
+///     Scale prices are divided by, default ``1.0``.
+/// vol_scale: float, optional
+///     Scale volumes are divided by, default ``1.0``.
+///
+#[pyclass]
+pub struct ObservationBuilder {
+    builder: BaseObservationBuilder,
+}
+
+#[pymethods]
+impl ObservationBuilder {
+    #[new]
+    #[pyo3(signature = (
+        touch=true,
+        spread=true,
+        imbalance=true,
+        levels=0,
+        inventory_trader=None,
+        price_scale=1.0,
+        vol_scale=1.0
+    ))]
+    pub fn new(
+        touch: bool,
+        spread: bool,
+        imbalance: bool,
+        levels: usize,
+        inventory_trader: Option<TraderId>,
+        price_scale: f64,
+        vol_scale: f64,
+    ) -> PyResult<Self> {
+        if levels > 10 {
+            return Err(PyValueError::new_err("Number of levels cannot exceed 10"));
+        }
+        if price_scale <= 0.0 || vol_scale <= 0.0 {
+            return Err(PyValueError::new_err("Scales must be positive"));
+        }
+        let config = ObservationConfig {
+            touch,
+            spread,
+            imbalance,
+            levels,
+            inventory: inventory_trader,
+            price_scale,
+            vol_scale,
+        };
+        Ok(Self {
+            builder: BaseObservationBuilder::new(config),
+        })
+    }
+
+    /// Length of observation arrays
+    pub fn __len__(&self) -> usize {
+        self.builder.len()
+    }
+
+    /// build(env: StepEnv) -> numpy.ndarray
+    ///
+    /// Build an observation from the current state of an environment
+    ///
+    /// Parameters
+    /// ----------
+    /// env: StepEnv
+    ///     Simulation environment.
+    ///
+    /// Returns
+    /// -------
+    /// np.ndarray
+    ///     Observation array.
+    ///
+    pub fn build<'a>(&self, py: Python<'a>, env: PyRef<StepEnv>) -> &'a PyArray1<f64> {
+        self.builder.build(&env.env).into_pyarray(py)
+    }
+}
@@ -1,8 +1,11 @@
 use super::types;
 use bourse_book::types::{Nanos, OrderCount, OrderId, Price, Side, TraderId, Vol};
 use bourse_book::OrderBook as BaseOrderBook;
+use numpy::IntoPyArray;
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
+use pyo3::types::IntoPyDict;
+use std::collections::HashMap;
 
 /// Rust orderbook interface
 ///
@@ -38,9 +41,17 @@ pub struct OrderBook(BaseOrderBook);
 #[pymethods]
 impl OrderBook {
     #[new]
-    #[pyo3(signature = (start_time, tick_size, trading=true))]
-    pub fn new(start_time: Nanos, tick_size: Price, trading: bool) -> PyResult<Self> {
-        let inner = BaseOrderBook::new(start_time, tick_size, trading);
+    #[pyo3(signature = (start_time, tick_size, trading=true, lot_size=1))]
+    pub fn new(
+        start_time: Nanos,
+        tick_size: Price,
+        trading: bool,
+        lot_size: Vol,
+    ) -> PyResult<Self> {
+        if lot_size == 0 {
+            return Err(PyValueError::new_err("Lot size must be non-zero"));
+        }
+        let inner = BaseOrderBook::new(start_time, tick_size, lot_size, trading);
         Ok(Self(inner))
     }
 
@@ -199,6 +210,8 @@ impl OrderBook {
     ///     - ``4 = Rejected`` Order has been
     ///       rejected (e.g. a market order in a
     ///       no-trade period)
+    ///     - ``5 = Pending`` Stop order waiting
+    ///       for its trigger price
     ///
     pub fn order_status(&self, order_id: OrderId) -> u8 {
         self.0.order(order_id).status.into()
@@ -361,7 +374,88 @@ impl OrderBook {
             .collect()
     }
 
-    /// save_json_snapshot(path: str, pretty: bool = False)
+    /// status_histogram() -> dict[str, int]
+    ///
+    /// Count orders in each status
+    ///
+    /// Returns
+    /// -------
+    /// dict[str, int]
+    ///     Number of created orders in each status, with
+    ///     keys ``new``, ``active``, ``filled``,
+    ///     ``cancelled``, ``rejected`` and ``pending``.
+    ///
+    pub fn status_histogram(&self) -> HashMap<String, usize> {
+        let counts = self.0.status_histogram();
+        [
+            "new",
+            "active",
+            "filled",
+            "cancelled",
+            "rejected",
+            "pending",
+        ]
+        .into_iter()
+        .zip(counts)
+        .map(|(k, v)| (k.to_string(), v))
+        .collect()
+    }
+
+    /// ohlcv_bars(bar_size: int) -> numpy.ndarray
+    ///
+    /// Aggregate trades into OHLCV bars
+    ///
+    /// Groups trades into open, high, low, close and
+    /// volume bars covering the time intervals
+    /// ``[k * bar_size, (k + 1) * bar_size)``.
+    /// Intervals without any trades are skipped.
+    ///
+    /// Parameters
+    /// ----------
+    /// bar_size: int
+    ///     Time-length of each bar.
+    ///
+    /// Returns
+    /// -------
+    /// numpy.ndarray
+    ///     Structured array of bars with fields ``t``
+    ///     (bar start time), ``open``, ``high``, ``low``,
+    ///     ``close`` and ``vol``.
+    ///
+    pub fn ohlcv_bars(&self, py: Python, bar_size: Nanos) -> PyResult<PyObject> {
+        if bar_size == 0 {
+            return Err(PyValueError::new_err("Bar size must be non-zero"));
+        }
+
+        let bars = self.0.ohlcv_bars(bar_size);
+
+        let t: Vec<Nanos> = bars.iter().map(|x| x.t).collect();
+        let open: Vec<Price> = bars.iter().map(|x| x.open).collect();
+        let high: Vec<Price> = bars.iter().map(|x| x.high).collect();
+        let low: Vec<Price> = bars.iter().map(|x| x.low).collect();
+        let close: Vec<Price> = bars.iter().map(|x| x.close).collect();
+        let vol: Vec<Vol> = bars.iter().map(|x| x.vol).collect();
+
+        let arrays: Vec<PyObject> = vec![
+            t.into_pyarray(py).to_object(py),
+            open.into_pyarray(py).to_object(py),
+            high.into_pyarray(py).to_object(py),
+            low.into_pyarray(py).to_object(py),
+            close.into_pyarray(py).to_object(py),
+            vol.into_pyarray(py).to_object(py),
+        ];
+        let kwargs = [("names", "t,open,high,low,close,vol")].into_py_dict(py);
+
+        let bars = py.import("numpy")?.getattr("rec")?.call_method(
+            "fromarrays",
+            (arrays,),
+            Some(kwargs),
+        )?;
+
+        Ok(bars.into())
+    }
+
+    /// save_json_snapshot(path: str, pretty: bool = False, prune: bool = False)
     ///
     /// Save a snapshot of the order book state to JSON
     ///
@@ -372,10 +466,16 @@ impl OrderBook {
     /// pretty: bool, optional
     ///     If ``True`` the JSON output will be pretty-print
     ///     formatted, default value is ``False``
-    ///
-    #[pyo3(signature = (path, pretty=false))]
-    pub fn save_json_snapshot(&self, path: String, pretty: bool) -> PyResult<()> {
-        self.0.save_json(path, pretty)?;
+    /// prune: bool, optional
+    ///     If ``True`` only live orders are saved, and the
+    ///     history of completed orders and trades is
+    ///     discarded. Live orders are assigned new ids
+    ///     in the loaded order book. Default value is
+    ///     ``False``.
+    ///
+    #[pyo3(signature = (path, pretty=false, prune=false))]
+    pub fn save_json_snapshot(&self, path: String, pretty: bool, prune: bool) -> PyResult<()> {
+        self.0.save_json(path, pretty, prune)?;
         Ok(())
     }
 }
@@ -1,10 +1,9 @@
-use std::array;
 use std::collections::HashMap;
 
-use super::types::{cast_order, cast_trade, PyOrder, PyTrade};
+use super::types::{cast_market_data, cast_order, cast_trade, PyOrder, PyTrade};
 use bourse_book::types::{Nanos, OrderCount, OrderId, Price, Side, TraderId, Vol};
 use bourse_de::Env as BaseEnv;
-use numpy::{PyArray1, ToPyArray};
+use numpy::{IntoPyArray, PyArray1, ToPyArray};
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use rand_xoshiro::rand_core::SeedableRng;
@@ -54,7 +53,7 @@ use rand_xoshiro::Xoroshiro128StarStar;
 ///
 #[pyclass]
 pub struct StepEnv {
-    env: BaseEnv,
+    pub(crate) env: BaseEnv,
     rng: Xoroshiro128StarStar,
 }
 
@@ -154,6 +153,8 @@ impl StepEnv {
     ///     - ``4 = Rejected`` Order has been
     ///       rejected (e.g. a market order in a
     ///       no-trade period)
+    ///     - ``5 = Pending`` Stop order waiting
+    ///       for its trigger price
     ///
     pub fn order_status(&self, order_id: OrderId) -> u8 {
         self.env.get_orderbook().order(order_id).status.into()
@@ -242,6 +243,52 @@ impl StepEnv {
         }
     }
 
+    /// place_order_with_expiry(bid: bool, vol: int, trader_id: int, price: int, expiry: int) -> int
+    ///
+    /// Submit a new good-till-time order transaction
+    ///
+    /// Creates a new limit order and submit an instruction
+    /// to place the order on the market. The order is
+    /// cancelled once the simulated time reaches the
+    /// expiry time, with expired orders cancelled at
+    /// the boundaries of each step.
+    ///
+    /// Parameters
+    /// ----------
+    /// bid: bool
+    ///     If ``True`` the order will be placed on the
+    ///     bid side.
+    /// vol: int
+    ///     Volume of the order.
+    /// trader_id: int
+    ///     Id of the agent/trader placing the order.
+    /// price: int
+    ///     Limit price of the order.
+    /// expiry: int
+    ///     Time the order expires.
+    ///
+    pub fn place_order_with_expiry(
+        &mut self,
+        bid: bool,
+        vol: Vol,
+        trader_id: TraderId,
+        price: Price,
+        expiry: Nanos,
+    ) -> PyResult<OrderId> {
+        let side = match bid {
+            true => Side::Bid,
+            false => Side::Ask,
+        };
+        let order_id = self
+            .env
+            .place_order_with_expiry(side, vol, trader_id, price, expiry);
+
+        match order_id {
+            Ok(i) => Ok(i),
+            Err(e) => Err(PyValueError::new_err(e.to_string())),
+        }
+    }
+
     /// cancel_order(order_id: int)
     ///
     /// Submit a cancel order transaction
@@ -301,6 +348,22 @@ impl StepEnv {
         (prices.0.to_pyarray(py), prices.1.to_pyarray(py))
     }
 
+    /// get_spreads() -> numpy.ndarray
+    ///
+    /// Get quoted spread history
+    ///
+    /// Returns
+    /// -------
+    /// np.ndarray
+    ///     Array of the difference between the ask and bid
+    ///     touch prices at each simulation step. Values
+    ///     are ``NaN`` for steps where either side of the
+    ///     book was empty.
+    ///
+    pub fn get_spreads<'a>(&self, py: Python<'a>) -> &'a PyArray1<f64> {
+        self.env.get_spreads().into_pyarray(py)
+    }
+
     /// get_volumes() -> tuple[numpy.ndarray, numpy.ndarray]
     ///
     /// Get volume histories
@@ -560,49 +623,6 @@ impl StepEnv {
     ///     +-----------------+--------------------------------------------+
     ///
     pub fn get_market_data<'a>(&self, py: Python<'a>) -> HashMap<String, &'a PyArray1<u32>> {
-        let data = self.env.get_level_2_data_history();
-        let trade_volumes = self.get_trade_volumes(py);
-
-        let bid_vols: [(String, &'a PyArray1<u32>); 10] = array::from_fn(|i| {
-            (
-                format!("bid_vol_{i}"),
-                data.volumes_at_levels.0[i].to_pyarray(py),
-            )
-        });
-        let ask_vols: [(String, &'a PyArray1<u32>); 10] = array::from_fn(|i| {
-            (
-                format!("ask_vol_{i}"),
-                data.volumes_at_levels.1[i].to_pyarray(py),
-            )
-        });
-
-        let bid_orders: [(String, &'a PyArray1<u32>); 10] = array::from_fn(|i| {
-            (
-                format!("n_bid_{i}"),
-                data.orders_at_levels.0[i].to_pyarray(py),
-            )
-        });
-        let ask_orders: [(String, &'a PyArray1<u32>); 10] = array::from_fn(|i| {
-            (
-                format!("n_ask_{i}"),
-                data.orders_at_levels.1[i].to_pyarray(py),
-            )
-        });
-
-        let mut py_data = HashMap::from([
-            ("bid_price".to_string(), data.prices.0.to_pyarray(py)),
-            ("ask_price".to_string(), data.prices.1.to_pyarray(py)),
-            ("bid_vol".to_string(), data.volumes.0.to_pyarray(py)),
-            ("ask_vol".to_string(), data.volumes.1.to_pyarray(py)),
-            ("trade_vol".to_string(), trade_volumes),
-        ]);
-
-        py_data.extend(bid_vols);
-        py_data.extend(ask_vols);
-
-        py_data.extend(bid_orders);
-        py_data.extend(ask_orders);
-
-        py_data
+        cast_market_data(py, &self.env)
     }
 }
@@ -1,10 +1,9 @@
-use std::array;
 use std::collections::HashMap;
 
-use super::types::{cast_order, cast_trade, NumpyInstructions, PyOrder, PyTrade};
+use super::types::{cast_market_data, cast_order, cast_trade, NumpyInstructions, PyOrder, PyTrade};
 use bourse_book::types::{Nanos, OrderId, Price, TraderId, Vol};
 use bourse_de::{Env as BaseEnv, OrderError};
-use numpy::{PyArray1, ToPyArray};
+use numpy::{IntoPyArray, PyArray1, ToPyArray};
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use rand_xoshiro::rand_core::SeedableRng;
@@ -128,6 +127,25 @@ impl StepEnvNumpy {
         Ok(())
     }
 
+    /// reseed_shuffle(seed: int)
+    ///
+    /// Reseed the transaction shuffling random generator
+    ///
+    /// The environment's random generator is only used
+    /// to shuffle the order transactions are processed
+    /// each step, reseeding allows the processing order
+    /// to be varied independently of the state of the
+    /// market (and any external agent randomness).
+    ///
+    /// Parameters
+    /// ----------
+    /// seed: int
+    ///     Random seed.
+    ///
+    pub fn reseed_shuffle(&mut self, seed: u64) {
+        self.rng = Xoroshiro128StarStar::seed_from_u64(seed);
+    }
+
     /// submit_limit_orders(orders: tuple[numpy.ndarray, numpy.ndarray, numpy.ndarray, numpy.ndarray])
     ///
     /// Submit new limit orders from a Numpy array
@@ -433,6 +451,22 @@ impl StepEnvNumpy {
         self.env.get_trades().iter().map(cast_trade).collect()
     }
 
+    /// get_spreads() -> numpy.ndarray
+    ///
+    /// Get quoted spread history
+    ///
+    /// Returns
+    /// -------
+    /// np.ndarray
+    ///     Array of the difference between the ask and bid
+    ///     touch prices at each simulation step. Values
+    ///     are ``NaN`` for steps where either side of the
+    ///     book was empty.
+    ///
+    pub fn get_spreads<'a>(&self, py: Python<'a>) -> &'a PyArray1<f64> {
+        self.env.get_spreads().into_pyarray(py)
+    }
+
     /// get_market_data() -> dict[str, numpy.ndarray]
     ///
     /// Get simulation market data
@@ -469,49 +503,6 @@ impl StepEnvNumpy {
     ///     +-----------------+--------------------------------------------+
     ///
     pub fn get_market_data<'a>(&self, py: Python<'a>) -> HashMap<String, &'a PyArray1<u32>> {
-        let data = self.env.get_level_2_data_history();
-        let trade_volumes = self.env.get_trade_vols().to_pyarray(py);
-
-        let bid_vols: [(String, &'a PyArray1<u32>); 10] = array::from_fn(|i| {
-            (
-                format!("bid_vol_{i}"),
-                data.volumes_at_levels.0[i].to_pyarray(py),
-            )
-        });
-        let ask_vols: [(String, &'a PyArray1<u32>); 10] = array::from_fn(|i| {
-            (
-                format!("ask_vol_{i}"),
-                data.volumes_at_levels.1[i].to_pyarray(py),
-            )
-        });
-
-        let bid_orders: [(String, &'a PyArray1<u32>); 10] = array::from_fn(|i| {
-            (
-                format!("n_bid_{i}"),
-                data.orders_at_levels.0[i].to_pyarray(py),
-            )
-        });
-        let ask_orders: [(String, &'a PyArray1<u32>); 10] = array::from_fn(|i| {
-            (
-                format!("n_ask_{i}"),
-                data.orders_at_levels.1[i].to_pyarray(py),
-            )
-        });
-
-        let mut py_data = HashMap::from([
-            ("bid_price".to_string(), data.prices.0.to_pyarray(py)),
-            ("ask_price".to_string(), data.prices.1.to_pyarray(py)),
-            ("bid_vol".to_string(), data.volumes.0.to_pyarray(py)),
-            ("ask_vol".to_string(), data.volumes.1.to_pyarray(py)),
-            ("trade_vol".to_string(), trade_volumes),
-        ]);
-
-        py_data.extend(bid_vols);
-        py_data.extend(ask_vols);
-
-        py_data.extend(bid_orders);
-        py_data.extend(ask_orders);
-
-        py_data
+        cast_market_data(py, &self.env)
     }
 }
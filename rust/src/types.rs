@@ -1,5 +1,9 @@
+use std::collections::HashMap;
+
 use bourse_de::types::{Nanos, Order, OrderId, Price, Trade, TraderId, Vol};
-use numpy::PyArray1;
+use bourse_de::Env;
+use numpy::{IntoPyArray, PyArray1};
+use pyo3::prelude::*;
 
 pub type PyTrade = (Nanos, bool, Price, Vol, OrderId, OrderId);
 
@@ -38,3 +42,47 @@ pub type NumpyInstructions<'a> = (
     &'a PyArray1<Price>,
     &'a PyArray1<OrderId>,
 );
+
+pub type PySimResults<'a> = (HashMap<String, &'a PyArray1<u32>>, Vec<PyTrade>);
+
+pub fn cast_market_data<'a>(py: Python<'a>, env: &Env) -> HashMap<String, &'a PyArray1<u32>> {
+    let data = env.history_soa();
+
+    let bid_vols = data
+        .bid_vols_at_levels
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| (format!("bid_vol_{i}"), x.into_pyarray(py)));
+    let ask_vols = data
+        .ask_vols_at_levels
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| (format!("ask_vol_{i}"), x.into_pyarray(py)));
+
+    let bid_orders = data
+        .bid_orders_at_levels
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| (format!("n_bid_{i}"), x.into_pyarray(py)));
+    let ask_orders = data
+        .ask_orders_at_levels
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| (format!("n_ask_{i}"), x.into_pyarray(py)));
+
+    let mut py_data = HashMap::from([
+        ("bid_price".to_string(), data.bid_price.into_pyarray(py)),
+        ("ask_price".to_string(), data.ask_price.into_pyarray(py)),
+        ("bid_vol".to_string(), data.bid_vol.into_pyarray(py)),
+        ("ask_vol".to_string(), data.ask_vol.into_pyarray(py)),
+        ("trade_vol".to_string(), data.trade_vol.into_pyarray(py)),
+    ]);
+
+    py_data.extend(bid_vols);
+    py_data.extend(ask_vols);
+
+    py_data.extend(bid_orders);
+    py_data.extend(ask_orders);
+
+    py_data
+}
@@ -15,6 +15,11 @@ def test_order_book_init():
     assert ob.best_ask_vol_and_orders() == (0, 0)
 
 
+def test_zero_lot_size():
+    with pytest.raises(ValueError):
+        bourse.core.OrderBook(0, 1, lot_size=0)
+
+
 def test_place_order():
     ob = bourse.core.OrderBook(0, 1)
 
@@ -187,6 +192,29 @@ def test_get_orders():
     assert list(orders_df["order_id"]) == [0, 1, 2, 3]
 
 
+def test_status_histogram():
+
+    ob = bourse.core.OrderBook(0, 1)
+
+    ob.place_order(True, 10, 11, price=50)
+    ob.place_order(True, 10, 11, price=51)
+    id_2 = ob.place_order(False, 20, 12, price=65)
+    ob.cancel_order(id_2)
+    ob.place_order(False, 10, 12)
+
+    ob.disable_trading()
+    ob.place_order(False, 10, 12)
+
+    assert ob.status_histogram() == {
+        "new": 0,
+        "active": 1,
+        "filled": 2,
+        "cancelled": 1,
+        "rejected": 1,
+        "pending": 0,
+    }
+
+
 def test_read_write_snapshot(tmp_path):
 
     ob = bourse.core.OrderBook(0, 1)
@@ -208,3 +236,53 @@ def test_read_write_snapshot(tmp_path):
 
     assert ob.get_orders() == loaded_ob.get_orders()
     assert ob.get_trades() == loaded_ob.get_trades()
+
+
+def test_read_write_pruned_snapshot(tmp_path):
+
+    ob = bourse.core.OrderBook(0, 1)
+
+    ob.place_order(True, 10, 11, price=50)
+    ob.place_order(False, 20, 12, price=60)
+    ob.place_order(True, 10, 11, price=55)
+    ob.place_order(False, 5, 12, price=55)
+
+    path = str(tmp_path / "foo.json")
+
+    ob.save_json_snapshot(path, prune=True)
+
+    loaded_ob = bourse.core.order_book_from_json(path)
+
+    assert ob.bid_ask() == loaded_ob.bid_ask()
+    assert ob.best_ask_vol_and_orders() == loaded_ob.best_ask_vol_and_orders()
+    assert ob.best_bid_vol_and_orders() == loaded_ob.best_bid_vol_and_orders()
+
+    assert len(ob.get_trades()) == 1
+    assert loaded_ob.get_trades() == []
+
+
+def test_ohlcv_bars():
+    ob = bourse.core.OrderBook(0, 1)
+
+    ob.place_order(False, 10, 11, price=100)
+    ob.place_order(False, 10, 11, price=102)
+
+    ob.set_time(10)
+    ob.place_order(True, 5, 12)
+    ob.set_time(50)
+    ob.place_order(True, 8, 12)
+    ob.set_time(150)
+    ob.place_order(True, 4, 12)
+
+    bars = ob.ohlcv_bars(100)
+
+    assert bars.shape == (2,)
+    assert list(bars["t"]) == [0, 100]
+    assert list(bars["open"]) == [100, 102]
+    assert list(bars["high"]) == [102, 102]
+    assert list(bars["low"]) == [100, 102]
+    assert list(bars["close"]) == [102, 102]
+    assert list(bars["vol"]) == [13, 4]
+
+    with pytest.raises(ValueError):
+        ob.ohlcv_bars(0)
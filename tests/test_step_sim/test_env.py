@@ -143,3 +143,56 @@ def test_runner():
     assert np.array_equal(data["bid_vol_0"], 10 * np.ones(10))
     assert np.array_equal(data["ask_vol_0"], 10 * np.ones(10))
     assert np.array_equal(data["trade_vol"], np.zeros(10))
+
+
+def test_get_spreads():
+    env = bourse.core.StepEnv(101, 0, 1, 100_000)
+
+    env.place_order(True, 100, 101, price=50)
+    env.place_order(False, 100, 101, price=60)
+    env.step()
+
+    env.place_order(True, 100, 101, price=55)
+    env.step()
+
+    env.place_order(False, 100, 101, price=57)
+    env.step()
+
+    spreads = env.get_spreads()
+    bids, asks = env.get_prices()
+
+    assert isinstance(spreads, np.ndarray)
+    assert spreads.dtype == np.float64
+    assert np.array_equal(spreads, (asks - bids).astype(np.float64))
+    assert np.array_equal(spreads, np.array([10.0, 5.0, 2.0]))
+
+
+def test_get_spreads_empty_book():
+    env = bourse.core.StepEnv(101, 0, 1, 100_000)
+
+    env.place_order(True, 100, 101, price=50)
+    env.step()
+
+    env.place_order(False, 100, 101, price=60)
+    env.step()
+
+    spreads = env.get_spreads()
+
+    assert np.isnan(spreads[0])
+    assert spreads[1] == 10.0
+
+
+def test_place_order_with_expiry():
+    env = bourse.core.StepEnv(101, 0, 1, 1_000)
+
+    order_id = env.place_order_with_expiry(True, 10, 101, 50, 1_000)
+    env.step()
+
+    assert env.order_status(order_id) == 1
+    assert env.bid_vol == 10
+
+    env.step()
+
+    assert env.order_status(order_id) == 3
+    assert env.bid_vol == 0
+    assert env.get_orders()[order_id][3] == 1_000
@@ -0,0 +1,55 @@
+import numpy as np
+
+import bourse
+
+
+def test_run_native_agents():
+    noise_agents = bourse.core.NoiseAgentConfig(
+        0, 50, 0.5, 0.1, 0.1, 10, price_dist_sigma=10.0
+    )
+    momentum_agents = bourse.core.MomentumAgentConfig(
+        50, 10, 0.1, 10, 1.0, 5.0, 0.5, 1.0, price_dist_sigma=10.0
+    )
+
+    n_steps = 100
+
+    market_data, trades = bourse.core.run_agents(
+        101,
+        0,
+        1,
+        100_000,
+        n_steps,
+        noise_agents=[noise_agents],
+        momentum_agents=[momentum_agents],
+    )
+
+    assert isinstance(market_data, dict)
+
+    for k in ["bid_price", "ask_price", "bid_vol", "ask_vol", "trade_vol"]:
+        assert isinstance(market_data[k], np.ndarray)
+        assert market_data[k].shape == (n_steps,)
+
+    assert len(trades) > 0
+    assert np.sum(market_data["trade_vol"]) == sum(t[3] for t in trades)
+
+
+def test_native_agent_seeds():
+    def run(noise_seed=None):
+        noise_agents = bourse.core.NoiseAgentConfig(
+            0, 50, 0.5, 0.1, 0.1, 10, price_dist_sigma=10.0, seed=noise_seed
+        )
+        random_agents = bourse.core.RandomAgentConfig(10, (90, 110), (5, 20), 0.5)
+        _, trades = bourse.core.run_agents(
+            101,
+            0,
+            1,
+            100_000,
+            50,
+            noise_agents=[noise_agents],
+            random_agents=[random_agents],
+        )
+        return trades
+
+    assert run() == run()
+    assert run(noise_seed=11) == run(noise_seed=11)
+    assert run(noise_seed=11) != run(noise_seed=12)
@@ -71,6 +71,29 @@ def test_cancel_orders_from_array():
     assert (level_1_data[7], level_1_data[8]) == (12, 1)
 
 
+def test_reseed_shuffle():
+    def processing_order(seed, shuffle_seed):
+        env = bourse.core.StepEnvNumpy(seed, 0, 1, 100_000)
+        env.reseed_shuffle(shuffle_seed)
+
+        sides = np.array([True] * 5 + [False] * 5)
+        vols = np.arange(1, 11, dtype=np.uint32)
+        ids = np.arange(10, dtype=np.uint32)
+        prices = np.array([20] * 5 + [30] * 5, dtype=np.uint32)
+
+        env.submit_limit_orders((sides, vols, ids, prices))
+        env.step()
+
+        # Orders are assigned arrival times in processing order
+        return [x[2] for x in env.get_orders()]
+
+    order_a = processing_order(101, 11)
+
+    assert order_a == processing_order(202, 11)
+    assert order_a != processing_order(101, 12)
+    assert sorted(order_a) == list(range(10))
+
+
 def test_numpy_random_agent():
 
     env = bourse.core.StepEnvNumpy(101, 0, 1, 100_000)
@@ -118,3 +141,36 @@ def test_runner():
     assert np.array_equal(data["bid_vol_0"], 10 * np.ones(10))
     assert np.array_equal(data["ask_vol_0"], 10 * np.ones(10))
     assert np.array_equal(data["trade_vol"], np.zeros(10))
+
+
+def test_get_spreads_numpy():
+    env = bourse.core.StepEnvNumpy(101, 0, 1, 100_000)
+
+    env.submit_limit_orders(
+        (
+            np.array([True]),
+            np.array([100], dtype=np.uint32),
+            np.array([101], dtype=np.uint32),
+            np.array([50], dtype=np.uint32),
+        )
+    )
+    env.step()
+
+    env.submit_limit_orders(
+        (
+            np.array([False]),
+            np.array([100], dtype=np.uint32),
+            np.array([101], dtype=np.uint32),
+            np.array([60], dtype=np.uint32),
+        )
+    )
+    env.step()
+
+    spreads = env.get_spreads()
+    market_data = env.get_market_data()
+
+    assert isinstance(spreads, np.ndarray)
+    assert spreads.dtype == np.float64
+    assert np.isnan(spreads[0])
+    assert spreads[1] == 10.0
+    assert spreads[1] == market_data["ask_price"][1] - market_data["bid_price"][1]
@@ -0,0 +1,52 @@
+import numpy as np
+import pytest
+
+import bourse
+
+
+def test_observation_builder():
+    env = bourse.core.StepEnv(101, 0, 1, 100_000)
+
+    env.place_order(True, 10, 101, price=50)
+    env.place_order(True, 20, 101, price=49)
+    env.place_order(False, 30, 101, price=60)
+    env.step()
+    env.place_order(False, 5, 102)
+    env.step()
+
+    builder = bourse.core.ObservationBuilder(levels=2, inventory_trader=102)
+    observation = builder.build(env)
+
+    assert isinstance(observation, np.ndarray)
+    assert observation.dtype == np.float64
+    assert observation.shape == (len(builder),)
+    assert len(builder) == 11
+
+    bid, ask = env.bid_ask
+    assert observation[0] == bid
+    assert observation[1] == ask
+    assert observation[2] == env.best_bid_vol
+    assert observation[3] == env.best_ask_vol
+    assert observation[4] == ask - bid
+    assert observation[-1] == -5.0
+
+
+def test_observation_builder_scaling():
+    env = bourse.core.StepEnv(101, 0, 1, 100_000)
+
+    env.place_order(True, 10, 101, price=50)
+    env.place_order(False, 30, 101, price=60)
+    env.step()
+
+    builder = bourse.core.ObservationBuilder(
+        imbalance=False, price_scale=10.0, vol_scale=5.0
+    )
+    observation = builder.build(env)
+
+    assert len(builder) == 5
+    assert np.array_equal(observation, np.array([5.0, 6.0, 2.0, 6.0, 1.0]))
+
+
+def test_observation_builder_invalid_levels():
+    with pytest.raises(ValueError):
+        bourse.core.ObservationBuilder(levels=11)